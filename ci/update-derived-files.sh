@@ -37,18 +37,24 @@ for cmd in \
     activate-pane-direction \
     adjust-pane-size \
     activate-tab \
+    apply-layout \
+    dump-layout \
     get-pane-direction \
     get-text \
     kill-pane \
     list \
     list-clients \
+    list-port-forwards \
+    log-pane \
     move-pane-to-new-tab \
     rename-workspace \
+    revoke-client \
     send-text \
     set-tab-title \
     set-window-title \
     spawn \
     split-pane \
+    toggle-port-forward \
     zoom-pane \
     ; do
   fname="docs/examples/cmd-synopsis-wezterm-cli-${cmd}--help.txt"
@@ -14,9 +14,11 @@
 use anyhow::{bail, Context as _, Error};
 use config::keyassignment::{PaneDirection, ScrollbackEraseMode};
 use mux::client::{ClientId, ClientInfo};
+use mux::domain::PortForward;
+use mux::layout::Layout;
 use mux::pane::PaneId;
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
-use mux::tab::{PaneNode, SerdeUrl, SplitRequest, TabId};
+use mux::tab::{FloatingPaneOp, PaneNode, SerdeUrl, SplitRequest, TabId};
 use mux::window::WindowId;
 use portable_pty::CommandBuilder;
 use rangeset::*;
@@ -29,12 +31,14 @@ use std::io::Cursor;
 use std::ops::Range;
 use std::path::PathBuf;
 use std::sync::Arc;
+use termwiz::escape::csi::ColorScheme;
+use termwiz::escape::osc::PointerShape;
 use termwiz::hyperlink::Hyperlink;
 use termwiz::image::{ImageData, TextureCoordinate};
 use termwiz::surface::{Line, SequenceNo};
 use thiserror::Error;
 use wezterm_term::color::ColorPalette;
-use wezterm_term::{Alert, ClipboardSelection, StableRowIndex, TerminalSize};
+use wezterm_term::{Alert, ClipboardItem, ClipboardSelection, StableRowIndex, TerminalSize};
 
 #[derive(Error, Debug)]
 #[error("Corrupt Response: {0}")]
@@ -441,7 +445,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 45;
+pub const CODEC_VERSION: usize = 58;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -502,6 +506,18 @@ pdu! {
     GetPaneDirection: 60,
     GetPaneDirectionResponse: 61,
     AdjustPaneSize: 62,
+    SetColorScheme: 63,
+    SetClipboardData: 64,
+    ApplyLayout: 65,
+    ApplyLayoutResponse: 66,
+    RevokeClient: 67,
+    SetPaneLogging: 68,
+    SpawnFloatingPane: 69,
+    ApplyFloatingPaneOp: 70,
+    ListPortForwards: 71,
+    ListPortForwardsResponse: 72,
+    SetPortForwardEnabled: 73,
+    SetPortForwardEnabledResponse: 74,
 }
 
 impl Pdu {
@@ -516,12 +532,39 @@ impl Pdu {
             | Self::SendPaste(_)
             | Self::Resize(_)
             | Self::SetClipboard(_)
+            | Self::SetClipboardData(_)
             | Self::SetPaneZoomed(_)
-            | Self::SpawnV2(_) => true,
+            | Self::SpawnV2(_)
+            | Self::SpawnFloatingPane(_)
+            | Self::ApplyFloatingPaneOp(_)
+            | Self::SetPortForwardEnabled(_)
+            | Self::ApplyLayout(_) => true,
             _ => false,
         }
     }
 
+    /// Returns true if this type of Pdu only observes the state of
+    /// the mux, and so may be sent by a client that attached in
+    /// read-only mode
+    pub fn is_safe_for_read_only(&self) -> bool {
+        matches!(
+            self,
+            Self::Ping(_)
+                | Self::ListPanes(_)
+                | Self::GetLines(_)
+                | Self::GetPaneRenderChanges(_)
+                | Self::GetCodecVersion(_)
+                | Self::GetTlsCreds(_)
+                | Self::SearchScrollbackRequest(_)
+                | Self::SetClientId(_)
+                | Self::GetClientList(_)
+                | Self::ListPortForwards(_)
+                | Self::GetImageCell(_)
+                | Self::GetPaneRenderableDimensions(_)
+                | Self::GetPaneDirection(_)
+        )
+    }
+
     pub fn stream_decode(buffer: &mut Vec<u8>) -> anyhow::Result<Option<DecodedPdu>> {
         let mut cursor = Cursor::new(buffer.as_slice());
         match Self::decode(&mut cursor) {
@@ -594,6 +637,7 @@ impl Pdu {
             | Pdu::SetPalette(SetPalette { pane_id, .. })
             | Pdu::NotifyAlert(NotifyAlert { pane_id, .. })
             | Pdu::SetClipboard(SetClipboard { pane_id, .. })
+            | Pdu::SetClipboardData(SetClipboardData { pane_id, .. })
             | Pdu::PaneFocused(PaneFocused { pane_id })
             | Pdu::PaneRemoved(PaneRemoved { pane_id }) => Some(*pane_id),
             _ => None,
@@ -628,7 +672,11 @@ pub struct Pong {}
 /// Requests a client certificate to authenticate against
 /// the TLS based server
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
-pub struct GetTlsCreds {}
+pub struct GetTlsCreds {
+    /// The name to embed in the client certificate, so that
+    /// the server can identify, audit and revoke this client
+    pub client_name: Option<String>,
+}
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetTlsCredsResponse {
@@ -769,6 +817,76 @@ pub struct SetClipboard {
     pub selection: ClipboardSelection,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetClipboardData {
+    pub pane_id: PaneId,
+    pub items: Vec<ClipboardItem>,
+    pub selection: ClipboardSelection,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ApplyLayout {
+    pub layout: Layout,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ApplyLayoutResponse {
+    pub window_ids: Vec<WindowId>,
+    pub pane_ids: Vec<PaneId>,
+}
+
+/// Adds a named TLS client to, or removes it from, the list
+/// of clients that are refused by the server
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RevokeClient {
+    pub client_name: String,
+    /// If true, lift a prior revocation instead
+    pub restore: bool,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneLogging {
+    pub pane_id: PaneId,
+    /// Where to log the output of the pane, or None to stop logging
+    pub target: Option<config::keyassignment::PaneLogTarget>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SpawnFloatingPane {
+    /// The new pane floats above the tab that contains this pane
+    pub pane_id: PaneId,
+    pub command: Option<CommandBuilder>,
+    pub command_dir: Option<String>,
+    pub domain: config::keyassignment::SpawnTabDomain,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ApplyFloatingPaneOp {
+    pub tab_id: TabId,
+    pub op: FloatingPaneOp,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListPortForwards {}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListPortForwardsResponse {
+    pub forwards: Vec<PortForward>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPortForwardEnabled {
+    pub domain_name: String,
+    pub forward_id: usize,
+    /// If None, toggle the forward
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPortForwardEnabledResponse {
+    pub enabled: bool,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SetWindowWorkspace {
     pub window_id: WindowId,
@@ -834,6 +952,9 @@ pub struct WindowWorkspaceChanged {
 pub struct SetClientId {
     pub client_id: ClientId,
     pub is_proxy: bool,
+    /// The client only wants to observe; the server will refuse
+    /// any request that would modify the state of the mux
+    pub read_only: bool,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -914,6 +1035,7 @@ pub struct LivenessResponse {
 pub struct GetPaneRenderChangesResponse {
     pub pane_id: PaneId,
     pub mouse_grabbed: bool,
+    pub pointer_shape: Option<PointerShape>,
     pub cursor_position: StableCursorPosition,
     pub dimensions: RenderableDimensions,
     pub dirty_lines: Vec<Range<StableRowIndex>>,
@@ -1115,6 +1237,12 @@ pub struct EraseScrollbackRequest {
     pub erase_mode: ScrollbackEraseMode,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetColorScheme {
+    pub pane_id: PaneId,
+    pub scheme: ColorScheme,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct SearchScrollbackRequest {
     pub pane_id: PaneId,
@@ -1250,6 +1378,18 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_read_only_pdus() {
+        assert!(Pdu::Ping(Ping {}).is_safe_for_read_only());
+        assert!(Pdu::GetClientList(GetClientList {}).is_safe_for_read_only());
+        assert!(!Pdu::WriteToPane(WriteToPane {
+            pane_id: 0,
+            data: b"ls\n".to_vec(),
+        })
+        .is_safe_for_read_only());
+        assert!(!Pdu::KillPane(KillPane { pane_id: 0 }).is_safe_for_read_only());
+    }
+
     #[test]
     fn test_bogus_pdu() {
         let mut encoded = Vec::new();
@@ -160,6 +160,9 @@ pub struct Palette {
     pub visual_bell: Option<RgbaColor>,
     /// The color to use for the cursor when a dead key or leader state is active
     pub compose_cursor: Option<RgbaColor>,
+    /// The color of the outline drawn around panes that take part
+    /// in input broadcasting
+    pub input_broadcast_border: Option<RgbaColor>,
 
     pub copy_mode_active_highlight_fg: Option<ColorSpec>,
     pub copy_mode_active_highlight_bg: Option<ColorSpec>,
@@ -217,6 +220,7 @@ impl Palette {
             split: overlay!(split),
             visual_bell: overlay!(visual_bell),
             compose_cursor: overlay!(compose_cursor),
+            input_broadcast_border: overlay!(input_broadcast_border),
             copy_mode_active_highlight_fg: overlay!(copy_mode_active_highlight_fg),
             copy_mode_active_highlight_bg: overlay!(copy_mode_active_highlight_bg),
             copy_mode_inactive_highlight_fg: overlay!(copy_mode_inactive_highlight_fg),
@@ -427,6 +431,36 @@ impl TabBarColors {
             .unwrap_or_else(default_inactive_tab_edge_hover)
     }
 
+    /// Apply the tab bar colors that an application has set
+    /// via OSC 21 in its palette
+    pub fn with_palette_overrides(&self, palette: &ColorPalette) -> Self {
+        let mut colors = self.clone();
+        if let Some(bg) = palette.tab_bar_bg {
+            colors.background = Some(bg.into());
+        }
+        if palette.active_tab_fg.is_some() || palette.active_tab_bg.is_some() {
+            let mut tab = self.active_tab();
+            if let Some(fg) = palette.active_tab_fg {
+                tab.fg_color = fg.into();
+            }
+            if let Some(bg) = palette.active_tab_bg {
+                tab.bg_color = bg.into();
+            }
+            colors.active_tab = Some(tab);
+        }
+        if palette.inactive_tab_fg.is_some() || palette.inactive_tab_bg.is_some() {
+            let mut tab = self.inactive_tab();
+            if let Some(fg) = palette.inactive_tab_fg {
+                tab.fg_color = fg.into();
+            }
+            if let Some(bg) = palette.inactive_tab_bg {
+                tab.bg_color = bg.into();
+            }
+            colors.inactive_tab = Some(tab);
+        }
+        colors
+    }
+
     pub fn overlay_with(&self, other: &Self) -> Self {
         macro_rules! overlay {
             ($name:ident) => {
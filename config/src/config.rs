@@ -368,6 +368,17 @@ pub struct Config {
     #[dynamic(default)]
     pub ssh_backend: SshBackend,
 
+    /// How long, in seconds, an ssh connection that has no open
+    /// channels is kept around so that it can be re-used by new
+    /// tabs and panes without authenticating again
+    #[dynamic(default = "default_ssh_idle_timeout_secs")]
+    pub ssh_idle_timeout_secs: u64,
+
+    /// If set, ssh connections are shared with other wezterm
+    /// processes via a unix domain socket at this path
+    #[dynamic(default)]
+    pub ssh_control_path: Option<String>,
+
     /// When running in server mode, defines configuration for
     /// each of the endpoints that we'll listen for connections
     #[dynamic(default)]
@@ -408,6 +419,51 @@ pub struct Config {
     #[dynamic(default = "default_mux_env_remove")]
     pub mux_env_remove: Vec<String>,
 
+    /// When true, the mux server periodically saves the layout of
+    /// its windows, tabs and panes and rebuilds it when it is next
+    /// started.
+    #[dynamic(default)]
+    pub mux_session_persistence: bool,
+
+    /// How often, in milliseconds, to save the mux session
+    #[dynamic(default = "default_mux_session_save_interval_ms")]
+    pub mux_session_save_interval_ms: u64,
+
+    /// How many lines of scrollback to save for each pane.
+    /// The default of 0 doesn't save any scrollback.
+    #[dynamic(default)]
+    pub mux_session_scrollback_lines: usize,
+
+    /// The names of programs that may be re-run when restoring
+    /// a pane whose foreground process was that program
+    #[dynamic(default)]
+    pub mux_session_restore_processes: Vec<String>,
+
+    /// How the mux server sizes panes that are viewed by several
+    /// clients with differently sized windows
+    #[dynamic(default)]
+    pub mux_window_size_policy: MuxWindowSizePolicy,
+
+    /// When non-empty, only TLS clients whose certificate was issued
+    /// for one of these client names may connect to the mux server
+    #[dynamic(default)]
+    pub mux_allowed_clients: Vec<String>,
+
+    /// TLS clients whose certificate was issued for one of these
+    /// client names are refused by the mux server
+    #[dynamic(default)]
+    pub mux_denied_clients: Vec<String>,
+
+    /// TLS clients whose certificate was issued for one of these
+    /// client names are always attached read-only, whatever they
+    /// request.  `*` matches every TLS client.
+    #[dynamic(default)]
+    pub mux_read_only_clients: Vec<String>,
+
+    /// If set, the mux server appends a record of client connections,
+    /// spawned commands and credential changes to this file
+    pub mux_audit_log: Option<PathBuf>,
+
     #[dynamic(default)]
     pub keys: Vec<Key>,
     #[dynamic(default)]
@@ -816,6 +872,11 @@ pub struct Config {
     #[dynamic(default = "default_true")]
     pub unzoom_on_switch_pane: bool,
 
+    /// The width and height of newly spawned floating panes,
+    /// as a percentage of the size of the tab
+    #[dynamic(default = "default_floating_pane_percent")]
+    pub floating_pane_percent: u8,
+
     #[dynamic(default = "default_max_fps")]
     pub max_fps: u8,
 
@@ -851,6 +912,9 @@ pub struct Config {
     #[dynamic(default = "default_true")]
     pub allow_download_protocols: bool,
 
+    #[dynamic(default)]
+    pub clipboard_read_access: ClipboardReadAccess,
+
     #[dynamic(default = "default_true")]
     pub allow_win32_input_mode: bool,
 
@@ -1657,6 +1721,10 @@ fn default_mux_output_parser_coalesce_delay_ms() -> u64 {
     3
 }
 
+fn default_ssh_idle_timeout_secs() -> u64 {
+    300
+}
+
 fn default_mux_output_parser_buffer_size() -> usize {
     128 * 1024
 }
@@ -1798,6 +1866,10 @@ fn default_mux_env_remove() -> Vec<String> {
     ]
 }
 
+fn default_mux_session_save_interval_ms() -> u64 {
+    30_000
+}
+
 fn default_anim_fps() -> u8 {
     10
 }
@@ -1806,6 +1878,10 @@ fn default_max_fps() -> u8 {
     60
 }
 
+fn default_floating_pane_percent() -> u8 {
+    80
+}
+
 fn default_tiling_desktop_environments() -> Vec<String> {
     [
         "X11 LG3D",
@@ -2153,6 +2229,29 @@ pub enum NotificationHandling {
     SuppressFromFocusedWindow,
 }
 
+/// Controls whether applications may read the clipboard via OSC 5522
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClipboardReadAccess {
+    /// Prompt the first time that a pane from a given domain
+    /// requests to read the clipboard
+    #[default]
+    Ask,
+    Allow,
+    Deny,
+}
+
+/// Decides the size of a pane that is attached to several mux clients
+#[derive(Debug, FromDynamic, ToDynamic, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MuxWindowSizePolicy {
+    /// Use the size requested by the client that most recently resized
+    #[default]
+    Latest,
+    /// Fit inside the smallest of the clients
+    Smallest,
+    /// Fill the largest of the clients
+    Largest,
+}
+
 fn validate_row_or_col(value: &u16) -> Result<(), String> {
     if *value < 1 {
         Err("initial_cols and initial_rows must be non-zero".to_string())
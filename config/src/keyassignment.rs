@@ -34,6 +34,7 @@ bitflags::bitflags! {
         const KEY_ASSIGNMENTS = 16;
         const WORKSPACES = 32;
         const COMMANDS = 64;
+        const PORT_FORWARDS = 128;
     }
 }
 
@@ -73,6 +74,9 @@ impl ToString for LauncherFlags {
         if self.contains(Self::COMMANDS) {
             s.push("COMMANDS");
         }
+        if self.contains(Self::PORT_FORWARDS) {
+            s.push("PORT_FORWARDS");
+        }
         s.join("|")
     }
 }
@@ -92,6 +96,7 @@ impl TryFrom<String> for LauncherFlags {
                 "KEY_ASSIGNMENTS" => flags |= Self::KEY_ASSIGNMENTS,
                 "WORKSPACES" => flags |= Self::WORKSPACES,
                 "COMMANDS" => flags |= Self::COMMANDS,
+                "PORT_FORWARDS" => flags |= Self::PORT_FORWARDS,
                 _ => {
                     return Err(format!("invalid LauncherFlags `{}` in `{}`", ele, s));
                 }
@@ -307,6 +312,43 @@ impl Default for ScrollbackEraseMode {
     }
 }
 
+/// Controls how pane output is written to a log
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, FromDynamic, ToDynamic, Serialize, Deserialize, Default,
+)]
+pub enum PaneLogMode {
+    /// The bytes emitted by the pty, including escape sequences
+    #[default]
+    Raw,
+    /// Only the printable text, with escape sequences removed
+    Text,
+}
+
+/// Where to send the output of a pane; exactly one of `path`
+/// and `command` must be set
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic, Serialize, Deserialize)]
+pub struct PaneLogTarget {
+    /// Append the output to this file.  A leading `~` is expanded
+    /// to the home directory, and relative paths are relative to it.
+    pub path: Option<PathBuf>,
+    /// Write the output to the stdin of this command
+    pub command: Option<Vec<String>>,
+    #[dynamic(default)]
+    pub mode: PaneLogMode,
+}
+
+/// Selects the panes that receive the input typed into the
+/// active pane while input broadcasting is enabled
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum InputBroadcastScope {
+    /// The other panes in the current tab
+    CurrentTab,
+    /// The other panes in the current workspace
+    CurrentWorkspace,
+    /// The panes marked via `TogglePaneBroadcastMark`
+    MarkedPanes,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
 pub enum ClipboardCopyDestination {
     Clipboard,
@@ -646,6 +688,23 @@ pub enum KeyAssignment {
     PromptInputLine(PromptInputLine),
     InputSelector(InputSelector),
     Confirmation(Confirmation),
+    ApplyLayout(String),
+    StartPaneLogging(PaneLogTarget),
+    StopPaneLogging,
+    ToggleInputBroadcast(InputBroadcastScope),
+    TogglePaneBroadcastMark,
+    SpawnFloatingPane(SpawnCommand),
+    ToggleFloatingPanes,
+    MoveFloatingPane(PaneDirection, usize),
+    ResizeFloatingPane(PaneDirection, usize),
+    PromoteFloatingPane(PaneDirection),
+    SetPortForwardEnabled {
+        domain: String,
+        forward_id: usize,
+        #[dynamic(default)]
+        enabled: Option<bool>,
+    },
+    ShowSftpBrowser,
 }
 impl_lua_conversion_dynamic!(KeyAssignment);
 
@@ -47,6 +47,31 @@ impl Default for Shell {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum SshPortForwardKind {
+    /// Equivalent to `ssh -L` and the `LocalForward` ssh_config option
+    Local,
+    /// Equivalent to `ssh -R` and the `RemoteForward` ssh_config option
+    Remote,
+    /// A SOCKS5 proxy; equivalent to `ssh -D` and the `DynamicForward`
+    /// ssh_config option
+    Dynamic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct SshPortForward {
+    pub kind: SshPortForwardKind,
+    /// `[bind_address:]port` on which to listen
+    pub listen: String,
+    /// `host:port` to which connections are made.
+    /// Not used by Dynamic forwards.
+    pub target: Option<String>,
+    /// If false, the forward is registered but doesn't listen
+    /// until it is enabled via the launcher or `wezterm cli`
+    #[dynamic(default = "crate::default_true")]
+    pub enabled: bool,
+}
+
 #[derive(Default, Debug, Clone, FromDynamic, ToDynamic)]
 pub struct SshDomain {
     /// The name of this specific domain.  Must be unique amongst
@@ -104,6 +129,16 @@ pub struct SshDomain {
 
     #[dynamic(default)]
     pub assume_shell: Shell,
+
+    /// Port forwards to establish in addition to those specified
+    /// by the ssh_config
+    #[dynamic(default)]
+    pub port_forwards: Vec<SshPortForward>,
+
+    /// When multiplexing is None, whether panes should reconnect
+    /// and restart their command if the connection is lost before
+    /// the command has exited.  Defaults to true.
+    pub auto_reconnect: Option<bool>,
 }
 impl_lua_conversion_dynamic!(SshDomain);
 
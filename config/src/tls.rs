@@ -26,6 +26,30 @@ pub struct TlsDomainServer {
     pub pem_root_certs: Vec<PathBuf>,
 }
 
+/// Client names are embedded in certificates and passed through
+/// the remote shell when bootstrapping, so we keep them simple
+pub fn validate_tls_client_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("the empty string is an invalid client name".to_string());
+    }
+    match name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@')))
+    {
+        Some(c) => Err(format!(
+            "client name \"{name}\" contains invalid character {c:?}"
+        )),
+        None => Ok(()),
+    }
+}
+
+fn validate_optional_tls_client_name(name: &Option<String>) -> Result<(), String> {
+    match name {
+        Some(name) => validate_tls_client_name(name),
+        None => Ok(()),
+    }
+}
+
 #[derive(Default, Debug, Clone, FromDynamic, ToDynamic)]
 pub struct TlsDomainClient {
     /// The name of this specific domain.  Must be unique amongst
@@ -38,6 +62,12 @@ pub struct TlsDomainClient {
     /// The value is "user@host:port", just like "wezterm ssh" accepts.
     pub bootstrap_via_ssh: Option<String>,
 
+    /// The name to request for the client certificate that is
+    /// obtained via `bootstrap_via_ssh`.  The server uses it to
+    /// identify this client in its access lists and audit log.
+    #[dynamic(validate = "validate_optional_tls_client_name")]
+    pub client_name: Option<String>,
+
     /// identifies the host:port pair of the remote server.
     pub remote_address: String,
 
@@ -94,6 +124,11 @@ pub struct TlsDomainClient {
     /// instead.
     #[dynamic(default)]
     pub overlay_lag_indicator: bool,
+
+    /// If true, attach to the server in read-only mode: panes are
+    /// displayed, but input, resizing and spawning are refused.
+    #[dynamic(default)]
+    pub read_only: bool,
 }
 
 impl TlsDomainClient {
@@ -62,6 +62,11 @@ pub struct UnixDomain {
     /// instead.
     #[dynamic(default)]
     pub overlay_lag_indicator: bool,
+
+    /// If true, attach to the server in read-only mode: panes are
+    /// displayed, but input, resizing and spawning are refused.
+    #[dynamic(default)]
+    pub read_only: bool,
 }
 
 impl Default for UnixDomain {
@@ -78,6 +83,7 @@ impl Default for UnixDomain {
             local_echo_threshold_ms: None,
             proxy_command: None,
             overlay_lag_indicator: false,
+            read_only: false,
         }
     }
 }
@@ -1,15 +1,17 @@
-use config::keyassignment::SpawnTabDomain;
+use config::keyassignment::{PaneLogTarget, SpawnTabDomain};
 use config::lua::mlua::{self, Lua, UserData, UserDataMethods, Value as LuaValue};
 use config::lua::{get_or_create_module, get_or_create_sub_module};
 use luahelper::impl_lua_conversion_dynamic;
 use mlua::UserDataRef;
 use mux::domain::{DomainId, SplitSource};
+use mux::layout::Layout;
 use mux::pane::{Pane, PaneId};
 use mux::tab::{SplitDirection, SplitRequest, SplitSize, Tab, TabId};
 use mux::window::{Window, WindowId};
 use mux::Mux;
 use portable_pty::CommandBuilder;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_term::TerminalSize;
@@ -53,6 +55,10 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
             let mux = get_mux()?;
             let workspaces = mux.iter_workspaces();
             if workspaces.contains(&workspace) {
+                if mux.iter_windows_in_workspace(&workspace).is_empty() {
+                    mux.activate_domain_workspace(&workspace, None)
+                        .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+                }
                 Ok(mux.set_active_workspace(&workspace))
             } else {
                 Err(mlua::Error::external(format!(
@@ -102,6 +108,24 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
         })?,
     )?;
 
+    mux_mod.set(
+        "apply_layout",
+        lua.create_async_function(|_, layout: LuaValue| {
+            let layout = layout_from_lua(layout);
+            async move {
+                let applied = layout?
+                    .apply()
+                    .await
+                    .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+                Ok(applied
+                    .pane_ids
+                    .into_iter()
+                    .map(MuxPane)
+                    .collect::<Vec<MuxPane>>())
+            }
+        })?,
+    )?;
+
     mux_mod.set(
         "spawn_window",
         lua.create_async_function(|_, spawn: SpawnWindow| async move { spawn.spawn().await })?,
@@ -196,6 +220,20 @@ impl CommandBuilderFrag {
     }
 }
 
+/// Accepts either the path to a layout file, or a table
+/// describing the layout
+fn layout_from_lua(value: LuaValue) -> mlua::Result<Layout> {
+    match value {
+        LuaValue::String(path) => Layout::load(Path::new(path.to_str()?))
+            .map_err(|e| mlua::Error::external(format!("{:#}", e))),
+        value => {
+            let mut layout: Layout = luahelper::from_lua_value_dynamic(value)?;
+            layout.resolve_paths(&config::HOME_DIR);
+            Ok(layout)
+        }
+    }
+}
+
 #[derive(Debug, FromDynamic, ToDynamic)]
 enum HandySplitDirection {
     Left,
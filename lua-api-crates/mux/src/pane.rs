@@ -433,6 +433,21 @@ impl UserData for MuxPane {
             let pane = this.resolve(&mux)?;
             Ok(pane.tty_name())
         });
+
+        methods.add_method("start_logging", |_lua, this, target: Value| {
+            let target: PaneLogTarget = from_lua(target)?;
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.start_logging(target)
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
+
+        methods.add_method("stop_logging", |_lua, this, ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.stop_logging()
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
     }
 }
 
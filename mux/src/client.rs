@@ -2,9 +2,11 @@ use crate::PaneId;
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
 use serde::*;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::SystemTime;
+use wezterm_term::TerminalSize;
 
 static CLIENT_ID: AtomicUsize = AtomicUsize::new(0);
 lazy_static::lazy_static! {
@@ -52,6 +54,15 @@ pub struct ClientInfo {
     pub last_input: DateTime<Utc>,
     /// The currently-focused pane
     pub focused_pane_id: Option<PaneId>,
+    /// Whether the client attached in read-only mode
+    pub read_only: bool,
+    /// The client name from the TLS certificate that this
+    /// client authenticated with, if any
+    pub identity: Option<String>,
+    /// The sizes that this client has requested for the panes
+    /// that it is viewing
+    #[serde(skip)]
+    pub pane_sizes: HashMap<PaneId, TerminalSize>,
 }
 
 impl ClientInfo {
@@ -62,6 +73,9 @@ impl ClientInfo {
             active_workspace: None,
             last_input: Utc::now(),
             focused_pane_id: None,
+            read_only: false,
+            identity: None,
+            pane_sizes: HashMap::new(),
         }
     }
 
@@ -7,7 +7,7 @@
 
 use crate::localpane::LocalPane;
 use crate::pane::{alloc_pane_id, Pane, PaneId};
-use crate::tab::{SplitRequest, Tab, TabId};
+use crate::tab::{FloatingPaneOp, SplitRequest, Tab, TabId};
 use crate::window::WindowId;
 use crate::Mux;
 use anyhow::{bail, Context, Error};
@@ -17,6 +17,7 @@ use config::{configuration, ExecDomain, SerialDomain, ValueOrFunc, WslDomain};
 use downcast_rs::{impl_downcast, Downcast};
 use parking_lot::Mutex;
 use portable_pty::{native_pty_system, CommandBuilder, ExitStatus, MasterPty, PtySize, PtySystem};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io::Write;
@@ -37,6 +38,18 @@ pub fn alloc_domain_id() -> DomainId {
     DOMAIN_ID.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed)
 }
 
+/// A port forward that is provided by a domain
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortForward {
+    pub domain_name: String,
+    pub forward_id: usize,
+    /// Describes the forward, such as `local 8080 -> db:5432`
+    pub description: String,
+    pub enabled: bool,
+    /// Set when the most recent attempt to listen failed
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum SplitSource {
     Spawn {
@@ -141,6 +154,36 @@ pub trait Domain: Downcast + Send + Sync {
         Ok(pane)
     }
 
+    /// Spawn a new command in a pane that floats above the
+    /// split tree of the specified tab
+    async fn spawn_floating_pane(
+        &self,
+        tab: TabId,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let mux = Mux::get();
+        let tab = match mux.get_tab(tab) {
+            Some(t) => t,
+            None => anyhow::bail!("Invalid tab id {}", tab),
+        };
+
+        let size = tab.compute_floating_size();
+        let pane = self.spawn_pane(size, command, command_dir).await?;
+        tab.add_floating_pane(&pane, size);
+        Ok(pane)
+    }
+
+    /// Applies `op` to the top-most floating pane of the specified tab
+    async fn apply_floating_pane_op(&self, tab: TabId, op: FloatingPaneOp) -> anyhow::Result<()> {
+        let mux = Mux::get();
+        let tab = match mux.get_tab(tab) {
+            Some(t) => t,
+            None => anyhow::bail!("Invalid tab id {}", tab),
+        };
+        tab.apply_floating_pane_op(op)
+    }
+
     async fn spawn_pane(
         &self,
         size: TerminalSize,
@@ -169,6 +212,36 @@ pub trait Domain: Downcast + Send + Sync {
         true
     }
 
+    /// Returns the names of workspaces that this domain can populate
+    /// on demand, even though there may not be any windows in them yet,
+    /// such as the sessions of a tmux server.
+    fn workspaces(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// Called when switching to a workspace that has no windows.
+    /// Returns Ok(true) if the domain is going to populate the
+    /// workspace itself, in which case the caller should not spawn
+    /// a default window into it.
+    fn activate_workspace(&self, _workspace: &str) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// Returns the port forwards that are associated with this domain
+    async fn list_port_forwards(&self) -> anyhow::Result<Vec<PortForward>> {
+        Ok(vec![])
+    }
+
+    /// Starts or stops listening on one of the port forwards
+    /// returned by `list_port_forwards`
+    async fn set_port_forward_enabled(
+        &self,
+        _forward_id: usize,
+        _enabled: bool,
+    ) -> anyhow::Result<()> {
+        bail!("domain {} has no port forwards", self.domain_name());
+    }
+
     /// Returns true if the `detach` method can be used
     /// to detach the domain, preserving the associated
     /// panes, or false if the `detach` method will never
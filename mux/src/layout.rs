@@ -0,0 +1,672 @@
+//! Declarative descriptions of windows, tabs and splits that can be
+//! loaded from a file and built in the mux, or captured from the
+//! live state of the mux.
+use crate::domain::SplitSource;
+use crate::pane::{Pane, PaneId};
+use crate::tab::{PaneNode, SplitDirection, SplitRequest, SplitSize, Tab};
+use crate::window::WindowId;
+use crate::Mux;
+use anyhow::{anyhow, Context};
+use config::keyassignment::SpawnTabDomain;
+use percent_encoding::percent_decode_str;
+use portable_pty::CommandBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromDynamic, ToDynamic)]
+pub struct Layout {
+    #[serde(default)]
+    #[dynamic(default)]
+    pub windows: Vec<LayoutWindow>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromDynamic, ToDynamic)]
+pub struct LayoutWindow {
+    /// The workspace for the window. If omitted, the active
+    /// workspace is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub workspace: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub title: Option<String>,
+    /// The default working directory for the panes in this window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub cwd: Option<String>,
+    /// The default domain for the panes in this window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub domain: Option<String>,
+    #[serde(default)]
+    #[dynamic(default)]
+    pub tabs: Vec<LayoutTab>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromDynamic, ToDynamic)]
+pub struct LayoutTab {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub title: Option<String>,
+    /// The root of the pane tree for this tab
+    #[serde(flatten)]
+    #[dynamic(flatten)]
+    pub root: LayoutPane,
+}
+
+/// Either a single pane, when `panes` is empty, or a container
+/// that splits its space between the `panes` in `direction`.
+/// cwd, domain and environment are inherited by contained panes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, FromDynamic, ToDynamic)]
+pub struct LayoutPane {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub direction: Option<SplitDirection>,
+    /// The percentage of the space of the containing split to
+    /// assign to this pane. Panes without a size share the
+    /// remaining space equally.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub size: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub domain: Option<String>,
+    /// The command to run instead of the default program
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[dynamic(default)]
+    pub args: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[dynamic(default)]
+    pub set_environment_variables: HashMap<String, String>,
+    /// Makes this the active pane of its tab
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    #[dynamic(default)]
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[dynamic(default)]
+    pub panes: Vec<LayoutPane>,
+}
+
+/// The ids of the objects created by `Layout::apply`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppliedLayout {
+    pub window_ids: Vec<WindowId>,
+    pub pane_ids: Vec<PaneId>,
+}
+
+/// The properties that a pane inherits from its containers
+#[derive(Debug, Clone, Default)]
+struct Inherited {
+    cwd: Option<String>,
+    domain: Option<String>,
+    env: HashMap<String, String>,
+}
+
+impl Inherited {
+    fn child(&self, pane: &LayoutPane) -> Self {
+        let mut env = self.env.clone();
+        env.extend(pane.set_environment_variables.clone());
+        Self {
+            cwd: pane.cwd.clone().or_else(|| self.cwd.clone()),
+            domain: pane.domain.clone().or_else(|| self.domain.clone()),
+            env,
+        }
+    }
+
+    fn spawn_domain(&self) -> SpawnTabDomain {
+        match &self.domain {
+            Some(name) => SpawnTabDomain::DomainName(name.clone()),
+            None => SpawnTabDomain::DefaultDomain,
+        }
+    }
+}
+
+impl LayoutPane {
+    pub fn is_leaf(&self) -> bool {
+        self.panes.is_empty()
+    }
+
+    /// Returns the top/left-most pane in this portion of the layout,
+    /// along with the properties that it inherits
+    fn first_leaf(&self, inherited: &Inherited) -> (&LayoutPane, Inherited) {
+        let inherited = inherited.child(self);
+        match self.panes.first() {
+            Some(first) => first.first_leaf(&inherited),
+            None => (self, inherited),
+        }
+    }
+
+    fn command(&self, inherited: &Inherited) -> Option<CommandBuilder> {
+        let mut builder = match &self.args {
+            Some(args) => CommandBuilder::from_argv(args.iter().map(Into::into).collect()),
+            None if inherited.env.is_empty() => return None,
+            None => CommandBuilder::new_default_prog(),
+        };
+        for (k, v) in &inherited.env {
+            builder.env(k, v);
+        }
+        Some(builder)
+    }
+
+    /// Resolves the relative weights of the contained panes
+    fn child_weights(&self) -> Vec<u32> {
+        let specified: u32 = self
+            .panes
+            .iter()
+            .filter_map(|p| p.size.map(u32::from))
+            .sum();
+        let unspecified = self.panes.iter().filter(|p| p.size.is_none()).count() as u32;
+        let share = 100u32
+            .saturating_sub(specified)
+            .checked_div(unspecified)
+            .unwrap_or(0)
+            .max(1);
+        self.panes
+            .iter()
+            .map(|p| p.size.map(u32::from).unwrap_or(share).max(1))
+            .collect()
+    }
+
+    fn resolve_paths(&mut self, base: &Path) {
+        let base = match &self.cwd {
+            Some(cwd) => {
+                let resolved = resolve_path(cwd, base);
+                self.cwd.replace(resolved.to_string_lossy().into_owned());
+                resolved
+            }
+            None => base.to_path_buf(),
+        };
+        for pane in &mut self.panes {
+            pane.resolve_paths(&base);
+        }
+    }
+}
+
+/// Computes `part` as a percentage of `whole`, rounded and limited
+/// to the range accepted by `SplitSize::Percent`.
+/// When applying a layout, `whole` is the weight of the space being
+/// split and `part` is the weight of the new pane that will hold
+/// the subsequent siblings.
+pub fn split_percent(part: usize, whole: usize) -> u8 {
+    ((part * 100 + whole / 2) / whole.max(1)).clamp(1, 99) as u8
+}
+
+/// Expands a leading `~` and makes relative paths relative to `base`
+fn resolve_path(path: &str, base: &Path) -> PathBuf {
+    if path == "~" {
+        return config::HOME_DIR.clone();
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        return config::HOME_DIR.join(rest);
+    }
+    base.join(path)
+}
+
+/// Returns the local path of a `file://` url, such as the working
+/// directory reported by a pane
+pub fn url_to_path(url: &url::Url) -> Option<String> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    let path = percent_decode_str(url.path()).decode_utf8().ok()?;
+    // On Windows the file URI can produce a path like `/C:\Users`;
+    // strip the leading slash as the windows file APIs dislike it
+    let bytes = path.as_bytes();
+    if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+        Some(path[1..].to_string())
+    } else {
+        Some(path.into_owned())
+    }
+}
+
+impl Layout {
+    /// Loads a layout from a TOML, YAML, JSON or Lua file, as
+    /// determined by its extension.  A leading `~` in `path` is
+    /// expanded to the home directory.  Relative paths in the layout
+    /// are resolved relative to the directory containing the file.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let path = match path.strip_prefix("~") {
+            Ok(rest) => config::HOME_DIR.join(rest),
+            Err(_) => path.to_path_buf(),
+        };
+        let path = path.as_path();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let mut layout = if extension == "lua" {
+            Self::load_lua(path)?
+        } else {
+            let text = std::fs::read_to_string(path)
+                .with_context(|| format!("reading layout {}", path.display()))?;
+            Self::parse(&text, &extension)
+                .with_context(|| format!("parsing layout {}", path.display()))?
+        };
+
+        let base = path
+            .canonicalize()
+            .ok()
+            .and_then(|p| p.parent().map(Path::to_path_buf))
+            .unwrap_or_else(|| PathBuf::from("."));
+        layout.resolve_paths(&base);
+        Ok(layout)
+    }
+
+    /// Parses a layout in the format named by `format`, which
+    /// is one of `toml`, `yaml`, `yml` or `json`
+    pub fn parse(text: &str, format: &str) -> anyhow::Result<Self> {
+        match format {
+            "toml" => Ok(toml::from_str(text)?),
+            "yaml" | "yml" => Ok(serde_yaml::from_str(text)?),
+            "json" => Ok(serde_json::from_str(text)?),
+            _ => anyhow::bail!("unsupported layout format `{format}`"),
+        }
+    }
+
+    /// Evaluates a lua file that returns the layout as a table
+    fn load_lua(path: &Path) -> anyhow::Result<Self> {
+        let code = std::fs::read_to_string(path)
+            .with_context(|| format!("reading layout {}", path.display()))?;
+        let lua = config::lua::make_lua_context(path)?;
+        let value: mlua::Value = lua
+            .load(&code)
+            .set_name(path.to_string_lossy())
+            .eval()
+            .with_context(|| format!("evaluating layout {}", path.display()))?;
+        luahelper::from_lua_value_dynamic(value)
+            .map_err(|err| anyhow!("{err:#}"))
+            .with_context(|| format!("converting layout {}", path.display()))
+    }
+
+    /// Serializes the layout in the format named by `format`;
+    /// see `Layout::parse`
+    pub fn to_format_string(&self, format: &str) -> anyhow::Result<String> {
+        match format {
+            "toml" => Ok(toml::to_string_pretty(self)?),
+            "yaml" | "yml" => Ok(serde_yaml::to_string(self)?),
+            "json" => Ok(serde_json::to_string_pretty(self)?),
+            _ => anyhow::bail!("unsupported layout format `{format}`"),
+        }
+    }
+
+    pub fn resolve_paths(&mut self, base: &Path) {
+        for window in &mut self.windows {
+            let base = match &window.cwd {
+                Some(cwd) => {
+                    let resolved = resolve_path(cwd, base);
+                    window.cwd.replace(resolved.to_string_lossy().into_owned());
+                    resolved
+                }
+                None => base.to_path_buf(),
+            };
+            for tab in &mut window.tabs {
+                tab.root.resolve_paths(&base);
+            }
+        }
+    }
+
+    /// Builds the layout from the pane trees, tab titles and window
+    /// titles reported by the mux, such as in `ListPanesResponse`.
+    /// The programs running in the panes are not known, so the
+    /// resulting layout runs the default program in each pane.
+    pub fn from_pane_trees(
+        tabs: &[PaneNode],
+        tab_titles: &[String],
+        window_titles: &HashMap<WindowId, String>,
+    ) -> Self {
+        let mut windows: Vec<(WindowId, LayoutWindow)> = vec![];
+
+        for (idx, tree) in tabs.iter().enumerate() {
+            let (window_id, _tab_id) = match tree.window_and_tab_ids() {
+                Some(ids) => ids,
+                None => continue,
+            };
+            let root = match pane_from_node(tree) {
+                Some(root) => root,
+                None => continue,
+            };
+            let tab = LayoutTab {
+                title: tab_titles.get(idx).filter(|t| !t.is_empty()).cloned(),
+                root,
+            };
+
+            match windows.iter_mut().find(|(id, _)| *id == window_id) {
+                Some((_, window)) => window.tabs.push(tab),
+                None => windows.push((
+                    window_id,
+                    LayoutWindow {
+                        workspace: first_entry_workspace(tree),
+                        title: window_titles
+                            .get(&window_id)
+                            .filter(|t| !t.is_empty())
+                            .cloned(),
+                        tabs: vec![tab],
+                        ..Default::default()
+                    },
+                )),
+            }
+        }
+
+        Self {
+            windows: windows.into_iter().map(|(_, window)| window).collect(),
+        }
+    }
+
+    /// Spawns the windows, tabs and panes described by the layout
+    pub async fn apply(&self) -> anyhow::Result<AppliedLayout> {
+        let mux = Mux::get();
+        let mut applied = AppliedLayout::default();
+
+        for window in &self.windows {
+            let inherited = Inherited {
+                cwd: window.cwd.clone(),
+                domain: window.domain.clone(),
+                env: HashMap::new(),
+            };
+            let workspace = window
+                .workspace
+                .clone()
+                .unwrap_or_else(|| mux.active_workspace());
+
+            let mut window_id = None;
+            let mut first_tab = None;
+            for tab in &window.tabs {
+                let (tab, id) =
+                    apply_tab(tab, window_id, &workspace, &inherited, &mut applied).await?;
+                window_id.replace(id);
+                first_tab.get_or_insert(tab);
+            }
+
+            if let Some(window_id) = window_id {
+                applied.window_ids.push(window_id);
+                if let Some(mut mux_window) = mux.get_window_mut(window_id) {
+                    if let Some(title) = &window.title {
+                        mux_window.set_title(title);
+                    }
+                    if let Some(idx) = first_tab.and_then(|tab| mux_window.idx_by_id(tab.tab_id()))
+                    {
+                        mux_window.save_and_then_set_active(idx);
+                    }
+                }
+            }
+        }
+
+        Ok(applied)
+    }
+}
+
+async fn apply_tab(
+    tab: &LayoutTab,
+    window_id: Option<WindowId>,
+    workspace: &str,
+    inherited: &Inherited,
+    applied: &mut AppliedLayout,
+) -> anyhow::Result<(Arc<Tab>, WindowId)> {
+    let mux = Mux::get();
+    let (leaf, leaf_inherited) = tab.root.first_leaf(inherited);
+    let (mux_tab, pane, window_id) = mux
+        .spawn_tab_or_window(
+            window_id,
+            leaf_inherited.spawn_domain(),
+            leaf.command(&leaf_inherited),
+            leaf_inherited.cwd.clone(),
+            config::configuration().initial_size(0, None),
+            None,
+            workspace.to_string(),
+            None,
+        )
+        .await
+        .context("spawning tab")?;
+
+    if let Some(title) = &tab.title {
+        mux_tab.set_title(title);
+    }
+
+    let mut active = None;
+    // Each entry is a portion of the layout along with the pane that
+    // currently occupies its space, which is already running the
+    // first leaf of that portion
+    let mut stack: Vec<(&LayoutPane, Arc<dyn Pane>, Inherited)> =
+        vec![(&tab.root, pane, inherited.clone())];
+
+    while let Some((node, pane, inherited)) = stack.pop() {
+        let inherited = inherited.child(node);
+        if node.is_leaf() {
+            applied.pane_ids.push(pane.pane_id());
+            if node.active {
+                active = Some(pane);
+            }
+            continue;
+        }
+
+        let direction = node.direction.unwrap_or(SplitDirection::Horizontal);
+        let weights = node.child_weights();
+        let mut panes = vec![pane];
+
+        for idx in 1..node.panes.len() {
+            let remaining: u32 = weights[idx - 1..].iter().sum();
+            let rest: u32 = weights[idx..].iter().sum();
+            let percent = split_percent(rest as usize, remaining as usize);
+            let (leaf, leaf_inherited) = node.panes[idx].first_leaf(&inherited);
+            let target = panes.last().expect("at least one pane").pane_id();
+
+            let (new_pane, _size) = mux
+                .split_pane(
+                    target,
+                    SplitRequest {
+                        direction,
+                        target_is_second: true,
+                        top_level: false,
+                        size: SplitSize::Percent(percent),
+                    },
+                    SplitSource::Spawn {
+                        command: leaf.command(&leaf_inherited),
+                        command_dir: leaf_inherited.cwd.clone(),
+                    },
+                    leaf_inherited.spawn_domain(),
+                )
+                .await
+                .context("splitting pane")?;
+            panes.push(new_pane);
+        }
+
+        for (child, pane) in node.panes.iter().zip(panes).rev() {
+            stack.push((child, pane, inherited.clone()));
+        }
+    }
+
+    if let Some(pane) = active {
+        mux_tab.set_active_pane(&pane);
+    }
+
+    Ok((mux_tab, window_id))
+}
+
+fn first_entry_workspace(node: &PaneNode) -> Option<String> {
+    match node {
+        PaneNode::Empty => None,
+        PaneNode::Split { left, right, .. } => {
+            first_entry_workspace(left).or_else(|| first_entry_workspace(right))
+        }
+        PaneNode::Leaf(entry) => Some(entry.workspace.clone()),
+        PaneNode::Floating { tree, floating, .. } => first_entry_workspace(tree)
+            .or_else(|| floating.first().map(|entry| entry.workspace.clone())),
+    }
+}
+
+/// Returns the size, in cells along the `direction` axis, of a node
+fn node_cells(node: &PaneNode, direction: SplitDirection) -> usize {
+    let size = match node.root_size() {
+        Some(size) => size,
+        None => return 0,
+    };
+    match direction {
+        SplitDirection::Horizontal => size.cols,
+        SplitDirection::Vertical => size.rows,
+    }
+}
+
+/// Collects the chain of nodes that are split in the same direction,
+/// so that eg: three side-by-side panes are represented as a single
+/// container rather than a nested pair of splits
+fn collect_split_children<'a>(
+    node: &'a PaneNode,
+    direction: SplitDirection,
+    children: &mut Vec<&'a PaneNode>,
+) {
+    match node {
+        PaneNode::Split {
+            left,
+            right,
+            node: split,
+        } if split.direction == direction => {
+            collect_split_children(left, direction, children);
+            collect_split_children(right, direction, children);
+        }
+        _ => children.push(node),
+    }
+}
+
+fn pane_from_node(node: &PaneNode) -> Option<LayoutPane> {
+    match node {
+        PaneNode::Empty => None,
+        // Floating panes are not part of the layout
+        PaneNode::Floating { tree, .. } => pane_from_node(tree),
+        PaneNode::Leaf(entry) => Some(LayoutPane {
+            cwd: entry
+                .working_dir
+                .as_ref()
+                .and_then(|url| url_to_path(&url.url)),
+            active: entry.is_active_pane,
+            ..Default::default()
+        }),
+        PaneNode::Split { node: split, .. } => {
+            let direction = split.direction;
+            let mut children = vec![];
+            collect_split_children(node, direction, &mut children);
+
+            let total: usize = children
+                .iter()
+                .map(|child| node_cells(child, direction))
+                .sum();
+            let mut panes = vec![];
+            for child in children {
+                let mut pane = pane_from_node(child)?;
+                let cells = node_cells(child, direction);
+                pane.size.replace(split_percent(cells, total));
+                panes.push(pane);
+            }
+
+            Some(LayoutPane {
+                direction: Some(direction),
+                panes,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+[[windows]]
+workspace = "project"
+cwd = "/src/project"
+
+[[windows.tabs]]
+title = "edit"
+direction = "Horizontal"
+
+[[windows.tabs.panes]]
+args = ["nvim"]
+size = 70
+active = true
+
+[[windows.tabs.panes]]
+direction = "Vertical"
+panes = [{ args = ["cargo", "watch"] }, { cwd = "docs" }]
+
+[[windows.tabs]]
+title = "shell"
+"#;
+
+    #[test]
+    fn parse_toml() {
+        let mut layout = Layout::parse(SAMPLE, "toml").unwrap();
+        layout.resolve_paths(Path::new("/"));
+
+        let window = &layout.windows[0];
+        assert_eq!(window.workspace.as_deref(), Some("project"));
+        assert_eq!(window.tabs.len(), 2);
+
+        let root = &window.tabs[0].root;
+        assert_eq!(root.direction, Some(SplitDirection::Horizontal));
+        assert_eq!(root.child_weights(), vec![70, 30]);
+        assert_eq!(root.panes[0].args, Some(vec!["nvim".to_string()]));
+        assert!(root.panes[0].active);
+
+        let inner = &root.panes[1];
+        assert_eq!(inner.child_weights(), vec![50, 50]);
+        assert_eq!(
+            inner.panes[1].cwd.as_deref().map(PathBuf::from),
+            Some(PathBuf::from("/src/project/docs"))
+        );
+
+        assert!(window.tabs[1].root.is_leaf());
+        assert_eq!(window.tabs[1].title.as_deref(), Some("shell"));
+    }
+
+    #[test]
+    fn first_leaf_inherits() {
+        let layout = Layout::parse(SAMPLE, "toml").unwrap();
+        let window = &layout.windows[0];
+        let inherited = Inherited {
+            cwd: window.cwd.clone(),
+            ..Default::default()
+        };
+        let (leaf, leaf_inherited) = window.tabs[0].root.panes[1].first_leaf(&inherited);
+        assert_eq!(
+            leaf.args,
+            Some(vec!["cargo".to_string(), "watch".to_string()])
+        );
+        assert_eq!(leaf_inherited.cwd.as_deref(), Some("/src/project"));
+    }
+
+    #[test]
+    fn round_trip_formats() {
+        let layout = Layout::parse(SAMPLE, "toml").unwrap();
+        for format in ["toml", "yaml", "json"] {
+            let text = layout.to_format_string(format).unwrap();
+            assert_eq!(Layout::parse(&text, format).unwrap(), layout, "{format}");
+        }
+    }
+
+    #[test]
+    fn split_percentages() {
+        // Three equal panes: the first split gives 2/3 to the rest,
+        // the second gives half of that to the last pane
+        assert_eq!(split_percent(66, 99), 67);
+        assert_eq!(split_percent(33, 66), 50);
+        assert_eq!(split_percent(30, 100), 30);
+
+        // Splits restored from the cell sizes of their halves,
+        // where the divider occupies a cell: an even split of 80
+        // columns, and a 2/3 + 1/3 split of 24 rows
+        assert_eq!(split_percent(39, 40 + 39 + 1), 49);
+        assert_eq!(split_percent(8, 15 + 8 + 1), 33);
+        // Degenerate sizes stay within the range accepted by split_pane
+        assert_eq!(split_percent(0, 101), 1);
+        assert_eq!(split_percent(100, 101), 99);
+        assert_eq!(split_percent(0, 0), 1);
+    }
+}
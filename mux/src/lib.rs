@@ -1,12 +1,12 @@
 use crate::client::{ClientId, ClientInfo};
 use crate::pane::{CachePolicy, Pane, PaneId};
 use crate::ssh_agent::AgentProxy;
-use crate::tab::{SplitRequest, Tab, TabId};
+use crate::tab::{FloatingPaneOp, SplitRequest, Tab, TabId};
 use crate::window::{Window, WindowId};
 use anyhow::{anyhow, Context, Error};
 use config::keyassignment::SpawnTabDomain;
-use config::{configuration, ExitBehavior, GuiPosition};
-use domain::{Domain, DomainId, DomainState, SplitSource};
+use config::{configuration, ExitBehavior, GuiPosition, MuxWindowSizePolicy};
+use domain::{Domain, DomainId, DomainState, PortForward, SplitSource};
 use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
 #[cfg(unix)]
 use libc::{c_int, SOL_SOCKET, SO_RCVBUF, SO_SNDBUF};
@@ -26,10 +26,10 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 use std::thread;
 use std::time::{Duration, Instant};
-use termwiz::escape::csi::{DecPrivateMode, DecPrivateModeCode, Device, Mode};
+use termwiz::escape::csi::{ColorScheme, DecPrivateMode, DecPrivateModeCode, Device, Mode};
 use termwiz::escape::{Action, CSI};
 use thiserror::*;
-use wezterm_term::{Clipboard, ClipboardSelection, DownloadHandler, TerminalSize};
+use wezterm_term::{Clipboard, ClipboardItem, ClipboardSelection, DownloadHandler, TerminalSize};
 #[cfg(windows)]
 use winapi::um::winsock2::{SOL_SOCKET, SO_RCVBUF, SO_SNDBUF};
 
@@ -37,8 +37,10 @@ pub mod activity;
 pub mod client;
 pub mod connui;
 pub mod domain;
+pub mod layout;
 pub mod localpane;
 pub mod pane;
+pub mod pane_log;
 pub mod renderable;
 pub mod ssh;
 pub mod ssh_agent;
@@ -73,10 +75,27 @@ pub enum MuxNotification {
         selection: ClipboardSelection,
         clipboard: Option<String>,
     },
+    AssignClipboardData {
+        pane_id: PaneId,
+        selection: ClipboardSelection,
+        items: Vec<ClipboardItem>,
+    },
     SaveToDownloads {
         name: Option<String>,
         data: Arc<Vec<u8>>,
     },
+    /// Like SaveToDownloads, but for data that has already been
+    /// streamed to the local temporary file `path`, which is moved
+    /// into the download directory, or removed if it isn't wanted
+    SaveFileToDownloads {
+        name: Option<String>,
+        path: std::path::PathBuf,
+    },
+    /// Writing the log of a pane failed, so it is no longer logged
+    PaneLoggingStopped {
+        pane_id: PaneId,
+        reason: String,
+    },
     TabAddedToWindow {
         tab_id: TabId,
         window_id: WindowId,
@@ -113,6 +132,7 @@ pub struct Mux {
     num_panes_by_workspace: RwLock<HashMap<String, usize>>,
     main_thread_id: std::thread::ThreadId,
     agent: Option<AgentProxy>,
+    color_scheme: RwLock<Option<ColorScheme>>,
 }
 
 const BUFSIZE: usize = 1024 * 1024;
@@ -448,6 +468,7 @@ impl Mux {
             num_panes_by_workspace: RwLock::new(HashMap::new()),
             main_thread_id: std::thread::current().id(),
             agent,
+            color_scheme: RwLock::new(None),
         }
     }
 
@@ -570,6 +591,18 @@ impl Mux {
             .insert((*client_id).clone(), ClientInfo::new(client_id));
     }
 
+    pub fn set_client_read_only(&self, client_id: &ClientId) {
+        if let Some(info) = self.clients.write().get_mut(client_id) {
+            info.read_only = true;
+        }
+    }
+
+    pub fn set_client_identity(&self, client_id: &ClientId, identity: String) {
+        if let Some(info) = self.clients.write().get_mut(client_id) {
+            info.identity.replace(identity);
+        }
+    }
+
     pub fn iter_clients(&self) -> Vec<ClientInfo> {
         self.clients
             .read()
@@ -579,7 +612,8 @@ impl Mux {
     }
 
     /// Returns a list of the unique workspace names known to the mux.
-    /// This is taken from all known windows.
+    /// This is taken from all known windows, as well as the workspaces
+    /// that domains can populate on demand.
     pub fn iter_workspaces(&self) -> Vec<String> {
         let mut names: Vec<String> = self
             .windows
@@ -587,11 +621,34 @@ impl Mux {
             .values()
             .map(|w| w.get_workspace().to_string())
             .collect();
+        for domain in self.iter_domains() {
+            names.extend(domain.workspaces());
+        }
         names.sort();
         names.dedup();
         names
     }
 
+    /// Gives the domains a chance to populate `workspace`, which has
+    /// no windows.  The domain that provides a workspace of that name
+    /// is asked first, followed by `preferred_domain`, which may choose
+    /// to create it.  Returns true if a domain is taking care of it.
+    pub fn activate_domain_workspace(
+        &self,
+        workspace: &str,
+        preferred_domain: Option<&Arc<dyn Domain>>,
+    ) -> anyhow::Result<bool> {
+        for domain in self.iter_domains() {
+            if domain.workspaces().iter().any(|w| w == workspace) {
+                return domain.activate_workspace(workspace);
+            }
+        }
+        match preferred_domain {
+            Some(domain) => domain.activate_workspace(workspace),
+            None => Ok(false),
+        }
+    }
+
     /// Generate a new unique workspace name
     pub fn generate_workspace_name(&self) -> String {
         let used = self.iter_workspaces();
@@ -686,7 +743,100 @@ impl Mux {
     }
 
     pub fn unregister_client(&self, client_id: &ClientId) {
-        self.clients.write().remove(client_id);
+        let info = self.clients.write().remove(client_id);
+        // The remaining clients may now call for a different size
+        if info.is_some() {
+            self.apply_window_size_policy();
+        }
+    }
+
+    /// Resizes a pane on behalf of a client.  The size that is actually
+    /// applied takes into account the sizes requested by the other
+    /// clients that are viewing the tab containing the pane, according
+    /// to the `mux_window_size_policy` configuration.
+    pub fn resize_pane_for_client(
+        &self,
+        client_id: Option<&ClientId>,
+        pane_id: PaneId,
+        size: TerminalSize,
+    ) -> anyhow::Result<()> {
+        let mut client_sizes = None;
+        if let Some(client_id) = client_id {
+            if let Some(info) = self.clients.write().get_mut(client_id) {
+                info.pane_sizes.insert(pane_id, size);
+                client_sizes = Some(info.pane_sizes.clone());
+            }
+        }
+
+        let tab = match self.resolve_pane_id(pane_id) {
+            Some((_domain_id, _window_id, tab_id)) => self.get_tab(tab_id),
+            None => None,
+        };
+        let (tab, resolved) = match tab {
+            Some(tab) => match self.resolve_tab_size(&tab) {
+                Some(resolved) => (tab, resolved),
+                None => return self.apply_pane_size(pane_id, size),
+            },
+            None => return self.apply_pane_size(pane_id, size),
+        };
+
+        // When this client is the one that determines the size of the
+        // tab, its layout of the panes is adopted as-is, so that moving
+        // a split in that client takes effect.  Otherwise the tab is
+        // sized for the other clients.
+        let client_tab_size = client_sizes.and_then(|sizes| tab.size_for_pane_sizes(&sizes));
+        if client_tab_size.map_or(false, |client| same_cells(&client, &resolved)) {
+            return self.apply_pane_size(pane_id, size);
+        }
+        if !same_cells(&tab.get_size(), &resolved) {
+            tab.resize(resolved);
+        }
+        Ok(())
+    }
+
+    /// Resizes each tab to the size that `mux_window_size_policy`
+    /// calls for, given the sizes that the attached clients have
+    /// requested.  This is used when a client detaches and when the
+    /// configuration is reloaded.
+    pub fn apply_window_size_policy(&self) {
+        let tabs: Vec<Arc<Tab>> = self.tabs.read().values().cloned().collect();
+        for tab in tabs {
+            if let Some(size) = self.resolve_tab_size(&tab) {
+                if !same_cells(&tab.get_size(), &size) {
+                    tab.resize(size);
+                }
+            }
+        }
+    }
+
+    /// Combines the sizes that the clients have requested for a tab.
+    /// Returns None when the most recent request should simply win.
+    fn resolve_tab_size(&self, tab: &Tab) -> Option<TerminalSize> {
+        let pane_sizes: Vec<HashMap<PaneId, TerminalSize>> = self
+            .clients
+            .read()
+            .values()
+            .map(|info| info.pane_sizes.clone())
+            .collect();
+        resolve_window_size(
+            configuration().mux_window_size_policy,
+            pane_sizes
+                .iter()
+                .filter_map(|sizes| tab.size_for_pane_sizes(sizes)),
+        )
+    }
+
+    fn apply_pane_size(&self, pane_id: PaneId, size: TerminalSize) -> anyhow::Result<()> {
+        let pane = self
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+        pane.resize(size)?;
+        if let Some((_domain_id, _window_id, tab_id)) = self.resolve_pane_id(pane_id) {
+            if let Some(tab) = self.get_tab(tab_id) {
+                tab.rebuild_splits_sizes_from_contained_panes();
+            }
+        }
+        Ok(())
     }
 
     pub fn subscribe<F>(&self, subscriber: F)
@@ -784,6 +934,10 @@ impl Mux {
         let downloader: Arc<dyn DownloadHandler> = Arc::new(MuxDownloader {});
         pane.set_download_handler(&downloader);
 
+        if let Some(scheme) = *self.color_scheme.read() {
+            pane.set_color_scheme(scheme);
+        }
+
         self.panes.write().insert(pane.pane_id(), Arc::clone(pane));
         let pane_id = pane.pane_id();
         if let Some(reader) = pane.reader()? {
@@ -818,6 +972,9 @@ impl Mux {
             self.notify(MuxNotification::PaneRemoved(pane_id));
             changed = true;
         }
+        for info in self.clients.write().values_mut() {
+            info.pane_sizes.remove(&pane_id);
+        }
 
         if changed {
             self.recompute_pane_count();
@@ -836,7 +993,11 @@ impl Mux {
         }
 
         let mut pane_ids = vec![];
-        for pos in tab.iter_panes_ignoring_zoom() {
+        for pos in tab
+            .iter_panes_ignoring_zoom()
+            .into_iter()
+            .chain(tab.iter_floating_panes())
+        {
             pane_ids.push(pos.pane.pane_id());
         }
         log::debug!("panes to remove: {pane_ids:?}");
@@ -1031,6 +1192,17 @@ impl Mux {
         self.is_workspace_empty(&workspace)
     }
 
+    /// Advise all panes of a change in the preferred color scheme.
+    /// Panes that are added later will also be advised of it.
+    pub fn set_color_scheme(&self, scheme: ColorScheme) {
+        if self.color_scheme.write().replace(scheme) == Some(scheme) {
+            return;
+        }
+        for pane in self.iter_panes() {
+            pane.set_color_scheme(scheme);
+        }
+    }
+
     pub fn iter_panes(&self) -> Vec<Arc<dyn Pane>> {
         self.panes
             .read()
@@ -1068,7 +1240,11 @@ impl Mux {
     pub fn resolve_pane_id(&self, pane_id: PaneId) -> Option<(DomainId, WindowId, TabId)> {
         let mut ids = None;
         for tab in self.tabs.read().values() {
-            for p in tab.iter_panes_ignoring_zoom() {
+            for p in tab
+                .iter_panes_ignoring_zoom()
+                .into_iter()
+                .chain(tab.iter_floating_panes())
+            {
                 if p.pane.pane_id() == pane_id {
                     ids = Some((tab.tab_id(), p.pane.domain_id()));
                     break;
@@ -1242,6 +1418,115 @@ impl Mux {
         Ok((pane, size))
     }
 
+    /// Spawns a new pane that floats above the split tree of
+    /// the tab that contains `pane_id`
+    pub async fn spawn_floating_pane(
+        &self,
+        pane_id: PaneId,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        domain: config::keyassignment::SpawnTabDomain,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let (_pane_domain_id, window_id, tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane_id {} invalid", pane_id))?;
+
+        let domain = self
+            .resolve_spawn_tab_domain(Some(pane_id), &domain)
+            .context("resolve_spawn_tab_domain")?;
+
+        if domain.state() == DomainState::Detached {
+            domain.attach(Some(window_id)).await?;
+        }
+
+        let current_pane = self
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane_id {} is invalid", pane_id))?;
+        let term_config = current_pane.get_config();
+
+        let command_dir = self.resolve_cwd(
+            command_dir,
+            Some(Arc::clone(&current_pane)),
+            domain.domain_id(),
+            CachePolicy::FetchImmediate,
+        );
+
+        let pane = domain
+            .spawn_floating_pane(tab_id, command, command_dir)
+            .await?;
+        if let Some(config) = term_config {
+            pane.set_config(config);
+        }
+
+        Ok(pane)
+    }
+
+    /// Applies `op` to the top-most floating pane of `tab_id`
+    pub async fn apply_floating_pane_op(
+        &self,
+        tab_id: TabId,
+        op: FloatingPaneOp,
+    ) -> anyhow::Result<()> {
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} invalid", tab_id))?;
+        let pane = tab
+            .iter_floating_panes()
+            .pop()
+            .ok_or_else(|| anyhow!("tab {} has no floating panes", tab_id))?
+            .pane;
+        let domain = self
+            .get_domain(pane.domain_id())
+            .ok_or_else(|| anyhow!("domain of pane {} not found", pane.pane_id()))?;
+        domain.apply_floating_pane_op(tab_id, op).await
+    }
+
+    /// Returns the port forwards of all of the domains
+    pub async fn list_port_forwards(&self) -> Vec<PortForward> {
+        let mut forwards = vec![];
+        for domain in self.iter_domains() {
+            match domain.list_port_forwards().await {
+                Ok(mut list) => forwards.append(&mut list),
+                Err(err) => log::error!(
+                    "listing port forwards of domain {}: {:#}",
+                    domain.domain_name(),
+                    err
+                ),
+            }
+        }
+        forwards
+    }
+
+    /// Enables or disables a port forward of the named domain.
+    /// If `enabled` is None, the forward is toggled.
+    /// Returns the new state of the forward.
+    pub async fn set_port_forward_enabled(
+        &self,
+        domain_name: &str,
+        forward_id: usize,
+        enabled: Option<bool>,
+    ) -> anyhow::Result<bool> {
+        let domain = self
+            .get_domain_by_name(domain_name)
+            .ok_or_else(|| anyhow!("domain {} not found", domain_name))?;
+        let enabled = match enabled {
+            Some(enabled) => enabled,
+            None => {
+                let forward = domain
+                    .list_port_forwards()
+                    .await?
+                    .into_iter()
+                    .find(|forward| forward.forward_id == forward_id)
+                    .ok_or_else(|| {
+                        anyhow!("domain {} has no port forward {}", domain_name, forward_id)
+                    })?;
+                !forward.enabled
+            }
+        };
+        domain.set_port_forward_enabled(forward_id, enabled).await?;
+        Ok(enabled)
+    }
+
     pub async fn move_pane_to_new_tab(
         &self,
         pane_id: PaneId,
@@ -1447,6 +1732,45 @@ impl Clipboard for MuxClipboard {
         });
         Ok(())
     }
+
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        items: Vec<ClipboardItem>,
+    ) -> anyhow::Result<()> {
+        let mux =
+            Mux::try_get().ok_or_else(|| anyhow::anyhow!("MuxClipboard::set_data: no Mux?"))?;
+        mux.notify(MuxNotification::AssignClipboardData {
+            pane_id: self.pane_id,
+            selection,
+            items,
+        });
+        Ok(())
+    }
+}
+
+fn same_cells(a: &TerminalSize, b: &TerminalSize) -> bool {
+    a.rows == b.rows && a.cols == b.cols
+}
+
+/// Combines the tab sizes requested by the attached clients according
+/// to `policy`.  Returns None for `Latest`, or when there are no sizes.
+fn resolve_window_size(
+    policy: MuxWindowSizePolicy,
+    sizes: impl Iterator<Item = TerminalSize>,
+) -> Option<TerminalSize> {
+    let combine: fn(usize, usize) -> usize = match policy {
+        MuxWindowSizePolicy::Latest => return None,
+        MuxWindowSizePolicy::Smallest => std::cmp::min,
+        MuxWindowSizePolicy::Largest => std::cmp::max,
+    };
+    sizes.reduce(|a, b| TerminalSize {
+        rows: combine(a.rows, b.rows),
+        cols: combine(a.cols, b.cols),
+        pixel_width: combine(a.pixel_width, b.pixel_width),
+        pixel_height: combine(a.pixel_height, b.pixel_height),
+        dpi: a.dpi.max(b.dpi),
+    })
 }
 
 struct MuxDownloader {}
@@ -1461,3 +1785,56 @@ impl wezterm_term::DownloadHandler for MuxDownloader {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn size(rows: usize, cols: usize, dpi: u32) -> TerminalSize {
+        TerminalSize {
+            rows,
+            cols,
+            pixel_width: cols * 10,
+            pixel_height: rows * 20,
+            dpi,
+        }
+    }
+
+    #[test]
+    fn window_size_policy() {
+        let sizes = [size(24, 80, 96), size(50, 60, 144)];
+
+        assert_eq!(
+            resolve_window_size(MuxWindowSizePolicy::Latest, sizes.iter().copied()),
+            None
+        );
+        assert_eq!(
+            resolve_window_size(MuxWindowSizePolicy::Smallest, sizes.iter().copied()),
+            Some(TerminalSize {
+                rows: 24,
+                cols: 60,
+                pixel_width: 600,
+                pixel_height: 480,
+                dpi: 144,
+            })
+        );
+        assert_eq!(
+            resolve_window_size(MuxWindowSizePolicy::Largest, sizes.iter().copied()),
+            Some(TerminalSize {
+                rows: 50,
+                cols: 80,
+                pixel_width: 800,
+                pixel_height: 1000,
+                dpi: 144,
+            })
+        );
+        assert_eq!(
+            resolve_window_size(MuxWindowSizePolicy::Smallest, std::iter::empty()),
+            None
+        );
+        assert_eq!(
+            resolve_window_size(MuxWindowSizePolicy::Largest, sizes[..1].iter().copied()),
+            Some(sizes[0])
+        );
+    }
+}
@@ -3,12 +3,13 @@ use crate::pane::{
     CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId, Pattern,
     SearchResult, WithPaneLines,
 };
+use crate::pane_log::{LoggingReader, PaneLogSlot, PaneLogger};
 use crate::renderable::*;
 use crate::tmux::{TmuxDomain, TmuxDomainState};
 use crate::{Domain, Mux, MuxNotification};
 use anyhow::Error;
 use async_trait::async_trait;
-use config::keyassignment::ScrollbackEraseMode;
+use config::keyassignment::{PaneLogTarget, ScrollbackEraseMode};
 use config::{configuration, ExitBehavior, ExitBehaviorMessaging};
 use fancy_regex::Regex;
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
@@ -23,7 +24,8 @@ use std::io::{Result as IoResult, Write};
 use std::ops::Range;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use termwiz::escape::csi::{Sgr, CSI};
+use termwiz::escape::csi::{ColorScheme, Sgr, CSI};
+use termwiz::escape::osc::PointerShape;
 use termwiz::escape::{Action, DeviceControlMode};
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo};
@@ -133,6 +135,7 @@ pub struct LocalPane {
     #[cfg(unix)]
     leader: Arc<Mutex<Option<CachedLeaderInfo>>>,
     command_description: String,
+    output_log: PaneLogSlot,
 }
 
 #[async_trait(?Send)]
@@ -224,9 +227,9 @@ impl Pane for LocalPane {
     }
 
     fn exit_behavior(&self) -> Option<ExitBehavior> {
-        // If we are ssh, and we've not yet fully connected,
-        // then override exit_behavior so that we can show
-        // connection issues
+        // If we are ssh, and we've not yet fully connected or
+        // are reconnecting, then override exit_behavior so that
+        // we can show connection issues
         let mut pty = self.pty.lock();
         let is_ssh_connecting = pty
             .downcast_mut::<crate::ssh::WrappedSshPty>()
@@ -434,7 +437,10 @@ impl Pane for LocalPane {
     }
 
     fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>> {
-        Ok(Some(self.pty.lock().try_clone_reader()?))
+        Ok(Some(Box::new(LoggingReader {
+            inner: self.pty.lock().try_clone_reader()?,
+            logger: Arc::clone(&self.output_log),
+        })))
     }
 
     fn send_paste(&self, text: &str) -> Result<(), Error> {
@@ -489,6 +495,21 @@ impl Pane for LocalPane {
         self.terminal.lock().focus_changed(focused);
     }
 
+    fn set_color_scheme(&self, scheme: ColorScheme) {
+        self.terminal.lock().set_color_scheme(scheme);
+    }
+
+    fn start_logging(&self, target: PaneLogTarget) -> anyhow::Result<()> {
+        let logger = PaneLogger::new(self.pane_id, &target)?;
+        self.output_log.lock().replace(logger);
+        Ok(())
+    }
+
+    fn stop_logging(&self) -> anyhow::Result<()> {
+        self.output_log.lock().take();
+        Ok(())
+    }
+
     fn has_unseen_output(&self) -> bool {
         self.terminal.lock().has_unseen_output()
     }
@@ -501,6 +522,14 @@ impl Pane for LocalPane {
         }
     }
 
+    fn get_pointer_shape(&self) -> Option<PointerShape> {
+        if self.tmux_domain.lock().is_some() {
+            None
+        } else {
+            self.terminal.lock().pointer_shape()
+        }
+    }
+
     fn is_alt_screen_active(&self) -> bool {
         if self.tmux_domain.lock().is_some() {
             false
@@ -1019,6 +1048,7 @@ impl LocalPane {
             #[cfg(unix)]
             leader: Arc::new(Mutex::new(None)),
             command_description,
+            output_log: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -2,7 +2,7 @@ use crate::domain::DomainId;
 use crate::renderable::*;
 use crate::ExitBehavior;
 use async_trait::async_trait;
-use config::keyassignment::{KeyAssignment, ScrollbackEraseMode};
+use config::keyassignment::{KeyAssignment, PaneLogTarget, ScrollbackEraseMode};
 use downcast_rs::{impl_downcast, Downcast};
 use parking_lot::MappedMutexGuard;
 use rangeset::RangeSet;
@@ -10,6 +10,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ops::Range;
 use std::sync::Arc;
+use termwiz::escape::csi::ColorScheme;
+use termwiz::escape::osc::PointerShape;
 use termwiz::hyperlink::Rule;
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo};
@@ -267,6 +269,20 @@ pub trait Pane: Downcast + Send + Sync {
     /// Called to advise on whether this tab has focus
     fn focus_changed(&self, _focused: bool) {}
 
+    /// Called to advise of a change in the preferred color scheme
+    fn set_color_scheme(&self, _scheme: ColorScheme) {}
+
+    /// Starts copying the output of the pane to `target`, replacing
+    /// any log that was already active
+    fn start_logging(&self, _target: PaneLogTarget) -> anyhow::Result<()> {
+        anyhow::bail!("this pane does not support output logging")
+    }
+
+    /// Stops copying the output of the pane to its log
+    fn stop_logging(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Called to advise remote mux that this is the active tab
     /// for the current identity
     fn advise_focus(&self) {}
@@ -311,6 +327,12 @@ pub trait Pane: Downcast + Send + Sync {
     fn is_mouse_grabbed(&self) -> bool;
     fn is_alt_screen_active(&self) -> bool;
 
+    /// Returns the mouse pointer shape requested by the application,
+    /// or None if the gui should pick its usual pointer.
+    fn get_pointer_shape(&self) -> Option<PointerShape> {
+        None
+    }
+
     fn set_clipboard(&self, _clipboard: &Arc<dyn Clipboard>) {}
     fn set_download_handler(&self, _handler: &Arc<dyn DownloadHandler>) {}
     fn set_config(&self, _config: Arc<dyn TerminalConfiguration>) {}
@@ -0,0 +1,283 @@
+//! Copies the output of a pane to a file or to the stdin of a
+//! command, similar to the `pipe-pane` command in tmux.
+use crate::pane::PaneId;
+use crate::{Mux, MuxNotification};
+use anyhow::Context;
+use config::keyassignment::{PaneLogMode, PaneLogTarget};
+use parking_lot::Mutex;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use strip_ansi_escapes::StripAnsiEscapes;
+use wezterm_term::{Alert, NotificationOccasion, NotificationUrgency};
+
+/// How many chunks of output may be waiting to be written to the
+/// log before the pane has to wait for it
+const MAX_PENDING_WRITES: usize = 1024;
+
+pub struct PaneLogger {
+    sender: Option<SyncSender<Vec<u8>>>,
+    writer: Option<JoinHandle<()>>,
+    stripper: Option<StripAnsiEscapes>,
+    text: String,
+}
+
+/// Expands a leading `~`; relative paths are relative to the
+/// home directory as the mux may not share the cwd of the user
+fn resolve_log_path(path: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => config::HOME_DIR.join(rest),
+        Err(_) => config::HOME_DIR.join(path),
+    }
+}
+
+impl PaneLogger {
+    pub fn new(pane_id: PaneId, target: &PaneLogTarget) -> anyhow::Result<Self> {
+        let (sink, child): (Box<dyn Write + Send>, Option<Child>) =
+            match (&target.path, &target.command) {
+                (Some(path), None) => {
+                    let path = resolve_log_path(path);
+                    // The log may capture passwords and other secrets
+                    // that were shown in the pane, so only the owner
+                    // may read it
+                    let mut options = std::fs::OpenOptions::new();
+                    options.create(true).append(true);
+                    #[cfg(unix)]
+                    {
+                        use std::os::unix::fs::OpenOptionsExt;
+                        options.mode(0o600);
+                    }
+                    let file = options
+                        .open(&path)
+                        .with_context(|| format!("opening {} for logging", path.display()))?;
+                    (Box::new(file), None)
+                }
+                (None, Some(argv)) => {
+                    let (prog, args) = argv
+                        .split_first()
+                        .ok_or_else(|| anyhow::anyhow!("the logging command is empty"))?;
+                    let mut child = Command::new(prog)
+                        .args(args)
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .spawn()
+                        .with_context(|| format!("spawning logging command {argv:?}"))?;
+                    let stdin = child
+                        .stdin
+                        .take()
+                        .ok_or_else(|| anyhow::anyhow!("logging command has no stdin"))?;
+                    (Box::new(stdin), Some(child))
+                }
+                _ => anyhow::bail!("exactly one of `path` or `command` must be specified"),
+            };
+
+        // Writing to the sink may block, for example when the logging
+        // command is slow to read its input, so it is done on a separate
+        // thread to avoid stalling the pane.
+        let (sender, receiver) = sync_channel(MAX_PENDING_WRITES);
+        let writer = std::thread::Builder::new()
+            .name(format!("pane-log-{pane_id}"))
+            .spawn(move || write_log(pane_id, sink, child, receiver))
+            .context("spawning pane logging thread")?;
+
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            stripper: match target.mode {
+                PaneLogMode::Raw => None,
+                PaneLogMode::Text => Some(StripAnsiEscapes::new()),
+            },
+            text: String::new(),
+        })
+    }
+
+    /// Prepares data to be written to the log, returning it along with
+    /// the channel that it should be sent on, or None if there is
+    /// nothing to write
+    fn prepare(&mut self, data: &[u8]) -> Option<(SyncSender<Vec<u8>>, Vec<u8>)> {
+        let data = match self.stripper.as_mut() {
+            Some(stripper) => {
+                self.text.clear();
+                stripper.strip(data, &mut self.text);
+                if self.text.is_empty() {
+                    return None;
+                }
+                self.text.as_bytes().to_vec()
+            }
+            None => data.to_vec(),
+        };
+        let sender = self.sender.as_ref()?.clone();
+        Some((sender, data))
+    }
+
+    /// Returns true if the logging thread has stopped, which
+    /// only happens by itself if writing to the log failed
+    fn has_stopped(&self) -> bool {
+        self.writer
+            .as_ref()
+            .map_or(true, |writer| writer.is_finished())
+    }
+
+    /// Stops logging and waits for the pending output to be written
+    #[cfg(test)]
+    fn close(mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            writer.join().ok();
+        }
+    }
+}
+
+/// Runs on the logging thread, writing the data queued by the
+/// PaneLogger until it is dropped or a write fails
+fn write_log(
+    pane_id: PaneId,
+    mut sink: Box<dyn Write + Send>,
+    child: Option<Child>,
+    receiver: Receiver<Vec<u8>>,
+) {
+    for data in receiver.iter() {
+        if let Err(err) = sink.write_all(&data) {
+            log::error!("stopped logging output of pane {pane_id} because of error: {err:#}");
+            report_logging_stopped(pane_id, format!("{err:#}"));
+            break;
+        }
+    }
+    sink.flush().ok();
+    // Closing the stdin of the command is its cue to finish up
+    drop(sink);
+    if let Some(mut child) = child {
+        child.wait().ok();
+    }
+}
+
+/// Tells the user that the output of the pane is no longer being
+/// logged, and lets the mux server record it in its audit log
+fn report_logging_stopped(pane_id: PaneId, reason: String) {
+    Mux::notify_from_any_thread(MuxNotification::Alert {
+        pane_id,
+        alert: Alert::ToastNotification {
+            title: Some("Pane logging stopped".to_string()),
+            body: format!("The output of pane {pane_id} is no longer being logged: {reason}"),
+            focus: true,
+            id: None,
+            urgency: NotificationUrgency::Critical,
+            occasion: NotificationOccasion::Always,
+            report_activation: false,
+            report_close: false,
+            timeout: None,
+        },
+    });
+    Mux::notify_from_any_thread(MuxNotification::PaneLoggingStopped { pane_id, reason });
+}
+
+impl Drop for PaneLogger {
+    fn drop(&mut self) {
+        // Dropping the sender lets the logging thread finish
+        // writing what is queued, then close the sink
+        self.sender.take();
+        self.writer.take();
+    }
+}
+
+/// The logger for a pane, shared between the pane and its reader
+pub type PaneLogSlot = Arc<Mutex<Option<PaneLogger>>>;
+
+/// Wraps the reader of a pane's pty so that the data read from it
+/// is also written to the pane's log, if any
+pub(crate) struct LoggingReader {
+    pub inner: Box<dyn Read + Send>,
+    pub logger: PaneLogSlot,
+}
+
+impl Read for LoggingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        if size > 0 {
+            let pending = match self.logger.lock().as_mut() {
+                Some(log) => log.prepare(&buf[..size]),
+                None => None,
+            };
+            if let Some((sender, data)) = pending {
+                // Every byte has to make it into the log, so when the log
+                // can't keep up the pane waits for it rather than dropping
+                // output.  The lock isn't held meanwhile, so that logging
+                // can still be stopped.
+                if sender.send(data).is_err() {
+                    // The logging thread has already reported why it stopped
+                    let mut logger = self.logger.lock();
+                    if logger.as_ref().map_or(false, PaneLogger::has_stopped) {
+                        logger.take();
+                    }
+                }
+            }
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read_all(reader: &mut LoggingReader) {
+        let mut buf = [0u8; 4];
+        while reader.read(&mut buf).unwrap() > 0 {}
+    }
+
+    #[test]
+    fn log_to_file() {
+        let dir = std::env::temp_dir().join(format!("wezterm-pane-log-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let raw_path = dir.join("raw.log");
+        let text_path = dir.join("text.log");
+        std::fs::remove_file(&raw_path).ok();
+        std::fs::remove_file(&text_path).ok();
+
+        let output = b"\x1b[31mred\x1b[0m text\r\n";
+        for (path, mode) in [
+            (&raw_path, PaneLogMode::Raw),
+            (&text_path, PaneLogMode::Text),
+        ] {
+            let logger = PaneLogger::new(
+                0,
+                &PaneLogTarget {
+                    path: Some(path.clone()),
+                    command: None,
+                    mode,
+                },
+            )
+            .unwrap();
+            let mut reader = LoggingReader {
+                inner: Box::new(&output[..]),
+                logger: Arc::new(Mutex::new(Some(logger))),
+            };
+            read_all(&mut reader);
+            reader.logger.lock().take().unwrap().close();
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&raw_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(std::fs::read(&raw_path).unwrap(), output);
+        assert_eq!(std::fs::read_to_string(&text_path).unwrap(), "red text\r\n");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn invalid_target() {
+        let target = PaneLogTarget {
+            path: None,
+            command: None,
+            mode: PaneLogMode::Raw,
+        };
+        assert!(PaneLogger::new(0, &target).is_err());
+    }
+}
@@ -1,18 +1,20 @@
 use crate::connui::ConnectionUI;
-use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState, WriterWrapper};
+use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState, PortForward, WriterWrapper};
 use crate::localpane::LocalPane;
-use crate::pane::{alloc_pane_id, Pane, PaneId};
+use crate::pane::{alloc_pane_id, CachePolicy, Pane, PaneId};
 use crate::Mux;
 use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
-use config::{Shell, SshBackend, SshDomain};
+use config::{Shell, SshBackend, SshDomain, SshPortForward, SshPortForwardKind};
 use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
+use percent_encoding::percent_decode_str;
 use portable_pty::cmdbuilder::CommandBuilder;
 use portable_pty::{ChildKiller, ExitStatus, MasterPty, PtySize};
-use smol::channel::{bounded, Receiver as AsyncReceiver};
+use smol::channel::Receiver as AsyncReceiver;
 use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
 use std::io::{BufWriter, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
@@ -23,7 +25,8 @@ use termwiz::render::terminfo::TerminfoRenderer;
 use termwiz::surface::{Change, LineAttribute};
 use termwiz::terminal::{ScreenSize, Terminal, TerminalWaker};
 use wezterm_ssh::{
-    ConfigMap, HostVerificationFailed, Session, SessionEvent, SshChildProcess, SshPty,
+    ConfigMap, ForwardKind, ForwardSpec, ForwardState, HostVerificationFailed, Session,
+    SessionEvent, Sftp, SshChildProcess, SshPty,
 };
 use wezterm_term::TerminalSize;
 
@@ -67,7 +70,7 @@ pub fn ssh_connect_with_ui(
             .get("hostname")
             .expect("ssh config to always set hostname");
         ui.output_str(&format!("Connecting to {} using SSH\n", remote_address));
-        let (session, events) = Session::connect(ssh_config.clone())?;
+        let (session, events) = Session::connect_shared(ssh_config.clone())?;
 
         while let Ok(event) = smol::block_on(events.recv()) {
             match event {
@@ -188,6 +191,15 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     let mut ssh_config = wezterm_ssh::Config::new();
     ssh_config.add_default_config_files();
 
+    let config = config::configuration();
+    ssh_config.set_option(
+        "wezterm_ssh_idle_timeout",
+        config.ssh_idle_timeout_secs.to_string(),
+    );
+    if let Some(control_path) = &config.ssh_control_path {
+        ssh_config.set_option("wezterm_ssh_control_path", control_path);
+    }
+
     let (remote_host_name, port) = {
         let parts: Vec<&str> = ssh_dom.remote_address.split(':').collect();
 
@@ -201,10 +213,7 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     let mut ssh_config = ssh_config.for_host(&remote_host_name);
     ssh_config.insert(
         "wezterm_ssh_backend".to_string(),
-        match ssh_dom
-            .ssh_backend
-            .unwrap_or_else(|| config::configuration().ssh_backend)
-        {
+        match ssh_dom.ssh_backend.unwrap_or_else(|| config.ssh_backend) {
             SshBackend::Ssh2 => "ssh2",
             SshBackend::LibSsh => "libssh",
         }
@@ -213,6 +222,13 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     for (k, v) in &ssh_dom.ssh_option {
         ssh_config.insert(k.to_string(), v.to_string());
     }
+    if !ssh_dom.ssh_option.is_empty() {
+        // Remember which options were overridden so that they
+        // can be applied to any jump hosts as well
+        let mut keys: Vec<&str> = ssh_dom.ssh_option.keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        ssh_config.insert("wezterm_ssh_option_overrides".to_string(), keys.join(" "));
+    }
 
     if let Some(username) = &ssh_dom.username {
         ssh_config.insert("user".to_string(), username.to_string());
@@ -229,6 +245,53 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     Ok(ssh_config)
 }
 
+/// Returns the sftp client for the ssh connection of `pane`,
+/// if it is a pane in an ssh domain
+pub fn sftp_for_pane(pane: &Arc<dyn Pane>) -> Option<Sftp> {
+    let domain = Mux::get().get_domain(pane.domain_id())?;
+    domain.downcast_ref::<RemoteSshDomain>()?.sftp()
+}
+
+/// Returns the directory on the remote host that the shell in
+/// `pane` has most recently reported via OSC 7, if any
+pub fn remote_working_dir(pane: &Arc<dyn Pane>) -> Option<String> {
+    let url = pane.get_current_working_dir(CachePolicy::AllowStale)?;
+    let path = percent_decode_str(url.path()).decode_utf8().ok()?;
+    if path.is_empty() {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+fn port_forward_to_spec(forward: &SshPortForward) -> anyhow::Result<ForwardSpec> {
+    let kind = match forward.kind {
+        SshPortForwardKind::Local => ForwardKind::Local,
+        SshPortForwardKind::Remote => ForwardKind::Remote,
+        SshPortForwardKind::Dynamic => ForwardKind::Dynamic,
+    };
+    let spec = match &forward.target {
+        Some(target) => format!("{} {}", forward.listen, target),
+        None => forward.listen.clone(),
+    };
+    ForwardSpec::parse(kind, &spec)
+}
+
+/// The session doesn't process requests until authentication has
+/// completed, so we don't want to block the launcher or the cli
+/// for the duration of an authentication prompt.
+async fn with_forward_timeout<T>(
+    fut: impl std::future::Future<Output = anyhow::Result<T>>,
+) -> anyhow::Result<T> {
+    smol::future::or(fut, async {
+        smol::Timer::after(Duration::from_secs(2)).await;
+        Err(anyhow!(
+            "timed out waiting for the ssh session; is it still authenticating?"
+        ))
+    })
+    .await
+}
+
 impl RemoteSshDomain {
     pub fn with_ssh_domain(dom: &SshDomain) -> anyhow::Result<Self> {
         let id = alloc_domain_id();
@@ -244,6 +307,64 @@ impl RemoteSshDomain {
         ssh_domain_to_ssh_config(&self.dom)
     }
 
+    /// Returns an sftp client that uses the same connection as the
+    /// panes in this domain, once that connection has been made
+    pub fn sftp(&self) -> Option<Sftp> {
+        self.session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.sftp())
+    }
+
+    /// Returns a session for a new pane, reusing the connection made
+    /// for an earlier pane while that remains open.
+    /// The events from the session must be processed by
+    /// `connect_ssh_session` before it can be used.
+    fn connect(&self) -> anyhow::Result<(Session, AsyncReceiver<SessionEvent>)> {
+        let (session, events) =
+            Session::connect_shared(self.ssh_config().context("obtain ssh config")?)
+                .context("connect to ssh server")?;
+        let previous = self.session.lock().unwrap().replace(session.clone());
+        let is_new_connection = match previous {
+            Some(previous) => previous.is_closed(),
+            None => true,
+        };
+        if is_new_connection {
+            self.add_port_forwards(&session);
+        }
+        Ok((session, events))
+    }
+
+    /// Registers the `port_forwards` from the domain config with
+    /// a newly established session
+    fn add_port_forwards(&self, session: &Session) {
+        let mut forwards = vec![];
+        for forward in &self.dom.port_forwards {
+            match port_forward_to_spec(forward) {
+                Ok(spec) => forwards.push((spec, forward.enabled)),
+                Err(err) => log::error!("ssh domain {}: {:#}", self.name, err),
+            }
+        }
+        if forwards.is_empty() {
+            return;
+        }
+
+        let session = session.clone();
+        let owner = self.name.clone();
+        promise::spawn::spawn_into_main_thread(async move {
+            for (spec, enabled) in forwards {
+                if let Err(err) = session
+                    .add_forward(spec, enabled, Some(owner.clone()))
+                    .await
+                {
+                    log::error!("adding port forward: {:#}", err);
+                }
+            }
+        })
+        .detach();
+    }
+
     fn build_command(
         &self,
         pane_id: PaneId,
@@ -324,22 +445,20 @@ impl RemoteSshDomain {
         Ok((command_line, env))
     }
 
-    async fn start_new_session(
+    fn start_new_session(
         &self,
         command_line: Option<String>,
         env: HashMap<String, String>,
         size: TerminalSize,
     ) -> anyhow::Result<StartNewSessionResult> {
-        let (session, events) = Session::connect(self.ssh_config().context("obtain ssh config")?)
-            .context("connect to ssh server")?;
-        self.session.lock().unwrap().replace(session.clone());
+        let (session, events) = self.connect()?;
 
-        // We get to establish the session!
-        //
         // Since we want spawn to return the Pane in which
         // we'll carry out interactive auth, we generate
         // some shim/wrapper versions of the pty, child
         // and reader/writer.
+        // The same wrappers allow a replacement pty and child
+        // to be swapped in if we have to reconnect later.
 
         let (stdout_read, stdout_write) = socketpair()?;
         let (reader_tx, reader_rx) = channel();
@@ -357,55 +476,54 @@ impl RemoteSshDomain {
         };
         let writer = Box::new(pty_writer);
 
-        let (child_tx, child_rx) = channel();
+        let (status_tx, status_rx) = channel();
+        let killer = WrappedSshChildKiller {
+            inner: Arc::new(Mutex::new(KillerInner {
+                killer: None,
+                pending_kill: false,
+            })),
+        };
 
         let child = Box::new(WrappedSshChild {
-            status: None,
-            rx: child_rx,
+            status: status_rx,
             exited: None,
-            killer: WrappedSshChildKiller {
-                inner: Arc::new(Mutex::new(KillerInner {
-                    killer: None,
-                    pending_kill: false,
-                })),
-            },
+            killer: killer.clone(),
         });
 
         let (pty_tx, pty_rx) = channel();
 
         let size = Arc::new(Mutex::new(size));
+        let disconnected = Arc::new(AtomicBool::new(false));
 
         let pty = Box::new(WrappedSshPty {
-            inner: RefCell::new(WrappedSshPtyInner::Connecting {
+            inner: RefCell::new(WrappedSshPtyInner {
                 size: Arc::clone(&size),
                 reader: Some(pty_reader),
                 connected: pty_rx,
+                pty: None,
             }),
+            disconnected: Arc::clone(&disconnected),
         });
 
         // And with those created, we can now spawn a new thread
         // to perform the blocking (from its perspective) terminal
-        // UI to carry out any authentication.
-        let mut stdout_write = BufWriter::new(stdout_write);
-        std::thread::spawn(move || {
-            if let Err(err) = connect_ssh_session(
-                session,
-                events,
-                stdin_read,
-                writer_tx,
-                &mut stdout_write,
-                reader_tx,
-                child_tx,
-                pty_tx,
-                size,
-                command_line,
-                env,
-            ) {
-                let _ = write!(stdout_write, "{:#}", err);
-                log::error!("Failed to connect ssh: {:#}", err);
-            }
-            let _ = stdout_write.flush();
-        });
+        // UI to carry out any authentication, and to look after
+        // the connection from then on.
+        let connection = SshPaneConnection {
+            domain_id: self.id,
+            remote_address: self.dom.remote_address.clone(),
+            auto_reconnect: self.dom.auto_reconnect.unwrap_or(true),
+            stdin_tx: writer_tx,
+            stdout_tx: reader_tx,
+            pty_tx,
+            status_tx,
+            killer,
+            disconnected,
+            size,
+            command_line,
+            env,
+        };
+        std::thread::spawn(move || connection.run(session, events, stdin_read, stdout_write));
 
         Ok(StartNewSessionResult { pty, child, writer })
     }
@@ -417,20 +535,21 @@ struct StartNewSessionResult {
     writer: BoxedWriter,
 }
 
-/// Carry out the authentication process and create the initial pty.
+/// Carry out the authentication process and create the pty.
+/// Returns None if the connection could not be made or the command
+/// could not be started; the reason has been shown in the pane.
 fn connect_ssh_session(
-    session: Session,
-    events: smol::channel::Receiver<SessionEvent>,
-    mut stdin_read: FileDescriptor,
-    stdin_tx: Sender<BoxedWriter>,
+    session: &Session,
+    events: AsyncReceiver<SessionEvent>,
+    stdin_read: &mut FileDescriptor,
+    stdin_tx: &Sender<BoxedWriter>,
     stdout_write: &mut BufWriter<FileDescriptor>,
-    stdout_tx: Sender<BoxedReader>,
-    child_tx: Sender<SshChildProcess>,
-    pty_tx: Sender<SshPty>,
-    size: Arc<Mutex<TerminalSize>>,
-    command_line: Option<String>,
+    stdout_tx: &Sender<BoxedReader>,
+    pty_tx: &Sender<SshPty>,
+    size: &Arc<Mutex<TerminalSize>>,
+    command_line: Option<&str>,
     env: HashMap<String, String>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<SshChildProcess>> {
     struct StdoutShim<'a> {
         size: Arc<Mutex<TerminalSize>>,
         stdout: &'a mut BufWriter<FileDescriptor>,
@@ -572,11 +691,11 @@ fn connect_ssh_session(
     let mut shim = TerminalShim {
         stdout: &mut StdoutShim {
             stdout: stdout_write,
-            size: Arc::clone(&size),
+            size: Arc::clone(size),
         },
-        size: Arc::clone(&size),
+        size: Arc::clone(size),
         renderer,
-        stdin: &mut stdin_read,
+        stdin: stdin_read,
         parser: InputParser::new(),
         input_queue: VecDeque::new(),
     };
@@ -652,7 +771,7 @@ fn connect_ssh_session(
                 match smol::block_on(session.request_pty(
                     &config::configuration().term,
                     crate::terminal_size_to_pty_size(*size.lock().unwrap())?,
-                    command_line.as_ref().map(|s| s.as_str()),
+                    command_line,
                     Some(env),
                 )) {
                     Err(err) => {
@@ -674,27 +793,267 @@ fn connect_ssh_session(
                             .send(Box::new(reader))
                             .map_err(|e| anyhow!("{:#}", e))?;
 
-                        // Likewise, send the real pty and child to
-                        // the wrappers
+                        // Likewise, send the real pty to the wrapper;
+                        // it will be picked up when it is next polled
+                        // or resized.
                         pty_tx.send(pty)?;
-                        child_tx.send(child)?;
 
-                        // Now when we return, our stdin_read and
-                        // stdout_write will close and that will cause
-                        // the PtyReader and PtyWriter to recv the
-                        // the new reader/writer above and continue.
-                        //
-                        // The pty and child will be picked up when
-                        // they are next polled or resized.
+                        // Once the caller closes stdin_read and
+                        // stdout_write, the PtyReader and PtyWriter
+                        // will recv the new reader/writer above and
+                        // continue.
+                        return Ok(Some(child));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// How long to wait before the first attempt to reconnect
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// The longest that we'll wait between attempts to reconnect
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+/// Disables mouse reporting (1000, 1002, 1003 and the UTF-8, SGR and
+/// urxvt encodings 1005, 1006 and 1015), focus reporting (1004) and
+/// bracketed paste (2004), shows the cursor (25), then pops every kitty
+/// keyboard protocol entry that the remote program may have pushed
+const RESET_INPUT_MODES: &str = concat!(
+    "\x1b[?1000l\x1b[?1002l\x1b[?1003l",
+    "\x1b[?1005l\x1b[?1006l\x1b[?1015l",
+    "\x1b[?1004l\x1b[?2004l\x1b[?25h\x1b[<999u"
+);
+
+/// Looks after the connection for a pane in an ssh domain.
+/// It carries out the authentication UI and starts the command,
+/// and if the connection is lost before the command exits, it keeps
+/// the pane (and its scrollback) alive while it reconnects with
+/// backoff and then starts the command again.
+struct SshPaneConnection {
+    domain_id: DomainId,
+    remote_address: String,
+    auto_reconnect: bool,
+    stdin_tx: Sender<BoxedWriter>,
+    stdout_tx: Sender<BoxedReader>,
+    pty_tx: Sender<SshPty>,
+    status_tx: Sender<ExitStatus>,
+    killer: WrappedSshChildKiller,
+    /// Shared with the WrappedSshPty, so that the pane knows not
+    /// to resize the dead pty while we are reconnecting
+    disconnected: Arc<AtomicBool>,
+    size: Arc<Mutex<TerminalSize>>,
+    command_line: Option<String>,
+    env: HashMap<String, String>,
+}
+
+impl SshPaneConnection {
+    fn run(
+        self,
+        session: Session,
+        events: AsyncReceiver<SessionEvent>,
+        stdin_read: FileDescriptor,
+        stdout_write: FileDescriptor,
+    ) {
+        let mut session = session;
+        let mut events = Some(events);
+        let mut stdin_read = stdin_read;
+        let mut stdout_write = BufWriter::new(stdout_write);
+        let mut reconnecting = false;
+        let mut delay = RECONNECT_INITIAL_DELAY;
+
+        loop {
+            let child = match events.take() {
+                Some(events) => match connect_ssh_session(
+                    &session,
+                    events,
+                    &mut stdin_read,
+                    &self.stdin_tx,
+                    &mut stdout_write,
+                    &self.stdout_tx,
+                    &self.pty_tx,
+                    &self.size,
+                    self.command_line.as_deref(),
+                    self.env.clone(),
+                ) {
+                    Ok(child) => child,
+                    Err(err) => {
+                        // Authentication was cancelled, or the pane
+                        // has gone away
+                        let _ = write!(stdout_write, "{:#}", err);
+                        let _ = stdout_write.flush();
+                        log::error!("Failed to connect ssh: {:#}", err);
+                        return;
+                    }
+                },
+                None => None,
+            };
+
+            if let Some(child) = child {
+                // Closing these causes the pane to switch over to
+                // the reader and writer of the real pty
+                drop(stdin_read);
+                drop(stdout_write);
+
+                if let Some(status) = self.wait_for_child(child) {
+                    self.report_exit(status);
+                    return;
+                }
+                log::warn!("ssh connection to {} was lost", self.remote_address);
+                if !self.auto_reconnect {
+                    self.report_exit(ExitStatus::with_exit_code(1));
+                    return;
+                }
 
-                        return Ok(());
+                // Give the pane something to read and write while
+                // we are disconnected, so that we can tell the user
+                // what is happening and let them retry sooner
+                match self.interim_stdio() {
+                    Ok((read, write)) => {
+                        stdin_read = read;
+                        stdout_write = write;
+                    }
+                    Err(err) => {
+                        log::debug!("pane went away while reconnecting: {:#}", err);
+                        self.report_exit(ExitStatus::with_exit_code(1));
+                        return;
                     }
                 }
+                self.disconnected.store(true, Ordering::SeqCst);
+                reconnecting = true;
+                delay = RECONNECT_INITIAL_DELAY;
+                // The command may have left the terminal in the alternate
+                // screen or with odd modes set; a soft reset (DECSTR)
+                // puts us back on the primary screen with its scrollback.
+                // DECSTR doesn't cover mouse and focus reporting, bracketed
+                // paste or the kitty keyboard protocol, so turn those off
+                // as well, otherwise keys typed while disconnected would be
+                // encoded for a program that is no longer there.  We also
+                // show the cursor, in case the program had hidden it.
+                let _ = write!(
+                    stdout_write,
+                    "\x1b[!p{}\r\n\x1b[1mConnection to {} was lost\x1b[0m\r\n",
+                    RESET_INPUT_MODES, self.remote_address
+                );
+            } else if !reconnecting {
+                // The initial connection failed, and the reason was
+                // shown in the pane; the pane will report that it
+                // failed once we drop status_tx.
+                let _ = stdout_write.flush();
+                return;
+            }
+
+            if !self.wait_before_reconnect(&mut stdin_read, &mut stdout_write, delay) {
+                self.report_exit(ExitStatus::with_exit_code(1));
+                return;
+            }
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+
+            let _ = write!(stdout_write, "Reconnecting to {}\r\n", self.remote_address);
+            let _ = stdout_write.flush();
+            match reconnect_domain(self.domain_id) {
+                Ok((new_session, new_events)) => {
+                    session = new_session;
+                    events.replace(new_events);
+                }
+                Err(err) => {
+                    let _ = write!(stdout_write, "{:#}\r\n", err);
+                }
+            }
+        }
+    }
+
+    /// Waits for the command to exit.
+    /// Returns None if the connection was lost before it did.
+    fn wait_for_child(&self, mut child: SshChildProcess) -> Option<ExitStatus> {
+        if self.killer.set_child(Some(child.clone_killer())) {
+            let _ = child.kill().ok();
+        }
+        self.disconnected.store(false, Ordering::SeqCst);
+
+        let status = smol::block_on(child.wait_for_exit_status());
+        self.killer.set_child(None);
+        status
+    }
+
+    fn report_exit(&self, status: ExitStatus) {
+        self.status_tx.send(status).ok();
+        promise::spawn::spawn_into_main_thread(async move {
+            if let Some(mux) = Mux::try_get() {
+                mux.prune_dead_windows();
+            }
+        })
+        .detach();
+    }
+
+    /// Sends a fresh reader and writer to the pane, returning the
+    /// other ends of them
+    fn interim_stdio(&self) -> anyhow::Result<(FileDescriptor, BufWriter<FileDescriptor>)> {
+        let (stdout_read, stdout_write) = socketpair()?;
+        let (stdin_read, stdin_write) = socketpair()?;
+        self.stdin_tx
+            .send(Box::new(stdin_write))
+            .map_err(|e| anyhow!("{:#}", e))?;
+        self.stdout_tx
+            .send(Box::new(stdout_read))
+            .map_err(|e| anyhow!("{:#}", e))?;
+        Ok((stdin_read, BufWriter::new(stdout_write)))
+    }
+
+    /// Waits for `delay` to elapse, or for the user to press Enter.
+    /// Returns false if the pane was closed or killed in the meantime.
+    fn wait_before_reconnect(
+        &self,
+        stdin_read: &mut FileDescriptor,
+        stdout_write: &mut BufWriter<FileDescriptor>,
+        delay: Duration,
+    ) -> bool {
+        let _ = write!(
+            stdout_write,
+            "Reconnecting in {}s; press Enter to reconnect now\r\n",
+            delay.as_secs()
+        );
+        if stdout_write.flush().is_err() || stdin_read.set_non_blocking(true).is_err() {
+            return false;
+        }
+
+        let deadline = Instant::now() + delay;
+        while Instant::now() < deadline {
+            if self.killer.kill_requested() {
+                return false;
+            }
+            let mut pfd = [pollfd {
+                fd: stdin_read.as_socket_descriptor(),
+                events: POLLIN,
+                revents: 0,
+            }];
+            if let Ok(1) = poll(&mut pfd, Some(Duration::from_millis(100))) {
+                let mut buf = [0u8; 64];
+                match stdin_read.read(&mut buf) {
+                    // The pane has closed its writer
+                    Ok(0) => return false,
+                    Ok(n) if buf[..n].contains(&b'\r') || buf[..n].contains(&b'\n') => break,
+                    Ok(_) => {}
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+                    Err(_) => return false,
+                }
             }
         }
+        !self.killer.kill_requested()
     }
+}
 
-    Ok(())
+/// Makes a new connection for a pane in the domain with the
+/// specified id
+fn reconnect_domain(domain_id: DomainId) -> anyhow::Result<(Session, AsyncReceiver<SessionEvent>)> {
+    let domain = Mux::try_get()
+        .and_then(|mux| mux.get_domain(domain_id))
+        .ok_or_else(|| anyhow!("ssh domain {} no longer exists", domain_id))?;
+    let domain = domain
+        .downcast_ref::<RemoteSshDomain>()
+        .ok_or_else(|| anyhow!("domain {} is not an ssh domain", domain_id))?;
+    domain.connect()
 }
 
 #[async_trait(?Send)]
@@ -711,47 +1070,11 @@ impl Domain for RemoteSshDomain {
             .build_command(pane_id, command, command_dir)
             .context("build_command")?;
 
-        // This needs to be separate from the if let block below in order
-        // for the lock to be released at the appropriate time
-        let mut session: Option<Session> = self.session.lock().unwrap().as_ref().cloned();
-
-        let StartNewSessionResult { pty, child, writer } = if let Some(session) = session.take() {
-            match session
-                .request_pty(
-                    &config::configuration().term,
-                    crate::terminal_size_to_pty_size(size)
-                        .context("compute pty size from terminal size")?,
-                    command_line.as_ref().map(|s| s.as_str()),
-                    Some(env.clone()),
-                )
-                .await
-                .context("request ssh pty")
-            {
-                Ok((concrete_pty, concrete_child)) => {
-                    let pty = Box::new(concrete_pty);
-                    let child = Box::new(concrete_child);
-                    let writer = Box::new(pty.take_writer().context("take writer from pty")?);
-
-                    StartNewSessionResult { pty, child, writer }
-                }
-                Err(err) => {
-                    if err
-                        .root_cause()
-                        .downcast_ref::<wezterm_ssh::DeadSession>()
-                        .is_some()
-                    {
-                        // Session died (perhaps they closed the initial tab?)
-                        // So we'll try making a new one
-                        self.start_new_session(command_line, env, size).await?
-                    } else {
-                        log::error!("{err:#?}");
-                        return Err(err);
-                    }
-                }
-            }
-        } else {
-            self.start_new_session(command_line, env, size).await?
-        };
+        // Each pane gets its own set of wrappers, even when it shares
+        // the connection with other panes, so that it can reconnect
+        // independently of them.
+        let StartNewSessionResult { pty, child, writer } =
+            self.start_new_session(command_line, env, size)?;
 
         // Wrap up the pty etc. in a LocalPane.  That allows for
         // eg: tmux integration to be tunnelled via the remote
@@ -802,6 +1125,52 @@ impl Domain for RemoteSshDomain {
         bail!("detach not implemented for RemoteSshDomain");
     }
 
+    async fn list_port_forwards(&self) -> anyhow::Result<Vec<PortForward>> {
+        let session = match self.session.lock().unwrap().as_ref().cloned() {
+            Some(session) => session,
+            None => return Ok(vec![]),
+        };
+        let forwards = with_forward_timeout(session.list_forwards()).await?;
+        Ok(forwards
+            .into_iter()
+            // The session may be shared with other domains,
+            // which list their own forwards
+            .filter(|info| {
+                info.owner.is_none() || info.owner.as_deref() == Some(self.name.as_str())
+            })
+            .map(|info| PortForward {
+                domain_name: self.name.clone(),
+                forward_id: info.id,
+                description: match info.bound_port {
+                    Some(port) if port != info.spec.bind_port => {
+                        format!("{} (listening on port {port})", info.spec)
+                    }
+                    _ => info.spec.to_string(),
+                },
+                enabled: info.state == ForwardState::Active,
+                error: match info.state {
+                    ForwardState::Failed(err) => Some(err),
+                    _ => None,
+                },
+            })
+            .collect())
+    }
+
+    async fn set_port_forward_enabled(
+        &self,
+        forward_id: usize,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        let session = self
+            .session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .cloned()
+            .ok_or_else(|| anyhow!("ssh domain {} is not connected", self.name))?;
+        with_forward_timeout(session.set_forward_enabled(forward_id, enabled)).await
+    }
+
     fn state(&self) -> DomainState {
         // Just pretend that we are always attached, as we don't
         // have a defined attach operation that is distinct from
@@ -813,9 +1182,10 @@ impl Domain for RemoteSshDomain {
 #[derive(Debug)]
 struct KillerInner {
     killer: Option<Box<dyn ChildKiller + Send + Sync>>,
-    /// If we haven't populated `killer` by the time someone has called
-    /// `kill`, then we use this to remember to kill as soon as we recv
-    /// the child process.
+    /// Set once someone has called `kill`.  If we haven't populated
+    /// `killer` by then, we use this to remember to kill as soon as we
+    /// recv the child process, and we don't try to reconnect after
+    /// it has been set.
     pending_kill: bool,
 }
 
@@ -824,76 +1194,50 @@ struct WrappedSshChildKiller {
     inner: Arc<Mutex<KillerInner>>,
 }
 
+impl WrappedSshChildKiller {
+    /// Sets the killer for the current child process, or clears it
+    /// when that child has gone away.
+    /// Returns true if a kill was requested, in which case the new
+    /// child should be killed.
+    fn set_child(&self, killer: Option<Box<dyn ChildKiller + Send + Sync>>) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        inner.killer = killer;
+        inner.pending_kill
+    }
+
+    fn kill_requested(&self) -> bool {
+        self.inner.lock().unwrap().pending_kill
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct WrappedSshChild {
-    status: Option<AsyncReceiver<ExitStatus>>,
-    rx: Receiver<SshChildProcess>,
+    /// Receives the exit status of the command from the
+    /// SshPaneConnection.  Losing the connection isn't reported
+    /// here unless we have given up on reconnecting.
+    status: Receiver<ExitStatus>,
     exited: Option<ExitStatus>,
     killer: WrappedSshChildKiller,
 }
 
-impl WrappedSshChild {
-    fn check_connected(&mut self) {
-        if self.status.is_none() {
-            match self.rx.try_recv() {
-                Ok(c) => {
-                    self.got_child(c);
-                }
-                Err(TryRecvError::Empty) => {}
-                Err(err) => {
-                    log::debug!("WrappedSshChild::check_connected err: {:#?}", err);
-                    self.exited.replace(ExitStatus::with_exit_code(1));
-                }
-            }
-        }
-    }
-
-    fn got_child(&mut self, mut child: SshChildProcess) {
-        {
-            let mut killer = self.killer.inner.lock().unwrap();
-            killer.killer.replace(child.clone_killer());
-            if killer.pending_kill {
-                let _ = child.kill().ok();
-            }
-        }
-
-        let (tx, rx) = bounded(1);
-        promise::spawn::spawn_into_main_thread(async move {
-            if let Ok(status) = child.async_wait().await {
-                tx.send(status).await.ok();
-                let mux = Mux::get();
-                mux.prune_dead_windows();
-            }
-        })
-        .detach();
-        self.status.replace(rx);
-    }
-}
-
 impl portable_pty::Child for WrappedSshChild {
     fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
         if let Some(status) = self.exited.as_ref() {
             return Ok(Some(status.clone()));
         }
 
-        self.check_connected();
-
-        if let Some(rx) = self.status.as_mut() {
-            match rx.try_recv() {
-                Ok(status) => {
-                    self.exited.replace(status.clone());
-                    Ok(Some(status))
-                }
-                Err(smol::channel::TryRecvError::Empty) => Ok(None),
-                Err(err) => {
-                    log::debug!("WrappedSshChild::try_wait err: {:#?}", err);
-                    let status = ExitStatus::with_exit_code(1);
-                    self.exited.replace(status.clone());
-                    Ok(Some(status))
-                }
+        match self.status.try_recv() {
+            Ok(status) => {
+                self.exited.replace(status.clone());
+                Ok(Some(status))
+            }
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(err) => {
+                log::debug!("WrappedSshChild::try_wait err: {:#?}", err);
+                let status = ExitStatus::with_exit_code(1);
+                self.exited.replace(status.clone());
+                Ok(Some(status))
             }
-        } else {
-            Ok(None)
         }
     }
 
@@ -902,28 +1246,13 @@ impl portable_pty::Child for WrappedSshChild {
             return Ok(status.clone());
         }
 
-        if self.status.is_none() {
-            match smol::block_on(async { self.rx.recv() }) {
-                Ok(c) => {
-                    self.got_child(c);
-                }
-                Err(err) => {
-                    log::debug!("WrappedSshChild err: {:#?}", err);
-                    let status = ExitStatus::with_exit_code(1);
-                    self.exited.replace(status.clone());
-                    return Ok(status);
-                }
-            }
-        }
-
-        let rx = self.status.as_mut().unwrap();
-        match smol::block_on(rx.recv()) {
+        match self.status.recv() {
             Ok(status) => {
                 self.exited.replace(status.clone());
                 Ok(status)
             }
             Err(err) => {
-                log::error!("WrappedSshChild err: {:#?}", err);
+                log::debug!("WrappedSshChild err: {:#?}", err);
                 let status = ExitStatus::with_exit_code(1);
                 self.exited.replace(status.clone());
                 Ok(status)
@@ -943,13 +1272,7 @@ impl portable_pty::Child for WrappedSshChild {
 
 impl ChildKiller for WrappedSshChild {
     fn kill(&mut self) -> std::io::Result<()> {
-        let mut killer = self.killer.inner.lock().unwrap();
-        if let Some(killer) = killer.killer.as_mut() {
-            killer.kill()
-        } else {
-            killer.pending_kill = true;
-            Ok(())
-        }
+        self.killer.kill()
     }
 
     fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
@@ -959,12 +1282,14 @@ impl ChildKiller for WrappedSshChild {
 
 impl ChildKiller for WrappedSshChildKiller {
     fn kill(&mut self) -> std::io::Result<()> {
-        let mut killer = self.inner.lock().unwrap();
-        if let Some(killer) = killer.killer.as_mut() {
-            killer.kill()
-        } else {
-            killer.pending_kill = true;
-            Ok(())
+        let mut inner = self.inner.lock().unwrap();
+        // Remember the request even if we can pass it on now, so
+        // that we don't try to reconnect if the connection drops
+        // before the command has exited
+        inner.pending_kill = true;
+        match inner.killer.as_mut() {
+            Some(killer) => killer.kill(),
+            None => Ok(()),
         }
     }
 
@@ -978,24 +1303,23 @@ type BoxedWriter = Box<(dyn Write + Send + 'static)>;
 
 pub(crate) struct WrappedSshPty {
     inner: RefCell<WrappedSshPtyInner>,
+    /// Set by the SshPaneConnection while it is reconnecting
+    disconnected: Arc<AtomicBool>,
 }
 
 impl WrappedSshPty {
+    /// Returns true while the pane is authenticating or reconnecting
     pub fn is_connecting(&mut self) -> bool {
-        self.inner.borrow_mut().is_connecting()
+        self.inner.borrow_mut().is_connecting() || self.disconnected.load(Ordering::SeqCst)
     }
 }
 
-enum WrappedSshPtyInner {
-    Connecting {
-        reader: Option<PtyReader>,
-        connected: Receiver<SshPty>,
-        size: Arc<Mutex<TerminalSize>>,
-    },
-    Connected {
-        reader: Option<PtyReader>,
-        pty: SshPty,
-    },
+struct WrappedSshPtyInner {
+    reader: Option<PtyReader>,
+    /// Receives the real pty each time that we (re)connect
+    connected: Receiver<SshPty>,
+    size: Arc<Mutex<TerminalSize>>,
+    pty: Option<SshPty>,
 }
 
 struct PtyReader {
@@ -1010,76 +1334,54 @@ struct PtyWriter {
 
 impl WrappedSshPtyInner {
     fn check_connected(&mut self) -> anyhow::Result<()> {
-        match self {
-            Self::Connecting {
-                reader,
-                connected,
-                size,
-                ..
-            } => {
-                if let Ok(pty) = connected.try_recv() {
-                    let res = pty.resize(crate::terminal_size_to_pty_size(*size.lock().unwrap())?);
-                    *self = Self::Connected {
-                        pty,
-                        reader: reader.take(),
-                    };
-                    res
-                } else {
-                    Ok(())
-                }
-            }
-            _ => Ok(()),
+        let mut res = Ok(());
+        while let Ok(pty) = self.connected.try_recv() {
+            res = pty.resize(crate::terminal_size_to_pty_size(
+                *self.size.lock().unwrap(),
+            )?);
+            self.pty.replace(pty);
         }
+        res
     }
 
     fn is_connecting(&mut self) -> bool {
         self.check_connected().ok();
-        match self {
-            Self::Connecting { .. } => true,
-            Self::Connected { .. } => false,
-        }
+        self.pty.is_none()
     }
 }
 
 impl portable_pty::MasterPty for WrappedSshPty {
     fn resize(&self, new_size: PtySize) -> anyhow::Result<()> {
         let mut inner = self.inner.borrow_mut();
-        match &mut *inner {
-            WrappedSshPtyInner::Connecting { ref mut size, .. } => {
-                {
-                    let mut size = size.lock().unwrap();
-                    size.cols = new_size.cols as usize;
-                    size.rows = new_size.rows as usize;
-                    size.pixel_height = new_size.pixel_height as usize;
-                    size.pixel_width = new_size.pixel_width as usize;
-                }
-                inner.check_connected()
-            }
-            WrappedSshPtyInner::Connected { pty, .. } => pty.resize(new_size),
+        {
+            let mut size = inner.size.lock().unwrap();
+            size.cols = new_size.cols as usize;
+            size.rows = new_size.rows as usize;
+            size.pixel_height = new_size.pixel_height as usize;
+            size.pixel_width = new_size.pixel_width as usize;
+        }
+        inner.check_connected()?;
+        match &inner.pty {
+            // The pty of a lost connection can't be resized; the
+            // new one will pick up the size when it arrives
+            Some(pty) if !self.disconnected.load(Ordering::SeqCst) => pty.resize(new_size),
+            _ => Ok(()),
         }
     }
 
     fn get_size(&self) -> anyhow::Result<PtySize> {
         let mut inner = self.inner.borrow_mut();
-        match &*inner {
-            WrappedSshPtyInner::Connecting { size, .. } => {
-                let size = crate::terminal_size_to_pty_size(*size.lock().unwrap())?;
-                inner.check_connected()?;
-                Ok(size)
-            }
-            WrappedSshPtyInner::Connected { pty, .. } => pty.get_size(),
-        }
+        inner.check_connected()?;
+        let size = crate::terminal_size_to_pty_size(*inner.size.lock().unwrap())?;
+        Ok(size)
     }
 
     fn try_clone_reader(&self) -> anyhow::Result<Box<(dyn Read + Send + 'static)>> {
         let mut inner = self.inner.borrow_mut();
         inner.check_connected()?;
-        match &mut *inner {
-            WrappedSshPtyInner::Connected { ref mut reader, .. }
-            | WrappedSshPtyInner::Connecting { ref mut reader, .. } => match reader.take() {
-                Some(r) => Ok(Box::new(r)),
-                None => anyhow::bail!("reader already taken"),
-            },
+        match inner.reader.take() {
+            Some(r) => Ok(Box::new(r)),
+            None => anyhow::bail!("reader already taken"),
         }
     }
 
@@ -1112,7 +1414,9 @@ impl std::io::Write for PtyWriter {
         // socket and we won't discover the issue until we write
         // the next byte.
         // <https://github.com/wezterm/wezterm/issues/771>
-        if let Ok(writer) = self.rx.try_recv() {
+        // We may have been sent several writers while reconnecting,
+        // in which case only the most recent one is still open.
+        while let Ok(writer) = self.rx.try_recv() {
             self.writer = writer;
         }
         self.writer.write(buf)
@@ -12,6 +12,7 @@ use std::collections::HashMap;
 use std::convert::TryInto;
 use std::sync::Arc;
 use url::Url;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_term::{StableRowIndex, TerminalSize};
 
 pub type Tree = bintree::Tree<Arc<dyn Pane>, SplitDirectionAndSize>;
@@ -37,6 +38,16 @@ impl Recency {
     }
 }
 
+/// A pane that is layered above the split tree of its tab,
+/// rather than being part of it
+struct FloatingPane {
+    pane: Arc<dyn Pane>,
+    /// The offset from the top left corner of the tab, in cells
+    left: usize,
+    top: usize,
+    size: TerminalSize,
+}
+
 struct TabInner {
     id: TabId,
     pane: Option<Tree>,
@@ -46,6 +57,11 @@ struct TabInner {
     zoomed: Option<Arc<dyn Pane>>,
     title: String,
     recency: Recency,
+    /// The floating panes, ordered from bottom-most to top-most
+    floating: Vec<FloatingPane>,
+    /// When true, the floating panes are shown and the top-most
+    /// of them has the focus
+    floating_visible: bool,
 }
 
 /// A Tab is a container of Panes
@@ -92,7 +108,7 @@ impl std::fmt::Debug for PositionedPane {
     }
 }
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize, FromDynamic, ToDynamic)]
 pub enum SplitDirection {
     Horizontal,
     Vertical,
@@ -141,6 +157,23 @@ impl Default for SplitRequest {
     }
 }
 
+/// An operation on the top-most floating pane of a tab.
+/// This type is used directly by the codec, take care to bump
+/// the codec version if you change this
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum FloatingPaneOp {
+    /// Shows or hides the floating panes
+    Toggle,
+    /// Moves the pane by a number of cells
+    Move(PaneDirection, usize),
+    /// Grows or shrinks the pane by moving its right or bottom
+    /// edge by a number of cells
+    Resize(PaneDirection, usize),
+    /// Moves the pane out of the floating layer and into the split
+    /// tree, placing it on the specified side of the active pane
+    Promote(PaneDirection),
+}
+
 impl SplitDirectionAndSize {
     fn top_of_second(&self) -> usize {
         match self.direction {
@@ -252,34 +285,47 @@ fn pane_tree(
                 node: data,
             }
         }
-        Tree::Leaf(pane) => {
-            let dims = pane.get_dimensions();
-            let working_dir = pane.get_current_working_dir(CachePolicy::AllowStale);
-            let cursor_pos = pane.get_cursor_position();
+        Tree::Leaf(pane) => PaneNode::Leaf(pane_entry(
+            pane, tab_id, window_id, active, zoomed, workspace, left_col, top_row,
+        )),
+    }
+}
 
-            PaneNode::Leaf(PaneEntry {
-                window_id,
-                tab_id,
-                pane_id: pane.pane_id(),
-                title: pane.get_title(),
-                is_active_pane: is_pane(pane, &active),
-                is_zoomed_pane: is_pane(pane, &zoomed),
-                size: TerminalSize {
-                    cols: dims.cols,
-                    rows: dims.viewport_rows,
-                    pixel_height: dims.pixel_height,
-                    pixel_width: dims.pixel_width,
-                    dpi: dims.dpi,
-                },
-                working_dir: working_dir.map(Into::into),
-                workspace: workspace.to_string(),
-                cursor_pos,
-                physical_top: dims.physical_top,
-                left_col,
-                top_row,
-                tty_name: pane.tty_name(),
-            })
-        }
+fn pane_entry(
+    pane: &Arc<dyn Pane>,
+    tab_id: TabId,
+    window_id: WindowId,
+    active: Option<&Arc<dyn Pane>>,
+    zoomed: Option<&Arc<dyn Pane>>,
+    workspace: &str,
+    left_col: usize,
+    top_row: usize,
+) -> PaneEntry {
+    let dims = pane.get_dimensions();
+    let working_dir = pane.get_current_working_dir(CachePolicy::AllowStale);
+    let cursor_pos = pane.get_cursor_position();
+
+    PaneEntry {
+        window_id,
+        tab_id,
+        pane_id: pane.pane_id(),
+        title: pane.get_title(),
+        is_active_pane: is_pane(pane, &active),
+        is_zoomed_pane: is_pane(pane, &zoomed),
+        size: TerminalSize {
+            cols: dims.cols,
+            rows: dims.viewport_rows,
+            pixel_height: dims.pixel_height,
+            pixel_width: dims.pixel_width,
+            dpi: dims.dpi,
+        },
+        working_dir: working_dir.map(Into::into),
+        workspace: workspace.to_string(),
+        cursor_pos,
+        physical_top: dims.physical_top,
+        left_col,
+        top_row,
+        tty_name: pane.tty_name(),
     }
 }
 
@@ -506,6 +552,23 @@ fn cell_dimensions(size: &TerminalSize) -> TerminalSize {
     }
 }
 
+/// Shrinks and moves a floating pane as needed so that it lies
+/// entirely within a tab of size `tab_size`
+fn constrain_floating_pane(floating: &mut FloatingPane, tab_size: &TerminalSize) {
+    let cell_dims = cell_dimensions(tab_size);
+    let cols = floating.size.cols.clamp(1, tab_size.cols);
+    let rows = floating.size.rows.clamp(1, tab_size.rows);
+    floating.size = TerminalSize {
+        rows,
+        cols,
+        pixel_width: cols * cell_dims.pixel_width,
+        pixel_height: rows * cell_dims.pixel_height,
+        dpi: cell_dims.dpi,
+    };
+    floating.left = floating.left.min(tab_size.cols - cols);
+    floating.top = floating.top.min(tab_size.rows - rows);
+}
+
 impl Tab {
     pub fn new(size: &TerminalSize) -> Self {
         let inner = TabInner::new(size);
@@ -630,6 +693,18 @@ impl Tab {
             .rebuild_splits_sizes_from_contained_panes()
     }
 
+    /// Computes the size that the tab would have if its panes had
+    /// the sizes in `pane_sizes`, which are the sizes most recently
+    /// requested for them by a client.  Panes without an entry keep
+    /// their current size.  Returns None if none of the panes in
+    /// the tab have an entry.
+    pub fn size_for_pane_sizes(
+        &self,
+        pane_sizes: &HashMap<PaneId, TerminalSize>,
+    ) -> Option<TerminalSize> {
+        self.inner.lock().size_for_pane_sizes(pane_sizes)
+    }
+
     /// Given split_index, the topological index of a split returned by
     /// iter_splits() as PositionedSplit::index, revised the split position
     /// by the provided delta; positive values move the split to the right/bottom,
@@ -751,6 +826,34 @@ impl Tab {
     pub fn get_zoomed_pane(&self) -> Option<Arc<dyn Pane>> {
         self.inner.lock().get_zoomed_pane()
     }
+
+    /// Returns the floating panes of this tab, bottom-most first,
+    /// regardless of whether they are currently visible
+    pub fn iter_floating_panes(&self) -> Vec<PositionedPane> {
+        self.inner.lock().iter_floating_panes()
+    }
+
+    /// Returns true if the floating panes are shown above the split
+    /// tree, in which case the top-most of them is the active pane
+    pub fn floating_panes_visible(&self) -> bool {
+        self.inner.lock().floating_has_focus()
+    }
+
+    /// Computes the size of a new floating pane, based on the
+    /// `floating_pane_percent` configuration
+    pub fn compute_floating_size(&self) -> TerminalSize {
+        self.inner.lock().compute_floating_size()
+    }
+
+    /// Adds `pane` as the top-most floating pane, centered over the tab,
+    /// and makes it the active pane
+    pub fn add_floating_pane(&self, pane: &Arc<dyn Pane>, size: TerminalSize) {
+        self.inner.lock().add_floating_pane(pane, size)
+    }
+
+    pub fn apply_floating_pane_op(&self, op: FloatingPaneOp) -> anyhow::Result<()> {
+        self.inner.lock().apply_floating_pane_op(op)
+    }
 }
 
 impl TabInner {
@@ -764,6 +867,8 @@ impl TabInner {
             zoomed: None,
             title: String::new(),
             recency: Recency::default(),
+            floating: vec![],
+            floating_visible: false,
         }
     }
 
@@ -776,6 +881,15 @@ impl TabInner {
 
         log::debug!("sync_with_pane_tree with size {:?}", size);
 
+        let (root, floating, floating_visible) = match root {
+            PaneNode::Floating {
+                tree,
+                floating,
+                visible,
+            } => (*tree, floating, visible),
+            root => (root, vec![], false),
+        };
+
         let t = build_from_pane_tree(root.into_tree(), &mut active, &mut zoomed, &mut make_pane);
         let mut cursor = t.cursor();
 
@@ -806,6 +920,16 @@ impl TabInner {
         self.pane.replace(cursor.tree());
         self.zoomed = zoomed;
         self.size = size;
+        self.floating = floating
+            .into_iter()
+            .map(|entry| FloatingPane {
+                left: entry.left_col,
+                top: entry.top_row,
+                size: entry.size,
+                pane: make_pane(entry),
+            })
+            .collect();
+        self.floating_visible = floating_visible;
 
         self.resize(size);
 
@@ -842,7 +966,7 @@ impl TabInner {
 
         let active = self.get_active_pane();
         let zoomed = self.zoomed.as_ref();
-        if let Some(root) = self.pane.as_ref() {
+        let tree = if let Some(root) = self.pane.as_ref() {
             pane_tree(
                 root,
                 tab_id,
@@ -855,6 +979,32 @@ impl TabInner {
             )
         } else {
             PaneNode::Empty
+        };
+
+        if self.floating.is_empty() {
+            return tree;
+        }
+
+        PaneNode::Floating {
+            tree: Box::new(tree),
+            floating: self
+                .floating
+                .iter()
+                .map(|floating| PaneEntry {
+                    size: floating.size,
+                    ..pane_entry(
+                        &floating.pane,
+                        tab_id,
+                        window_id,
+                        active.as_ref(),
+                        zoomed,
+                        &workspace,
+                        floating.left,
+                        floating.top,
+                    )
+                })
+                .collect(),
+            visible: self.floating_visible,
         }
     }
 
@@ -871,7 +1021,7 @@ impl TabInner {
                 Ok(c) => cursor = c,
                 Err(c) => {
                     self.pane.replace(c.tree());
-                    return count;
+                    return count + self.floating.len();
                 }
             }
         }
@@ -890,15 +1040,17 @@ impl TabInner {
 
     fn toggle_zoom(&mut self) {
         let size = self.size;
-        if self.zoomed.take().is_some() {
+        if let Some(pane) = self.zoomed.take() {
             // We were zoomed, but now we are not.
             // Re-apply the size to the panes
-            if let Some(pane) = self.get_active_pane() {
-                pane.set_zoomed(false);
-            }
+            pane.set_zoomed(false);
             self.size = self.size_before_zoom;
             self.resize(size);
         } else {
+            // Floating panes cannot be zoomed
+            if self.floating_has_focus() {
+                return;
+            }
             // We weren't zoomed, but now we want to zoom.
             // Locate the active pane
             self.size_before_zoom = size;
@@ -919,6 +1071,9 @@ impl TabInner {
                 Tree::Leaf(p) => p.pane_id() == pane,
             }
         }
+        if self.floating.iter().any(|f| f.pane.pane_id() == pane) {
+            return true;
+        }
         match &self.pane {
             Some(root) => contains(root, pane),
             None => false,
@@ -1008,7 +1163,7 @@ impl TabInner {
                 let size = self.size;
                 panes.push(PositionedPane {
                     index: 0,
-                    is_active: true,
+                    is_active: !self.floating_has_focus(),
                     is_zoomed: true,
                     left: 0,
                     top: 0,
@@ -1022,7 +1177,13 @@ impl TabInner {
             }
         }
 
-        let active_idx = self.active;
+        // While a floating pane has the focus, none of the
+        // panes in the tree are active
+        let active_idx = if self.floating_has_focus() {
+            usize::MAX
+        } else {
+            self.active
+        };
         let zoomed_id = self.zoomed.as_ref().map(|p| p.pane_id());
         let root_size = self.size;
         let mut cursor = self.pane.take().unwrap().cursor();
@@ -1179,6 +1340,15 @@ impl TabInner {
             apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
         }
 
+        let tab_size = self.size;
+        for floating in &mut self.floating {
+            let prior_size = floating.size;
+            constrain_floating_pane(floating, &tab_size);
+            if floating.size != prior_size {
+                floating.pane.resize(floating.size).ok();
+            }
+        }
+
         Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
     }
 
@@ -1258,6 +1428,61 @@ impl TabInner {
         Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
     }
 
+    fn size_for_pane_sizes(
+        &self,
+        pane_sizes: &HashMap<PaneId, TerminalSize>,
+    ) -> Option<TerminalSize> {
+        if let Some(zoomed) = &self.zoomed {
+            return pane_sizes.get(&zoomed.pane_id()).copied();
+        }
+
+        // Returns the size of the node, and whether any of the
+        // panes within it have a requested size
+        fn compute_size(
+            node: &Tree,
+            pane_sizes: &HashMap<PaneId, TerminalSize>,
+        ) -> Option<(TerminalSize, bool)> {
+            match node {
+                Tree::Empty => None,
+                Tree::Leaf(pane) => match pane_sizes
+                    .get(&pane.pane_id())
+                    .filter(|size| size.rows > 0 && size.cols > 0)
+                {
+                    Some(size) => Some((*size, true)),
+                    None => {
+                        let dims = pane.get_dimensions();
+                        let size = TerminalSize {
+                            cols: dims.cols,
+                            rows: dims.viewport_rows,
+                            pixel_height: dims.pixel_height,
+                            pixel_width: dims.pixel_width,
+                            dpi: dims.dpi,
+                        };
+                        Some((size, false))
+                    }
+                },
+                Tree::Node { left, right, data } => {
+                    let mut data = (*data)?;
+                    let mut requested = false;
+                    if let Some((first, req)) = compute_size(left, pane_sizes) {
+                        data.first = first;
+                        requested |= req;
+                    }
+                    if let Some((second, req)) = compute_size(right, pane_sizes) {
+                        data.second = second;
+                        requested |= req;
+                    }
+                    Some((data.size(), requested))
+                }
+            }
+        }
+
+        match compute_size(self.pane.as_ref()?, pane_sizes)? {
+            (size, true) => Some(size),
+            (_, false) => None,
+        }
+    }
+
     fn resize_split_by(&mut self, split_index: usize, delta: isize) {
         if self.zoomed.is_some() {
             return;
@@ -1694,6 +1919,19 @@ impl TabInner {
             let active_idx = self.active;
             removed_indices.retain(|&idx| idx <= active_idx);
             self.active = active_idx.saturating_sub(removed_indices.len());
+
+            // Floating panes are not part of the tree, so they are
+            // numbered after the panes that are
+            let mut floating = std::mem::take(&mut self.floating);
+            floating.retain(|floating| {
+                let remove = f(pane_index, &floating.pane);
+                pane_index += 1;
+                if remove {
+                    dead_panes.push(Arc::clone(&floating.pane));
+                }
+                !remove
+            });
+            self.floating = floating;
         }
 
         if !dead_panes.is_empty() && kill {
@@ -1733,6 +1971,10 @@ impl TabInner {
     }
 
     fn get_active_pane(&mut self) -> Option<Arc<dyn Pane>> {
+        if self.floating_has_focus() {
+            return self.floating.last().map(|f| Arc::clone(&f.pane));
+        }
+
         if let Some(zoomed) = self.zoomed.as_ref() {
             return Some(Arc::clone(zoomed));
         }
@@ -1754,6 +1996,22 @@ impl TabInner {
             return;
         }
 
+        if let Some(idx) = self
+            .floating
+            .iter()
+            .position(|f| f.pane.pane_id() == pane.pane_id())
+        {
+            // Raise the floating pane to the top, and show it
+            let floating = self.floating.remove(idx);
+            self.floating.push(floating);
+            self.floating_visible = true;
+            self.advise_focus_change(prior);
+            return;
+        }
+
+        // Activating a pane in the tree hides the floating panes
+        self.floating_visible = false;
+
         if self.zoomed.is_some() {
             if !configuration().unzoom_on_switch_pane {
                 return;
@@ -1773,17 +2031,18 @@ impl TabInner {
     }
 
     fn advise_focus_change(&mut self, prior: Option<Arc<dyn Pane>>) {
-        let mux = Mux::get();
         let current = self.get_active_pane();
         match (prior, current) {
             (Some(prior), Some(current)) if prior.pane_id() != current.pane_id() => {
                 prior.focus_changed(false);
                 current.focus_changed(true);
-                mux.notify(MuxNotification::PaneFocused(current.pane_id()));
+                Mux::try_get()
+                    .map(|mux| mux.notify(MuxNotification::PaneFocused(current.pane_id())));
             }
             (None, Some(current)) => {
                 current.focus_changed(true);
-                mux.notify(MuxNotification::PaneFocused(current.pane_id()));
+                Mux::try_get()
+                    .map(|mux| mux.notify(MuxNotification::PaneFocused(current.pane_id())));
             }
             (Some(prior), None) => {
                 prior.focus_changed(false);
@@ -1796,6 +2055,7 @@ impl TabInner {
 
     fn set_active_idx(&mut self, pane_index: usize) {
         let prior = self.get_active_pane();
+        self.floating_visible = false;
         self.active = pane_index;
         self.recency.tag(pane_index);
         self.advise_focus_change(prior);
@@ -2095,6 +2355,153 @@ impl TabInner {
     fn get_zoomed_pane(&self) -> Option<Arc<dyn Pane>> {
         self.zoomed.clone()
     }
+
+    fn floating_has_focus(&self) -> bool {
+        self.floating_visible && !self.floating.is_empty()
+    }
+
+    fn iter_floating_panes(&self) -> Vec<PositionedPane> {
+        let has_focus = self.floating_has_focus();
+        let top = self.floating.len().saturating_sub(1);
+        self.floating
+            .iter()
+            .enumerate()
+            .map(|(index, floating)| PositionedPane {
+                index,
+                is_active: has_focus && index == top,
+                is_zoomed: false,
+                left: floating.left,
+                top: floating.top,
+                width: floating.size.cols,
+                pixel_width: floating.size.pixel_width,
+                height: floating.size.rows,
+                pixel_height: floating.size.pixel_height,
+                pane: Arc::clone(&floating.pane),
+            })
+            .collect()
+    }
+
+    fn compute_floating_size(&self) -> TerminalSize {
+        let percent = configuration().floating_pane_percent.clamp(1, 100) as usize;
+        let cell_dims = self.cell_dimensions();
+        let cols = (self.size.cols * percent / 100).max(1);
+        let rows = (self.size.rows * percent / 100).max(1);
+        TerminalSize {
+            rows,
+            cols,
+            pixel_width: cols * cell_dims.pixel_width,
+            pixel_height: rows * cell_dims.pixel_height,
+            dpi: cell_dims.dpi,
+        }
+    }
+
+    fn add_floating_pane(&mut self, pane: &Arc<dyn Pane>, size: TerminalSize) {
+        let prior = self.get_active_pane();
+
+        let mut floating = FloatingPane {
+            pane: Arc::clone(pane),
+            left: 0,
+            top: 0,
+            size,
+        };
+        constrain_floating_pane(&mut floating, &self.size);
+        floating.left = (self.size.cols - floating.size.cols) / 2;
+        floating.top = (self.size.rows - floating.size.rows) / 2;
+        pane.resize(floating.size).ok();
+
+        self.floating.push(floating);
+        self.floating_visible = true;
+        self.advise_focus_change(prior);
+        Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
+    }
+
+    fn apply_floating_pane_op(&mut self, op: FloatingPaneOp) -> anyhow::Result<()> {
+        if self.floating.is_empty() {
+            anyhow::bail!("tab {} has no floating panes", self.id);
+        }
+        let tab_size = self.size;
+
+        match op {
+            FloatingPaneOp::Toggle => {
+                let prior = self.get_active_pane();
+                self.floating_visible = !self.floating_visible;
+                self.advise_focus_change(prior);
+            }
+            FloatingPaneOp::Move(direction, amount) => {
+                let floating = self.floating.last_mut().unwrap();
+                match direction {
+                    PaneDirection::Left => floating.left = floating.left.saturating_sub(amount),
+                    PaneDirection::Right => floating.left = floating.left.saturating_add(amount),
+                    PaneDirection::Up => floating.top = floating.top.saturating_sub(amount),
+                    PaneDirection::Down => floating.top = floating.top.saturating_add(amount),
+                    PaneDirection::Next | PaneDirection::Prev => {}
+                }
+                constrain_floating_pane(floating, &tab_size);
+            }
+            FloatingPaneOp::Resize(direction, amount) => {
+                let floating = self.floating.last_mut().unwrap();
+                match direction {
+                    PaneDirection::Left => {
+                        floating.size.cols = floating.size.cols.saturating_sub(amount)
+                    }
+                    PaneDirection::Right => {
+                        floating.size.cols = floating.size.cols.saturating_add(amount)
+                    }
+                    PaneDirection::Up => {
+                        floating.size.rows = floating.size.rows.saturating_sub(amount)
+                    }
+                    PaneDirection::Down => {
+                        floating.size.rows = floating.size.rows.saturating_add(amount)
+                    }
+                    PaneDirection::Next | PaneDirection::Prev => {}
+                }
+                // Grow into the space to the left/above when
+                // the pane would otherwise extend beyond the tab
+                let cols = floating.size.cols.clamp(1, tab_size.cols);
+                let rows = floating.size.rows.clamp(1, tab_size.rows);
+                floating.left = floating.left.min(tab_size.cols - cols);
+                floating.top = floating.top.min(tab_size.rows - rows);
+                constrain_floating_pane(floating, &tab_size);
+                floating.pane.resize(floating.size).ok();
+            }
+            FloatingPaneOp::Promote(direction) => {
+                let request = SplitRequest {
+                    direction: match direction {
+                        PaneDirection::Up | PaneDirection::Down => SplitDirection::Vertical,
+                        PaneDirection::Left
+                        | PaneDirection::Right
+                        | PaneDirection::Next
+                        | PaneDirection::Prev => SplitDirection::Horizontal,
+                    },
+                    target_is_second: !matches!(direction, PaneDirection::Left | PaneDirection::Up),
+                    top_level: false,
+                    size: SplitSize::default(),
+                };
+
+                let prior = self.get_active_pane();
+                let floating = self.floating.pop().unwrap();
+                let was_visible = self.floating_visible;
+                // The pane that we are splitting is the active pane of the tree
+                self.floating_visible = false;
+                self.set_zoomed(false);
+                match self.split_and_insert(self.active, request, Arc::clone(&floating.pane)) {
+                    Ok(pane_index) => {
+                        self.active = pane_index;
+                        self.recency.tag(pane_index);
+                        self.advise_focus_change(prior);
+                    }
+                    Err(err) => {
+                        self.floating.push(floating);
+                        self.floating_visible = was_visible;
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
+        Ok(())
+    }
 }
 
 /// This type is used directly by the codec, take care to bump
@@ -2108,12 +2515,22 @@ pub enum PaneNode {
         node: SplitDirectionAndSize,
     },
     Leaf(PaneEntry),
+    /// The split tree of a tab, together with the panes that float
+    /// above it.  The `left_col` and `top_row` of the floating entries
+    /// hold their position within the tab.
+    Floating {
+        tree: Box<PaneNode>,
+        floating: Vec<PaneEntry>,
+        visible: bool,
+    },
 }
 
 impl PaneNode {
+    /// Returns the split tree, omitting any floating panes
     pub fn into_tree(self) -> bintree::Tree<PaneEntry, SplitDirectionAndSize> {
         match self {
             PaneNode::Empty => bintree::Tree::Empty,
+            PaneNode::Floating { tree, .. } => tree.into_tree(),
             PaneNode::Split { left, right, node } => bintree::Tree::Node {
                 left: Box::new((*left).into_tree()),
                 right: Box::new((*right).into_tree()),
@@ -2128,6 +2545,15 @@ impl PaneNode {
             PaneNode::Empty => None,
             PaneNode::Split { node, .. } => Some(node.size()),
             PaneNode::Leaf(entry) => Some(entry.size),
+            PaneNode::Floating { tree, .. } => tree.root_size(),
+        }
+    }
+
+    /// Returns the entries for the floating panes, bottom-most first
+    pub fn floating_entries(&self) -> &[PaneEntry] {
+        match self {
+            PaneNode::Floating { floating, .. } => floating,
+            _ => &[],
         }
     }
 
@@ -2139,6 +2565,9 @@ impl PaneNode {
                 None => right.window_and_tab_ids(),
             },
             PaneNode::Leaf(entry) => Some((entry.window_id, entry.tab_id)),
+            PaneNode::Floating { tree, floating, .. } => tree
+                .window_and_tab_ids()
+                .or_else(|| floating.first().map(|e| (e.window_id, e.tab_id))),
         }
     }
 }
@@ -2267,7 +2696,18 @@ mod test {
         }
 
         fn get_dimensions(&self) -> RenderableDimensions {
-            unimplemented!();
+            let size = *self.size.lock();
+            RenderableDimensions {
+                cols: size.cols,
+                viewport_rows: size.rows,
+                scrollback_rows: size.rows,
+                physical_top: 0,
+                scrollback_top: 0,
+                dpi: size.dpi,
+                pixel_width: size.pixel_width,
+                pixel_height: size.pixel_height,
+                reverse_video: false,
+            }
         }
 
         fn get_title(&self) -> String {
@@ -2517,6 +2957,145 @@ mod test {
         assert_eq!(600, panes[2].pixel_height);
     }
 
+    #[test]
+    fn floating_panes() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new(1, size));
+
+        let float_size = TerminalSize {
+            rows: 12,
+            cols: 40,
+            pixel_width: 400,
+            pixel_height: 300,
+            dpi: 96,
+        };
+        let floating = FakePane::new(2, float_size);
+        tab.add_floating_pane(&floating, float_size);
+
+        assert!(tab.floating_panes_visible());
+        assert_eq!(2, tab.get_active_pane().unwrap().pane_id());
+        assert_eq!(Some(2), tab.count_panes());
+        assert_eq!(false, tab.iter_panes()[0].is_active);
+
+        let panes = tab.iter_floating_panes();
+        assert_eq!(1, panes.len());
+        assert_eq!(true, panes[0].is_active);
+        assert_eq!(20, panes[0].left);
+        assert_eq!(6, panes[0].top);
+        assert_eq!(40, panes[0].width);
+        assert_eq!(12, panes[0].height);
+
+        // Moving is constrained to the bounds of the tab
+        tab.apply_floating_pane_op(FloatingPaneOp::Move(PaneDirection::Right, 100))
+            .unwrap();
+        tab.apply_floating_pane_op(FloatingPaneOp::Move(PaneDirection::Up, 2))
+            .unwrap();
+        let panes = tab.iter_floating_panes();
+        assert_eq!(40, panes[0].left);
+        assert_eq!(4, panes[0].top);
+
+        // Growing beyond the right edge shifts the pane to the left
+        tab.apply_floating_pane_op(FloatingPaneOp::Resize(PaneDirection::Right, 10))
+            .unwrap();
+        tab.apply_floating_pane_op(FloatingPaneOp::Resize(PaneDirection::Up, 2))
+            .unwrap();
+        let panes = tab.iter_floating_panes();
+        assert_eq!(30, panes[0].left);
+        assert_eq!(50, panes[0].width);
+        assert_eq!(10, panes[0].height);
+        assert_eq!(500, panes[0].pixel_width);
+        assert_eq!(250, panes[0].pixel_height);
+
+        tab.apply_floating_pane_op(FloatingPaneOp::Toggle).unwrap();
+        assert!(!tab.floating_panes_visible());
+        assert_eq!(1, tab.get_active_pane().unwrap().pane_id());
+        assert_eq!(false, tab.iter_floating_panes()[0].is_active);
+
+        tab.set_active_pane(&floating);
+        assert!(tab.floating_panes_visible());
+        assert_eq!(2, tab.get_active_pane().unwrap().pane_id());
+
+        tab.apply_floating_pane_op(FloatingPaneOp::Promote(PaneDirection::Left))
+            .unwrap();
+        assert!(tab.iter_floating_panes().is_empty());
+        assert!(!tab.floating_panes_visible());
+        assert_eq!(2, tab.get_active_pane().unwrap().pane_id());
+
+        let panes = tab.iter_panes();
+        assert_eq!(2, panes.len());
+        assert_eq!(2, panes[0].pane.pane_id());
+        assert_eq!(true, panes[0].is_active);
+        assert_eq!(0, panes[0].left);
+        assert_eq!(40, panes[0].width);
+        assert_eq!(1, panes[1].pane.pane_id());
+        assert_eq!(41, panes[1].left);
+        assert_eq!(39, panes[1].width);
+
+        assert!(tab.apply_floating_pane_op(FloatingPaneOp::Toggle).is_err());
+    }
+
+    #[test]
+    fn size_for_pane_sizes() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new(1, size));
+        let request = SplitRequest {
+            direction: SplitDirection::Horizontal,
+            ..Default::default()
+        };
+        let split_size = tab.compute_split_size(0, request).unwrap();
+        tab.split_and_insert(0, request, FakePane::new(2, split_size.second))
+            .unwrap();
+
+        assert_eq!(None, tab.size_for_pane_sizes(&HashMap::new()));
+
+        // A client that requested a narrower second pane wants a narrower tab
+        let mut pane_sizes = HashMap::new();
+        pane_sizes.insert(
+            2,
+            TerminalSize {
+                rows: 30,
+                cols: 20,
+                pixel_width: 200,
+                pixel_height: 750,
+                dpi: 96,
+            },
+        );
+        assert_eq!(
+            Some(TerminalSize {
+                rows: 24,
+                cols: 60,
+                pixel_width: 600,
+                pixel_height: 600,
+                dpi: 96,
+            }),
+            tab.size_for_pane_sizes(&pane_sizes)
+        );
+
+        // When zoomed, the tab is the size of the zoomed pane
+        tab.set_active_idx(1);
+        tab.set_zoomed(true);
+        assert_eq!(Some(pane_sizes[&2]), tab.size_for_pane_sizes(&pane_sizes));
+        pane_sizes.remove(&2);
+        pane_sizes.insert(1, size);
+        assert_eq!(None, tab.size_for_pane_sizes(&pane_sizes));
+    }
+
     fn is_send_and_sync<T: Send + Sync>() -> bool {
         true
     }
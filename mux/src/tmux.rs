@@ -3,7 +3,8 @@ use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState, SplitSource}
 use crate::pane::{Pane, PaneId};
 use crate::tab::{SplitRequest, Tab, TabId};
 use crate::tmux_commands::{
-    ListAllPanes, ListAllWindows, ListCommands, NewWindow, SplitPane, TmuxCommand,
+    DetachClient, ListAllPanes, ListAllWindows, ListCommands, ListSessions, NewSession, NewWindow,
+    SplitPane, SwitchClient, TmuxCommand,
 };
 use crate::window::WindowId;
 use crate::{Mux, MuxWindowBuilder};
@@ -13,6 +14,7 @@ use parking_lot::{Condvar, Mutex};
 use portable_pty::CommandBuilder;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use termwiz::tmux_cc::*;
 use wezterm_term::TerminalSize;
@@ -23,6 +25,14 @@ pub enum AttachState {
     Done,
 }
 
+/// How to attach the control client to the session for a workspace
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum SessionSwitch {
+    AlreadyAttached,
+    Switch(TmuxSessionId),
+    Create,
+}
+
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
 enum State {
     WaitForInitialGuard,
@@ -60,6 +70,8 @@ pub(crate) struct TmuxTab {
     pub tmux_window_id: TmuxWindowId,
     pub layout_csum: String,
     pub panes: HashSet<TmuxPaneId>, // tmux panes within tmux window
+    pub name: String,               // last known name of the tmux window
+    pub window_size: Option<(usize, usize)>, // last (cols, rows) sent to tmux
 }
 
 pub(crate) type TmuxCmdQueue = VecDeque<Box<dyn TmuxCommand>>;
@@ -72,10 +84,14 @@ pub(crate) struct TmuxDomainState {
     pub gui_tabs: Mutex<HashMap<TmuxWindowId, TmuxTab>>,
     pub remote_panes: Mutex<HashMap<TmuxPaneId, RefTmuxRemotePane>>,
     pub tmux_session: Mutex<Option<TmuxSessionId>>,
+    pub sessions: Mutex<HashMap<TmuxSessionId, String>>, // all sessions on the server
     pub support_commands: Mutex<HashMap<String, String>>,
     pub attach_state: Mutex<AttachState>,
     pending_splits: Mutex<VecDeque<promise::Promise<TmuxPaneId>>>,
     pub backlog: Mutex<HashMap<TmuxPaneId, Vec<u8>>>,
+    /// Set once we have asked tmux to detach, so that the local
+    /// panes going away don't kill their tmux counterparts
+    detaching: AtomicBool,
 }
 
 pub struct TmuxDomain {
@@ -118,8 +134,11 @@ impl TmuxDomainState {
                 }
                 Event::Exit { reason: _ } => {
                     *self.state.lock() = State::Exit;
+                    // Forget the remote panes; the local panes will close
+                    // once their active_lock is released, and there is
+                    // nothing left in tmux for them to kill
                     let mut pane_map = self.remote_panes.lock();
-                    for (_, v) in pane_map.iter_mut() {
+                    for (_, v) in pane_map.drain() {
                         let remote_pane = v.lock();
                         let (lock, condvar) = &*remote_pane.active_lock;
                         let mut released = lock.lock();
@@ -171,14 +190,41 @@ impl TmuxDomainState {
                         log::debug!("Tmux pane {} havn't been attached", pane);
                     }
                 }
-                Event::SessionChanged { session, name: _ } => {
-                    *self.tmux_session.lock() = Some(*session);
-                    let mut cmd_queue = self.cmd_queue.as_ref().lock();
-                    cmd_queue.push_back(Box::new(ListCommands));
+                Event::SessionChanged { session, name } => {
+                    self.sessions.lock().insert(*session, name.to_string());
+                    let previous = self.tmux_session.lock().replace(*session);
+                    match previous {
+                        None => {
+                            let mut cmd_queue = self.cmd_queue.as_ref().lock();
+                            cmd_queue.push_back(Box::new(ListCommands));
 
-                    self.subscribe_notification();
+                            self.subscribe_notification();
+                        }
+                        Some(previous) if previous != *session => {
+                            self.switch_session(*session, name);
+                        }
+                        Some(_) => {}
+                    }
+                    self.cmd_queue
+                        .as_ref()
+                        .lock()
+                        .push_back(Box::new(ListSessions));
                     log::info!("tmux session changed:{}", session);
                 }
+                Event::SessionsChanged => {
+                    self.cmd_queue
+                        .as_ref()
+                        .lock()
+                        .push_back(Box::new(ListSessions));
+                }
+                Event::SessionRenamed { name } => {
+                    if let Some(session) = *self.tmux_session.lock() {
+                        let old_name = self.sessions.lock().insert(session, name.to_string());
+                        if let Some(old_name) = old_name {
+                            Mux::get().rename_workspace(&old_name, name);
+                        }
+                    }
+                }
                 Event::WindowAdd { window } => {
                     // Only handle the new tab, the first empty window handled by sync_window_state
                     if !self.gui_window.lock().is_none() {
@@ -212,8 +258,11 @@ impl TmuxDomainState {
                     log::info!("tmux window pane changed: {}:{}", window, pane);
                 }
                 Event::WindowRenamed { window, name } => {
-                    let gui_tabs = self.gui_tabs.lock();
-                    if let Some(x) = gui_tabs.get(&window) {
+                    let mut gui_tabs = self.gui_tabs.lock();
+                    if let Some(x) = gui_tabs.get_mut(&window) {
+                        // Remember the name so that the resulting title
+                        // change isn't sent back to tmux
+                        x.name = name.to_string();
                         let mux = Mux::get();
                         if let Some(tab) = mux.get_tab(x.tab_id) {
                             tab.set_title(&format!("{}", name));
@@ -271,6 +320,40 @@ impl TmuxDomainState {
         .detach();
     }
 
+    /// Returns true if the client is detaching from, or has left, tmux;
+    /// local panes that are removed then should be left alone in tmux
+    pub(crate) fn is_detaching(&self) -> bool {
+        self.detaching.load(Ordering::SeqCst) || *self.state.lock() == State::Exit
+    }
+
+    /// Called when tmux refused to detach
+    pub(crate) fn detach_failed(&self) {
+        self.detaching.store(false, Ordering::SeqCst);
+    }
+
+    /// Resolves the tmux session that corresponds to `workspace`
+    fn session_switch(&self, workspace: &str) -> SessionSwitch {
+        let session = self
+            .sessions
+            .lock()
+            .iter()
+            .find(|(_, name)| name.as_str() == workspace)
+            .map(|(session, _)| *session);
+        match session {
+            Some(session) if Some(session) == *self.tmux_session.lock() => {
+                SessionSwitch::AlreadyAttached
+            }
+            Some(session) => SessionSwitch::Switch(session),
+            None => SessionSwitch::Create,
+        }
+    }
+
+    /// Returns the name of the session that we are currently attached to
+    pub fn current_session_name(&self) -> Option<String> {
+        let session = (*self.tmux_session.lock())?;
+        self.sessions.lock().get(&session).cloned()
+    }
+
     /// create a standalone window for tmux tabs
     pub fn create_gui_window(&self) {
         if self.gui_window.lock().is_none() {
@@ -283,10 +366,7 @@ impl TmuxDomainState {
                         notified: false,
                     }
                 } else {
-                    mux.new_empty_window(
-                        None, /* TODO: pass session here */
-                        None, /* position */
-                    )
+                    mux.new_empty_window(self.current_session_name(), None /* position */)
                 };
 
             log::info!("Tmux create window id {}", window_builder.window_id);
@@ -297,6 +377,57 @@ impl TmuxDomainState {
         };
     }
 
+    /// Move the window holding our tabs into the workspace named after
+    /// the tmux session, keeping it active if it was the active workspace
+    pub fn set_gui_workspace(&self, workspace: &str) {
+        let mux = Mux::get();
+        let mut gui_window = self.gui_window.lock();
+        let Some(window_id) = gui_window.as_ref().map(|w| **w) else {
+            return;
+        };
+        let old_workspace = match mux.get_window_mut(window_id) {
+            Some(mut window) => {
+                let old_workspace = window.get_workspace().to_string();
+                window.set_workspace(workspace);
+                old_workspace
+            }
+            None => {
+                // The window went away; a new one will be made on demand
+                gui_window.take();
+                return;
+            }
+        };
+        if mux.active_workspace() == old_workspace {
+            mux.set_active_workspace(workspace);
+        }
+    }
+
+    /// The control client has been switched to a different session:
+    /// replace the tabs of the previous session with those of the new one
+    fn switch_session(&self, session: TmuxSessionId, name: &str) {
+        *self.attach_state.lock() = AttachState::Init;
+        self.set_gui_workspace(name);
+
+        let windows: Vec<TmuxWindowId> = self.gui_tabs.lock().keys().cloned().collect();
+        for window in windows {
+            let _ = self.remove_detached_window(window);
+        }
+
+        // Removing the last tab may have pruned the window
+        let mux = Mux::get();
+        let mut gui_window = self.gui_window.lock();
+        if let Some(window_id) = gui_window.as_ref().map(|w| **w) {
+            if mux.get_window(window_id).is_none() {
+                gui_window.take();
+            }
+        }
+
+        self.cmd_queue.lock().push_back(Box::new(ListAllWindows {
+            session_id: session,
+            window_id: None,
+        }));
+    }
+
     /// create a tmux window
     pub fn create_tmux_window(&self) {
         let mut cmd_queue = self.cmd_queue.as_ref().lock();
@@ -346,10 +477,12 @@ impl TmuxDomain {
             gui_tabs: Mutex::new(HashMap::default()),
             remote_panes: Mutex::new(HashMap::default()),
             tmux_session: Mutex::new(None),
+            sessions: Mutex::new(HashMap::default()),
             support_commands: Mutex::new(HashMap::default()),
             attach_state: Mutex::new(AttachState::Init),
             pending_splits: Mutex::new(VecDeque::default()),
             backlog: Mutex::new(HashMap::default()),
+            detaching: AtomicBool::new(false),
         });
 
         Self { inner }
@@ -421,15 +554,128 @@ impl Domain for TmuxDomain {
         Ok(())
     }
 
+    fn workspaces(&self) -> Vec<String> {
+        if *self.inner.state.lock() == State::Exit {
+            return vec![];
+        }
+        self.inner.sessions.lock().values().cloned().collect()
+    }
+
+    fn activate_workspace(&self, workspace: &str) -> anyhow::Result<bool> {
+        if *self.inner.state.lock() == State::Exit {
+            return Ok(false);
+        }
+        let cmd: Option<Box<dyn TmuxCommand>> = match self.inner.session_switch(workspace) {
+            SessionSwitch::AlreadyAttached => None,
+            SessionSwitch::Switch(session) => Some(Box::new(SwitchClient { session })),
+            SessionSwitch::Create => Some(Box::new(NewSession {
+                name: workspace.to_string(),
+            })),
+        };
+
+        // Move our window over right away so that the workspace isn't
+        // empty while we wait for tmux to switch sessions
+        self.inner.set_gui_workspace(workspace);
+        if let Some(cmd) = cmd {
+            self.inner.cmd_queue.lock().push_back(cmd);
+            TmuxDomainState::schedule_send_next_command(self.inner.domain_id);
+        }
+        Ok(true)
+    }
+
     fn detachable(&self) -> bool {
-        false
+        true
     }
 
     fn detach(&self) -> anyhow::Result<()> {
-        anyhow::bail!("detach not implemented for TmuxDomain");
+        if *self.inner.state.lock() == State::Exit {
+            anyhow::bail!("tmux domain is already detached");
+        }
+        // The window is about to be removed along with its tabs; that
+        // must not be mistaken for the user closing the tmux windows
+        self.inner.detaching.store(true, Ordering::SeqCst);
+        self.inner
+            .cmd_queue
+            .lock()
+            .push_back(Box::new(DetachClient));
+        TmuxDomainState::schedule_send_next_command(self.inner.domain_id);
+        Ok(())
     }
 
     fn state(&self) -> DomainState {
-        DomainState::Attached
+        if *self.inner.state.lock() == State::Exit {
+            DomainState::Detached
+        } else {
+            DomainState::Attached
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sessions_map_to_workspaces() {
+        let domain = TmuxDomain::new(0);
+        {
+            let mut sessions = domain.inner.sessions.lock();
+            sessions.insert(1, "main".to_string());
+            sessions.insert(2, "work".to_string());
+        }
+        domain.inner.tmux_session.lock().replace(1);
+
+        let mut workspaces = domain.workspaces();
+        workspaces.sort();
+        assert_eq!(workspaces, vec!["main".to_string(), "work".to_string()]);
+
+        assert_eq!(
+            domain.inner.session_switch("main"),
+            SessionSwitch::AlreadyAttached
+        );
+        assert_eq!(
+            domain.inner.session_switch("work"),
+            SessionSwitch::Switch(2)
+        );
+        assert_eq!(domain.inner.session_switch("new"), SessionSwitch::Create);
+
+        // Once detached, the sessions are no longer offered as workspaces
+        *domain.inner.state.lock() = State::Exit;
+        assert!(domain.workspaces().is_empty());
+        assert!(!domain.activate_workspace("work").unwrap());
+    }
+
+    #[test]
+    fn detach_keeps_remote_windows() {
+        let _executor = promise::spawn::ScopedExecutor::new();
+        let domain = TmuxDomain::new(0);
+        *domain.inner.state.lock() = State::Idle;
+
+        let pipe = filedescriptor::Pipe::new().unwrap();
+        domain.inner.remote_panes.lock().insert(
+            3,
+            Arc::new(Mutex::new(TmuxRemotePane {
+                local_pane_id: 42,
+                output_write: pipe.write,
+                active_lock: Arc::new((Mutex::new(false), Condvar::new())),
+                session_id: 0,
+                window_id: 1,
+                pane_id: 3,
+                cursor_x: 0,
+                cursor_y: 0,
+                pane_width: 80,
+                pane_height: 24,
+                pane_left: 0,
+                pane_top: 0,
+            })),
+        );
+
+        // Closing the window detaches, then removes its tabs and panes
+        domain.detach().unwrap();
+        domain.inner.kill_removed_pane(42);
+
+        let cmd_queue = domain.inner.cmd_queue.lock();
+        let commands: Vec<String> = cmd_queue.iter().map(|cmd| cmd.get_command(0)).collect();
+        assert_eq!(commands, vec!["detach-client\n".to_string()]);
     }
 }
@@ -8,7 +8,7 @@ use crate::{Mux, MuxNotification, Pane};
 use anyhow::{anyhow, Context};
 use parking_lot::{Condvar, Mutex};
 use portable_pty::{MasterPty, PtySize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Write};
 use std::io::Write as _;
 use std::sync::Arc;
@@ -101,6 +101,8 @@ impl TmuxDomainState {
                     tmux_window_id: target.window_id,
                     layout_csum: target.layout_csum.clone(),
                     panes: HashSet::new(),
+                    name: target.window_name.clone(),
+                    window_size: None,
                 },
             );
         }
@@ -124,8 +126,10 @@ impl TmuxDomainState {
 
         let mux = Mux::get();
         for p in to_remove {
-            let pane_map = self.remote_panes.lock();
-            let Some(pane) = pane_map.get(&p) else {
+            // Forget the remote pane before removing the local one, so that
+            // the resulting PaneRemoved notification doesn't try to kill it
+            // in tmux a second time
+            let Some(pane) = self.remote_panes.lock().remove(&p) else {
                 continue;
             };
             let local_pane_id = pane.lock().local_pane_id;
@@ -150,6 +154,13 @@ impl TmuxDomainState {
             }
         };
 
+        {
+            let mut pane_map = self.remote_panes.lock();
+            for p in tab.panes.iter() {
+                pane_map.remove(p);
+            }
+        }
+
         let mux = Mux::get();
         mux.remove_tab(tab.tab_id);
         gui_tabs.remove(&window_id);
@@ -157,6 +168,83 @@ impl TmuxDomainState {
         Ok(())
     }
 
+    /// Called when a local pane has been removed.  If it is still mapped
+    /// to a tmux pane then it was closed from our side, so kill the remote
+    /// pane too, or the whole tmux window if its local tab has gone away.
+    /// Nothing is killed while detaching, as the local panes are then
+    /// removed only because the window holding them was closed.
+    pub(crate) fn kill_removed_pane(&self, local_pane_id: PaneId) {
+        if self.is_detaching() {
+            return;
+        }
+        let remote_pane = {
+            let mut pane_map = self.remote_panes.lock();
+            let Some(pane_id) = pane_map
+                .iter()
+                .find(|(_, p)| p.lock().local_pane_id == local_pane_id)
+                .map(|(pane_id, _)| *pane_id)
+            else {
+                return;
+            };
+            match pane_map.remove(&pane_id) {
+                Some(p) => p,
+                None => return,
+            }
+        };
+
+        let (pane_id, window_id) = {
+            let remote_pane = remote_pane.lock();
+            let (lock, condvar) = &*remote_pane.active_lock;
+            *lock.lock() = true;
+            condvar.notify_all();
+            (remote_pane.pane_id, remote_pane.window_id)
+        };
+
+        let cmd: Box<dyn TmuxCommand> = {
+            let mut gui_tabs = self.gui_tabs.lock();
+            let Some(local_tab) = gui_tabs.get_mut(&window_id) else {
+                // The window has already been killed as a whole
+                return;
+            };
+            local_tab.panes.remove(&pane_id);
+
+            if Mux::get().get_tab(local_tab.tab_id).is_none() {
+                gui_tabs.remove(&window_id);
+                Box::new(KillWindow { window_id })
+            } else {
+                Box::new(KillPane { pane_id })
+            }
+        };
+
+        self.cmd_queue.lock().push_back(cmd);
+        TmuxDomainState::schedule_send_next_command(self.domain_id);
+    }
+
+    /// Propagate a title change of a local tab to its tmux window
+    fn rename_window_for_tab(&self, tab_id: TabId, title: &str) {
+        if title.is_empty() {
+            return;
+        }
+        let window_id = {
+            let mut gui_tabs = self.gui_tabs.lock();
+            let Some(local_tab) = gui_tabs.values_mut().find(|t| t.tab_id == tab_id) else {
+                return;
+            };
+            // This is the echo of a rename that came from tmux
+            if local_tab.name == title {
+                return;
+            }
+            local_tab.name = title.to_string();
+            local_tab.tmux_window_id
+        };
+
+        self.cmd_queue.lock().push_back(Box::new(RenameWindow {
+            window_id,
+            name: title.to_string(),
+        }));
+        TmuxDomainState::schedule_send_next_command(self.domain_id);
+    }
+
     fn set_pane_cursor_position(&self, pane: &Arc<dyn Pane>, x: usize, y: usize) {
         pane.perform_actions(vec![Action::CSI(CSI::Cursor(
             Cursor::CharacterAndLinePosition {
@@ -613,6 +701,12 @@ impl TmuxDomainState {
                             }
                         }
                     }
+                    MuxNotification::PaneRemoved(pane_id) => {
+                        tmux_domain.inner.kill_removed_pane(pane_id);
+                    }
+                    MuxNotification::TabTitleChanged { tab_id, title } => {
+                        tmux_domain.inner.rename_window_for_tab(tab_id, &title);
+                    }
                     _ => {}
                 }
             })
@@ -916,8 +1010,8 @@ impl TmuxCommand for Resize {
             None => return "".to_string(),
         };
 
-        let gui_tabs = tmux_domain.inner.gui_tabs.lock();
-        let local_tab = match gui_tabs.get(&tmux_window_id) {
+        let mut gui_tabs = tmux_domain.inner.gui_tabs.lock();
+        let local_tab = match gui_tabs.get_mut(&tmux_window_id) {
             Some(t) => t,
             None => return "".to_string(),
         };
@@ -927,6 +1021,18 @@ impl TmuxCommand for Resize {
             None => return "".to_string(),
         };
 
+        // When only a split has moved, the window size is unchanged and
+        // resizing the pane is sufficient
+        if local_tab.window_size == Some((size.cols, size.rows)) {
+            return ResizePane {
+                pane_id: self.pane_id,
+                cols: self.size.cols as usize,
+                rows: self.size.rows as usize,
+            }
+            .get_command(domain_id);
+        }
+        local_tab.window_size = Some((size.cols, size.rows));
+
         let support_commands = tmux_domain.inner.support_commands.lock();
 
         if let Some(_x) = support_commands.get("resize-window") {
@@ -1196,3 +1302,218 @@ impl TmuxCommand for AttachDone {
         Ok(())
     }
 }
+
+/// Quote `text` so that tmux's command parser treats it as a single
+/// argument
+fn tmux_quote(text: &str) -> String {
+    let text: String = text.chars().filter(|c| !c.is_control()).collect();
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[derive(Debug)]
+pub(crate) struct ResizePane {
+    pub pane_id: TmuxPaneId,
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl TmuxCommand for ResizePane {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        format!(
+            "resize-pane -x {} -y {} -t %{}\n",
+            self.cols, self.rows, self.pane_id
+        )
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("resize-pane in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct KillPane {
+    pub pane_id: TmuxPaneId,
+}
+
+impl TmuxCommand for KillPane {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        format!("kill-pane -t %{}\n", self.pane_id)
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("kill-pane in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct KillWindow {
+    pub window_id: TmuxWindowId,
+}
+
+impl TmuxCommand for KillWindow {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        format!("kill-window -t @{}\n", self.window_id)
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("kill-window in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RenameWindow {
+    pub window_id: TmuxWindowId,
+    pub name: String,
+}
+
+impl TmuxCommand for RenameWindow {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        format!(
+            "rename-window -t @{} {}\n",
+            self.window_id,
+            tmux_quote(&self.name)
+        )
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("rename-window in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+/// Detaching makes tmux leave control mode; it will respond with
+/// `%exit`, which tears down the domain state
+#[derive(Debug)]
+pub(crate) struct DetachClient;
+impl TmuxCommand for DetachClient {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        "detach-client\n".to_owned()
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("detach-client in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            if let Some(domain) = Mux::get().get_domain(domain_id) {
+                if let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() {
+                    tmux_domain.inner.detach_failed();
+                }
+            }
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ListSessions;
+impl TmuxCommand for ListSessions {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        "list-sessions -F '#{session_id} #{session_name}'\n".to_owned()
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("list-sessions in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        let mux = Mux::get();
+        let domain = match mux.get_domain(domain_id) {
+            Some(d) => d,
+            None => anyhow::bail!("Tmux domain lost"),
+        };
+        let tmux_domain = match domain.downcast_ref::<TmuxDomain>() {
+            Some(t) => t,
+            None => anyhow::bail!("Tmux domain lost"),
+        };
+
+        let mut sessions = HashMap::new();
+        for line in result.output.split('\n') {
+            if line.is_empty() {
+                continue;
+            }
+            // Session names may contain spaces, so only split once
+            let (session_id, name) = line
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("missing session_name"))?;
+            sessions.insert(parse_sigil_number(session_id)?, name.to_string());
+        }
+        log::debug!("sessions in domain_id {}: {:?}", domain_id, sessions);
+        *tmux_domain.inner.sessions.lock() = sessions;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SwitchClient {
+    pub session: TmuxSessionId,
+}
+
+impl TmuxCommand for SwitchClient {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        format!("switch-client -t ${}\n", self.session)
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("switch-client in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+/// Creating a session from the control client also switches
+/// the client over to it
+#[derive(Debug)]
+pub(crate) struct NewSession {
+    pub name: String,
+}
+
+impl TmuxCommand for NewSession {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        format!("new-session -s {}\n", tmux_quote(&self.name))
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("new-session in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn quote() {
+        assert_eq!(tmux_quote("hello"), "'hello'");
+        assert_eq!(tmux_quote("it's"), "'it'\\''s'");
+        assert_eq!(tmux_quote("a\nb"), "'ab'");
+    }
+}
@@ -0,0 +1,45 @@
+//! Removes escape sequences from terminal output, preserving
+//! only printable characters and CR, LF and HT.
+use termwiz::escape::parser::Parser;
+use termwiz::escape::{Action, ControlCode};
+
+/// Incrementally strips escape sequences from a stream of bytes.
+/// The parser state is retained between calls, so sequences that
+/// are split across multiple reads are handled correctly.
+#[derive(Default)]
+pub struct StripAnsiEscapes {
+    parser: Parser,
+}
+
+impl StripAnsiEscapes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the text portion of `data` to `output`
+    pub fn strip(&mut self, data: &[u8], output: &mut String) {
+        self.parser.parse(data, |action| match action {
+            Action::Print(c) => output.push(c),
+            Action::Control(
+                c @ (ControlCode::HorizontalTab
+                | ControlCode::LineFeed
+                | ControlCode::CarriageReturn),
+            ) => output.push(c as u8 as char),
+            _ => {}
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn strip_split_sequences() {
+        let mut stripper = StripAnsiEscapes::new();
+        let mut output = String::new();
+        stripper.strip(b"\x1b[1mbold\x1b[", &mut output);
+        stripper.strip(b"0m\tplain\x07\r\n", &mut output);
+        assert_eq!(output, "bold\tplain\r\n");
+    }
+}
@@ -1,7 +1,6 @@
 use clap::Parser;
-use std::io::{Read, Result};
-use termwiz::escape::parser::Parser as TWParser;
-use termwiz::escape::{Action, ControlCode};
+use std::io::{Read, Result, Write};
+use strip_ansi_escapes::StripAnsiEscapes;
 
 #[derive(Debug, Parser)]
 /// This is a little utility that strips escape sequences from
@@ -17,7 +16,8 @@ fn main() -> Result<()> {
     let _ = Opt::parse();
     let mut buf = [0u8; 4096];
 
-    let mut parser = TWParser::new();
+    let mut stripper = StripAnsiEscapes::new();
+    let mut text = String::new();
 
     loop {
         let len = std::io::stdin().read(&mut buf)?;
@@ -25,15 +25,8 @@ fn main() -> Result<()> {
             return Ok(());
         }
 
-        parser.parse(&buf[0..len], |action| match action {
-            Action::Print(c) => print!("{}", c),
-            Action::Control(c) => match c {
-                ControlCode::HorizontalTab
-                | ControlCode::LineFeed
-                | ControlCode::CarriageReturn => print!("{}", c as u8 as char),
-                _ => {}
-            },
-            _ => {}
-        });
+        text.clear();
+        stripper.strip(&buf[0..len], &mut text);
+        std::io::stdout().write_all(text.as_bytes())?;
     }
 }
@@ -5,6 +5,7 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 use std::result::Result;
 pub use wezterm_cell::color::{AnsiColor, ColorAttribute, RgbColor, SrgbaTuple};
+use wezterm_escape_parser::osc::{ColorControlKey, ColorControlValue};
 
 #[derive(Clone, PartialEq)]
 pub struct Palette256(pub [SrgbaTuple; 256]);
@@ -56,6 +57,18 @@ pub struct ColorPalette {
     pub selection_bg: SrgbaTuple,
     pub scrollbar_thumb: SrgbaTuple,
     pub split: SrgbaTuple,
+    /// Tab bar colors that were set by the application via OSC 21.
+    /// None means that the configured tab bar colors are used.
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub active_tab_fg: Option<SrgbaTuple>,
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub active_tab_bg: Option<SrgbaTuple>,
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub inactive_tab_fg: Option<SrgbaTuple>,
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub inactive_tab_bg: Option<SrgbaTuple>,
+    #[cfg_attr(feature = "use_serde", serde(default))]
+    pub tab_bar_bg: Option<SrgbaTuple>,
 }
 
 impl fmt::Debug for Palette256 {
@@ -87,6 +100,75 @@ impl ColorPalette {
     }
 }
 
+impl ColorPalette {
+    /// Returns the color named by an OSC 21 key.
+    /// Unrecognized keys yield `Query` and colors that are not set
+    /// yield `Reset`, which is how they are reported to the application.
+    pub fn color_control_get(&self, key: &ColorControlKey) -> ColorControlValue {
+        let color = match key {
+            ColorControlKey::Foreground => Some(self.foreground),
+            ColorControlKey::Background => Some(self.background),
+            ColorControlKey::SelectionForeground => Some(self.selection_fg),
+            ColorControlKey::SelectionBackground => Some(self.selection_bg),
+            ColorControlKey::Cursor => Some(self.cursor_bg),
+            ColorControlKey::CursorText => Some(self.cursor_fg),
+            ColorControlKey::ActiveTabForeground => self.active_tab_fg,
+            ColorControlKey::ActiveTabBackground => self.active_tab_bg,
+            ColorControlKey::InactiveTabForeground => self.inactive_tab_fg,
+            ColorControlKey::InactiveTabBackground => self.inactive_tab_bg,
+            ColorControlKey::TabBarBackground => self.tab_bar_bg,
+            ColorControlKey::PaletteIndex(idx) => Some(self.colors.0[*idx as usize]),
+            ColorControlKey::Unknown(_) => return ColorControlValue::Query,
+        };
+        match color {
+            Some(color) => ColorControlValue::Color(color),
+            None => ColorControlValue::Reset,
+        }
+    }
+
+    /// Sets the color named by an OSC 21 key.  `None` resets it
+    /// to the corresponding color from `base`.
+    pub fn color_control_set(
+        &mut self,
+        key: &ColorControlKey,
+        color: Option<SrgbaTuple>,
+        base: &ColorPalette,
+    ) {
+        macro_rules! set {
+            ($name:ident) => {
+                self.$name = color.unwrap_or(base.$name)
+            };
+        }
+        macro_rules! set_optional {
+            ($name:ident) => {
+                self.$name = color.or(base.$name)
+            };
+        }
+        match key {
+            ColorControlKey::Foreground => set!(foreground),
+            ColorControlKey::Background => set!(background),
+            ColorControlKey::SelectionForeground => set!(selection_fg),
+            ColorControlKey::SelectionBackground => set!(selection_bg),
+            ColorControlKey::Cursor => {
+                // As for OSC 12, the border follows the cursor color
+                set!(cursor_bg);
+                set!(cursor_border);
+            }
+            ColorControlKey::CursorText => set!(cursor_fg),
+            ColorControlKey::ActiveTabForeground => set_optional!(active_tab_fg),
+            ColorControlKey::ActiveTabBackground => set_optional!(active_tab_bg),
+            ColorControlKey::InactiveTabForeground => set_optional!(inactive_tab_fg),
+            ColorControlKey::InactiveTabBackground => set_optional!(inactive_tab_bg),
+            ColorControlKey::TabBarBackground => set_optional!(tab_bar_bg),
+            ColorControlKey::PaletteIndex(idx) => {
+                let idx = *idx as usize;
+                self.colors.0[idx] = color.unwrap_or(base.colors.0[idx]);
+            }
+            ColorControlKey::Unknown(_) => {}
+        }
+    }
+}
+
 lazy_static::lazy_static! {
     static ref DEFAULT_PALETTE: ColorPalette = ColorPalette::compute_default();
 }
@@ -188,6 +270,11 @@ impl ColorPalette {
             selection_bg,
             scrollbar_thumb,
             split,
+            active_tab_fg: None,
+            active_tab_bg: None,
+            inactive_tab_fg: None,
+            inactive_tab_bg: None,
+            tab_bar_bg: None,
         }
     }
 }
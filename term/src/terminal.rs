@@ -10,12 +10,44 @@ pub enum ClipboardSelection {
     PrimarySelection,
 }
 
+/// One representation of the clipboard contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub struct ClipboardItem {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ClipboardItem {
+    pub fn is_text(&self) -> bool {
+        self.mime_type == "text/plain" || self.mime_type.starts_with("text/plain;")
+    }
+}
+
 pub trait Clipboard: Send + Sync {
     fn set_contents(
         &self,
         selection: ClipboardSelection,
         data: Option<String>,
     ) -> anyhow::Result<()>;
+
+    /// Set the clipboard to hold the data in one or more MIME types.
+    /// The default implementation assigns only the plain text
+    /// representation, and leaves the clipboard untouched if there
+    /// is no such representation.
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        items: Vec<ClipboardItem>,
+    ) -> anyhow::Result<()> {
+        match items.into_iter().find(ClipboardItem::is_text) {
+            Some(item) => self.set_contents(
+                selection,
+                Some(String::from_utf8_lossy(&item.data).into_owned()),
+            ),
+            None => Ok(()),
+        }
+    }
 }
 
 impl Clipboard for Box<dyn Clipboard> {
@@ -26,6 +58,14 @@ impl Clipboard for Box<dyn Clipboard> {
     ) -> anyhow::Result<()> {
         self.as_ref().set_contents(selection, data)
     }
+
+    fn set_data(
+        &self,
+        selection: ClipboardSelection,
+        items: Vec<ClipboardItem>,
+    ) -> anyhow::Result<()> {
+        self.as_ref().set_data(selection, items)
+    }
 }
 
 pub trait DeviceControlHandler: Send + Sync {
@@ -42,6 +82,27 @@ pub enum Progress {
     Indeterminate,
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum NotificationUrgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum NotificationOccasion {
+    /// Subject only to the `notification_handling` configuration
+    #[default]
+    Always,
+    /// Only when the pane that generated it is not focused
+    Unfocused,
+    /// Only when the pane that generated it is not visible
+    Invisible,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
 pub enum Alert {
@@ -54,7 +115,25 @@ pub enum Alert {
         /// Whether clicking on the notification should focus the
         /// window/tab/pane that generated it
         focus: bool,
+        /// An application assigned identifier for the notification.
+        /// A later notification with the same id replaces this one.
+        id: Option<String>,
+        urgency: NotificationUrgency,
+        /// When the notification should be shown
+        occasion: NotificationOccasion,
+        /// Whether activating the notification should be reported
+        /// back to the application
+        report_activation: bool,
+        /// Whether closing the notification should be reported
+        /// back to the application
+        report_close: bool,
+        /// Automatically dismiss the notification after this duration.
+        /// None uses the system default behavior.
+        timeout: Option<std::time::Duration>,
     },
+    /// The application requested that the notification with the
+    /// specified id be closed
+    CloseToastNotification(String),
     CurrentWorkingDirectoryChanged,
     IconTitleChanged(Option<String>),
     WindowTitleChanged(String),
@@ -71,6 +150,17 @@ pub enum Alert {
     OutputSinceFocusLost,
     /// A change to the progress bar state
     Progress(Progress),
+    /// The application requested the contents of the clipboard via
+    /// OSC 5522.  The response must be sent to the application once
+    /// the user has allowed it.
+    ClipboardRead {
+        selection: ClipboardSelection,
+        /// Echoed back to the application in the response
+        id: Option<String>,
+        /// The MIME types to read; `.` requests the list of
+        /// available types
+        mime_types: Vec<String>,
+    },
 }
 
 pub trait AlertHandler: Send + Sync {
@@ -0,0 +1,178 @@
+use crate::terminal::{Alert, ClipboardItem};
+use crate::{ClipboardSelection, TerminalState};
+use std::io::Write;
+use wezterm_escape_parser::osc::{KittyClipboard, KittyClipboardType};
+use wezterm_escape_parser::OperatingSystemCommand;
+
+/// Upper bound on the accumulated size of a chunked clipboard write,
+/// to avoid unbounded growth from a misbehaving application
+const MAX_CLIPBOARD_WRITE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Accumulates the chunks of a kitty clipboard write until the
+/// application marks it as complete
+#[derive(Debug, Default)]
+pub(crate) struct PendingClipboardWrite {
+    id: Option<String>,
+    primary: bool,
+    items: Vec<ClipboardItem>,
+    /// (target, alias) pairs of MIME types
+    aliases: Vec<(String, String)>,
+    size: usize,
+    /// Set when the write exceeded MAX_CLIPBOARD_WRITE_SIZE
+    too_large: bool,
+}
+
+impl PendingClipboardWrite {
+    fn append(&mut self, mime_type: String, data: Vec<u8>) {
+        self.size += data.len();
+        if self.size > MAX_CLIPBOARD_WRITE_SIZE {
+            self.too_large = true;
+            self.items.clear();
+            return;
+        }
+        match self
+            .items
+            .iter_mut()
+            .find(|item| item.mime_type == mime_type)
+        {
+            Some(item) => item.data.extend_from_slice(&data),
+            None => self.items.push(ClipboardItem { mime_type, data }),
+        }
+    }
+
+    fn into_items(mut self) -> Vec<ClipboardItem> {
+        for (target, alias) in std::mem::take(&mut self.aliases) {
+            if self.items.iter().any(|item| item.mime_type == alias) {
+                continue;
+            }
+            if let Some(item) = self.items.iter().find(|item| item.mime_type == target) {
+                let data = item.data.clone();
+                self.items.push(ClipboardItem {
+                    mime_type: alias,
+                    data,
+                });
+            }
+        }
+        self.items
+    }
+}
+
+fn selection(primary: bool) -> ClipboardSelection {
+    if primary {
+        ClipboardSelection::PrimarySelection
+    } else {
+        ClipboardSelection::Clipboard
+    }
+}
+
+impl TerminalState {
+    pub(crate) fn kitty_clipboard(&mut self, clip: KittyClipboard) {
+        match clip.kind {
+            KittyClipboardType::Read => self.kitty_clipboard_read(clip),
+            KittyClipboardType::Write => {
+                self.pending_clipboard_write.replace(PendingClipboardWrite {
+                    id: clip.id,
+                    primary: clip.primary,
+                    ..Default::default()
+                });
+            }
+            KittyClipboardType::WriteData => {
+                match (clip.mime, self.pending_clipboard_write.as_mut()) {
+                    (Some(mime), Some(pending)) => {
+                        pending.append(mime, clip.payload.unwrap_or_default());
+                    }
+                    (None, Some(_)) => {
+                        let pending = self.pending_clipboard_write.take().unwrap();
+                        self.kitty_clipboard_finish_write(pending);
+                    }
+                    (_, None) => {
+                        self.kitty_clipboard_respond(
+                            KittyClipboardType::Write,
+                            clip.id,
+                            clip.primary,
+                            "EINVAL",
+                        );
+                    }
+                }
+            }
+            KittyClipboardType::WriteAlias => {
+                if let (Some(target), Some(pending)) =
+                    (clip.mime, self.pending_clipboard_write.as_mut())
+                {
+                    let aliases = String::from_utf8_lossy(&clip.payload.unwrap_or_default())
+                        .split_whitespace()
+                        .map(|alias| (target.clone(), alias.to_string()))
+                        .collect::<Vec<_>>();
+                    pending.aliases.extend(aliases);
+                }
+            }
+        }
+    }
+
+    fn kitty_clipboard_finish_write(&mut self, pending: PendingClipboardWrite) {
+        let id = pending.id.clone();
+        let primary = pending.primary;
+        let status = if pending.too_large {
+            "EIO"
+        } else {
+            match self.clipboard.as_ref() {
+                Some(clip) => match clip.set_data(selection(primary), pending.into_items()) {
+                    Ok(()) => "DONE",
+                    Err(err) => {
+                        log::error!("failed to set clipboard in response to OSC 5522: {err:#}");
+                        "EIO"
+                    }
+                },
+                None => "ENOSYS",
+            }
+        };
+        self.kitty_clipboard_respond(KittyClipboardType::Write, id, primary, status);
+    }
+
+    fn kitty_clipboard_read(&mut self, clip: KittyClipboard) {
+        let mime_types: Vec<String> = String::from_utf8_lossy(&clip.payload.unwrap_or_default())
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        if mime_types.is_empty() {
+            self.kitty_clipboard_respond(KittyClipboardType::Read, clip.id, clip.primary, "EINVAL");
+            return;
+        }
+
+        // The hosting application is responsible for obtaining permission
+        // from the user and sending the data back to the application
+        match self.alert_handler.as_mut() {
+            Some(handler) => handler.alert(Alert::ClipboardRead {
+                selection: selection(clip.primary),
+                id: clip.id,
+                mime_types,
+            }),
+            None => {
+                self.kitty_clipboard_respond(
+                    KittyClipboardType::Read,
+                    clip.id,
+                    clip.primary,
+                    "ENOSYS",
+                );
+            }
+        }
+    }
+
+    fn kitty_clipboard_respond(
+        &mut self,
+        kind: KittyClipboardType,
+        id: Option<String>,
+        primary: bool,
+        status: &str,
+    ) {
+        let response = OperatingSystemCommand::KittyClipboard(KittyClipboard {
+            kind,
+            id,
+            primary,
+            status: Some(status.to_string()),
+            ..Default::default()
+        });
+        write!(self.writer, "{}", response).ok();
+        self.writer.flush().ok();
+    }
+}
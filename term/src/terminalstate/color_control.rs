@@ -0,0 +1,50 @@
+use crate::TerminalState;
+use std::io::Write;
+use wezterm_escape_parser::osc::{ColorControl, ColorControlValue};
+use wezterm_escape_parser::OperatingSystemCommand;
+
+impl TerminalState {
+    /// Handle an OSC 21 sequence.  All of the changes are applied
+    /// together, and the queries are answered in a single response.
+    pub(crate) fn kitty_color_control(&mut self, items: Vec<ColorControl>) {
+        let mut responses = vec![];
+        let mut changed = false;
+
+        for item in items {
+            match item.value {
+                ColorControlValue::Query => {
+                    let value = self.palette().color_control_get(&item.key);
+                    responses.push(ColorControl {
+                        key: item.key,
+                        value,
+                    });
+                }
+                ColorControlValue::Reset => {
+                    if self.palette.is_some() {
+                        let base = self.config.color_palette();
+                        self.palette_mut().color_control_set(&item.key, None, &base);
+                        changed = true;
+                    }
+                }
+                ColorControlValue::Color(color) => {
+                    let base = self.config.color_palette();
+                    self.palette_mut()
+                        .color_control_set(&item.key, Some(color), &base);
+                    changed = true;
+                }
+            }
+        }
+
+        if !responses.is_empty() {
+            let response = OperatingSystemCommand::KittyColorControl(responses);
+            log::trace!("Color control response {:?}", response);
+            write!(self.writer, "{}", response).ok();
+            self.writer.flush().ok();
+        }
+
+        if changed {
+            self.implicit_palette_reset_if_same_as_configured();
+            self.palette_did_change();
+        }
+    }
+}
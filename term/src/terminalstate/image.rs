@@ -12,6 +12,7 @@ use wezterm_surface::TextureCoordinate;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PlacementInfo {
     pub first_row: StableRowIndex,
+    pub first_col: usize,
     pub rows: usize,
     pub cols: usize,
 }
@@ -248,6 +249,7 @@ impl TerminalState {
 
         Ok(PlacementInfo {
             first_row,
+            first_col: cursor_x,
             rows: height_in_cells,
             cols: width_in_cells,
         })
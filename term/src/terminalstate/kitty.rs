@@ -1,6 +1,7 @@
 use crate::terminalstate::image::*;
+use crate::terminalstate::placeholder::Placeholder;
 use crate::terminalstate::{ImageAttachParams, PlacementInfo};
-use crate::{StableRowIndex, TerminalState};
+use crate::{StableRowIndex, TerminalState, VisibleRowIndex};
 use ::image::{
     DynamicImage, GenericImage, GenericImageView, ImageBuffer, RgbImage, Rgba, RgbaImage,
 };
@@ -9,11 +10,15 @@ use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::sync::Arc;
 use std::time::Duration;
-use wezterm_cell::image::ImageDataType;
+use wezterm_cell::image::{
+    AnimationControl, AnimationState, ImageCell, ImageDataType, TextureCoordinate,
+    KITTY_PLACEHOLDER,
+};
+use wezterm_cell::CellAttributes;
 use wezterm_escape_parser::apc::{
-    KittyFrameCompositionMode, KittyImage, KittyImageCompression, KittyImageData, KittyImageDelete,
-    KittyImageFormat, KittyImageFrame, KittyImageFrameCompose, KittyImagePlacement,
-    KittyImageTransmit, KittyImageVerbosity,
+    KittyAnimationState, KittyFrameCompositionMode, KittyImage, KittyImageAnimation,
+    KittyImageCompression, KittyImageData, KittyImageDelete, KittyImageFormat, KittyImageFrame,
+    KittyImageFrameCompose, KittyImagePlacement, KittyImageTransmit, KittyImageVerbosity,
 };
 use wezterm_surface::change::ImageData;
 
@@ -24,9 +29,107 @@ pub struct KittyImageState {
     number_to_id: HashMap<u32, u32>,
     id_to_data: HashMap<u32, Arc<ImageData>>,
     placements: HashMap<(u32, Option<u32>), PlacementInfo>,
+    virtual_placements: HashMap<(u32, Option<u32>), VirtualPlacement>,
+    relations: HashMap<(u32, Option<u32>), RelativePlacement>,
+    /// The most recently printed placeholder and its position, used to
+    /// infer the diacritics that were omitted from the next one
+    last_placeholder: Option<(usize, VisibleRowIndex, Placeholder)>,
     used_memory: usize,
 }
 
+/// Relative placements may be nested up to this depth
+const MAX_PLACEMENT_CHAIN: usize = 8;
+
+/// Records the parent of a relative placement, so that it can
+/// follow the parent when it moves, and be deleted along with it
+#[derive(Debug)]
+struct RelativePlacement {
+    parent: (u32, Option<u32>),
+    placement: KittyImagePlacement,
+}
+
+/// A placement created with `U=1`.  Rather than being assigned to
+/// the cells at the cursor, it is displayed by the unicode placeholder
+/// cells that reference it.
+#[derive(Debug)]
+struct VirtualPlacement {
+    data: Arc<ImageData>,
+    image_width: u32,
+    image_height: u32,
+    source_origin_x: u32,
+    source_origin_y: u32,
+    source_width: u32,
+    source_height: u32,
+    columns: usize,
+    rows: usize,
+    z_index: i32,
+}
+
+impl VirtualPlacement {
+    /// Computes the slice of the image that is displayed by the
+    /// placeholder at `row`, `col` of this placement.  The image is
+    /// scaled to fit the placement, preserving its aspect ratio, and
+    /// is centered within it.
+    fn image_cell(
+        &self,
+        row: u32,
+        col: u32,
+        image_id: u32,
+        placement_id: Option<u32>,
+        cell_pixel_width: usize,
+        cell_pixel_height: usize,
+    ) -> Option<Box<ImageCell>> {
+        if row as usize >= self.rows
+            || col as usize >= self.columns
+            || self.source_width == 0
+            || self.source_height == 0
+        {
+            return None;
+        }
+        let cell_width = cell_pixel_width as f32;
+        let cell_height = cell_pixel_height as f32;
+        let source_width = self.source_width as f32;
+        let source_height = self.source_height as f32;
+
+        let scale = (self.columns as f32 * cell_width / source_width)
+            .min(self.rows as f32 * cell_height / source_height);
+        let offset_x = (self.columns as f32 * cell_width - source_width * scale) / 2.;
+        let offset_y = (self.rows as f32 * cell_height - source_height * scale) / 2.;
+
+        // The portion of this cell covered by the image, in pixels
+        // relative to the top left of the placement
+        let cell_left = col as f32 * cell_width;
+        let cell_top = row as f32 * cell_height;
+        let left = cell_left.max(offset_x);
+        let top = cell_top.max(offset_y);
+        let right = (cell_left + cell_width).min(offset_x + source_width * scale);
+        let bottom = (cell_top + cell_height).min(offset_y + source_height * scale);
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        let tex_x = |x: f32| {
+            (self.source_origin_x as f32 + (x - offset_x) / scale) / self.image_width as f32
+        };
+        let tex_y = |y: f32| {
+            (self.source_origin_y as f32 + (y - offset_y) / scale) / self.image_height as f32
+        };
+
+        Some(Box::new(ImageCell::with_z_index(
+            TextureCoordinate::new_f32(tex_x(left), tex_y(top)),
+            TextureCoordinate::new_f32(tex_x(right), tex_y(bottom)),
+            Arc::clone(&self.data),
+            self.z_index,
+            (left - cell_left) as u16,
+            (top - cell_top) as u16,
+            (cell_left + cell_width - right) as u16,
+            (cell_top + cell_height - bottom) as u16,
+            Some(image_id),
+            placement_id,
+        )))
+    }
+}
+
 impl KittyImageState {
     fn remove_data_for_id(&mut self, image_id: u32) {
         if let Some(data) = self.id_to_data.remove(&image_id) {
@@ -46,7 +149,12 @@ impl KittyImageState {
     fn prune_unreferenced(&mut self) {
         let budget = 320 * 1024 * 1024; // FIXME: make this configurable
         if self.used_memory > budget {
-            let referenced: HashSet<u32> = self.placements.keys().map(|(k, _)| *k).collect();
+            let referenced: HashSet<u32> = self
+                .placements
+                .keys()
+                .chain(self.virtual_placements.keys())
+                .map(|(k, _)| *k)
+                .collect();
             let target = self.used_memory - budget;
             let mut freed = 0;
             self.id_to_data.retain(|id, data| {
@@ -100,6 +208,27 @@ impl TerminalState {
             placement,
             verbosity
         );
+        let key = (image_id, placement.placement_id);
+        let relative_origin = match placement.parent_image_id {
+            Some(parent_image_id) if !placement.virtual_placement => {
+                let parent = (parent_image_id, placement.parent_placement_id);
+                match self.kitty_relative_origin(key, parent, &placement) {
+                    Ok(origin) => Some(origin),
+                    Err(code) => {
+                        self.kitty_send_response(
+                            verbosity,
+                            false,
+                            Some(image_id),
+                            image_number,
+                            code.to_string(),
+                        );
+                        anyhow::bail!("cannot place {:?} relative to {:?}: {}", key, parent, code);
+                    }
+                }
+            }
+            _ => None,
+        };
+
         if image_id != 0 {
             self.kitty_remove_placement(image_id, placement.placement_id);
         }
@@ -113,6 +242,51 @@ impl TerminalState {
 
         let (image_width, image_height) = img.data().dimensions()?;
 
+        if placement.virtual_placement {
+            self.kitty_add_virtual_placement(image_id, img, image_width, image_height, placement);
+            self.kitty_move_children(key);
+            return Ok(());
+        }
+
+        let info = self.kitty_assign_placement(image_id, img, &placement, relative_origin)?;
+        self.kitty_img.placements.insert(key, info);
+        log::trace!(
+            "record placement for {} (image_number {:?}) {:?}",
+            image_id,
+            image_number,
+            placement.placement_id
+        );
+
+        if let Some(parent_image_id) = placement.parent_image_id {
+            let parent = (parent_image_id, placement.parent_placement_id);
+            self.kitty_img
+                .relations
+                .insert(key, RelativePlacement { parent, placement });
+        }
+
+        self.kitty_move_children(key);
+
+        Ok(())
+    }
+
+    /// Assigns the image to the cells of a non-virtual placement.
+    /// A relative placement is assigned to the cells at its origin
+    /// without disturbing the cursor
+    fn kitty_assign_placement(
+        &mut self,
+        image_id: u32,
+        img: Arc<ImageData>,
+        placement: &KittyImagePlacement,
+        relative_origin: Option<(usize, VisibleRowIndex)>,
+    ) -> anyhow::Result<PlacementInfo> {
+        let (image_width, image_height) = img.data().dimensions()?;
+
+        let saved_cursor = (self.cursor.x, self.cursor.y);
+        if let Some((x, y)) = relative_origin {
+            self.cursor.x = x;
+            self.cursor.y = y;
+        }
+
         let info = self.assign_image_to_cells(ImageAttachParams {
             image_width,
             image_height,
@@ -129,20 +303,145 @@ impl TerminalState {
             rows: placement.rows.map(|x| x as usize),
             image_id: Some(image_id),
             placement_id: placement.placement_id,
-            do_not_move_cursor: placement.do_not_move_cursor,
-        })?;
+            do_not_move_cursor: placement.do_not_move_cursor || relative_origin.is_some(),
+        });
+        if relative_origin.is_some() {
+            (self.cursor.x, self.cursor.y) = saved_cursor;
+        }
+        info
+    }
 
-        self.kitty_img
-            .placements
-            .insert((image_id, placement.placement_id), info);
-        log::trace!(
-            "record placement for {} (image_number {:?}) {:?}",
-            image_id,
-            image_number,
-            placement.placement_id
-        );
+    /// Children follow their parent when it moves. Each child is moved
+    /// to its new origin without touching any other placement of its
+    /// image; a child that can no longer be positioned is left where
+    /// it is, as there is no request to respond to
+    fn kitty_move_children(&mut self, parent: (u32, Option<u32>)) {
+        let children: Vec<_> = self
+            .kitty_img
+            .relations
+            .iter()
+            .filter(|(_, rel)| rel.parent == parent)
+            .map(|(&key, rel)| (key, rel.placement.clone()))
+            .collect();
+
+        for (key, placement) in children {
+            let origin = match self.kitty_relative_origin(key, parent, &placement) {
+                Ok(origin) => origin,
+                Err(code) => {
+                    log::warn!("cannot move {:?} relative to {:?}: {}", key, parent, code);
+                    continue;
+                }
+            };
+            let img = match self.kitty_img.id_to_data.get(&key.0) {
+                Some(img) => Arc::clone(img),
+                None => {
+                    log::warn!("cannot move {:?}: image data is missing", key);
+                    continue;
+                }
+            };
 
-        Ok(())
+            if let Some(info) = self.kitty_img.placements.remove(&key) {
+                self.kitty_remove_placement_from_model(key.0, key.1, info);
+            }
+            match self.kitty_assign_placement(key.0, img, &placement, Some(origin)) {
+                Ok(info) => {
+                    self.kitty_img.placements.insert(key, info);
+                    self.kitty_move_children(key);
+                }
+                Err(err) => {
+                    log::warn!("cannot move {:?} relative to {:?}: {:#}", key, parent, err);
+                }
+            }
+        }
+    }
+
+    /// Computes the visible cell position of a placement that is
+    /// relative to `parent`, or returns the kitty error code that
+    /// explains why it cannot be placed.
+    /// A virtual parent is located through the placeholder cells
+    /// that display it.
+    fn kitty_relative_origin(
+        &self,
+        key: (u32, Option<u32>),
+        parent: (u32, Option<u32>),
+        placement: &KittyImagePlacement,
+    ) -> Result<(usize, VisibleRowIndex), &'static str> {
+        let screen = self.screen();
+        let (parent_x, parent_y) = match self.kitty_img.placements.get(&parent) {
+            Some(info) => {
+                let phys = screen
+                    .stable_row_to_phys(info.first_row)
+                    .ok_or("ENOPARENT:parent placement is no longer in the scrollback")?;
+                let y = phys as VisibleRowIndex
+                    - (screen.scrollback_rows() - screen.physical_rows) as VisibleRowIndex;
+                (info.first_col as i64, y)
+            }
+            None if self.kitty_img.virtual_placements.contains_key(&parent) => self
+                .kitty_virtual_placement_origin(parent)
+                .ok_or("ENOPARENT:virtual parent placement is not displayed")?,
+            None => return Err("ENOPARENT:parent placement not found"),
+        };
+
+        // Walk up the chain of parents to make sure that this placement
+        // isn't one of its own ancestors, and that the chain is not
+        // unreasonably long
+        let mut ancestor = parent;
+        let mut depth = 1;
+        loop {
+            if ancestor == key {
+                return Err("ECYCLE:placement would be its own ancestor");
+            }
+            match self.kitty_img.relations.get(&ancestor) {
+                Some(rel) => {
+                    depth += 1;
+                    if depth > MAX_PLACEMENT_CHAIN {
+                        return Err("ETOODEEP:chain of parent placements is too long");
+                    }
+                    ancestor = rel.parent;
+                }
+                None => break,
+            }
+        }
+
+        let y = parent_y + placement.vertical_offset.unwrap_or(0) as VisibleRowIndex;
+        let x = parent_x + placement.horizontal_offset.unwrap_or(0) as i64;
+
+        // We can only assign cells on the visible screen
+        Ok((
+            x.max(0) as usize,
+            y.clamp(0, screen.physical_rows as VisibleRowIndex - 1),
+        ))
+    }
+
+    /// Returns the position of the top left cell of a virtual placement,
+    /// derived from the first placeholder on the visible screen that
+    /// displays it. That cell may itself be off screen.
+    fn kitty_virtual_placement_origin(
+        &self,
+        key: (u32, Option<u32>),
+    ) -> Option<(i64, VisibleRowIndex)> {
+        for (y, line) in self.screen().visible_lines().iter().enumerate() {
+            let mut prev: Option<(usize, Placeholder)> = None;
+            for cell in line.visible_cells() {
+                let x = cell.cell_index();
+                let adjacent = prev.filter(|(prev_x, _)| prev_x + 1 == x);
+                let placeholder = Placeholder::decode(
+                    cell.str(),
+                    cell.attrs(),
+                    adjacent.as_ref().map(|(_, p)| p),
+                );
+                prev = placeholder.map(|p| (x, p));
+                if let Some(p) = placeholder {
+                    if self.kitty_placeholder_placement(&p) == Some(key) {
+                        return Some((
+                            x as i64 - p.col as i64,
+                            y as VisibleRowIndex - p.row as VisibleRowIndex,
+                        ));
+                    }
+                }
+            }
+        }
+        None
     }
 
     fn kitty_img_inner(&mut self, img: KittyImage) -> anyhow::Result<()> {
@@ -255,7 +554,7 @@ impl TerminalState {
                     verbosity
                 );
 
-                self.kitty_remove_placement(image_id, placement_id);
+                self.kitty_delete_placement(image_id, placement_id);
 
                 if delete {
                     self.kitty_img.remove_data_for_id(image_id);
@@ -284,6 +583,17 @@ impl TerminalState {
                     log::error!("Error {:#} while handling KittyImage::ComposeFrame", err);
                 }
             }
+            KittyImage::AnimationControl {
+                animation,
+                verbosity,
+            } => {
+                if let Err(err) = self.kitty_animation_control(animation, verbosity) {
+                    log::error!(
+                        "Error {:#} while handling KittyImage::AnimationControl",
+                        err
+                    );
+                }
+            }
         };
 
         Ok(())
@@ -309,7 +619,277 @@ impl TerminalState {
         }
     }
 
+    fn kitty_add_virtual_placement(
+        &mut self,
+        image_id: u32,
+        data: Arc<ImageData>,
+        image_width: u32,
+        image_height: u32,
+        placement: KittyImagePlacement,
+    ) {
+        let (cell_pixel_width, cell_pixel_height) = self.kitty_cell_pixel_dimensions();
+        let source_origin_x = placement.x.unwrap_or(0).min(image_width);
+        let source_origin_y = placement.y.unwrap_or(0).min(image_height);
+        let source_width = placement
+            .w
+            .unwrap_or(image_width)
+            .min(image_width - source_origin_x);
+        let source_height = placement
+            .h
+            .unwrap_or(image_height)
+            .min(image_height - source_origin_y);
+
+        // In the absence of an explicit size, the placement covers
+        // as many cells as the image would naturally occupy
+        let columns = placement
+            .columns
+            .map(|c| c as usize)
+            .unwrap_or_else(|| (source_width as usize).div_ceil(cell_pixel_width));
+        let rows = placement
+            .rows
+            .map(|r| r as usize)
+            .unwrap_or_else(|| (source_height as usize).div_ceil(cell_pixel_height));
+
+        self.kitty_img.virtual_placements.insert(
+            (image_id, placement.placement_id),
+            VirtualPlacement {
+                data,
+                image_width,
+                image_height,
+                source_origin_x,
+                source_origin_y,
+                source_width,
+                source_height,
+                columns,
+                rows,
+                z_index: placement.z_index.unwrap_or(0),
+            },
+        );
+        log::trace!(
+            "record virtual placement for {} {:?} {}x{} cells",
+            image_id,
+            placement.placement_id,
+            columns,
+            rows
+        );
+
+        // Placeholders may have been printed before the placement
+        // was created; now that it exists, they can show the image
+        self.kitty_refresh_placeholders();
+    }
+
+    fn kitty_cell_pixel_dimensions(&self) -> (usize, usize) {
+        let screen = self.screen();
+        (
+            (self.pixel_width / screen.physical_cols).max(1),
+            (self.pixel_height / screen.physical_rows).max(1),
+        )
+    }
+
+    /// Returns the key of the virtual placement that a placeholder cell
+    /// references. When the placeholder doesn't specify a placement id,
+    /// any virtual placement of the image may be used.
+    fn kitty_placeholder_placement(&self, placeholder: &Placeholder) -> Option<(u32, Option<u32>)> {
+        let image_id = placeholder.image_id();
+        match placeholder.placement_id {
+            Some(_) => {
+                let key = (image_id, placeholder.placement_id);
+                self.kitty_img
+                    .virtual_placements
+                    .contains_key(&key)
+                    .then_some(key)
+            }
+            None => self
+                .kitty_img
+                .virtual_placements
+                .keys()
+                .filter(|(id, _)| *id == image_id)
+                .min_by_key(|(_, p)| *p)
+                .copied(),
+        }
+    }
+
+    /// Returns the slice of the image referenced by a placeholder cell,
+    /// if the virtual placement that it references exists.
+    fn kitty_placeholder_image(&self, placeholder: &Placeholder) -> Option<Box<ImageCell>> {
+        let image_id = placeholder.image_id();
+        let key = self.kitty_placeholder_placement(placeholder)?;
+        let placement = self.kitty_img.virtual_placements.get(&key)?;
+        let (cell_pixel_width, cell_pixel_height) = self.kitty_cell_pixel_dimensions();
+        placement.image_cell(
+            placeholder.row,
+            placeholder.col,
+            image_id,
+            key.1,
+            cell_pixel_width,
+            cell_pixel_height,
+        )
+    }
+
+    /// Called when printing a unicode placeholder at `x`, `y`; assigns the
+    /// slice of the referenced image to the attributes of the cell
+    pub(crate) fn kitty_placeholder_attrs(
+        &mut self,
+        x: usize,
+        y: VisibleRowIndex,
+        text: &str,
+        attrs: &mut CellAttributes,
+    ) {
+        let prev = match self.kitty_img.last_placeholder {
+            Some((prev_x, prev_y, prev)) if prev_y == y && prev_x + 1 == x => Some(prev),
+            _ => None,
+        };
+        let placeholder = Placeholder::decode(text, attrs, prev.as_ref());
+        self.kitty_img.last_placeholder = placeholder.map(|p| (x, y, p));
+
+        if let Some(image) = placeholder.and_then(|p| self.kitty_placeholder_image(&p)) {
+            attrs.set_image(image);
+        }
+    }
+
+    /// Re-evaluates the placeholder cells in the visible portion of the
+    /// screen following a change to the set of virtual placements
+    fn kitty_refresh_placeholders(&mut self) {
+        let seqno = self.seqno;
+        let rows = self.screen().physical_rows as VisibleRowIndex;
+        for y in 0..rows {
+            let idx = self.screen().phys_row(y);
+            let placeholders: Vec<(usize, Placeholder)> = {
+                let line = self.screen_mut().line_mut(idx);
+                if !line
+                    .visible_cells()
+                    .any(|cell| cell.str().starts_with(KITTY_PLACEHOLDER))
+                {
+                    continue;
+                }
+                let mut prev: Option<(usize, Placeholder)> = None;
+                let mut placeholders = vec![];
+                for cell in line.visible_cells() {
+                    let x = cell.cell_index();
+                    let adjacent = prev.filter(|(prev_x, _)| prev_x + 1 == x);
+                    let placeholder = Placeholder::decode(
+                        cell.str(),
+                        cell.attrs(),
+                        adjacent.as_ref().map(|(_, p)| p),
+                    );
+                    prev = placeholder.map(|p| (x, p));
+                    if let Some(p) = placeholder {
+                        placeholders.push((x, p));
+                    }
+                }
+                placeholders
+            };
+
+            let images: Vec<(usize, Option<Box<ImageCell>>)> = placeholders
+                .iter()
+                .map(|(x, p)| (*x, self.kitty_placeholder_image(p)))
+                .collect();
+
+            let line = self.screen_mut().line_mut(idx);
+            let mut changed = false;
+            let cells = line.cells_mut();
+            for (x, image) in images {
+                let attrs = match cells.get_mut(x) {
+                    Some(cell) => cell.attrs_mut(),
+                    None => continue,
+                };
+                let current = attrs.images().unwrap_or_default();
+                let wanted: Vec<ImageCell> = image.iter().map(|i| (**i).clone()).collect();
+                if current != wanted {
+                    attrs.clear_images();
+                    if let Some(image) = image {
+                        attrs.set_image(image);
+                    }
+                    changed = true;
+                }
+            }
+            if changed {
+                line.update_last_change_seqno(seqno);
+            }
+        }
+    }
+
+    /// Marks the lines that hold placements of the specified image
+    /// as changed, so that the renderer will pick up changes to
+    /// its frames or animation state
+    fn kitty_touch_placements(&mut self, image_id: u32) {
+        let ranges: Vec<_> = self
+            .kitty_img
+            .placements
+            .iter()
+            .filter(|((id, _), _)| *id == image_id)
+            .map(|(_, info)| info.first_row..info.first_row + info.rows as StableRowIndex)
+            .collect();
+        let seqno = self.seqno;
+        let has_virtual = self
+            .kitty_img
+            .virtual_placements
+            .keys()
+            .any(|(id, _)| *id == image_id);
+        let screen = self.screen_mut();
+        for range in ranges {
+            for idx in screen.stable_range(&range) {
+                screen.line_mut(idx).update_last_change_seqno(seqno);
+            }
+        }
+        if has_virtual {
+            let rows = screen.physical_rows as VisibleRowIndex;
+            for idx in screen.phys_range(&(0..rows)) {
+                let line = screen.line_mut(idx);
+                let shows_image = line.visible_cells().any(|cell| {
+                    cell.attrs()
+                        .images()
+                        .map(|images| images.iter().any(|i| i.image_id() == Some(image_id)))
+                        .unwrap_or(false)
+                });
+                if shows_image {
+                    line.update_last_change_seqno(seqno);
+                }
+            }
+        }
+    }
+
+    /// Deletes placements of an image, along with the placements
+    /// that are positioned relative to them
+    fn kitty_delete_placement(&mut self, image_id: u32, placement_id: Option<u32>) {
+        let children: Vec<(u32, Option<u32>)> = self
+            .kitty_img
+            .relations
+            .iter()
+            .filter(|(_, rel)| {
+                rel.parent.0 == image_id && (placement_id.is_none() || rel.parent.1 == placement_id)
+            })
+            .map(|(child, _)| *child)
+            .collect();
+
+        self.kitty_remove_placement(image_id, placement_id);
+
+        for (child_image_id, child_placement_id) in children {
+            // The child may already have been removed via another path
+            if self
+                .kitty_img
+                .relations
+                .contains_key(&(child_image_id, child_placement_id))
+            {
+                self.kitty_delete_placement(child_image_id, child_placement_id);
+            }
+        }
+    }
+
     fn kitty_remove_placement(&mut self, image_id: u32, placement_id: Option<u32>) {
+        let matches = |&(id, p): &(u32, Option<u32>)| {
+            id == image_id && (placement_id.is_none() || p == placement_id)
+        };
+        self.kitty_img.relations.retain(|key, _| !matches(key));
+
+        let num_virtual = self.kitty_img.virtual_placements.len();
+        self.kitty_img
+            .virtual_placements
+            .retain(|key, _| !matches(key));
+        if self.kitty_img.virtual_placements.len() != num_virtual {
+            self.kitty_refresh_placeholders();
+        }
+
         if placement_id.is_some() {
             if let Some(info) = self.kitty_img.placements.remove(&(image_id, placement_id)) {
                 log::trace!("removed placement {} {:?}", image_id, placement_id);
@@ -341,6 +921,11 @@ impl TerminalState {
         for ((image_id, p), info) in std::mem::take(&mut self.kitty_img.placements).into_iter() {
             self.kitty_remove_placement_from_model(image_id, p, info);
         }
+        self.kitty_img.relations.clear();
+        if !self.kitty_img.virtual_placements.is_empty() {
+            self.kitty_img.virtual_placements.clear();
+            self.kitty_refresh_placeholders();
+        }
         if delete {
             self.kitty_img.id_to_data.clear();
             self.kitty_img.used_memory = 0;
@@ -534,6 +1119,100 @@ impl TerminalState {
             }
         }
 
+        drop(img);
+        self.kitty_touch_placements(image_id);
+
+        Ok(())
+    }
+
+    fn kitty_animation_control(
+        &mut self,
+        animation: KittyImageAnimation,
+        verbosity: KittyImageVerbosity,
+    ) -> anyhow::Result<()> {
+        let image_id = match animation.image_number {
+            Some(no) => self.kitty_img.number_to_id.get(&no).copied(),
+            None => animation.image_id,
+        };
+        let img = match image_id.and_then(|id| self.kitty_img.id_to_data.get(&id)) {
+            Some(img) => Arc::clone(img),
+            None => {
+                self.kitty_send_response(
+                    verbosity,
+                    false,
+                    animation.image_id,
+                    animation.image_number,
+                    "ENOENT".to_string(),
+                );
+                anyhow::bail!(
+                    "no such image id {:?} or number {:?}",
+                    animation.image_id,
+                    animation.image_number
+                );
+            }
+        };
+
+        match &mut *img.data() {
+            ImageDataType::AnimRgba8 {
+                durations,
+                frames,
+                animation: control,
+                ..
+            } => {
+                if let (Some(frame_no), Some(ms)) = (animation.frame_number, animation.duration_ms)
+                {
+                    let frame_no = frame_no as usize;
+                    anyhow::ensure!(
+                        frame_no > 0 && frame_no <= durations.len(),
+                        "attempted to set the gap of frame {} which is outside range 1-{}",
+                        frame_no,
+                        durations.len()
+                    );
+                    // A negative value requests a gapless frame, which
+                    // has no duration and is skipped during playback
+                    durations[frame_no - 1] = Duration::from_millis(ms.max(0) as u64);
+                }
+
+                let mut changed = false;
+                if let Some(state) = animation.state {
+                    control.state = match state {
+                        KittyAnimationState::Stop => AnimationState::Stopped,
+                        KittyAnimationState::Loading => AnimationState::Loading,
+                        KittyAnimationState::Run => AnimationState::Running,
+                    };
+                    changed = true;
+                }
+                if let Some(loops) = animation.loops {
+                    control.max_loops = if loops == 1 { None } else { Some(loops - 1) };
+                    changed = true;
+                }
+                if let Some(frame_no) = animation.current_frame {
+                    let frame_no = frame_no as usize;
+                    anyhow::ensure!(
+                        frame_no > 0 && frame_no <= frames.len(),
+                        "attempted to show frame {} which is outside range 1-{}",
+                        frame_no,
+                        frames.len()
+                    );
+                    control.current_frame = Some(frame_no - 1);
+                    changed = true;
+                }
+                if changed {
+                    control.generation = control.generation.wrapping_add(1);
+                }
+            }
+            ImageDataType::Rgba8 { .. } => {
+                // There is nothing to animate with a single frame
+            }
+            ImageDataType::EncodedLease(_) | ImageDataType::EncodedFile(_) => {
+                anyhow::bail!("Expected decoded image for image id {:?}", image_id)
+            }
+        }
+
+        if let Some(image_id) = image_id {
+            self.kitty_touch_placements(image_id);
+        }
+
         Ok(())
     }
 
@@ -665,6 +1344,7 @@ impl TerminalState {
                             frames,
                             durations,
                             hashes,
+                            animation: AnimationControl::default(),
                         };
                     }
                     Some(n) => anyhow::bail!(
@@ -679,6 +1359,7 @@ impl TerminalState {
                 frames,
                 durations,
                 hashes,
+                ..
             } => {
                 let frame_no = frame.frame_number.unwrap_or(frames.len() as u32 + 1);
                 if frame_no == frames.len() as u32 + 1 {
@@ -737,6 +1418,9 @@ impl TerminalState {
             }
         }
 
+        drop(anim);
+        self.kitty_touch_placements(image_id);
+
         Ok(())
     }
 
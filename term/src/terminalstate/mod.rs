@@ -18,22 +18,32 @@ use wezterm_bidi::ParagraphDirectionHint;
 use wezterm_cell::image::ImageData;
 use wezterm_cell::UnicodeVersion;
 use wezterm_escape_parser::csi::{
-    Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit, EraseInDisplay,
-    EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics,
-    XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus, XtermKeyModifierResource,
+    ColorScheme, Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit,
+    EraseInDisplay, EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode,
+    Window, XtSmGraphics, XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus,
+    XtermKeyModifierResource,
 };
+use wezterm_escape_parser::osc::PointerShape;
 use wezterm_escape_parser::{OneBased, OperatingSystemCommand, CSI};
 use wezterm_surface::{CursorShape, CursorVisibility, SequenceNo};
 
+mod clipboard;
+mod color_control;
 mod image;
 mod iterm;
 mod keyboard;
 mod kitty;
 mod mouse;
+mod notification;
 pub(crate) mod performer;
+mod placeholder;
+mod pointer;
 mod sixel;
+mod text_sizing;
+use crate::terminalstate::clipboard::PendingClipboardWrite;
 use crate::terminalstate::image::*;
 use crate::terminalstate::kitty::*;
+use crate::terminalstate::notification::PendingNotification;
 
 lazy_static::lazy_static! {
     static ref DB: Database = {
@@ -311,6 +321,13 @@ pub struct TerminalState {
     /// Movement events enabled
     any_event_mouse: bool,
     focus_tracking: bool,
+    /// DEC mode 2031: report changes to the color scheme
+    color_scheme_updates: bool,
+    /// DEC mode 2048: report size changes in-band
+    in_band_resize: bool,
+    /// The color scheme most recently advised by the embedding
+    /// application via `set_color_scheme`
+    color_scheme: ColorScheme,
     /// X10 (legacy), SGR, and SGR-Pixels style mouse tracking and
     /// reporting is enabled
     mouse_encoding: MouseEncoding,
@@ -361,6 +378,14 @@ pub struct TerminalState {
     user_vars: HashMap<String, String>,
 
     kitty_img: KittyImageState,
+    /// Partially received kitty desktop notifications, keyed by id
+    pending_notifications: HashMap<String, PendingNotification>,
+    /// A partially received OSC 5522 clipboard write
+    pending_clipboard_write: Option<PendingClipboardWrite>,
+    /// OSC 22 pointer shapes for the primary and alternate screens.
+    /// The top of each stack is the current shape.
+    pointer_shapes: Vec<PointerShape>,
+    alt_pointer_shapes: Vec<PointerShape>,
     seqno: SequenceNo,
 
     /// The unicode version that is in effect
@@ -538,6 +563,9 @@ impl TerminalState {
             application_keypad: false,
             bracketed_paste: false,
             focus_tracking: false,
+            color_scheme_updates: false,
+            in_band_resize: false,
+            color_scheme: ColorScheme::Dark,
             mouse_encoding: MouseEncoding::X10,
             keyboard_encoding: KeyboardEncoding::Xterm,
             sixel_scrolls_right: false,
@@ -569,6 +597,10 @@ impl TerminalState {
             image_cache: lru::LruCache::new(NonZeroUsize::new(16).unwrap()),
             user_vars: HashMap::new(),
             kitty_img: Default::default(),
+            pending_notifications: HashMap::new(),
+            pending_clipboard_write: None,
+            pointer_shapes: vec![],
+            alt_pointer_shapes: vec![],
             seqno,
             unicode_version,
             unicode_version_stack: vec![],
@@ -794,6 +826,26 @@ impl TerminalState {
         }
     }
 
+    /// Advise the terminal about a change in the preferred color scheme,
+    /// such as the system switching between light and dark mode.
+    /// If the application has enabled DEC mode 2031, it will be
+    /// notified of the change.
+    pub fn set_color_scheme(&mut self, scheme: ColorScheme) {
+        if scheme == self.color_scheme {
+            return;
+        }
+        self.color_scheme = scheme;
+        if self.color_scheme_updates {
+            self.report_color_scheme();
+        }
+    }
+
+    fn report_color_scheme(&mut self) {
+        let report = CSI::Device(Box::new(Device::ColorSchemeReport(self.color_scheme)));
+        write!(self.writer, "{}", report).ok();
+        self.writer.flush().ok();
+    }
+
     /// Returns true if there is new output since the terminal
     /// lost focus
     pub fn has_unseen_output(&self) -> bool {
@@ -911,6 +963,22 @@ impl TerminalState {
                 saved.wrap_next = false;
             }
         }
+
+        if self.in_band_resize {
+            self.report_in_band_resize();
+        }
+    }
+
+    fn report_in_band_resize(&mut self) {
+        let size = self.get_size();
+        let report = Box::new(Window::InBandResizeReport {
+            rows: size.rows as i64,
+            cols: size.cols as i64,
+            pixel_height: size.pixel_height as i64,
+            pixel_width: size.pixel_width as i64,
+        });
+        write!(self.writer, "{}", CSI::Window(report)).ok();
+        self.writer.flush().ok();
     }
 
     pub fn get_size(&self) -> TerminalSize {
@@ -1336,6 +1404,10 @@ impl TerminalState {
                 self.writer.write(b"\x1b[0n").ok();
                 self.writer.flush().ok();
             }
+            Device::RequestColorScheme => {
+                self.report_color_scheme();
+            }
+            Device::ColorSchemeReport(_) => {}
             Device::XtSmGraphics(g) => {
                 let response = if matches!(g.item, XtSmGraphicsItem::Unspecified(_)) {
                     XtSmGraphics {
@@ -1815,6 +1887,41 @@ impl TerminalState {
                 self.decqrm_response(mode, true, self.focus_tracking);
             }
 
+            Mode::SetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates,
+            )) => {
+                self.color_scheme_updates = true;
+            }
+            Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates,
+            )) => {
+                self.color_scheme_updates = false;
+            }
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates,
+            )) => {
+                self.decqrm_response(mode, true, self.color_scheme_updates);
+            }
+
+            Mode::SetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::InBandResizeNotifications,
+            )) => {
+                self.in_band_resize = true;
+                // The current size is reported immediately, so that the
+                // application doesn't need to separately query it
+                self.report_in_band_resize();
+            }
+            Mode::ResetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::InBandResizeNotifications,
+            )) => {
+                self.in_band_resize = false;
+            }
+            Mode::QueryDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::InBandResizeNotifications,
+            )) => {
+                self.decqrm_response(mode, true, self.in_band_resize);
+            }
+
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SGRMouse)) => {
                 self.mouse_encoding = MouseEncoding::SGR;
                 self.last_mouse_move.take();
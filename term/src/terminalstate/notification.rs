@@ -0,0 +1,148 @@
+use crate::terminal::{Alert, NotificationOccasion, NotificationUrgency};
+use crate::TerminalState;
+use std::io::Write;
+use std::time::Duration;
+use wezterm_escape_parser::osc::{
+    KittyDesktopNotification, KittyNotificationActions, KittyNotificationOccasion,
+    KittyNotificationPayload, KittyNotificationUrgency,
+};
+use wezterm_escape_parser::OperatingSystemCommand;
+
+/// Upper bound on the accumulated size of a chunked notification,
+/// to avoid unbounded growth from a misbehaving application
+const MAX_NOTIFICATION_SIZE: usize = 64 * 1024;
+
+/// Accumulates the chunks of a kitty desktop notification until
+/// the application marks it as done
+#[derive(Debug, Default)]
+pub(crate) struct PendingNotification {
+    title: String,
+    body: String,
+    actions: Option<KittyNotificationActions>,
+    urgency: Option<KittyNotificationUrgency>,
+    occasion: Option<KittyNotificationOccasion>,
+    report_close: bool,
+    expire_ms: Option<i64>,
+}
+
+impl PendingNotification {
+    fn size(&self) -> usize {
+        self.title.len() + self.body.len()
+    }
+
+    fn merge(&mut self, notif: &KittyDesktopNotification) {
+        match notif.payload_type {
+            KittyNotificationPayload::Title => self.title.push_str(&notif.payload),
+            KittyNotificationPayload::Body => self.body.push_str(&notif.payload),
+            _ => {}
+        }
+        if notif.actions.is_some() {
+            self.actions = notif.actions;
+        }
+        if notif.urgency.is_some() {
+            self.urgency = notif.urgency;
+        }
+        if notif.occasion.is_some() {
+            self.occasion = notif.occasion;
+        }
+        if notif.expire_ms.is_some() {
+            self.expire_ms = notif.expire_ms;
+        }
+        self.report_close |= notif.report_close;
+    }
+}
+
+impl TerminalState {
+    pub(crate) fn kitty_desktop_notification(&mut self, notif: KittyDesktopNotification) {
+        match notif.payload_type {
+            KittyNotificationPayload::Query => {
+                self.respond_to_notification_query(notif.id);
+            }
+            KittyNotificationPayload::Close => {
+                let Some(id) = notif.id else {
+                    log::warn!("kitty notification close request without an id");
+                    return;
+                };
+                self.pending_notifications.remove(&id);
+                if let Some(handler) = self.alert_handler.as_mut() {
+                    handler.alert(Alert::CloseToastNotification(id));
+                }
+            }
+            KittyNotificationPayload::Title | KittyNotificationPayload::Body => {
+                let key = notif.id.clone().unwrap_or_default();
+                let pending = self.pending_notifications.entry(key.clone()).or_default();
+                pending.merge(&notif);
+
+                if pending.size() > MAX_NOTIFICATION_SIZE {
+                    log::warn!("kitty notification {key:?} is too large, discarding it");
+                    self.pending_notifications.remove(&key);
+                    return;
+                }
+
+                if notif.done {
+                    if let Some(pending) = self.pending_notifications.remove(&key) {
+                        self.show_kitty_notification(notif.id, pending);
+                    }
+                }
+            }
+            KittyNotificationPayload::Alive
+            | KittyNotificationPayload::Icon
+            | KittyNotificationPayload::Buttons => {
+                log::debug!("ignoring unsupported kitty notification {notif:?}");
+            }
+        }
+    }
+
+    fn show_kitty_notification(&mut self, id: Option<String>, pending: PendingNotification) {
+        let (title, body) = if pending.body.is_empty() {
+            (None, pending.title)
+        } else {
+            (Some(pending.title), pending.body)
+        };
+        let actions = pending.actions.unwrap_or_default();
+
+        let alert = Alert::ToastNotification {
+            title,
+            body,
+            focus: actions.focus,
+            // Reports cannot be routed back to the application
+            // without an id
+            report_activation: actions.report && id.is_some(),
+            report_close: pending.report_close && id.is_some(),
+            id,
+            urgency: match pending.urgency {
+                Some(KittyNotificationUrgency::Low) => NotificationUrgency::Low,
+                Some(KittyNotificationUrgency::Normal) | None => NotificationUrgency::Normal,
+                Some(KittyNotificationUrgency::Critical) => NotificationUrgency::Critical,
+            },
+            occasion: match pending.occasion {
+                Some(KittyNotificationOccasion::Always) | None => NotificationOccasion::Always,
+                Some(KittyNotificationOccasion::Unfocused) => NotificationOccasion::Unfocused,
+                Some(KittyNotificationOccasion::Invisible) => NotificationOccasion::Invisible,
+            },
+            timeout: match pending.expire_ms {
+                Some(ms) if ms > 0 => Some(Duration::from_millis(ms as u64)),
+                _ => None,
+            },
+        };
+
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(alert);
+        } else {
+            log::info!("Application sends kitty desktop notification: {alert:?}");
+        }
+    }
+
+    fn respond_to_notification_query(&mut self, id: Option<String>) {
+        let response = OperatingSystemCommand::KittyDesktopNotification(KittyDesktopNotification {
+            id,
+            payload_type: KittyNotificationPayload::Query,
+            payload: "a=focus,report:o=always,unfocused,invisible:u=0,1,2:\
+                          p=title,body,?,close:c=1:w=1"
+                .to_string(),
+            ..Default::default()
+        });
+        write!(self.writer, "{}", response).ok();
+        self.writer.flush().ok();
+    }
+}
@@ -1,4 +1,4 @@
-use crate::terminal::{Alert, Progress};
+use crate::terminal::{Alert, NotificationOccasion, NotificationUrgency, Progress};
 use crate::terminalstate::{
     default_color_map, CharSet, MouseEncoding, TabStop, UnicodeVersionStackEntry,
 };
@@ -14,6 +14,7 @@ use termwiz::input::KeyboardEncoding;
 use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
+use wezterm_cell::image::KITTY_PLACEHOLDER;
 use wezterm_cell::{
     grapheme_column_width, is_white_space_grapheme, Cell, CellAttributes, SemanticType,
 };
@@ -198,7 +199,10 @@ impl<'a> Performer<'a> {
             let y = self.cursor.y;
             let width = self.left_and_right_margins.end;
 
-            let pen = self.pen.clone();
+            let mut pen = self.pen.clone();
+            if g.starts_with(KITTY_PLACEHOLDER) {
+                self.kitty_placeholder_attrs(x, y, g, &mut pen);
+            }
 
             let wrappable = x + print_width >= width;
 
@@ -695,6 +699,11 @@ impl<'a> Performer<'a> {
                 self.application_keypad = false;
                 self.bracketed_paste = false;
                 self.focus_tracking = false;
+                self.color_scheme_updates = false;
+                self.in_band_resize = false;
+                self.pointer_shapes.clear();
+                self.alt_pointer_shapes.clear();
+                self.pending_clipboard_write = None;
                 self.mouse_tracking = false;
                 self.mouse_encoding = MouseEncoding::X10;
                 self.keyboard_encoding = KeyboardEncoding::Xterm;
@@ -907,6 +916,14 @@ impl<'a> Performer<'a> {
                         title: None,
                         body: message,
                         focus: true,
+                        id: None,
+                        // These were always shown as critical, and
+                        // there's no way to ask for anything else
+                        urgency: NotificationUrgency::Critical,
+                        occasion: NotificationOccasion::default(),
+                        report_activation: false,
+                        report_close: false,
+                        timeout: None,
                     });
                 } else {
                     log::info!("Application sends SystemNotification: {}", message);
@@ -929,10 +946,31 @@ impl<'a> Performer<'a> {
                             title,
                             body,
                             focus: true,
+                            id: None,
+                            urgency: NotificationUrgency::Critical,
+                            occasion: NotificationOccasion::default(),
+                            report_activation: false,
+                            report_close: false,
+                            timeout: None,
                         });
                     }
                 }
             }
+            OperatingSystemCommand::KittyDesktopNotification(notif) => {
+                self.kitty_desktop_notification(notif);
+            }
+            OperatingSystemCommand::TextSizing(sizing) => {
+                self.text_sizing(sizing);
+            }
+            OperatingSystemCommand::PointerShape(request) => {
+                self.pointer_shape_request(request);
+            }
+            OperatingSystemCommand::KittyClipboard(clip) => {
+                self.kitty_clipboard(clip);
+            }
+            OperatingSystemCommand::KittyColorControl(items) => {
+                self.kitty_color_control(items);
+            }
             OperatingSystemCommand::CurrentWorkingDirectory(url) => {
                 self.current_dir = Url::parse(&url).ok();
                 if let Some(handler) = self.alert_handler.as_mut() {
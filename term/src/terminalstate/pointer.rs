@@ -0,0 +1,77 @@
+use crate::TerminalState;
+use std::io::Write;
+use wezterm_escape_parser::osc::{PointerShape, PointerShapeRequest};
+
+/// Limits the depth of the pointer shape stack, so that an application
+/// that never pops can't consume unbounded memory
+const MAX_POINTER_SHAPES: usize = 16;
+
+impl TerminalState {
+    /// Returns the pointer shape requested by the application via
+    /// OSC 22 for the active screen, or None if the hosting application
+    /// should use its default choice of pointer.
+    pub fn pointer_shape(&self) -> Option<PointerShape> {
+        if self.screen.is_alt_screen_active() {
+            self.alt_pointer_shapes.last().copied()
+        } else {
+            self.pointer_shapes.last().copied()
+        }
+    }
+
+    fn pointer_shape_stack(&mut self) -> &mut Vec<PointerShape> {
+        if self.screen.is_alt_screen_active() {
+            &mut self.alt_pointer_shapes
+        } else {
+            &mut self.pointer_shapes
+        }
+    }
+
+    pub(crate) fn pointer_shape_request(&mut self, request: PointerShapeRequest) {
+        /// The first of the names that we recognize
+        fn resolve(names: &[String]) -> Option<PointerShape> {
+            names.iter().find_map(|name| PointerShape::from_name(name))
+        }
+
+        match request {
+            PointerShapeRequest::Set(names) if names.is_empty() => {
+                self.pointer_shape_stack().clear();
+            }
+            PointerShapeRequest::Set(names) => {
+                if let Some(shape) = resolve(&names) {
+                    let stack = self.pointer_shape_stack();
+                    stack.pop();
+                    stack.push(shape);
+                }
+            }
+            PointerShapeRequest::Push(names) => {
+                if let Some(shape) = resolve(&names) {
+                    let stack = self.pointer_shape_stack();
+                    if stack.len() >= MAX_POINTER_SHAPES {
+                        stack.remove(0);
+                    }
+                    stack.push(shape);
+                }
+            }
+            PointerShapeRequest::Pop => {
+                self.pointer_shape_stack().pop();
+            }
+            PointerShapeRequest::Query(names) => {
+                let answers: Vec<&str> = names
+                    .iter()
+                    .map(|name| match name.as_str() {
+                        "__current__" => self
+                            .pointer_shape()
+                            .map(PointerShape::css_name)
+                            .unwrap_or("0"),
+                        "__default__" => PointerShape::Text.css_name(),
+                        "__grabbed__" => PointerShape::Default.css_name(),
+                        name if PointerShape::from_name(name).is_some() => "1",
+                        _ => "0",
+                    })
+                    .collect();
+                write!(self.writer, "\x1b]22;{}\x1b\\", answers.join(",")).ok();
+                self.writer.flush().ok();
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{Position, TerminalState};
+use finl_unicode::grapheme_clusters::Graphemes;
+use wezterm_cell::{
+    grapheme_column_width, TextSize, TextSizeHorizontalAlign, TextSizeVerticalAlign,
+};
+use wezterm_escape_parser::osc::{TextSizing, TextSizingHorizontalAlign, TextSizingVerticalAlign};
+
+impl TerminalState {
+    /// Handles the kitty text sizing protocol (OSC 66).
+    /// Each block of text occupies `scale` rows, starting at the cursor
+    /// row, and the cursor is left on that row after the block, as
+    /// though the text had been printed normally.
+    pub(crate) fn text_sizing(&mut self, sizing: TextSizing) {
+        let scale = sizing.scale.max(1);
+        let size = TextSize {
+            scale,
+            cols: 0,
+            numerator: sizing.numerator,
+            denominator: sizing.denominator,
+            vertical_align: match sizing.vertical_align {
+                TextSizingVerticalAlign::Top => TextSizeVerticalAlign::Top,
+                TextSizingVerticalAlign::Bottom => TextSizeVerticalAlign::Bottom,
+                TextSizingVerticalAlign::Center => TextSizeVerticalAlign::Center,
+            },
+            horizontal_align: match sizing.horizontal_align {
+                TextSizingHorizontalAlign::Left => TextSizeHorizontalAlign::Left,
+                TextSizingHorizontalAlign::Right => TextSizeHorizontalAlign::Right,
+                TextSizingHorizontalAlign::Center => TextSizeHorizontalAlign::Center,
+            },
+            row: 0,
+        };
+
+        if sizing.width > 0 {
+            // An explicit width means that the whole text is a single block
+            self.print_text_sized_block(&sizing.text, sizing.width as usize * scale as usize, size);
+        } else {
+            for g in Graphemes::new(&sizing.text) {
+                let width = grapheme_column_width(g, Some(&self.unicode_version));
+                if width == 0 {
+                    continue;
+                }
+                self.print_text_sized_block(g, width * scale as usize, size);
+            }
+        }
+    }
+
+    fn print_text_sized_block(&mut self, text: &str, cols: usize, size: TextSize) {
+        let rows = size.scale as usize;
+        let left_margin = self.left_and_right_margins.start;
+        let right_margin = self.left_and_right_margins.end;
+        let num_rows =
+            (self.top_and_bottom_margins.end - self.top_and_bottom_margins.start) as usize;
+
+        if cols > right_margin - left_margin || cols > u8::MAX as usize || rows > num_rows {
+            log::debug!("text sizing block of {cols}x{rows} cells doesn't fit; ignoring {text:?}");
+            return;
+        }
+
+        if self.wrap_next || self.cursor.x + cols > right_margin {
+            if !self.dec_auto_wrap {
+                return;
+            }
+            let seqno = self.seqno;
+            let y = self.cursor.y;
+            let screen = self.screen_mut();
+            let y = screen.phys_row(y);
+            screen.line_mut(y).set_last_cell_was_wrapped(true, seqno);
+            self.new_line(true);
+        }
+
+        // Make room for the rows below the cursor, scrolling if required
+        let x = self.cursor.x;
+        for _ in 1..rows {
+            self.new_line(false);
+        }
+        let y = self.cursor.y - (rows as i64 - 1);
+
+        let seqno = self.seqno;
+        let pen = self.pen.clone();
+        for row in 0..rows {
+            let mut attrs = pen.clone();
+            attrs.set_text_size(Some(TextSize {
+                cols: cols as u8,
+                row: row as u8,
+                ..size
+            }));
+            let row_y = y + row as i64;
+            let screen = self.screen_mut();
+            if row == 0 {
+                screen.set_cell_grapheme(x, row_y, text, cols, attrs, seqno);
+            } else {
+                for col in x..x + cols {
+                    screen.set_cell_grapheme(col, row_y, " ", 1, attrs.clone(), seqno);
+                }
+            }
+        }
+
+        let next_x = x + cols;
+        if next_x >= right_margin {
+            self.set_cursor_pos(
+                &Position::Absolute(right_margin as i64 - 1),
+                &Position::Absolute(y),
+            );
+            self.wrap_next = self.dec_auto_wrap;
+        } else {
+            self.set_cursor_pos(&Position::Absolute(next_x as i64), &Position::Absolute(y));
+        }
+    }
+}
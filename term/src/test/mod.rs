@@ -7,9 +7,10 @@ use bitflags::bitflags;
 mod c1;
 mod csi;
 // mod selection; FIXME: port to render layer
-use crate::color::ColorPalette;
+use crate::color::{ColorPalette, SrgbaTuple};
 use k9::assert_equal as assert_eq;
 use std::sync::{Arc, Mutex};
+use wezterm_cell::image::TextureCoordinate;
 use wezterm_escape_parser::csi::{Edit, EraseInDisplay, EraseInLine};
 use wezterm_escape_parser::{OneBased, OperatingSystemCommand, CSI};
 use wezterm_surface::{CursorShape, CursorVisibility, SequenceNo, SEQ_ZERO};
@@ -54,10 +55,61 @@ impl TerminalConfiguration for TestTermConfig {
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
+
+    fn enable_kitty_graphics(&self) -> bool {
+        true
+    }
+}
+
+/// Forwards the responses that the terminal writes back to the
+/// application, so that tests can check them
+struct ResponseWriter(std::sync::mpsc::Sender<Vec<u8>>);
+
+impl std::io::Write for ResponseWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.send(buf.to_vec()).ok();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Waits for the terminal to write `expect` back to the application
+fn assert_response(responses: &std::sync::mpsc::Receiver<Vec<u8>>, expect: &str) {
+    let mut response = vec![];
+    while response.len() < expect.len() {
+        match responses.recv_timeout(std::time::Duration::from_secs(5)) {
+            Ok(data) => response.extend_from_slice(&data),
+            Err(_) => break,
+        }
+    }
+    assert_eq!(
+        String::from_utf8_lossy(&response).to_string(),
+        expect.to_string()
+    );
 }
 
 impl TestTerm {
     fn new(height: usize, width: usize, scrollback: usize) -> Self {
+        Self::with_writer(height, width, scrollback, Box::new(Vec::new()))
+    }
+
+    /// Creates a terminal whose responses can be checked with
+    /// `assert_response`
+    fn with_responses(height: usize, width: usize) -> (Self, std::sync::mpsc::Receiver<Vec<u8>>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let term = Self::with_writer(height, width, 0, Box::new(ResponseWriter(tx)));
+        (term, rx)
+    }
+
+    fn with_writer(
+        height: usize,
+        width: usize,
+        scrollback: usize,
+        writer: Box<dyn std::io::Write + Send>,
+    ) -> Self {
         let _ = env_logger::Builder::new()
             .is_test(true)
             .filter_level(log::LevelFilter::Trace)
@@ -74,7 +126,7 @@ impl TestTerm {
             Arc::new(TestTermConfig { scrollback }),
             "WezTerm",
             "O_o",
-            Box::new(Vec::new()),
+            writer,
         );
         let clip: Arc<dyn Clipboard> = Arc::new(LocalClip::new());
         term.set_clipboard(&clip);
@@ -755,6 +807,476 @@ fn test_dec_double_width() {
     assert!(lines[3].is_single_width());
 }
 
+/// Test OSC 99 notifications that are split over several chunks
+#[test]
+fn test_kitty_desktop_notification() {
+    struct AlertCapture(Arc<Mutex<Vec<Alert>>>);
+
+    impl AlertHandler for AlertCapture {
+        fn alert(&mut self, alert: Alert) {
+            self.0.lock().unwrap().push(alert);
+        }
+    }
+
+    let mut term = TestTerm::new(4, 10, 0);
+    let alerts = Arc::new(Mutex::new(vec![]));
+    term.set_notification_handler(Box::new(AlertCapture(Arc::clone(&alerts))));
+
+    // The title and body of notification "1" are interleaved with
+    // the chunks of notification "2" and only shown when done
+    term.print("\u{1b}]99;i=1:d=0:u=2;Hel\u{1b}\\");
+    term.print("\u{1b}]99;i=2:d=0;Other\u{1b}\\");
+    term.print("\u{1b}]99;i=1:d=0;lo\u{1b}\\");
+    term.print("\u{1b}]99;i=1:d=0:p=body;Wor\u{1b}\\");
+    assert!(alerts.lock().unwrap().is_empty());
+    term.print("\u{1b}]99;i=1:p=body:a=report;ld\u{1b}\\");
+
+    // A chunk without an id on its own is a complete notification
+    // with the normal urgency, while OSC 9 remains critical
+    term.print("\u{1b}]99;;Plain\u{1b}\\");
+    term.print("\u{1b}]9;Legacy\u{1b}\\");
+
+    let toast =
+        |title: Option<&str>, body: &str, id: Option<&str>, urgency| Alert::ToastNotification {
+            title: title.map(str::to_string),
+            body: body.to_string(),
+            focus: true,
+            id: id.map(str::to_string),
+            urgency,
+            occasion: NotificationOccasion::Always,
+            report_activation: false,
+            report_close: false,
+            timeout: None,
+        };
+    let mut reported = toast(
+        Some("Hello"),
+        "World",
+        Some("1"),
+        NotificationUrgency::Critical,
+    );
+    if let Alert::ToastNotification {
+        report_activation, ..
+    } = &mut reported
+    {
+        *report_activation = true;
+    }
+    assert_eq!(
+        alerts.lock().unwrap().clone(),
+        vec![
+            reported,
+            toast(None, "Plain", None, NotificationUrgency::Normal),
+            toast(None, "Legacy", None, NotificationUrgency::Critical),
+        ]
+    );
+
+    // Closing a pending notification discards its chunks
+    term.print("\u{1b}]99;i=2:p=close;\u{1b}\\");
+    term.print("\u{1b}]99;i=2;Body\u{1b}\\");
+    assert_eq!(
+        alerts.lock().unwrap().split_off(3),
+        vec![
+            Alert::CloseToastNotification("2".to_string()),
+            toast(None, "Body", Some("2"), NotificationUrgency::Normal),
+        ]
+    );
+}
+
+/// Test DEC mode 2031 color scheme change notifications
+#[test]
+fn test_color_scheme_updates() {
+    use wezterm_escape_parser::csi::ColorScheme;
+
+    let (mut term, responses) = TestTerm::with_responses(4, 10);
+
+    // Changes are not reported until the mode is enabled
+    term.set_color_scheme(ColorScheme::Light);
+    term.set_mode("?2031", true);
+    term.print("\u{1b}[?2031$p");
+    assert_response(&responses, "\u{1b}[?2031;1$y");
+
+    term.set_color_scheme(ColorScheme::Dark);
+    assert_response(&responses, "\u{1b}[?997;1n");
+
+    // Advising the same scheme again is not a change
+    term.set_color_scheme(ColorScheme::Dark);
+    term.set_color_scheme(ColorScheme::Light);
+    assert_response(&responses, "\u{1b}[?997;2n");
+
+    // The application can also query the current scheme
+    term.print("\u{1b}[?996n");
+    assert_response(&responses, "\u{1b}[?997;2n");
+
+    term.set_mode("?2031", false);
+    term.set_color_scheme(ColorScheme::Dark);
+    term.print("\u{1b}[?2031$p");
+    assert_response(&responses, "\u{1b}[?2031;2$y");
+}
+
+/// Test DEC mode 2048 in-band resize notifications
+#[test]
+fn test_in_band_resize() {
+    let (mut term, responses) = TestTerm::with_responses(4, 10);
+
+    term.print("\u{1b}[?2048$p");
+    assert_response(&responses, "\u{1b}[?2048;2$y");
+
+    // Enabling the mode reports the current size right away
+    term.set_mode("?2048", true);
+    assert_response(&responses, "\u{1b}[48;4;10;64;80t");
+    term.print("\u{1b}[?2048$p");
+    assert_response(&responses, "\u{1b}[?2048;1$y");
+
+    term.resize(TerminalSize {
+        rows: 5,
+        cols: 20,
+        pixel_width: 160,
+        pixel_height: 80,
+        dpi: 0,
+    });
+    assert_response(&responses, "\u{1b}[48;5;20;80;160t");
+
+    term.set_mode("?2048", false);
+    term.resize(TerminalSize {
+        rows: 4,
+        cols: 10,
+        pixel_width: 80,
+        pixel_height: 64,
+        dpi: 0,
+    });
+    term.print("\u{1b}[?2048$p");
+    assert_response(&responses, "\u{1b}[?2048;2$y");
+}
+
+/// Test the kitty text sizing protocol (OSC 66)
+#[test]
+fn test_text_sizing() {
+    let mut term = TestTerm::new(4, 10, 0);
+
+    term.print("a\u{1b}]66;s=2;bc\u{1b}\\d");
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "     ", "", ""]);
+    term.assert_cursor_pos(6, 0, None, None);
+
+    let lines = term.screen().visible_lines();
+    let anchor = lines[0].get_cell(1).unwrap();
+    assert_eq!(anchor.width(), 2);
+    let size = anchor.attrs().text_size().unwrap();
+    assert_eq!((size.scale, size.cols, size.row), (2, 2, 0));
+    let filler = lines[1].get_cell(2).unwrap();
+    let size = filler.attrs().text_size().unwrap();
+    assert_eq!((size.scale, size.cols, size.row), (2, 2, 1));
+}
+
+/// Test kitty graphics animation control
+#[test]
+fn test_kitty_animation_control() {
+    use std::time::Duration;
+    use wezterm_cell::image::{AnimationState, ImageDataType};
+
+    let mut term = TestTerm::new(4, 10, 0);
+
+    // A 1x1 image with a second frame added to it
+    term.print("\u{1b}_Ga=T,f=32,s=1,v=1,i=1,q=2;AAAAAA==\u{1b}\\");
+    term.print("\u{1b}_Ga=f,f=32,s=1,v=1,i=1,q=2;/wAA/w==\u{1b}\\");
+
+    term.print("\u{1b}_Ga=a,i=1,r=1,z=100,q=2\u{1b}\\");
+    // A negative gap makes the frame gapless
+    term.print("\u{1b}_Ga=a,i=1,r=2,z=-1,q=2\u{1b}\\");
+    term.print("\u{1b}_Ga=a,i=1,s=1,c=2,v=3,q=2\u{1b}\\");
+    // Frames that don't exist are rejected
+    term.print("\u{1b}_Ga=a,i=1,c=3,q=2\u{1b}\\");
+
+    let images = term.screen().visible_lines()[0]
+        .get_cell(0)
+        .unwrap()
+        .attrs()
+        .images()
+        .unwrap();
+    match &*images[0].image_data().data() {
+        ImageDataType::AnimRgba8 {
+            durations,
+            frames,
+            animation,
+            ..
+        } => {
+            assert_eq!(frames.len(), 2);
+            assert_eq!(
+                durations.clone(),
+                vec![Duration::from_millis(100), Duration::ZERO]
+            );
+            assert_eq!(animation.state, AnimationState::Stopped);
+            assert_eq!(animation.current_frame, Some(1));
+            assert_eq!(animation.max_loops, Some(2));
+        }
+        wat => panic!("expected an animation, got {:?}", wat),
+    }
+}
+
+/// Test that gapless frames are skipped during playback
+#[test]
+fn test_kitty_gapless_frames() {
+    use std::time::Duration;
+    use wezterm_cell::image::{AnimationControl, AnimationState};
+
+    let ms = Duration::from_millis;
+    let durations = [Duration::ZERO, ms(100), Duration::ZERO, ms(50)];
+    let mut control = AnimationControl::default();
+    let mut loops_played = 0;
+
+    assert_eq!(control.first_frame(&durations), 1);
+    assert_eq!(
+        control.next_frame(1, &durations, &mut loops_played),
+        Some(3)
+    );
+    assert_eq!(
+        control.next_frame(3, &durations, &mut loops_played),
+        Some(1)
+    );
+    assert_eq!(loops_played, 1);
+
+    // A frame that the application asks for is shown, gapless or not
+    control.current_frame = Some(2);
+    assert_eq!(control.first_frame(&durations), 2);
+    assert_eq!(
+        control.next_frame(2, &durations, &mut loops_played),
+        Some(3)
+    );
+
+    control.state = AnimationState::Loading;
+    assert_eq!(control.next_frame(3, &durations, &mut loops_played), None);
+
+    // When every frame is gapless, they are all shown
+    let durations = [Duration::ZERO, Duration::ZERO];
+    let control = AnimationControl::default();
+    assert_eq!(control.first_frame(&durations), 0);
+    assert_eq!(
+        control.next_frame(0, &durations, &mut loops_played),
+        Some(1)
+    );
+}
+
+/// Test kitty graphics unicode placeholders
+#[test]
+fn test_kitty_placeholders() {
+    let mut term = TestTerm::new(4, 10, 0);
+
+    // A placeholder printed before the placement exists shows nothing
+    // until the placement is created
+    term.print("\u{1b}[38;5;5m\u{10EEEE}\u{0305}\u{0305}\u{10EEEE}\u{1b}[m\r\n");
+
+    // A transparent 16x16 image, placed virtually over 2x1 cells of 8x16
+    let data = format!("{}AA==", "AAAA".repeat(341));
+    term.print(format!("\u{1b}_Ga=t,f=32,s=16,v=16,i=5,q=2;{data}\u{1b}\\"));
+    term.print("\u{1b}_Ga=p,U=1,i=5,c=2,r=1,q=2\u{1b}\\");
+    // A virtual placement doesn't move the cursor
+    let cursor = term.cursor_pos();
+    assert_eq!((cursor.x, cursor.y), (0, 1));
+
+    // The column of the second cell is inferred from the first
+    term.print("\u{1b}[38;5;5m\u{10EEEE}\u{0305}\u{0305}\u{10EEEE}\u{1b}[m");
+
+    let lines = term.screen().visible_lines();
+    for line in &lines[0..2] {
+        let images: Vec<_> = (0..2)
+            .map(|x| {
+                let mut images = line.get_cell(x).unwrap().attrs().images().unwrap();
+                assert_eq!(images.len(), 1);
+                images.remove(0)
+            })
+            .collect();
+        assert_eq!(images[0].image_id(), Some(5));
+        assert_eq!(images[0].top_left(), TextureCoordinate::new_f32(0., 0.));
+        assert_eq!(
+            images[0].bottom_right(),
+            TextureCoordinate::new_f32(0.5, 1.)
+        );
+        assert_eq!(images[1].top_left(), TextureCoordinate::new_f32(0.5, 0.));
+        assert_eq!(images[1].bottom_right(), TextureCoordinate::new_f32(1., 1.));
+    }
+
+    // Deleting the placement removes the image from the placeholders
+    term.print("\u{1b}_Ga=d,d=i,i=5,q=2\u{1b}\\");
+    let lines = term.screen().visible_lines();
+    assert!(lines[1].get_cell(0).unwrap().attrs().images().is_none());
+}
+
+/// Test kitty graphics relative placements
+#[test]
+fn test_kitty_relative_placements() {
+    let mut term = TestTerm::new(4, 10, 0);
+
+    // A 16x16 parent image spanning 2x1 cells, and an 8x16 child
+    // image filling a single cell
+    let parent = format!("{}AA==", "AAAA".repeat(341));
+    term.print(format!(
+        "\u{1b}_Ga=t,f=32,s=16,v=16,i=1,q=2;{parent}\u{1b}\\"
+    ));
+    let child = format!("{}AAA=", "AAAA".repeat(170));
+    term.print(format!("\u{1b}_Ga=t,f=32,s=8,v=16,i=2,q=2;{child}\u{1b}\\"));
+
+    let has_image = |term: &TestTerm, x: usize, y: usize, image_id: u32| {
+        term.screen().visible_lines()[y]
+            .get_cell(x)
+            .and_then(|cell| cell.attrs().images())
+            .map(|images| images.iter().any(|i| i.image_id() == Some(image_id)))
+            .unwrap_or(false)
+    };
+
+    term.cup(2, 1);
+    term.print("\u{1b}_Ga=p,i=1,p=1,C=1,q=2\u{1b}\\");
+    term.cup(5, 3);
+    term.print("\u{1b}_Ga=p,i=2,p=1,P=1,Q=1,H=1,V=1,q=2\u{1b}\\");
+    assert!(has_image(&term, 3, 2, 2));
+
+    // A relative placement doesn't move the cursor
+    let cursor = term.cursor_pos();
+    assert_eq!((cursor.x, cursor.y), (5, 3));
+
+    // The child follows the parent when it moves
+    term.cup(0, 0);
+    term.print("\u{1b}_Ga=p,i=1,p=1,C=1,q=2\u{1b}\\");
+    assert!(has_image(&term, 1, 1, 2));
+    assert!(!has_image(&term, 3, 2, 2));
+
+    // Deleting the parent also deletes the child
+    term.print("\u{1b}_Ga=d,d=i,i=1,p=1,q=2\u{1b}\\");
+    assert!(!has_image(&term, 0, 0, 1));
+    assert!(!has_image(&term, 1, 1, 2));
+
+    // Moving the parent only moves the child's own placement, leaving
+    // the other placements of the child image alone
+    term.cup(2, 1);
+    term.print("\u{1b}_Ga=p,i=1,p=1,C=1,q=2\u{1b}\\");
+    term.print("\u{1b}_Ga=p,i=2,P=1,Q=1,H=1,V=1,q=2\u{1b}\\");
+    term.cup(8, 3);
+    term.print("\u{1b}_Ga=p,i=2,p=2,C=1,q=2\u{1b}\\");
+    term.cup(0, 0);
+    term.print("\u{1b}_Ga=p,i=1,p=1,C=1,q=2\u{1b}\\");
+    assert!(has_image(&term, 1, 1, 2));
+    assert!(!has_image(&term, 3, 2, 2));
+    assert!(has_image(&term, 8, 3, 2));
+
+    // A virtual parent is located by the placeholder cells showing it
+    term.print("\u{1b}_Ga=d,d=i,i=1,p=1,q=2\u{1b}\\");
+    term.print("\u{1b}_Ga=p,U=1,i=1,p=3,c=2,r=1,q=2\u{1b}\\");
+    term.cup(4, 2);
+    term.print("\u{1b}[38;5;1;58;5;3m\u{10EEEE}\u{0305}\u{0305}\u{10EEEE}\u{1b}[m");
+    term.print("\u{1b}_Ga=p,i=2,p=1,P=1,Q=3,H=1,q=2\u{1b}\\");
+    assert!(has_image(&term, 5, 2, 2));
+}
+
+/// Test OSC 22 pointer shape changes
+#[test]
+fn test_pointer_shape() {
+    use wezterm_escape_parser::osc::PointerShape;
+
+    let mut term = TestTerm::new(4, 10, 0);
+    assert_eq!(term.pointer_shape(), None);
+
+    term.print("\u{1b}]22;bogus,hand2\u{1b}\\");
+    assert_eq!(term.pointer_shape(), Some(PointerShape::Pointer));
+
+    term.print("\u{1b}]22;>ew-resize\u{1b}\\");
+    assert_eq!(term.pointer_shape(), Some(PointerShape::EwResize));
+
+    // The alternate screen has its own stack
+    term.set_mode("?1049", true);
+    assert_eq!(term.pointer_shape(), None);
+    term.print("\u{1b}]22;=wait\u{1b}\\");
+    assert_eq!(term.pointer_shape(), Some(PointerShape::Wait));
+    term.set_mode("?1049", false);
+
+    term.print("\u{1b}]22;<\u{1b}\\");
+    assert_eq!(term.pointer_shape(), Some(PointerShape::Pointer));
+
+    term.print("\u{1b}]22;\u{1b}\\");
+    assert_eq!(term.pointer_shape(), None);
+}
+
+/// Test OSC 5522 clipboard writes with multiple MIME types
+#[test]
+fn test_kitty_clipboard_write() {
+    #[derive(Default)]
+    struct DataClip {
+        items: Mutex<Vec<ClipboardItem>>,
+    }
+
+    impl Clipboard for DataClip {
+        fn set_contents(
+            &self,
+            _selection: ClipboardSelection,
+            _clip: Option<String>,
+        ) -> anyhow::Result<()> {
+            anyhow::bail!("expected set_data to be used");
+        }
+
+        fn set_data(
+            &self,
+            _selection: ClipboardSelection,
+            items: Vec<ClipboardItem>,
+        ) -> anyhow::Result<()> {
+            *self.items.lock().unwrap() = items;
+            Ok(())
+        }
+    }
+
+    let mut term = TestTerm::new(4, 10, 0);
+    let clip = Arc::new(DataClip::default());
+    let dyn_clip: Arc<dyn Clipboard> = clip.clone();
+    term.set_clipboard(&dyn_clip);
+
+    term.print("\u{1b}]5522;type=write\u{1b}\\");
+    // "hel" + "lo" as text/plain
+    term.print("\u{1b}]5522;type=wdata:mime=dGV4dC9wbGFpbg==;aGVs\u{1b}\\");
+    term.print("\u{1b}]5522;type=wdata:mime=dGV4dC9wbGFpbg==;bG8=\u{1b}\\");
+    // "<b>hi</b>" as text/html
+    term.print("\u{1b}]5522;type=wdata:mime=dGV4dC9odG1s;PGI+aGk8L2I+\u{1b}\\");
+    // UTF8_STRING as an alias for text/plain
+    term.print("\u{1b}]5522;type=walias:mime=dGV4dC9wbGFpbg==;VVRGOF9TVFJJTkc=\u{1b}\\");
+    assert!(clip.items.lock().unwrap().is_empty());
+
+    // The clipboard is only assigned once the write is complete
+    term.print("\u{1b}]5522;type=wdata\u{1b}\\");
+    let item = |mime_type: &str, data: &str| ClipboardItem {
+        mime_type: mime_type.to_string(),
+        data: data.as_bytes().to_vec(),
+    };
+    assert_eq!(
+        clip.items.lock().unwrap().clone(),
+        vec![
+            item("text/plain", "hello"),
+            item("text/html", "<b>hi</b>"),
+            item("UTF8_STRING", "hello"),
+        ]
+    );
+}
+
+/// Test OSC 21 color control
+#[test]
+fn test_kitty_color_control() {
+    let mut term = TestTerm::new(4, 10, 0);
+    let default = term.palette();
+
+    term.print(
+        "\u{1b}]21;foreground=#ff0000;cursor=#00ff00;1=#0000ff;\
+         active_tab_background=#ffffff;bogus=#ffffff\u{1b}\\",
+    );
+    let palette = term.palette();
+    let red = SrgbaTuple(1., 0., 0., 1.);
+    let green = SrgbaTuple(0., 1., 0., 1.);
+    let blue = SrgbaTuple(0., 0., 1., 1.);
+    assert_eq!(palette.foreground, red);
+    assert_eq!(palette.cursor_bg, green);
+    assert_eq!(palette.cursor_border, green);
+    assert_eq!(palette.colors.0[1], blue);
+    assert_eq!(palette.active_tab_bg, Some(SrgbaTuple(1., 1., 1., 1.)));
+    assert_eq!(palette.background, default.background);
+
+    // Resetting everything that was changed returns to the
+    // configured palette
+    term.print("\u{1b}]21;foreground=;cursor=;1=;active_tab_background=\u{1b}\\");
+    assert_eq!(term.palette(), default);
+}
+
 /// This test skips over an edge case with cursor positioning,
 /// while sizing down, but tries to trip over the same edge
 /// case while sizing back up again
@@ -71,6 +71,11 @@ impl TextureCoordinate {
     }
 }
 
+/// The kitty graphics protocol uses this codepoint, combined with
+/// colors and diacritics that encode an image and placement id, to
+/// display a virtual placement of an image in the text of the terminal
+pub const KITTY_PLACEHOLDER: char = '\u{10EEEE}';
+
 /// Tracks data for displaying an image in the place of the normal cell
 /// character data.  Since an Image can span multiple cells, we need to logically
 /// carve up the image and track each slice of it.  Each cell needs to know
@@ -194,6 +199,80 @@ impl ImageCell {
     }
 }
 
+/// How an animated image should be played back
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationState {
+    /// Remain on the current frame
+    Stopped,
+    /// Play the frames, but wait on the last frame until
+    /// more frames are added
+    Loading,
+    /// Play the frames, looping back to the start after the last frame
+    #[default]
+    Running,
+}
+
+/// Playback controls for an animated image.
+/// These can be changed by the application via the kitty
+/// graphics protocol.
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationControl {
+    pub state: AnimationState,
+    /// The number of times to play through the frames before
+    /// stopping on the last frame.  None means to loop forever.
+    pub max_loops: Option<u32>,
+    /// The 0-based frame that should be shown when playback
+    /// is (re)started
+    pub current_frame: Option<usize>,
+    /// Incremented each time the controls are changed, so that
+    /// the renderer knows to reset its playback position
+    pub generation: usize,
+}
+
+impl AnimationControl {
+    /// Returns the frame that playback starts from: the one that the
+    /// application asked for, or else the first that isn't gapless
+    pub fn first_frame(&self, durations: &[Duration]) -> usize {
+        self.current_frame
+            .or_else(|| (0..durations.len()).find(|&idx| is_shown(durations, idx)))
+            .unwrap_or(0)
+    }
+
+    /// Determines which frame follows `current`, honoring the playback
+    /// controls and skipping over gapless frames.
+    /// Returns None if playback should remain on the current frame.
+    pub fn next_frame(
+        &self,
+        current: usize,
+        durations: &[Duration],
+        loops_played: &mut u32,
+    ) -> Option<usize> {
+        if let Some(next) = (current + 1..durations.len()).find(|&idx| is_shown(durations, idx)) {
+            return Some(next);
+        }
+        match self.state {
+            AnimationState::Stopped | AnimationState::Loading => None,
+            AnimationState::Running => {
+                *loops_played += 1;
+                if let Some(max_loops) = self.max_loops {
+                    if *loops_played >= max_loops {
+                        return None;
+                    }
+                }
+                (0..durations.len()).find(|&idx| is_shown(durations, idx))
+            }
+        }
+    }
+}
+
+/// Frames with a zero duration are gapless, and are skipped during
+/// playback, unless there is nothing else to show
+fn is_shown(durations: &[Duration], idx: usize) -> bool {
+    !durations[idx].is_zero() || durations.iter().all(Duration::is_zero)
+}
+
 #[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
 #[derive(Clone, PartialEq, Eq)]
 pub enum ImageDataType {
@@ -225,6 +304,8 @@ pub enum ImageDataType {
         durations: Vec<Duration>,
         frames: Vec<Vec<u8>>,
         hashes: Vec<[u8; 32]>,
+        #[cfg_attr(feature = "use_serde", serde(default))]
+        animation: AnimationControl,
     },
 }
 
@@ -254,6 +335,7 @@ impl std::fmt::Debug for ImageDataType {
                 height,
                 durations,
                 hashes,
+                animation,
             } => fmt
                 .debug_struct("AnimRgba8")
                 .field("frames_of_len", &frames.len())
@@ -261,6 +343,7 @@ impl std::fmt::Debug for ImageDataType {
                 .field("height", &height)
                 .field("durations", durations)
                 .field("hashes", hashes)
+                .field("animation", animation)
                 .finish(),
         }
     }
@@ -478,6 +561,7 @@ impl ImageDataType {
             frames,
             durations,
             hashes,
+            animation: AnimationControl::default(),
         }
     }
 
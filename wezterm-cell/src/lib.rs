@@ -8,7 +8,7 @@ use core::hash::{Hash, Hasher};
 use core::mem;
 use finl_unicode::grapheme_clusters::Graphemes;
 #[cfg(feature = "use_serde")]
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 pub use wezterm_char_props::emoji::Presentation;
 use wezterm_char_props::emoji_variation::WCWIDTH_TABLE;
 use wezterm_char_props::widechar_width::WcWidth;
@@ -99,6 +99,8 @@ struct FatAttributes {
     underline_color: ColorAttribute,
     foreground: ColorAttribute,
     background: ColorAttribute,
+    /// Set for cells that are part of a block of scaled text
+    text_size: Option<TextSize>,
 }
 
 impl FatAttributes {
@@ -113,6 +115,61 @@ impl FatAttributes {
         self.underline_color.hash(hasher);
         self.foreground.hash(hasher);
         self.background.hash(hasher);
+        self.text_size.hash(hasher);
+    }
+}
+
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextSizeVerticalAlign {
+    #[default]
+    Top,
+    Bottom,
+    Center,
+}
+
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextSizeHorizontalAlign {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+/// Describes a cell that is part of a block of text rendered at
+/// a larger size, as produced by the kitty text sizing protocol.
+/// The block spans `scale` rows and `cols` columns.  The cell in
+/// the top left of the block holds the text and has a width of
+/// `cols`; the cells in the rows below it are blank and exist to
+/// reserve the space into which the text is drawn.
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextSize {
+    /// The multiplier applied to the font size, which is also
+    /// the number of rows spanned by the block
+    pub scale: u8,
+    /// The number of columns spanned by the block
+    pub cols: u8,
+    /// The fractional scale is applied on top of `scale`
+    /// when `denominator > numerator`
+    pub numerator: u8,
+    pub denominator: u8,
+    pub vertical_align: TextSizeVerticalAlign,
+    pub horizontal_align: TextSizeHorizontalAlign,
+    /// Which row of the block this cell occupies
+    pub row: u8,
+}
+
+impl TextSize {
+    /// Returns the effective multiplier to apply to the font size
+    pub fn font_scale(&self) -> f64 {
+        let scale = self.scale.max(1) as f64;
+        if self.denominator > self.numerator && self.numerator > 0 {
+            scale * self.numerator as f64 / self.denominator as f64
+        } else {
+            scale
+        }
     }
 }
 
@@ -323,6 +380,7 @@ impl CellAttributes {
                 underline_color: ColorAttribute::Default,
                 foreground: ColorAttribute::Default,
                 background: ColorAttribute::Default,
+                text_size: None,
             }));
         }
     }
@@ -342,6 +400,7 @@ impl CellAttributes {
                     && fat.underline_color == ColorAttribute::Default
                     && fat.foreground == ColorAttribute::Default
                     && fat.background == ColorAttribute::Default
+                    && fat.text_size.is_none()
             })
             .unwrap_or(false);
         if deallocate {
@@ -349,6 +408,21 @@ impl CellAttributes {
         }
     }
 
+    pub fn set_text_size(&mut self, text_size: Option<TextSize>) -> &mut Self {
+        if text_size.is_none() && self.fat.is_none() {
+            self
+        } else {
+            self.allocate_fat_attributes();
+            self.fat.as_mut().unwrap().text_size = text_size;
+            self.deallocate_fat_attributes_if_none();
+            self
+        }
+    }
+
+    pub fn text_size(&self) -> Option<&TextSize> {
+        self.fat.as_ref().and_then(|fat| fat.text_size.as_ref())
+    }
+
     pub fn set_hyperlink(&mut self, link: Option<Arc<Hyperlink>>) -> &mut Self {
         if link.is_none() && self.fat.is_none() {
             self
@@ -521,13 +595,30 @@ impl CellAttributes {
     }
 }
 
+/// The serialized form of a `Cell`.
+/// The width of the cell is not serialized; it is recomputed
+/// from the text, except for the cell that holds the text of
+/// a text-sized block whose width is taken from its attributes.
 #[cfg(feature = "use_serde")]
-fn deserialize_teenystring<'de, D>(deserializer: D) -> Result<TeenyString, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    let text = String::deserialize(deserializer)?;
-    Ok(TeenyString::from_str(&text, None, None))
+#[derive(Deserialize)]
+struct SerializedCell {
+    text: alloc::string::String,
+    attrs: CellAttributes,
+}
+
+#[cfg(feature = "use_serde")]
+impl From<SerializedCell> for Cell {
+    fn from(cell: SerializedCell) -> Self {
+        let width = cell
+            .attrs
+            .text_size()
+            .filter(|size| size.row == 0)
+            .map(|size| size.cols as usize);
+        Self {
+            text: TeenyString::from_str(&cell.text, width, None),
+            attrs: cell.attrs,
+        }
+    }
 }
 
 #[cfg(feature = "use_serde")]
@@ -710,16 +801,14 @@ impl core::cmp::PartialEq for TeenyString {
 impl core::cmp::Eq for TeenyString {}
 
 /// Models the contents of a cell on the terminal display
-#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "use_serde",
+    derive(Serialize, Deserialize),
+    serde(from = "SerializedCell")
+)]
 #[derive(Clone, Eq, PartialEq)]
 pub struct Cell {
-    #[cfg_attr(
-        feature = "use_serde",
-        serde(
-            deserialize_with = "deserialize_teenystring",
-            serialize_with = "serialize_teenystring"
-        )
-    )]
+    #[cfg_attr(feature = "use_serde", serde(serialize_with = "serialize_teenystring"))]
     text: TeenyString,
     attrs: CellAttributes,
 }
@@ -882,10 +882,15 @@ impl Reconnectable {
                 let creds = ui.run_and_log_error(|| {
                     // The `tlscreds` command will start the server if needed and then
                     // obtain client credentials that we can use for tls.
-                    let cmd = format!(
+                    let mut cmd = format!(
                         "{} cli tlscreds",
                         Self::wezterm_bin_path(&tls_client.remote_wezterm_path)
                     );
+                    // The name is validated by the config layer, so it
+                    // is safe to pass through the remote shell as-is
+                    if let Some(name) = &tls_client.client_name {
+                        cmd.push_str(&format!(" --client-name {name}"));
+                    }
 
                     ui.output_str(&format!("Running: {}\n", cmd));
                     let mut exec = smol::block_on(sess.exec(&cmd, None))
@@ -1141,6 +1146,11 @@ impl Client {
         }
     }
 
+    /// Returns true if this client attached to the server in read-only mode
+    pub fn is_read_only(&self) -> bool {
+        self.client_domain_config.read_only()
+    }
+
     pub fn into_client_domain_config(self) -> ClientDomainConfig {
         self.client_domain_config
     }
@@ -1166,6 +1176,7 @@ impl Client {
                 self.set_client_id(SetClientId {
                     client_id: self.client_id.clone(),
                     is_proxy: false,
+                    read_only: self.is_read_only(),
                 })
                 .await?;
                 Ok(info)
@@ -1366,7 +1377,7 @@ impl Client {
         GetPaneRenderableDimensionsResponse
     );
     rpc!(get_codec_version, GetCodecVersion, GetCodecVersionResponse);
-    rpc!(get_tls_creds, GetTlsCreds = (), GetTlsCredsResponse);
+    rpc!(get_tls_creds, GetTlsCreds, GetTlsCredsResponse);
     rpc!(
         search_scrollback,
         SearchScrollbackRequest,
@@ -1383,10 +1394,26 @@ impl Client {
     rpc!(set_window_title, WindowTitleChanged, UnitResponse);
     rpc!(rename_workspace, RenameWorkspace, UnitResponse);
     rpc!(erase_scrollback, EraseScrollbackRequest, UnitResponse);
+    rpc!(set_color_scheme, SetColorScheme, UnitResponse);
     rpc!(
         get_pane_direction,
         GetPaneDirection,
         GetPaneDirectionResponse
     );
     rpc!(adjust_pane_size, AdjustPaneSize, UnitResponse);
+    rpc!(apply_layout, ApplyLayout, ApplyLayoutResponse);
+    rpc!(revoke_client, RevokeClient, UnitResponse);
+    rpc!(set_pane_logging, SetPaneLogging, UnitResponse);
+    rpc!(spawn_floating_pane, SpawnFloatingPane, SpawnResponse);
+    rpc!(apply_floating_pane_op, ApplyFloatingPaneOp, UnitResponse);
+    rpc!(
+        list_port_forwards,
+        ListPortForwards = (),
+        ListPortForwardsResponse
+    );
+    rpc!(
+        set_port_forward_enabled,
+        SetPortForwardEnabled,
+        SetPortForwardEnabledResponse
+    );
 }
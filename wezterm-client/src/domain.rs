@@ -8,12 +8,13 @@ use config::{SshDomain, TlsDomainClient, UnixDomain};
 use mux::connui::{ConnectionUI, ConnectionUIParams};
 use mux::domain::{alloc_domain_id, Domain, DomainId, DomainState, SplitSource};
 use mux::pane::{Pane, PaneId};
-use mux::tab::{SplitRequest, Tab, TabId};
+use mux::tab::{FloatingPaneOp, SplitRequest, Tab, TabId};
 use mux::window::WindowId;
 use mux::{Mux, MuxNotification};
 use portable_pty::CommandBuilder;
 use promise::spawn::spawn_into_new_thread;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use wezterm_term::TerminalSize;
 
@@ -228,6 +229,28 @@ impl ClientDomainConfig {
             ClientDomainConfig::Ssh(ssh) => ssh.connect_automatically,
         }
     }
+
+    pub fn read_only(&self) -> bool {
+        match self {
+            ClientDomainConfig::Unix(unix) => unix.read_only,
+            ClientDomainConfig::Tls(tls) => tls.read_only,
+            ClientDomainConfig::Ssh(_) => false,
+        }
+    }
+
+    fn set_read_only(&mut self, read_only: bool) -> anyhow::Result<()> {
+        match self {
+            ClientDomainConfig::Unix(unix) => unix.read_only = read_only,
+            ClientDomainConfig::Tls(tls) => tls.read_only = read_only,
+            ClientDomainConfig::Ssh(ssh) => {
+                bail!(
+                    "read-only mode is not supported for ssh domain {}",
+                    ssh.name
+                )
+            }
+        }
+        Ok(())
+    }
 }
 
 impl ClientInner {
@@ -252,6 +275,7 @@ impl ClientInner {
 
 pub struct ClientDomain {
     config: ClientDomainConfig,
+    read_only: AtomicBool,
     label: String,
     inner: Mutex<Option<Arc<ClientInner>>>,
     local_domain_id: DomainId,
@@ -400,14 +424,25 @@ impl ClientDomain {
         let local_domain_id = alloc_domain_id();
         let label = config.label();
         Mux::get().subscribe(move |notif| mux_notify_client_domain(local_domain_id, notif));
+        let read_only = AtomicBool::new(config.read_only());
         Self {
             config,
+            read_only,
             label,
             inner: Mutex::new(None),
             local_domain_id,
         }
     }
 
+    /// Overrides the `read_only` setting from the configuration.
+    /// This takes effect the next time that the domain is attached.
+    pub fn set_read_only(&self, read_only: bool) -> anyhow::Result<()> {
+        // Validate that this kind of domain supports it
+        self.config.clone().set_read_only(read_only)?;
+        self.read_only.store(read_only, Ordering::Relaxed);
+        Ok(())
+    }
+
     fn inner(&self) -> Option<Arc<ClientInner>> {
         self.inner.lock().unwrap().as_ref().map(Arc::clone)
     }
@@ -928,6 +963,80 @@ impl Domain for ClientDomain {
         Ok(pane)
     }
 
+    async fn spawn_floating_pane(
+        &self,
+        tab_id: TabId,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let mux = Mux::get();
+        let tab = mux
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} is invalid", tab_id))?;
+
+        // The floating pane is positioned over the tab that contains
+        // this pane on the remote end
+        let remote_pane_id = tab
+            .iter_panes_ignoring_zoom()
+            .into_iter()
+            .find_map(|pos| {
+                pos.pane
+                    .downcast_ref::<ClientPane>()
+                    .map(|pane| pane.remote_pane_id)
+            })
+            .ok_or_else(|| anyhow!("tab {} has no remote panes", tab_id))?;
+
+        let result = inner
+            .client
+            .spawn_floating_pane(codec::SpawnFloatingPane {
+                pane_id: remote_pane_id,
+                command,
+                command_dir,
+                domain: SpawnTabDomain::CurrentPaneDomain,
+            })
+            .await?;
+
+        let pane: Arc<dyn Pane> = Arc::new(ClientPane::new(
+            &inner,
+            result.tab_id,
+            result.pane_id,
+            result.size,
+            "wezterm",
+        ));
+        tab.add_floating_pane(&pane, result.size);
+        mux.add_pane(&pane)?;
+
+        Ok(pane)
+    }
+
+    async fn apply_floating_pane_op(
+        &self,
+        tab_id: TabId,
+        op: FloatingPaneOp,
+    ) -> anyhow::Result<()> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+        let remote_tab_id = inner
+            .local_to_remote_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} has no remote counterpart", tab_id))?;
+
+        inner
+            .client
+            .apply_floating_pane_op(codec::ApplyFloatingPaneOp {
+                tab_id: remote_tab_id,
+                op,
+            })
+            .await?;
+
+        // Pick up the revised positions and focus from the server
+        self.resync().await
+    }
+
     async fn attach(&self, window_id: Option<WindowId>) -> anyhow::Result<()> {
         if self.state() == DomainState::Attached {
             // Already attached
@@ -935,7 +1044,8 @@ impl Domain for ClientDomain {
         }
 
         let domain_id = self.local_domain_id;
-        let config = self.config.clone();
+        let mut config = self.config.clone();
+        config.set_read_only(self.read_only.load(Ordering::Relaxed))?;
 
         let activity = mux::activity::Activity::new();
         let ui = ConnectionUI::with_params(ConnectionUIParams {
@@ -5,7 +5,7 @@ use anyhow::bail;
 use async_trait::async_trait;
 use codec::*;
 use config::configuration;
-use config::keyassignment::ScrollbackEraseMode;
+use config::keyassignment::{PaneLogTarget, ScrollbackEraseMode};
 use mux::domain::DomainId;
 use mux::pane::{
     alloc_pane_id, CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId,
@@ -21,6 +21,8 @@ use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
 use std::ops::Range;
 use std::sync::Arc;
+use termwiz::escape::csi::ColorScheme;
+use termwiz::escape::osc::PointerShape;
 use termwiz::input::KeyEvent;
 use termwiz::surface::SequenceNo;
 use url::Url;
@@ -44,6 +46,7 @@ pub struct ClientPane {
     mouse: Arc<Mutex<MouseState>>,
     clipboard: Mutex<Option<Arc<dyn Clipboard>>>,
     mouse_grabbed: Mutex<bool>,
+    pointer_shape: Mutex<Option<PointerShape>>,
     ignore_next_kill: Mutex<bool>,
     user_vars: Mutex<HashMap<String, String>>,
     config: Mutex<Option<Arc<dyn TerminalConfiguration>>>,
@@ -126,6 +129,7 @@ impl ClientPane {
             palette: Mutex::new(palette),
             clipboard: Mutex::new(None),
             mouse_grabbed: Mutex::new(false),
+            pointer_shape: Mutex::new(None),
             ignore_next_kill: Mutex::new(false),
             unseen_output: Mutex::new(false),
             user_vars: Mutex::new(HashMap::new()),
@@ -138,6 +142,7 @@ impl ClientPane {
         match pdu {
             Pdu::GetPaneRenderChangesResponse(mut delta) => {
                 *self.mouse_grabbed.lock() = delta.mouse_grabbed;
+                *self.pointer_shape.lock() = delta.pointer_shape;
 
                 let bonus_lines = std::mem::take(&mut delta.bonus_lines);
                 let client = { Arc::clone(&self.renderable.lock().inner.borrow().client) };
@@ -168,6 +173,23 @@ impl ClientPane {
                     log::error!("ClientPane: Ignoring SetClipboard request {:?}", clipboard);
                 }
             },
+            Pdu::SetClipboardData(SetClipboardData {
+                items, selection, ..
+            }) => match self.clipboard.lock().as_ref() {
+                Some(clip) => {
+                    log::debug!(
+                        "Pdu::SetClipboardData pane={} remote={} {:?} {} items",
+                        self.local_pane_id,
+                        self.remote_pane_id,
+                        selection,
+                        items.len()
+                    );
+                    clip.set_data(selection, items)?;
+                }
+                None => {
+                    log::error!("ClientPane: Ignoring SetClipboardData request");
+                }
+            },
             Pdu::SetPalette(SetPalette { palette, .. }) => {
                 *self.application_palette.lock() = palette != *self.configured_palette.lock();
 
@@ -250,6 +272,31 @@ impl ClientPane {
     pub fn ignore_next_kill(&self) {
         *self.ignore_next_kill.lock() = true;
     }
+
+    /// When attached read-only, the server refuses input and resizing,
+    /// so we don't send it in the first place
+    fn is_read_only(&self) -> bool {
+        self.client.client.is_read_only()
+    }
+
+    /// The pty is on the server, so that is where the logging happens
+    fn set_logging(&self, target: Option<PaneLogTarget>) {
+        let client = Arc::clone(&self.client);
+        let remote_pane_id = self.remote_pane_id;
+        promise::spawn::spawn(async move {
+            if let Err(err) = client
+                .client
+                .set_pane_logging(SetPaneLogging {
+                    pane_id: remote_pane_id,
+                    target,
+                })
+                .await
+            {
+                log::error!("failed to set logging for pane {remote_pane_id}: {err:#}");
+            }
+        })
+        .detach();
+    }
 }
 
 #[async_trait(?Send)]
@@ -330,6 +377,9 @@ impl Pane for ClientPane {
     }
 
     fn send_paste(&self, text: &str) -> anyhow::Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         let client = Arc::clone(&self.client);
         let remote_pane_id = self.remote_pane_id;
         self.renderable
@@ -365,6 +415,9 @@ impl Pane for ClientPane {
     }
 
     fn set_zoomed(&self, zoomed: bool) {
+        if self.is_read_only() {
+            return;
+        }
         let render = self.renderable.lock();
         let mut inner = render.inner.borrow_mut();
         let client = Arc::clone(&self.client);
@@ -387,6 +440,9 @@ impl Pane for ClientPane {
     }
 
     fn resize(&self, size: TerminalSize) -> anyhow::Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         let render = self.renderable.lock();
         let mut inner = render.inner.borrow_mut();
 
@@ -448,6 +504,9 @@ impl Pane for ClientPane {
     }
 
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         let input_serial;
         {
             let renderable = self.renderable.lock();
@@ -522,6 +581,9 @@ impl Pane for ClientPane {
     }
 
     fn mouse_event(&self, event: MouseEvent) -> anyhow::Result<()> {
+        if self.is_read_only() {
+            return Ok(());
+        }
         self.mouse.lock().append(event);
         if MouseState::next(Arc::clone(&self.mouse)) {
             self.renderable.lock().inner.borrow_mut().update_last_send();
@@ -545,6 +607,10 @@ impl Pane for ClientPane {
         *self.mouse_grabbed.lock()
     }
 
+    fn get_pointer_shape(&self) -> Option<PointerShape> {
+        *self.pointer_shape.lock()
+    }
+
     fn is_alt_screen_active(&self) -> bool {
         // FIXME: retrieve this from the remote
         false
@@ -576,7 +642,35 @@ impl Pane for ClientPane {
         .detach();
     }
 
+    fn set_color_scheme(&self, scheme: ColorScheme) {
+        let client = Arc::clone(&self.client);
+        let remote_pane_id = self.remote_pane_id;
+        promise::spawn::spawn(async move {
+            client
+                .client
+                .set_color_scheme(SetColorScheme {
+                    pane_id: remote_pane_id,
+                    scheme,
+                })
+                .await
+        })
+        .detach();
+    }
+
+    fn start_logging(&self, target: PaneLogTarget) -> anyhow::Result<()> {
+        self.set_logging(Some(target));
+        Ok(())
+    }
+
+    fn stop_logging(&self) -> anyhow::Result<()> {
+        self.set_logging(None);
+        Ok(())
+    }
+
     fn advise_focus(&self) {
+        if self.is_read_only() {
+            return;
+        }
         let mut focused_pane = self.client.focused_remote_pane_id.lock().unwrap();
         if *focused_pane != Some(self.remote_pane_id) {
             focused_pane.replace(self.remote_pane_id);
@@ -648,6 +742,9 @@ struct PaneWriter {
 
 impl std::io::Write for PaneWriter {
     fn write(&mut self, data: &[u8]) -> Result<usize, std::io::Error> {
+        if self.client.client.is_read_only() {
+            return Ok(data.len());
+        }
         promise::spawn::block_on(self.client.client.write_to_pane(WriteToPane {
             pane_id: self.remote_pane_id,
             data: data.to_vec(),
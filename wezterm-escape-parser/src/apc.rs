@@ -321,7 +321,7 @@ fn read_shared_memory_data(
 mod win {
     use winapi::um::handleapi::CloseHandle;
     use winapi::um::memoryapi::{
-        FILE_MAP_ALL_ACCESS, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, VirtualQuery,
+        MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, VirtualQuery, FILE_MAP_ALL_ACCESS,
     };
     use winapi::um::winnt::{HANDLE, MEMORY_BASIC_INFORMATION};
 
@@ -609,6 +609,23 @@ pub struct KittyImagePlacement {
     pub placement_id: Option<u32>,
     /// z=...
     pub z_index: Option<i32>,
+    /// Create a virtual placement that is displayed wherever
+    /// unicode placeholder cells that reference it are printed,
+    /// rather than at the cursor position.
+    /// U=0, U=1
+    pub virtual_placement: bool,
+    /// Position this placement relative to the placement of
+    /// another image, rather than at the cursor.
+    /// P=...
+    pub parent_image_id: Option<u32>,
+    /// The placement id of the parent placement.
+    /// Q=...
+    pub parent_placement_id: Option<u32>,
+    /// Offset in cells from the top left of the parent placement.
+    /// H=...
+    pub horizontal_offset: Option<i32>,
+    /// V=...
+    pub vertical_offset: Option<i32>,
 }
 
 impl KittyImagePlacement {
@@ -629,6 +646,15 @@ impl KittyImagePlacement {
                 _ => return None,
             },
             z_index: geti(keys, "z"),
+            virtual_placement: match get(keys, "U") {
+                None | Some("0") => false,
+                Some("1") => true,
+                _ => return None,
+            },
+            parent_image_id: geti(keys, "P"),
+            parent_placement_id: geti(keys, "Q"),
+            horizontal_offset: geti(keys, "H"),
+            vertical_offset: geti(keys, "V"),
         })
     }
 
@@ -648,6 +674,15 @@ impl KittyImagePlacement {
         }
 
         set(keys, "z", &self.z_index);
+
+        if self.virtual_placement {
+            keys.insert("U", "1".to_string());
+        }
+
+        set(keys, "P", &self.parent_image_id);
+        set(keys, "Q", &self.parent_placement_id);
+        set(keys, "H", &self.horizontal_offset);
+        set(keys, "V", &self.vertical_offset);
     }
 }
 
@@ -1005,6 +1040,102 @@ impl KittyImageFrame {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyAnimationState {
+    /// s=1: stop the animation
+    Stop,
+    /// s=2: run the animation, but wait for new frames
+    /// when the last frame has been shown
+    Loading,
+    /// s=3: run the animation, looping back to the first
+    /// frame after the last frame has been shown
+    Run,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyImageAnimation {
+    /// i=...
+    pub image_id: Option<u32>,
+    /// I=...
+    pub image_number: Option<u32>,
+
+    /// s=...
+    pub state: Option<KittyAnimationState>,
+
+    /// 1-based number of the frame whose gap should be changed
+    /// to duration_ms.
+    /// r=...
+    pub frame_number: Option<u32>,
+
+    /// Gap in milliseconds between frame_number and the next frame.
+    /// Negative values make the frame gapless, which causes it
+    /// to be skipped during playback.
+    /// z=...
+    pub duration_ms: Option<i32>,
+
+    /// 1-based number of the frame that should be made current.
+    /// c=...
+    pub current_frame: Option<u32>,
+
+    /// The number of loops to play.  1 means loop forever,
+    /// and any other number n means to loop n-1 times.
+    /// v=...
+    pub loops: Option<u32>,
+}
+
+impl KittyImageAnimation {
+    fn from_keys(keys: &BTreeMap<&str, &str>) -> Option<Self> {
+        Some(Self {
+            image_id: geti(keys, "i"),
+            image_number: geti(keys, "I"),
+            state: match geti(keys, "s") {
+                None => None,
+                Some(1) => Some(KittyAnimationState::Stop),
+                Some(2) => Some(KittyAnimationState::Loading),
+                Some(3) => Some(KittyAnimationState::Run),
+                _ => return None,
+            },
+            frame_number: match geti(keys, "r") {
+                None | Some(0) => None,
+                n => n,
+            },
+            duration_ms: match geti(keys, "z") {
+                None | Some(0) => None,
+                n => n,
+            },
+            current_frame: match geti(keys, "c") {
+                None | Some(0) => None,
+                n => n,
+            },
+            loops: match geti(keys, "v") {
+                None | Some(0) => None,
+                n => n,
+            },
+        })
+    }
+
+    fn to_keys(&self, keys: &mut BTreeMap<&'static str, String>) {
+        set(keys, "i", &self.image_id);
+        set(keys, "I", &self.image_number);
+        match &self.state {
+            None => {}
+            Some(KittyAnimationState::Stop) => {
+                keys.insert("s", "1".to_string());
+            }
+            Some(KittyAnimationState::Loading) => {
+                keys.insert("s", "2".to_string());
+            }
+            Some(KittyAnimationState::Run) => {
+                keys.insert("s", "3".to_string());
+            }
+        }
+        set(keys, "r", &self.frame_number);
+        set(keys, "z", &self.duration_ms);
+        set(keys, "c", &self.current_frame);
+        set(keys, "v", &self.loops);
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum KittyImage {
     /// a='t'
@@ -1043,6 +1174,11 @@ pub enum KittyImage {
         frame: KittyImageFrameCompose,
         verbosity: KittyImageVerbosity,
     },
+    /// a='a'
+    AnimationControl {
+        animation: KittyImageAnimation,
+        verbosity: KittyImageVerbosity,
+    },
 }
 
 impl KittyImage {
@@ -1055,6 +1191,7 @@ impl KittyImage {
             Self::Delete { verbosity, .. } => *verbosity,
             Self::TransmitFrame { verbosity, .. } => *verbosity,
             Self::ComposeFrame { verbosity, .. } => *verbosity,
+            Self::AnimationControl { verbosity, .. } => *verbosity,
         }
     }
 
@@ -1108,6 +1245,10 @@ impl KittyImage {
                 frame: KittyImageFrameCompose::from_keys(&keys)?,
                 verbosity,
             }),
+            "a" => Some(Self::AnimationControl {
+                animation: KittyImageAnimation::from_keys(&keys)?,
+                verbosity,
+            }),
             _ => None,
         }
     }
@@ -1172,6 +1313,14 @@ impl KittyImage {
                 frame.to_keys(keys);
                 verbosity.to_keys(keys);
             }
+            Self::AnimationControl {
+                animation,
+                verbosity,
+            } => {
+                keys.insert("a", "a".to_string());
+                animation.to_keys(keys);
+                verbosity.to_keys(keys);
+            }
         }
     }
 }
@@ -1266,4 +1415,93 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn kitty_animation() {
+        let anim = KittyImage::parse_apc("Ga=a,i=3,s=3,v=1,c=2,r=4,z=-1".as_bytes()).unwrap();
+        assert_eq!(
+            anim,
+            KittyImage::AnimationControl {
+                animation: KittyImageAnimation {
+                    image_id: Some(3),
+                    image_number: None,
+                    state: Some(KittyAnimationState::Run),
+                    frame_number: Some(4),
+                    duration_ms: Some(-1),
+                    current_frame: Some(2),
+                    loops: Some(1),
+                },
+                verbosity: KittyImageVerbosity::Verbose,
+            }
+        );
+        assert_eq!(anim.to_string(), "\x1b_Ga=a,c=2,i=3,r=4,s=3,v=1,z=-1");
+
+        assert_eq!(KittyImage::parse_apc("Ga=a,i=3,s=4".as_bytes()), None);
+    }
+
+    #[test]
+    fn kitty_virtual_placement() {
+        let place = KittyImage::parse_apc("Ga=p,U=1,i=7,c=4,r=2,q=2".as_bytes()).unwrap();
+        assert_eq!(
+            place,
+            KittyImage::Display {
+                image_id: Some(7),
+                image_number: None,
+                placement: KittyImagePlacement {
+                    x: None,
+                    y: None,
+                    w: None,
+                    h: None,
+                    x_offset: None,
+                    y_offset: None,
+                    columns: Some(4),
+                    rows: Some(2),
+                    do_not_move_cursor: false,
+                    placement_id: None,
+                    z_index: None,
+                    virtual_placement: true,
+                    parent_image_id: None,
+                    parent_placement_id: None,
+                    horizontal_offset: None,
+                    vertical_offset: None,
+                },
+                verbosity: KittyImageVerbosity::Quiet,
+            }
+        );
+        assert_eq!(place.to_string(), "\x1b_GU=1,a=p,c=4,i=7,q=2,r=2");
+
+        assert_eq!(KittyImage::parse_apc("Ga=p,i=7,U=2".as_bytes()), None);
+    }
+
+    #[test]
+    fn kitty_relative_placement() {
+        let place = KittyImage::parse_apc("Ga=p,i=2,p=1,P=7,Q=3,H=-2,V=1".as_bytes()).unwrap();
+        assert_eq!(
+            place,
+            KittyImage::Display {
+                image_id: Some(2),
+                image_number: None,
+                placement: KittyImagePlacement {
+                    x: None,
+                    y: None,
+                    w: None,
+                    h: None,
+                    x_offset: None,
+                    y_offset: None,
+                    columns: None,
+                    rows: None,
+                    do_not_move_cursor: false,
+                    placement_id: Some(1),
+                    z_index: None,
+                    virtual_placement: false,
+                    parent_image_id: Some(7),
+                    parent_placement_id: Some(3),
+                    horizontal_offset: Some(-2),
+                    vertical_offset: Some(1),
+                },
+                verbosity: KittyImageVerbosity::Verbose,
+            }
+        );
+        assert_eq!(place.to_string(), "\x1b_GH=-2,P=7,Q=3,V=1,a=p,i=2,p=1");
+    }
 }
@@ -462,6 +462,21 @@ pub enum Device {
     RequestTerminalNameAndVersion,
     RequestTerminalParameters(i64),
     XtSmGraphics(XtSmGraphics),
+    /// Query the preferred color scheme; `CSI ? 996 n`.
+    /// <https://contour-terminal.org/vt-extensions/color-palette-update-notifications/>
+    RequestColorScheme,
+    /// Report the preferred color scheme; `CSI ? 997 ; Ps n`.
+    /// This is sent in response to `RequestColorScheme` and also
+    /// unsolicited when `DecPrivateModeCode::ColorSchemeUpdates` is enabled.
+    ColorSchemeReport(ColorScheme),
+}
+
+/// The color scheme reported via `Device::ColorSchemeReport`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum ColorScheme {
+    Dark = 1,
+    Light = 2,
 }
 
 impl Display for Device {
@@ -482,6 +497,8 @@ impl Display for Device {
             Device::RequestTerminalNameAndVersion => write!(f, ">q")?,
             Device::RequestTerminalParameters(n) => write!(f, "{};1;1;128;128;1;0x", n + 2)?,
             Device::StatusReport => write!(f, "5n")?,
+            Device::RequestColorScheme => write!(f, "?996n")?,
+            Device::ColorSchemeReport(scheme) => write!(f, "?997;{}n", *scheme as u8)?,
             Device::XtSmGraphics(g) => {
                 write!(f, "?{};{}", g.item, g.action_or_status)?;
                 for v in &g.value {
@@ -587,6 +604,15 @@ pub enum Window {
         bottom: OneBased,
         right: OneBased,
     },
+    /// Sent to the application when `DecPrivateModeCode::InBandResizeNotifications`
+    /// is enabled and the terminal is resized.
+    /// <https://gist.github.com/rockorager/e695fb2924d36b2bcf1fff4a3704bd83>
+    InBandResizeReport {
+        rows: i64,
+        cols: i64,
+        pixel_height: i64,
+        pixel_width: i64,
+    },
 }
 
 fn numstr_or_empty(x: &Option<i64>) -> String {
@@ -659,6 +685,12 @@ impl Display for Window {
                 "{};{};{};{};{};{}*y",
                 request_id, page_number, top, left, bottom, right,
             ),
+            Window::InBandResizeReport {
+                rows,
+                cols,
+                pixel_height,
+                pixel_width,
+            } => write!(f, "48;{};{};{};{}t", rows, cols, pixel_height, pixel_width),
         }
     }
 }
@@ -959,6 +991,15 @@ pub enum DecPrivateModeCode {
     /// xterm: adjust cursor positioning after emitting sixel
     SixelScrollsRight = 8452,
 
+    /// Report changes to the preferred color scheme via
+    /// `Device::ColorSchemeReport`.
+    /// <https://contour-terminal.org/vt-extensions/color-palette-update-notifications/>
+    ColorSchemeUpdates = 2031,
+
+    /// Report terminal size changes via `Window::InBandResizeReport`.
+    /// <https://gist.github.com/rockorager/e695fb2924d36b2bcf1fff4a3704bd83>
+    InBandResizeNotifications = 2048,
+
     /// Windows Terminal: win32-input-mode
     /// <https://github.com/microsoft/terminal/blob/main/doc/specs/%234999%20-%20Improved%20keyboard%20handling%20in%20Conpty.md>
     Win32InputMode = 9001,
@@ -1849,6 +1890,20 @@ impl<'a> CSIParser<'a> {
                 .map(|dev| CSI::Device(Box::new(dev))),
 
             ('S', [CsiParam::P(b'?'), ..]) => XtSmGraphics::parse(params),
+            ('n', [CsiParam::P(b'?'), CsiParam::Integer(996)]) => {
+                Ok(CSI::Device(Box::new(Device::RequestColorScheme)))
+            }
+            (
+                'n',
+                [
+                    CsiParam::P(b'?'),
+                    CsiParam::Integer(997),
+                    CsiParam::P(b';'),
+                    CsiParam::Integer(scheme),
+                ],
+            ) => Ok(CSI::Device(Box::new(Device::ColorSchemeReport(
+                FromPrimitive::from_i64(*scheme).ok_or(())?,
+            )))),
             ('p', [CsiParam::Integer(_), CsiParam::P(b'$')])
             | ('p', [CsiParam::P(b'?'), CsiParam::Integer(_), CsiParam::P(b'$')]) => {
                 self.decrqm(params)
@@ -2642,6 +2697,12 @@ impl<'a> CSIParser<'a> {
                 Some(2) => Ok(Window::PopWindowTitle),
                 _ => Err(()),
             },
+            48 => Ok(Window::InBandResizeReport {
+                rows: params.int(1)?,
+                cols: params.int(2)?,
+                pixel_height: params.int(3)?,
+                pixel_width: params.int(4)?,
+            }),
             _ => Err(()),
         }
     }
@@ -3209,6 +3270,15 @@ mod test {
                 }
             ))]
         );
+        assert_eq!(
+            parse('t', &[48, 24, 80, 384, 640], "\x1b[48;24;80;384;640t"),
+            vec![CSI::Window(Box::new(Window::InBandResizeReport {
+                rows: 24,
+                cols: 80,
+                pixel_height: 384,
+                pixel_width: 640,
+            }))]
+        );
     }
 
     #[test]
@@ -3392,4 +3462,41 @@ mod test {
         );
         assert_eq!(encode(&res), "\x1b[?63;1;2;4;6;9;15;22c");
     }
+
+    #[test]
+    fn color_scheme() {
+        let res: Vec<_> =
+            CSI::parse(&[CsiParam::P(b'?'), CsiParam::Integer(996)], false, 'n').collect();
+        assert_eq!(encode(&res), "\x1b[?996n");
+        assert_eq!(res, vec![CSI::Device(Box::new(Device::RequestColorScheme))]);
+
+        let res: Vec<_> = CSI::parse(
+            &[
+                CsiParam::P(b'?'),
+                CsiParam::Integer(997),
+                CsiParam::P(b';'),
+                CsiParam::Integer(2),
+            ],
+            false,
+            'n',
+        )
+        .collect();
+        assert_eq!(encode(&res), "\x1b[?997;2n");
+        assert_eq!(
+            res,
+            vec![CSI::Device(Box::new(Device::ColorSchemeReport(
+                ColorScheme::Light
+            )))]
+        );
+
+        let res: Vec<_> =
+            CSI::parse(&[CsiParam::P(b'?'), CsiParam::Integer(2031)], false, 'h').collect();
+        assert_eq!(encode(&res), "\x1b[?2031h");
+        assert_eq!(
+            res,
+            vec![CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
+                DecPrivateModeCode::ColorSchemeUpdates
+            )))]
+        );
+    }
 }
@@ -9,6 +9,8 @@ use core::str::FromStr;
 use num_derive::*;
 use num_traits::FromPrimitive;
 use ordered_float::NotNan;
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "std")]
 use std::sync::LazyLock;
 
@@ -50,6 +52,11 @@ pub enum OperatingSystemCommand {
     ResetColors(Vec<u8>),
     RxvtExtension(Vec<String>),
     ConEmuProgress(Progress),
+    KittyDesktopNotification(KittyDesktopNotification),
+    TextSizing(TextSizing),
+    PointerShape(PointerShapeRequest),
+    KittyClipboard(KittyClipboard),
+    KittyColorControl(Vec<ColorControl>),
 
     Unspecified(Vec<Vec<u8>>),
 }
@@ -361,6 +368,18 @@ impl OperatingSystemCommand {
             }
             FinalTermSemanticPrompt => self::FinalTermSemanticPrompt::parse(osc)
                 .map(OperatingSystemCommand::FinalTermSemanticPrompt),
+            KittyDesktopNotification => self::KittyDesktopNotification::parse(osc)
+                .map(OperatingSystemCommand::KittyDesktopNotification),
+            TextSizing => self::TextSizing::parse(osc).map(OperatingSystemCommand::TextSizing),
+            PointerShape => {
+                PointerShapeRequest::parse(osc).map(OperatingSystemCommand::PointerShape)
+            }
+            KittyClipboard => {
+                self::KittyClipboard::parse(osc).map(OperatingSystemCommand::KittyClipboard)
+            }
+            KittyColorControl => {
+                ColorControl::parse_list(osc).map(OperatingSystemCommand::KittyColorControl)
+            }
             ChangeColorNumber => Self::parse_change_color_number(osc),
             ResetColors => Self::parse_reset_colors(osc),
 
@@ -478,10 +497,20 @@ osc_entries!(
     SetHighlightBackgroundColor = "17",
     SetTektronixCursorColor = "18",
     SetHighlightForegroundColor = "19",
+    /// See <https://sw.kovidgoyal.net/kitty/color-stack/#setting-and-querying-colors>
+    KittyColorControl = "21",
+    /// See <https://sw.kovidgoyal.net/kitty/pointer-shapes/>
+    PointerShape = "22",
     SetLogFileName = "46",
     SetFont = "50",
     EmacsShell = "51",
     ManipulateSelectionData = "52",
+    /// See <https://sw.kovidgoyal.net/kitty/clipboard/>
+    KittyClipboard = "5522",
+    /// See <https://sw.kovidgoyal.net/kitty/text-sizing-protocol/>
+    TextSizing = "66",
+    /// See <https://sw.kovidgoyal.net/kitty/desktop-notifications/>
+    KittyDesktopNotification = "99",
     ResetColors = "104",
     ResetSpecialColor = "105",
     ResetTextForegroundColor = "110",
@@ -610,6 +639,16 @@ impl Display for OperatingSystemCommand {
             ConEmuProgress(Progress::SetError(pct)) => write!(f, "9;4;2;{pct}")?,
             ConEmuProgress(Progress::SetIndeterminate) => write!(f, "9;4;3")?,
             ConEmuProgress(Progress::Paused) => write!(f, "9;4;4")?,
+            KittyDesktopNotification(n) => n.fmt(f)?,
+            TextSizing(t) => t.fmt(f)?,
+            PointerShape(p) => p.fmt(f)?,
+            KittyClipboard(c) => c.fmt(f)?,
+            KittyColorControl(items) => {
+                write!(f, "21")?;
+                for item in items {
+                    write!(f, ";{}", item)?;
+                }
+            }
         };
         // Use the longer form ST as neovim doesn't like the BEL version
         write!(f, "\x1b\\")?;
@@ -887,6 +926,827 @@ pub enum Progress {
     Paused,
 }
 
+/// The kind of data carried by the payload of a kitty desktop
+/// notification escape sequence (the `p` key)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KittyNotificationPayload {
+    #[default]
+    Title,
+    Body,
+    /// Request that the notification with the matching id be closed
+    Close,
+    /// Query the terminal for the supported capabilities
+    Query,
+    /// Query which of the listed notification ids are still alive
+    Alive,
+    Icon,
+    Buttons,
+}
+
+impl KittyNotificationPayload {
+    fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "title" => Self::Title,
+            "body" => Self::Body,
+            "close" => Self::Close,
+            "?" => Self::Query,
+            "alive" => Self::Alive,
+            "icon" => Self::Icon,
+            "buttons" => Self::Buttons,
+            _ => bail!("invalid kitty notification payload type {}", s),
+        })
+    }
+}
+
+impl Display for KittyNotificationPayload {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let s = match self {
+            Self::Title => "title",
+            Self::Body => "body",
+            Self::Close => "close",
+            Self::Query => "?",
+            Self::Alive => "alive",
+            Self::Icon => "icon",
+            Self::Buttons => "buttons",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyNotificationUrgency {
+    Low = 0,
+    Normal = 1,
+    Critical = 2,
+}
+
+/// Controls when the notification should be displayed (the `o` key)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyNotificationOccasion {
+    Always,
+    /// Only when the window is not focused
+    Unfocused,
+    /// Only when the window is not visible to the user
+    Invisible,
+}
+
+/// What should happen when the user activates the notification (the `a` key)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KittyNotificationActions {
+    /// Focus the window that produced the notification
+    pub focus: bool,
+    /// Report the activation back to the application
+    pub report: bool,
+}
+
+impl Default for KittyNotificationActions {
+    fn default() -> Self {
+        Self {
+            focus: true,
+            report: false,
+        }
+    }
+}
+
+/// <https://sw.kovidgoyal.net/kitty/desktop-notifications/>
+/// `OSC 99 ; metadata ; payload ST`, where metadata is a colon separated
+/// list of `key=value` pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyDesktopNotification {
+    /// `i`: an application chosen identifier for the notification.
+    /// It is used to associate chunks, close requests and reports.
+    pub id: Option<String>,
+    /// `d`: false if more chunks will follow for this notification
+    pub done: bool,
+    /// `p`: what the payload represents
+    pub payload_type: KittyNotificationPayload,
+    /// `e`: if true, the payload is base64 encoded on the wire
+    pub base64: bool,
+    /// `a`: the actions to take when the notification is activated
+    pub actions: Option<KittyNotificationActions>,
+    /// `u`
+    pub urgency: Option<KittyNotificationUrgency>,
+    /// `c`: if true, report back to the application when the
+    /// notification is closed
+    pub report_close: bool,
+    /// `o`
+    pub occasion: Option<KittyNotificationOccasion>,
+    /// `w`: expire time in milliseconds; -1 uses the system default,
+    /// 0 means never expire
+    pub expire_ms: Option<i64>,
+    /// The decoded payload
+    pub payload: String,
+}
+
+impl Default for KittyDesktopNotification {
+    fn default() -> Self {
+        Self {
+            id: None,
+            done: true,
+            payload_type: KittyNotificationPayload::default(),
+            base64: false,
+            actions: None,
+            urgency: None,
+            report_close: false,
+            occasion: None,
+            expire_ms: None,
+            payload: String::new(),
+        }
+    }
+}
+
+impl KittyDesktopNotification {
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        ensure!(osc.len() > 1, "not enough args");
+        let mut notif = Self::default();
+
+        let metadata = str::from_utf8(osc[1])?;
+        for item in metadata.split(':') {
+            if item.is_empty() {
+                continue;
+            }
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| format!("malformed kitty notification metadata {}", item))?;
+            match key {
+                "i" => notif.id = Some(value.to_string()),
+                "d" => notif.done = value != "0",
+                "p" => notif.payload_type = KittyNotificationPayload::parse(value)?,
+                "e" => notif.base64 = value == "1",
+                "a" => {
+                    let mut actions = KittyNotificationActions::default();
+                    for action in value.split(',') {
+                        match action {
+                            "focus" => actions.focus = true,
+                            "-focus" => actions.focus = false,
+                            "report" => actions.report = true,
+                            "-report" => actions.report = false,
+                            _ => {}
+                        }
+                    }
+                    notif.actions = Some(actions);
+                }
+                "u" => {
+                    notif.urgency = Some(match value {
+                        "0" => KittyNotificationUrgency::Low,
+                        "1" => KittyNotificationUrgency::Normal,
+                        "2" => KittyNotificationUrgency::Critical,
+                        _ => bail!("invalid kitty notification urgency {}", value),
+                    })
+                }
+                "c" => notif.report_close = value == "1",
+                "o" => {
+                    notif.occasion = Some(match value {
+                        "always" => KittyNotificationOccasion::Always,
+                        "unfocused" => KittyNotificationOccasion::Unfocused,
+                        "invisible" => KittyNotificationOccasion::Invisible,
+                        _ => bail!("invalid kitty notification occasion {}", value),
+                    })
+                }
+                "w" => notif.expire_ms = Some(value.parse()?),
+                // Other keys (application name, icons, sounds and so on)
+                // are not currently supported and are ignored, as
+                // recommended by the spec.
+                _ => {}
+            }
+        }
+
+        // The payload may itself contain `;`, which will have been
+        // split into separate params by the OSC parser
+        let mut payload = vec![];
+        for (idx, p) in osc.iter().skip(2).enumerate() {
+            if idx > 0 {
+                payload.push(b';');
+            }
+            payload.extend_from_slice(p);
+        }
+        if notif.base64 {
+            payload = base64_decode(payload)?;
+        }
+        notif.payload = String::from_utf8(payload)?;
+
+        Ok(notif)
+    }
+}
+
+impl Display for KittyDesktopNotification {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut keys = vec![];
+        if let Some(id) = &self.id {
+            keys.push(format!("i={}", id));
+        }
+        if !self.done {
+            keys.push("d=0".to_string());
+        }
+        if self.payload_type != KittyNotificationPayload::Title {
+            keys.push(format!("p={}", self.payload_type));
+        }
+        if self.base64 {
+            keys.push("e=1".to_string());
+        }
+        if let Some(actions) = &self.actions {
+            keys.push(format!(
+                "a={},{}",
+                if actions.focus { "focus" } else { "-focus" },
+                if actions.report { "report" } else { "-report" }
+            ));
+        }
+        if let Some(urgency) = self.urgency {
+            keys.push(format!("u={}", urgency as u8));
+        }
+        if self.report_close {
+            keys.push("c=1".to_string());
+        }
+        if let Some(occasion) = &self.occasion {
+            keys.push(format!(
+                "o={}",
+                match occasion {
+                    KittyNotificationOccasion::Always => "always",
+                    KittyNotificationOccasion::Unfocused => "unfocused",
+                    KittyNotificationOccasion::Invisible => "invisible",
+                }
+            ));
+        }
+        if let Some(w) = self.expire_ms {
+            keys.push(format!("w={}", w));
+        }
+        write!(f, "99;{};", keys.join(":"))?;
+        if self.base64 {
+            write!(f, "{}", base64_encode(&self.payload))
+        } else {
+            write!(f, "{}", self.payload)
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum TextSizingVerticalAlign {
+    #[default]
+    Top = 0,
+    Bottom = 1,
+    Center = 2,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromPrimitive)]
+pub enum TextSizingHorizontalAlign {
+    #[default]
+    Left = 0,
+    Right = 1,
+    Center = 2,
+}
+
+/// The kitty text sizing protocol renders a run of text at an
+/// integer multiple of the cell size, occupying a block of cells.
+/// <https://sw.kovidgoyal.net/kitty/text-sizing-protocol/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextSizing {
+    /// `s`: the overall scale, 1-7. The text occupies this many rows
+    pub scale: u8,
+    /// `w`: the width, 0-7, in units of scaled cells.
+    /// 0 means that the width is computed from the text itself
+    pub width: u8,
+    /// `n`: the numerator of the fractional scale, 0-15
+    pub numerator: u8,
+    /// `d`: the denominator of the fractional scale, 0-15.
+    /// The fractional scale only applies when it is larger
+    /// than the numerator
+    pub denominator: u8,
+    /// `v`: how fractionally scaled text is aligned within its rows
+    pub vertical_align: TextSizingVerticalAlign,
+    /// `h`: how fractionally scaled text is aligned within its columns
+    pub horizontal_align: TextSizingHorizontalAlign,
+    pub text: String,
+}
+
+impl Default for TextSizing {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            width: 0,
+            numerator: 0,
+            denominator: 0,
+            vertical_align: TextSizingVerticalAlign::default(),
+            horizontal_align: TextSizingHorizontalAlign::default(),
+            text: String::new(),
+        }
+    }
+}
+
+impl TextSizing {
+    /// The spec limits the text payload to this many bytes
+    pub const MAX_TEXT_LEN: usize = 4096;
+
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        ensure!(osc.len() > 1, "not enough args");
+        let mut sizing = Self::default();
+
+        let metadata = str::from_utf8(osc[1])?;
+        for item in metadata.split(':') {
+            if item.is_empty() {
+                continue;
+            }
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| format!("malformed text sizing metadata {}", item))?;
+            let value: u8 = value.parse()?;
+            match key {
+                "s" => {
+                    ensure!((1..=7).contains(&value), "invalid text scale {}", value);
+                    sizing.scale = value;
+                }
+                "w" => {
+                    ensure!(value <= 7, "invalid text width {}", value);
+                    sizing.width = value;
+                }
+                "n" => {
+                    ensure!(value <= 15, "invalid text numerator {}", value);
+                    sizing.numerator = value;
+                }
+                "d" => {
+                    ensure!(value <= 15, "invalid text denominator {}", value);
+                    sizing.denominator = value;
+                }
+                "v" => {
+                    sizing.vertical_align = FromPrimitive::from_u8(value)
+                        .ok_or_else(|| format!("invalid vertical alignment {}", value))?;
+                }
+                "h" => {
+                    sizing.horizontal_align = FromPrimitive::from_u8(value)
+                        .ok_or_else(|| format!("invalid horizontal alignment {}", value))?;
+                }
+                _ => {}
+            }
+        }
+
+        // The text may itself contain `;`, which will have been
+        // split into separate params by the OSC parser
+        let mut text = vec![];
+        for (idx, p) in osc.iter().skip(2).enumerate() {
+            if idx > 0 {
+                text.push(b';');
+            }
+            text.extend_from_slice(p);
+        }
+        ensure!(
+            text.len() <= Self::MAX_TEXT_LEN,
+            "text sizing payload is too long"
+        );
+        sizing.text = String::from_utf8(text)?;
+
+        Ok(sizing)
+    }
+}
+
+impl Display for TextSizing {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut keys = vec![];
+        if self.scale != 1 {
+            keys.push(format!("s={}", self.scale));
+        }
+        if self.width != 0 {
+            keys.push(format!("w={}", self.width));
+        }
+        if self.numerator != 0 {
+            keys.push(format!("n={}", self.numerator));
+        }
+        if self.denominator != 0 {
+            keys.push(format!("d={}", self.denominator));
+        }
+        if self.vertical_align != TextSizingVerticalAlign::default() {
+            keys.push(format!("v={}", self.vertical_align as u8));
+        }
+        if self.horizontal_align != TextSizingHorizontalAlign::default() {
+            keys.push(format!("h={}", self.horizontal_align as u8));
+        }
+        write!(f, "66;{};{}", keys.join(":"), self.text)
+    }
+}
+
+/// The mouse pointer shapes that can be requested via OSC 22.
+/// The variants correspond to the CSS cursor names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+pub enum PointerShape {
+    Default,
+    Text,
+    Pointer,
+    Help,
+    Wait,
+    Progress,
+    Crosshair,
+    Cell,
+    VerticalText,
+    Move,
+    EResize,
+    NeResize,
+    NwResize,
+    NResize,
+    SeResize,
+    SwResize,
+    SResize,
+    WResize,
+    EwResize,
+    NsResize,
+    NeswResize,
+    NwseResize,
+    ZoomIn,
+    ZoomOut,
+    Alias,
+    Copy,
+    NotAllowed,
+    NoDrop,
+    Grab,
+    Grabbing,
+}
+
+impl PointerShape {
+    /// Maps CSS names, and their X11 cursor font equivalents,
+    /// to the corresponding shape
+    const NAMES: &'static [(&'static str, Self)] = &[
+        ("default", Self::Default),
+        ("left_ptr", Self::Default),
+        ("arrow", Self::Default),
+        ("top_left_arrow", Self::Default),
+        ("text", Self::Text),
+        ("xterm", Self::Text),
+        ("ibeam", Self::Text),
+        ("pointer", Self::Pointer),
+        ("hand", Self::Pointer),
+        ("hand2", Self::Pointer),
+        ("pointing_hand", Self::Pointer),
+        ("help", Self::Help),
+        ("question_arrow", Self::Help),
+        ("whats_this", Self::Help),
+        ("wait", Self::Wait),
+        ("watch", Self::Wait),
+        ("clock", Self::Wait),
+        ("progress", Self::Progress),
+        ("left_ptr_watch", Self::Progress),
+        ("crosshair", Self::Crosshair),
+        ("cross", Self::Crosshair),
+        ("tcross", Self::Crosshair),
+        ("cell", Self::Cell),
+        ("plus", Self::Cell),
+        ("vertical-text", Self::VerticalText),
+        ("move", Self::Move),
+        ("fleur", Self::Move),
+        ("e-resize", Self::EResize),
+        ("right_side", Self::EResize),
+        ("ne-resize", Self::NeResize),
+        ("top_right_corner", Self::NeResize),
+        ("nw-resize", Self::NwResize),
+        ("top_left_corner", Self::NwResize),
+        ("n-resize", Self::NResize),
+        ("top_side", Self::NResize),
+        ("se-resize", Self::SeResize),
+        ("bottom_right_corner", Self::SeResize),
+        ("sw-resize", Self::SwResize),
+        ("bottom_left_corner", Self::SwResize),
+        ("s-resize", Self::SResize),
+        ("bottom_side", Self::SResize),
+        ("w-resize", Self::WResize),
+        ("left_side", Self::WResize),
+        ("ew-resize", Self::EwResize),
+        ("sb_h_double_arrow", Self::EwResize),
+        ("split_h", Self::EwResize),
+        ("ns-resize", Self::NsResize),
+        ("sb_v_double_arrow", Self::NsResize),
+        ("split_v", Self::NsResize),
+        ("nesw-resize", Self::NeswResize),
+        ("fd_double_arrow", Self::NeswResize),
+        ("size_bdiag", Self::NeswResize),
+        ("nwse-resize", Self::NwseResize),
+        ("bd_double_arrow", Self::NwseResize),
+        ("size_fdiag", Self::NwseResize),
+        ("zoom-in", Self::ZoomIn),
+        ("zoom_in", Self::ZoomIn),
+        ("zoom-out", Self::ZoomOut),
+        ("zoom_out", Self::ZoomOut),
+        ("alias", Self::Alias),
+        ("dnd-link", Self::Alias),
+        ("copy", Self::Copy),
+        ("dnd-copy", Self::Copy),
+        ("not-allowed", Self::NotAllowed),
+        ("forbidden", Self::NotAllowed),
+        ("crossed_circle", Self::NotAllowed),
+        ("no-drop", Self::NoDrop),
+        ("dnd-no-drop", Self::NoDrop),
+        ("grab", Self::Grab),
+        ("openhand", Self::Grab),
+        ("hand1", Self::Grab),
+        ("grabbing", Self::Grabbing),
+        ("closedhand", Self::Grabbing),
+    ];
+
+    /// Resolves a CSS or X11 cursor name to a shape
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, shape)| *shape)
+    }
+
+    /// Returns the CSS name of the shape
+    pub fn css_name(self) -> &'static str {
+        Self::NAMES
+            .iter()
+            .find(|(_, shape)| *shape == self)
+            .map(|(name, _)| *name)
+            .expect("every shape has a name")
+    }
+}
+
+/// An OSC 22 request to change or query the mouse pointer shape.
+/// The names are kept as sent by the application, which may use
+/// names that we don't recognize.
+/// <https://sw.kovidgoyal.net/kitty/pointer-shapes/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PointerShapeRequest {
+    /// `=` (or no prefix): replace the current shape with the first
+    /// recognized name. An empty list restores the default shape.
+    Set(Vec<String>),
+    /// `>`: push the first recognized name onto the stack of shapes
+    Push(Vec<String>),
+    /// `<`: pop the most recently pushed shape
+    Pop,
+    /// `?`: ask whether the terminal supports the named shapes
+    Query(Vec<String>),
+}
+
+impl PointerShapeRequest {
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        let spec = match osc.get(1) {
+            Some(spec) => str::from_utf8(spec)?,
+            None => "",
+        };
+
+        fn names(list: &str) -> Vec<String> {
+            list.split(',')
+                .filter(|name| !name.is_empty())
+                .map(|name| name.to_string())
+                .collect()
+        }
+
+        Ok(match spec.chars().next() {
+            Some('=') => Self::Set(names(&spec[1..])),
+            Some('>') => Self::Push(names(&spec[1..])),
+            Some('<') => Self::Pop,
+            Some('?') => Self::Query(names(&spec[1..])),
+            _ => Self::Set(names(spec)),
+        })
+    }
+}
+
+impl Display for PointerShapeRequest {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Set(names) => write!(f, "22;{}", names.join(",")),
+            Self::Push(names) => write!(f, "22;>{}", names.join(",")),
+            Self::Pop => write!(f, "22;<"),
+            Self::Query(names) => write!(f, "22;?{}", names.join(",")),
+        }
+    }
+}
+
+/// The `type` key of a kitty clipboard request or response
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum KittyClipboardType {
+    /// Read the clipboard; the payload is the space separated
+    /// list of MIME types to read, or `.` to list the available types.
+    #[default]
+    Read,
+    /// Start writing to the clipboard
+    Write,
+    /// A chunk of data for a MIME type being written.
+    /// A `wdata` packet without a MIME type completes the write.
+    WriteData,
+    /// Declare that the payload, a space separated list of MIME types,
+    /// are aliases for the data of the MIME type being written
+    WriteAlias,
+}
+
+impl KittyClipboardType {
+    fn parse(s: &str) -> Result<Self> {
+        Ok(match s {
+            "read" => Self::Read,
+            "write" => Self::Write,
+            "wdata" => Self::WriteData,
+            "walias" => Self::WriteAlias,
+            _ => bail!("invalid kitty clipboard type {}", s),
+        })
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::WriteData => "wdata",
+            Self::WriteAlias => "walias",
+        }
+    }
+}
+
+/// <https://sw.kovidgoyal.net/kitty/clipboard/>
+/// `OSC 5522 ; metadata ; payload ST`, where metadata is a colon separated
+/// list of `key=value` pairs and the payload is base64 encoded.
+/// The same structure is used both for requests from the application
+/// and for the responses sent back to it.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct KittyClipboard {
+    /// `type`
+    pub kind: KittyClipboardType,
+    /// `mime`: the MIME type of the data; base64 encoded on the wire
+    pub mime: Option<String>,
+    /// `id`: an application chosen identifier that is echoed back
+    /// in responses
+    pub id: Option<String>,
+    /// `loc`: true to use the primary selection rather than the clipboard
+    pub primary: bool,
+    /// `status`: only present in responses
+    pub status: Option<String>,
+    /// The decoded payload
+    pub payload: Option<Vec<u8>>,
+}
+
+impl KittyClipboard {
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        ensure!(osc.len() > 1, "not enough args");
+        let mut clip = Self::default();
+
+        let metadata = str::from_utf8(osc[1])?;
+        for item in metadata.split(':') {
+            if item.is_empty() {
+                continue;
+            }
+            let (key, value) = item
+                .split_once('=')
+                .ok_or_else(|| format!("malformed kitty clipboard metadata {}", item))?;
+            match key {
+                "type" => clip.kind = KittyClipboardType::parse(value)?,
+                "mime" => clip.mime = Some(String::from_utf8(base64_decode(value)?)?),
+                "id" => clip.id = Some(value.to_string()),
+                "loc" => clip.primary = value == "primary",
+                "status" => clip.status = Some(value.to_string()),
+                // Passwords and human readable names that allow skipping
+                // the permission prompt are not supported and are ignored
+                _ => {}
+            }
+        }
+
+        if let Some(payload) = osc.get(2) {
+            clip.payload = Some(base64_decode(payload)?);
+        }
+
+        Ok(clip)
+    }
+}
+
+impl Display for KittyClipboard {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "5522;type={}", self.kind.as_str())?;
+        if let Some(mime) = &self.mime {
+            write!(f, ":mime={}", base64_encode(mime))?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, ":id={}", id)?;
+        }
+        if self.primary {
+            write!(f, ":loc=primary")?;
+        }
+        if let Some(status) = &self.status {
+            write!(f, ":status={}", status)?;
+        }
+        if let Some(payload) = &self.payload {
+            write!(f, ";{}", base64_encode(payload))?;
+        }
+        Ok(())
+    }
+}
+
+/// A color that can be set or queried via OSC 21
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorControlKey {
+    Foreground,
+    Background,
+    SelectionForeground,
+    SelectionBackground,
+    Cursor,
+    CursorText,
+    ActiveTabForeground,
+    ActiveTabBackground,
+    InactiveTabForeground,
+    InactiveTabBackground,
+    TabBarBackground,
+    /// An entry in the 256 color palette
+    PaletteIndex(u8),
+    /// A key that we don't recognize; it is retained so that
+    /// a query for it can be answered
+    Unknown(String),
+}
+
+impl ColorControlKey {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "foreground" => Self::Foreground,
+            "background" => Self::Background,
+            "selection_foreground" => Self::SelectionForeground,
+            "selection_background" => Self::SelectionBackground,
+            "cursor" => Self::Cursor,
+            "cursor_text" => Self::CursorText,
+            "active_tab_foreground" => Self::ActiveTabForeground,
+            "active_tab_background" => Self::ActiveTabBackground,
+            "inactive_tab_foreground" => Self::InactiveTabForeground,
+            "inactive_tab_background" => Self::InactiveTabBackground,
+            "tab_bar_background" => Self::TabBarBackground,
+            name => match name.parse::<u8>() {
+                Ok(idx) => Self::PaletteIndex(idx),
+                Err(_) => Self::Unknown(name.to_string()),
+            },
+        }
+    }
+}
+
+impl Display for ColorControlKey {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let name = match self {
+            Self::Foreground => "foreground",
+            Self::Background => "background",
+            Self::SelectionForeground => "selection_foreground",
+            Self::SelectionBackground => "selection_background",
+            Self::Cursor => "cursor",
+            Self::CursorText => "cursor_text",
+            Self::ActiveTabForeground => "active_tab_foreground",
+            Self::ActiveTabBackground => "active_tab_background",
+            Self::InactiveTabForeground => "inactive_tab_foreground",
+            Self::InactiveTabBackground => "inactive_tab_background",
+            Self::TabBarBackground => "tab_bar_background",
+            Self::PaletteIndex(idx) => return write!(f, "{}", idx),
+            Self::Unknown(name) => name,
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorControlValue {
+    /// `?`: report the current color.
+    /// In a response, indicates that the key is not recognized.
+    Query,
+    /// An empty value: reset the color to its configured value.
+    /// In a response, indicates that the color is not set.
+    Reset,
+    Color(SrgbaTuple),
+}
+
+/// A single `key=value` entry of an OSC 21 color control sequence,
+/// which takes the form `OSC 21 ; key=value ; key=value ... ST`.
+/// The same form is used to respond to queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorControl {
+    pub key: ColorControlKey,
+    pub value: ColorControlValue,
+}
+
+impl ColorControl {
+    fn parse_list(osc: &[&[u8]]) -> Result<Vec<Self>> {
+        let mut items = vec![];
+        for entry in osc.iter().skip(1) {
+            if entry.is_empty() {
+                continue;
+            }
+            let entry = str::from_utf8(entry)?;
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("malformed color control entry {:?}", entry))?;
+            let value = match value {
+                "" => ColorControlValue::Reset,
+                "?" => ColorControlValue::Query,
+                spec => ColorControlValue::Color(
+                    SrgbaTuple::from_str(spec)
+                        .map_err(|()| format!("invalid color spec {:?}", spec))?,
+                ),
+            };
+            items.push(Self {
+                key: ColorControlKey::from_name(key),
+                value,
+            });
+        }
+        Ok(items)
+    }
+}
+
+impl Display for ColorControl {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.value {
+            ColorControlValue::Query => write!(f, "{}=?", self.key),
+            ColorControlValue::Reset => write!(f, "{}=", self.key),
+            ColorControlValue::Color(c) => {
+                write!(f, "{}={}", self.key, c.to_x11_16bit_rgb_string())
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ITermProprietary {
     /// The "Set Mark" command allows you to record a location and then jump back to it later
@@ -1722,6 +2582,243 @@ mod test {
         );
     }
 
+    #[test]
+    fn kitty_notification() {
+        assert_eq!(
+            parse(&["99", "", "Hello world"], "\x1b]99;;Hello world\x1b\\"),
+            OperatingSystemCommand::KittyDesktopNotification(KittyDesktopNotification {
+                payload: "Hello world".into(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(
+                &["99", "i=1:d=0:u=2", "Hello; world"],
+                "\x1b]99;i=1:d=0:u=2;Hello; world\x1b\\"
+            ),
+            OperatingSystemCommand::KittyDesktopNotification(KittyDesktopNotification {
+                id: Some("1".into()),
+                done: false,
+                urgency: Some(KittyNotificationUrgency::Critical),
+                payload: "Hello; world".into(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(
+                &["99", "i=1:p=body:e=1:a=report:c=1", "Ym9keQ=="],
+                "\x1b]99;i=1:p=body:e=1:a=focus,report:c=1;Ym9keQ==\x1b\\"
+            ),
+            OperatingSystemCommand::KittyDesktopNotification(KittyDesktopNotification {
+                id: Some("1".into()),
+                payload_type: KittyNotificationPayload::Body,
+                base64: true,
+                actions: Some(KittyNotificationActions {
+                    focus: true,
+                    report: true,
+                }),
+                report_close: true,
+                payload: "body".into(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(&["99", "i=x:p=close", ""], "\x1b]99;i=x:p=close;\x1b\\"),
+            OperatingSystemCommand::KittyDesktopNotification(KittyDesktopNotification {
+                id: Some("x".into()),
+                payload_type: KittyNotificationPayload::Close,
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(
+                &["99", "o=unfocused:w=500:a=-focus", "x"],
+                "\x1b]99;a=-focus,-report:o=unfocused:w=500;x\x1b\\"
+            ),
+            OperatingSystemCommand::KittyDesktopNotification(KittyDesktopNotification {
+                actions: Some(KittyNotificationActions {
+                    focus: false,
+                    report: false,
+                }),
+                occasion: Some(KittyNotificationOccasion::Unfocused),
+                expire_ms: Some(500),
+                payload: "x".into(),
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn text_sizing() {
+        assert_eq!(
+            parse(&["66", "s=2", "Hello"], "\x1b]66;s=2;Hello\x1b\\"),
+            OperatingSystemCommand::TextSizing(TextSizing {
+                scale: 2,
+                text: "Hello".into(),
+                ..Default::default()
+            })
+        );
+        assert_eq!(
+            parse(
+                &["66", "w=3:n=1:d=2:v=2:h=1", "a;b"],
+                "\x1b]66;w=3:n=1:d=2:v=2:h=1;a;b\x1b\\"
+            ),
+            OperatingSystemCommand::TextSizing(TextSizing {
+                width: 3,
+                numerator: 1,
+                denominator: 2,
+                vertical_align: TextSizingVerticalAlign::Center,
+                horizontal_align: TextSizingHorizontalAlign::Right,
+                text: "a;b".into(),
+                ..Default::default()
+            })
+        );
+        // Out of range values are rejected
+        assert_eq!(
+            parse(&["66", "s=8", "x"], "\x1b]66;s=8;x\x1b\\"),
+            OperatingSystemCommand::Unspecified(vec![
+                b"66".to_vec(),
+                b"s=8".to_vec(),
+                b"x".to_vec()
+            ])
+        );
+    }
+
+    #[test]
+    fn pointer_shape() {
+        assert_eq!(
+            parse(&["22", "xterm"], "\x1b]22;xterm\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(vec!["xterm".into()]))
+        );
+        assert_eq!(
+            parse(&["22", "=pointer,hand2"], "\x1b]22;pointer,hand2\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(vec![
+                "pointer".into(),
+                "hand2".into()
+            ]))
+        );
+        assert_eq!(
+            parse(&["22", ""], "\x1b]22;\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Set(vec![]))
+        );
+        assert_eq!(
+            parse(&["22", ">ew-resize"], "\x1b]22;>ew-resize\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Push(vec![
+                "ew-resize".into()
+            ]))
+        );
+        assert_eq!(
+            parse(&["22", "<"], "\x1b]22;<\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Pop)
+        );
+        assert_eq!(
+            parse(&["22", "?wait,bogus"], "\x1b]22;?wait,bogus\x1b\\"),
+            OperatingSystemCommand::PointerShape(PointerShapeRequest::Query(vec![
+                "wait".into(),
+                "bogus".into()
+            ]))
+        );
+
+        assert_eq!(
+            PointerShape::from_name("hand2"),
+            Some(PointerShape::Pointer)
+        );
+        assert_eq!(PointerShape::from_name("bogus"), None);
+        assert_eq!(PointerShape::NsResize.css_name(), "ns-resize");
+    }
+
+    #[test]
+    fn kitty_clipboard() {
+        // "text/plain text/html"
+        assert_eq!(
+            parse(
+                &["5522", "type=read:id=a1", "dGV4dC9wbGFpbiB0ZXh0L2h0bWw="],
+                "\x1b]5522;type=read:id=a1;dGV4dC9wbGFpbiB0ZXh0L2h0bWw=\x1b\\"
+            ),
+            OperatingSystemCommand::KittyClipboard(KittyClipboard {
+                kind: KittyClipboardType::Read,
+                id: Some("a1".into()),
+                payload: Some(b"text/plain text/html".to_vec()),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            parse(
+                &["5522", "type=write:loc=primary"],
+                "\x1b]5522;type=write:loc=primary\x1b\\"
+            ),
+            OperatingSystemCommand::KittyClipboard(KittyClipboard {
+                kind: KittyClipboardType::Write,
+                primary: true,
+                ..Default::default()
+            })
+        );
+
+        // "image/png", "hello"
+        assert_eq!(
+            parse(
+                &["5522", "type=wdata:mime=aW1hZ2UvcG5n", "aGVsbG8="],
+                "\x1b]5522;type=wdata:mime=aW1hZ2UvcG5n;aGVsbG8=\x1b\\"
+            ),
+            OperatingSystemCommand::KittyClipboard(KittyClipboard {
+                kind: KittyClipboardType::WriteData,
+                mime: Some("image/png".into()),
+                payload: Some(b"hello".to_vec()),
+                ..Default::default()
+            })
+        );
+
+        assert_eq!(
+            OperatingSystemCommand::KittyClipboard(KittyClipboard {
+                kind: KittyClipboardType::Write,
+                status: Some("DONE".into()),
+                ..Default::default()
+            })
+            .to_string(),
+            "\x1b]5522;type=write:status=DONE\x1b\\"
+        );
+    }
+
+    #[test]
+    fn kitty_color_control() {
+        assert_eq!(
+            parse(
+                &["21", "foreground=?", "background=", "3=#ff0000", "bogus=?"],
+                "\x1b]21;foreground=?;background=;3=rgb:ffff/0000/0000;bogus=?\x1b\\"
+            ),
+            OperatingSystemCommand::KittyColorControl(vec![
+                ColorControl {
+                    key: ColorControlKey::Foreground,
+                    value: ColorControlValue::Query,
+                },
+                ColorControl {
+                    key: ColorControlKey::Background,
+                    value: ColorControlValue::Reset,
+                },
+                ColorControl {
+                    key: ColorControlKey::PaletteIndex(3),
+                    value: ColorControlValue::Color(SrgbaTuple(1., 0., 0., 1.)),
+                },
+                ColorControl {
+                    key: ColorControlKey::Unknown("bogus".into()),
+                    value: ColorControlValue::Query,
+                },
+            ])
+        );
+
+        assert_eq!(
+            parse(
+                &["21", "active_tab_background=rgb:0000/ffff/0000"],
+                "\x1b]21;active_tab_background=rgb:0000/ffff/0000\x1b\\"
+            ),
+            OperatingSystemCommand::KittyColorControl(vec![ColorControl {
+                key: ColorControlKey::ActiveTabBackground,
+                value: ColorControlValue::Color(SrgbaTuple(0., 1., 0., 1.)),
+            }])
+        );
+    }
+
     #[test]
     fn iterm() {
         assert_eq!(
@@ -440,6 +440,7 @@ impl FallbackResolveInfo {
                     ),
                     url: Some(url.to_string()),
                     timeout: Some(Duration::from_secs(15)),
+                    ..Default::default()
                 }
                 .show();
             } else {
@@ -462,6 +463,9 @@ enum Entity {
 
 struct FontConfigInner {
     fonts: RefCell<HashMap<TextStyle, Rc<LoadedFont>>>,
+    /// Fonts scaled relative to the configured size, keyed by
+    /// the style and the bits of the scale factor
+    scaled_fonts: RefCell<HashMap<(TextStyle, u64), Rc<LoadedFont>>>,
     metrics: RefCell<Option<FontMetrics>>,
     dpi: RefCell<usize>,
     font_scale: RefCell<f64>,
@@ -488,6 +492,7 @@ impl FontConfigInner {
         let locator = new_locator(config.font_locator);
         Ok(Self {
             fonts: RefCell::new(HashMap::new()),
+            scaled_fonts: RefCell::new(HashMap::new()),
             locator,
             metrics: RefCell::new(None),
             title_font: RefCell::new(None),
@@ -508,6 +513,7 @@ impl FontConfigInner {
         *self.config.borrow_mut() = config.clone();
         // Config was reloaded, invalidate our caches
         fonts.clear();
+        self.scaled_fonts.borrow_mut().clear();
         self.title_font.borrow_mut().take();
         self.pane_select_font.borrow_mut().take();
         self.char_select_font.borrow_mut().take();
@@ -875,11 +881,53 @@ impl FontConfigInner {
             return Ok(Rc::clone(entry));
         }
 
-        let mut font_size = config.font_size * *self.font_scale.borrow();
+        let loaded = self.load_font(myself, style, &config, def_font, 1.0)?;
+        fonts.insert(style.clone(), Rc::clone(&loaded));
+
+        Ok(loaded)
+    }
+
+    /// Like `resolve_font`, but loads the font at `scale` times
+    /// the configured size, such as for text sized by the application
+    fn resolve_scaled_font(
+        &self,
+        myself: &Rc<Self>,
+        style: &TextStyle,
+        scale: f64,
+    ) -> anyhow::Result<Rc<LoadedFont>> {
+        let key = (style.clone(), scale.to_bits());
+        if let Some(entry) = self.scaled_fonts.borrow().get(&key) {
+            return Ok(Rc::clone(entry));
+        }
+
+        let config = self.config.borrow();
+        let is_default = *style == config.font;
+        let def_font = if !is_default && config.use_cap_height_to_scale_fallback_fonts {
+            Some(self.resolve_scaled_font(myself, &config.font, scale)?)
+        } else {
+            None
+        };
+        let loaded = self.load_font(myself, style, &config, def_font, scale)?;
+        self.scaled_fonts
+            .borrow_mut()
+            .insert(key, Rc::clone(&loaded));
+
+        Ok(loaded)
+    }
+
+    fn load_font(
+        &self,
+        myself: &Rc<Self>,
+        style: &TextStyle,
+        config: &ConfigHandle,
+        def_font: Option<Rc<LoadedFont>>,
+        scale: f64,
+    ) -> anyhow::Result<Rc<LoadedFont>> {
+        let mut font_size = config.font_size * *self.font_scale.borrow() * scale;
         let dpi = *self.dpi.borrow() as u32;
         let pixel_size = (font_size * dpi as f64 / 72.0) as u16;
 
-        let (mut shaper, mut handles) = self.resolve_font_helper(style, &config, pixel_size)?;
+        let (mut shaper, mut handles) = self.resolve_font_helper(style, config, pixel_size)?;
 
         let mut metrics = shaper.metrics(font_size, dpi).with_context(|| {
             format!(
@@ -908,7 +956,7 @@ impl FontConfigInner {
                             metrics,
                         );
                         let (alt_shaper, alt_handles) =
-                            self.resolve_font_helper(style, &config, scaled_pixel_size)?;
+                            self.resolve_font_helper(style, config, scaled_pixel_size)?;
                         shaper = alt_shaper;
                         handles = alt_handles;
 
@@ -941,8 +989,6 @@ impl FontConfigInner {
             pixel_geometry: config.display_pixel_geometry,
         });
 
-        fonts.insert(style.clone(), Rc::clone(&loaded));
-
         Ok(loaded)
     }
 
@@ -953,6 +999,7 @@ impl FontConfigInner {
         *self.dpi.borrow_mut() = dpi;
         *self.font_scale.borrow_mut() = font_scale;
         self.fonts.borrow_mut().clear();
+        self.scaled_fonts.borrow_mut().clear();
         self.metrics.borrow_mut().take();
         self.title_font.borrow_mut().take();
 
@@ -1085,6 +1132,19 @@ impl FontConfiguration {
         self.inner.resolve_font(&self.inner, style)
     }
 
+    /// Given a text style, load (with caching) the font that best
+    /// matches it, at `scale` times the configured font size.
+    pub fn resolve_scaled_font(
+        &self,
+        style: &TextStyle,
+        scale: f64,
+    ) -> anyhow::Result<Rc<LoadedFont>> {
+        if scale == 1.0 {
+            return self.resolve_font(style);
+        }
+        self.inner.resolve_scaled_font(&self.inner, style, scale)
+    }
+
     pub fn change_scaling(&self, font_scale: f64, dpi: usize) -> (f64, usize) {
         self.inner.change_scaling(font_scale, dpi)
     }
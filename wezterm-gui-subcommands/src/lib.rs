@@ -101,6 +101,12 @@ pub struct StartCommand {
     #[arg(long, requires = "domain")]
     pub attach: bool,
 
+    /// When used with --domain, attach to the multiplexer domain
+    /// in read-only mode: its panes are shown, but input, resizing
+    /// and spawning are refused.
+    #[arg(long, requires = "attach")]
+    pub read_only: bool,
+
     /// Instead of executing your shell, run PROG.
     /// For example: `wezterm start -- bash -l` will spawn bash
     /// as if it were a login shell. [aliases: -e]
@@ -212,6 +218,11 @@ pub struct ConnectCommand {
     #[arg(long)]
     pub new_tab: bool,
 
+    /// Attach in read-only mode: the panes in the domain are shown,
+    /// but input, resizing and spawning are refused.
+    #[arg(long)]
+    pub read_only: bool,
+
     /// Override the default windowing system class.
     /// The default is "org.wezfurlong.wezterm".
     /// Under X11 and Windows this changes the window class.
@@ -602,7 +602,8 @@ fn spawn_command_from_action(action: &KeyAssignment) -> Option<&SpawnCommand> {
         SplitHorizontal(command)
         | SplitVertical(command)
         | SpawnCommandInNewWindow(command)
-        | SpawnCommandInNewTab(command) => Some(command),
+        | SpawnCommandInNewTab(command)
+        | SpawnFloatingPane(command) => Some(command),
         _ => None,
     }
 }
@@ -820,6 +821,130 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &[],
             icon: None,
         },
+        ApplyLayout(path) => CommandDef {
+            brief: format!("Apply layout `{path}`").into(),
+            doc: "Spawns the windows, tabs and panes described by the layout file".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &[],
+            icon: None,
+        },
+        StartPaneLogging(target) => CommandDef {
+            brief: match (&target.path, &target.command) {
+                (Some(path), _) => format!("Log the output of the pane to `{}`", path.display()),
+                (None, Some(argv)) => format!("Pipe the output of the pane to `{}`", argv.join(" ")),
+                (None, None) => "Log the output of the pane".to_string(),
+            }
+            .into(),
+            doc: "Copies everything that the current pane outputs to a file or command".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        StopPaneLogging => CommandDef {
+            brief: "Stop logging the output of the pane".into(),
+            doc: "Stops copying the output of the current pane to its log".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        ToggleInputBroadcast(scope) => CommandDef {
+            brief: match scope {
+                InputBroadcastScope::CurrentTab => "Toggle broadcasting input to all panes in the tab",
+                InputBroadcastScope::CurrentWorkspace => {
+                    "Toggle broadcasting input to all panes in the workspace"
+                }
+                InputBroadcastScope::MarkedPanes => "Toggle broadcasting input to the marked panes",
+            }
+            .into(),
+            doc: "Sends the keys typed and text pasted into the active pane to other panes as well"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        TogglePaneBroadcastMark => CommandDef {
+            brief: "Toggle whether the pane receives broadcast input".into(),
+            doc: "Adds or removes the current pane from the set of marked panes \
+                  that receive input when broadcasting to MarkedPanes"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        SpawnFloatingPane(cmd) => CommandDef {
+            brief: label_string(action, "New Floating Pane".to_string()).into(),
+            doc: format!("Spawn {cmd:?} into a pane that floats above the current tab").into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        ToggleFloatingPanes => CommandDef {
+            brief: "Show/Hide Floating Panes".into(),
+            doc: "Shows or hides the panes that float above the current tab".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        MoveFloatingPane(direction, amount) => CommandDef {
+            brief: format!("Move Floating Pane {amount} cell(s) {direction:?}").into(),
+            doc: "Moves the top-most floating pane of the current tab".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        ResizeFloatingPane(direction, amount) => CommandDef {
+            brief: format!("Resize Floating Pane {amount} cell(s) {direction:?}").into(),
+            doc: "Grows or shrinks the top-most floating pane of the current tab".into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        PromoteFloatingPane(direction) => CommandDef {
+            brief: format!("Move Floating Pane into a Split ({direction:?})").into(),
+            doc: "Moves the top-most floating pane of the current tab into \
+                  the split tree, alongside the active pane"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: None,
+        },
+        SetPortForwardEnabled {
+            domain,
+            forward_id,
+            enabled,
+        } => CommandDef {
+            brief: match enabled {
+                Some(true) => format!("Enable port forward {forward_id} of {domain}"),
+                Some(false) => format!("Disable port forward {forward_id} of {domain}"),
+                None => format!("Toggle port forward {forward_id} of {domain}"),
+            }
+            .into(),
+            doc: "Starts or stops listening on a port forward of an ssh domain".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &[],
+            icon: None,
+        },
+        ShowSftpBrowser => CommandDef {
+            brief: "Browse remote files via SFTP".into(),
+            doc: "Activates an overlay that browses the files on the remote host \
+                  of an ssh domain pane, and uploads and downloads files"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("md_folder_network"),
+        },
         PromptInputLine(_) => CommandDef {
             brief: "Prompt the user for a line of text".into(),
             doc: "Activates the prompt overlay and wait for input".into(),
@@ -1,7 +1,7 @@
 use anyhow::Context;
 use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Simple heuristics to try to avoid obvious trickery with
 /// the name provided by the remote system
@@ -61,6 +61,31 @@ pub fn save_to_downloads(orig_name: Option<String>, data: &[u8]) -> anyhow::Resu
     file.write_all(data)
         .with_context(|| format!("writing {} of data to {}", data.len(), name.display()))?;
 
+    notify_download_completed(&name);
+    Ok(())
+}
+
+/// Moves the file at `path` into the download directory, copying it
+/// if it lives on a different filesystem.  The file at `path` is
+/// left in place if this fails.
+pub fn move_to_downloads(orig_name: Option<String>, path: &Path) -> anyhow::Result<()> {
+    let (name, file) = resolve_file_name(orig_name.as_deref())?;
+    drop(file);
+
+    if std::fs::rename(path, &name).is_err() {
+        if let Err(err) = std::fs::copy(path, &name) {
+            std::fs::remove_file(&name).ok();
+            return Err(err)
+                .with_context(|| format!("copying {} to {}", path.display(), name.display()));
+        }
+        std::fs::remove_file(path).ok();
+    }
+
+    notify_download_completed(&name);
+    Ok(())
+}
+
+fn notify_download_completed(name: &Path) {
     let url = format!("file://{}", name.display());
     wezterm_toast_notification::persistent_toast_notification_with_click_to_open_url(
         "Download completed",
@@ -69,6 +94,4 @@ pub fn save_to_downloads(orig_name: Option<String>, data: &[u8]) -> anyhow::Resu
     );
 
     log::info!("Downloaded {}", name.display());
-
-    Ok(())
 }
@@ -7,14 +7,19 @@ use anyhow::{Context, Error};
 use config::keyassignment::{KeyAssignment, SpawnCommand};
 use config::{ConfigSubscription, NotificationHandling};
 use mux::client::ClientId;
+use mux::pane::PaneId;
 use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification};
 use promise::{Future, Promise};
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashSet};
+use std::io::Write;
 use std::rc::Rc;
 use std::sync::Arc;
-use wezterm_term::{Alert, ClipboardSelection};
+use termwiz::escape::csi::ColorScheme;
+use termwiz::escape::osc::{KittyDesktopNotification, KittyNotificationPayload};
+use termwiz::escape::OperatingSystemCommand;
+use wezterm_term::{Alert, ClipboardSelection, NotificationOccasion, NotificationUrgency};
 use wezterm_toast_notification::*;
 
 pub struct GuiFrontEnd {
@@ -94,13 +99,20 @@ impl GuiFrontEnd {
                 MuxNotification::WindowInvalidated(_) => {}
                 MuxNotification::PaneOutput(_) => {}
                 MuxNotification::PaneAdded(_) => {}
+                MuxNotification::PaneLoggingStopped { .. } => {}
                 MuxNotification::Alert {
                     pane_id,
                     alert:
                         Alert::ToastNotification {
                             title,
                             body,
-                            focus: _,
+                            focus,
+                            id,
+                            urgency,
+                            occasion,
+                            report_activation,
+                            report_close,
+                            timeout,
                         },
                 } => {
                     let mux = Mux::get();
@@ -119,19 +131,75 @@ impl GuiFrontEnd {
                                 NotificationHandling::SuppressFromFocusedWindow => {
                                     f_window != window_id
                                 }
+                            } && match occasion {
+                                NotificationOccasion::Always => true,
+                                NotificationOccasion::Unfocused => f_pane != pane_id,
+                                NotificationOccasion::Invisible => f_tab != tab_id,
                             };
 
                             if show {
-                                let message = if title.is_none() { "" } else { &body };
-                                let title = title.as_ref().unwrap_or(&body);
-                                // FIXME: if notification.focus is true, we should do
-                                // something here to arrange to focus pane_id when the
-                                // notification is clicked
-                                persistent_toast_notification(title, message);
+                                let message = if title.is_none() {
+                                    String::new()
+                                } else {
+                                    body.clone()
+                                };
+                                let title = title.unwrap_or(body);
+
+                                let on_activate = if focus || report_activation {
+                                    let report_id = id.clone().filter(|_| report_activation);
+                                    Some(ToastCallback::new(move || {
+                                        let report_id = report_id.clone();
+                                        promise::spawn::spawn_into_main_thread(async move {
+                                            activate_notification(pane_id, focus, report_id);
+                                        })
+                                        .detach();
+                                    }))
+                                } else {
+                                    None
+                                };
+
+                                let on_close = if report_close {
+                                    let report_id = id.clone();
+                                    Some(ToastCallback::new(move || {
+                                        let report = KittyDesktopNotification {
+                                            id: report_id.clone(),
+                                            payload_type: KittyNotificationPayload::Close,
+                                            ..Default::default()
+                                        };
+                                        promise::spawn::spawn_into_main_thread(async move {
+                                            report_notification(pane_id, report);
+                                        })
+                                        .detach();
+                                    }))
+                                } else {
+                                    None
+                                };
+
+                                ToastNotification {
+                                    title,
+                                    message,
+                                    url: None,
+                                    timeout,
+                                    tag: id.map(|id| notification_tag(pane_id, &id)),
+                                    urgency: match urgency {
+                                        NotificationUrgency::Low => ToastUrgency::Low,
+                                        NotificationUrgency::Normal => ToastUrgency::Normal,
+                                        NotificationUrgency::Critical => ToastUrgency::Critical,
+                                    },
+                                    on_activate,
+                                    on_close,
+                                }
+                                .show();
                             }
                         }
                     }
                 }
+                MuxNotification::Alert {
+                    pane_id,
+                    alert: Alert::CloseToastNotification(id),
+                } => {
+                    close_notification(&notification_tag(pane_id, &id));
+                }
                 MuxNotification::Alert {
                     pane_id: _,
                     alert: Alert::Bell | Alert::Progress(_),
@@ -147,7 +215,8 @@ impl GuiFrontEnd {
                         | Alert::WindowTitleChanged(_)
                         | Alert::TabTitleChanged(_)
                         | Alert::IconTitleChanged(_)
-                        | Alert::SetUserVar { .. },
+                        | Alert::SetUserVar { .. }
+                        | Alert::ClipboardRead { .. },
                 } => {}
                 MuxNotification::Empty => {
                     if config::configuration().quit_when_all_windows_are_closed {
@@ -171,6 +240,25 @@ impl GuiFrontEnd {
                         log::error!("save_to_downloads: {:#}", err);
                     }
                 }
+                MuxNotification::SaveFileToDownloads { name, path } => {
+                    if !config::configuration().allow_download_protocols {
+                        log::error!(
+                            "Ignoring download request for {:?}, \
+                                 as allow_download_protocols=false",
+                            name
+                        );
+                        std::fs::remove_file(&path).ok();
+                    } else {
+                        // Moving the file may mean copying it to
+                        // another filesystem, so don't block the caller
+                        std::thread::spawn(move || {
+                            if let Err(err) = crate::download::move_to_downloads(name, &path) {
+                                log::error!("move_to_downloads: {:#}", err);
+                                std::fs::remove_file(&path).ok();
+                            }
+                        });
+                    }
+                }
                 MuxNotification::AssignClipboard {
                     pane_id,
                     selection,
@@ -200,6 +288,41 @@ impl GuiFrontEnd {
                     })
                     .detach();
                 }
+                MuxNotification::AssignClipboardData {
+                    pane_id,
+                    selection,
+                    items,
+                } => {
+                    promise::spawn::spawn_into_main_thread(async move {
+                        let fe = crate::frontend::front_end();
+                        log::trace!(
+                            "set clipboard data in pane {} {:?} {} items",
+                            pane_id,
+                            selection,
+                            items.len()
+                        );
+                        if let Some(window) = fe.known_windows.borrow().keys().next() {
+                            window.set_clipboard_data(
+                                match selection {
+                                    ClipboardSelection::Clipboard => Clipboard::Clipboard,
+                                    ClipboardSelection::PrimarySelection => {
+                                        Clipboard::PrimarySelection
+                                    }
+                                },
+                                items
+                                    .into_iter()
+                                    .map(|item| ClipboardItem {
+                                        mime_type: item.mime_type,
+                                        data: item.data,
+                                    })
+                                    .collect(),
+                            );
+                        } else {
+                            log::error!("Cannot assign clipboard as there are no windows");
+                        };
+                    })
+                    .detach();
+                }
             }
             true
         });
@@ -208,6 +331,12 @@ impl GuiFrontEnd {
         // before any windows are created
         config::reload();
 
+        // Let panes know the initial color scheme, so that they can
+        // answer queries about it and report subsequent changes
+        Mux::get().set_color_scheme(color_scheme_for_appearance(
+            Connection::get().unwrap().get_appearance(),
+        ));
+
         // And build the initial menu bar.
         // TODO: arrange for this to happen on config reload.
         crate::commands::CommandDef::recreate_menubar(&config::configuration());
@@ -490,6 +619,56 @@ thread_local! {
     static FRONT_END: RefCell<Option<Rc<GuiFrontEnd>>> = RefCell::new(None);
 }
 
+/// Maps the windowing environment appearance to the color scheme
+/// that is reported to applications running in panes
+pub fn color_scheme_for_appearance(appearance: Appearance) -> ColorScheme {
+    match appearance {
+        Appearance::Light | Appearance::LightHighContrast => ColorScheme::Light,
+        Appearance::Dark | Appearance::DarkHighContrast => ColorScheme::Dark,
+    }
+}
+
+/// Produces the tag used to identify an application assigned
+/// notification id in the toast notification system
+fn notification_tag(pane_id: PaneId, id: &str) -> String {
+    format!("wezterm-{pane_id}-{id}")
+}
+
+/// Sends a kitty desktop notification report back to the
+/// application running in the pane
+fn report_notification(pane_id: PaneId, report: KittyDesktopNotification) {
+    let mux = Mux::get();
+    if let Some(pane) = mux.get_pane(pane_id) {
+        let report = OperatingSystemCommand::KittyDesktopNotification(report);
+        if let Err(err) = write!(pane.writer(), "{}", report) {
+            log::error!("Failed to report notification to pane {pane_id}: {err:#}");
+        }
+    }
+}
+
+fn activate_notification(pane_id: PaneId, focus: bool, report_id: Option<String>) {
+    if focus {
+        let mux = Mux::get();
+        if let Err(err) = mux.focus_pane_and_containing_tab(pane_id) {
+            log::error!("Failed to focus pane {pane_id} for notification: {err:#}");
+        }
+        if let Some((_domain, window_id, _tab_id)) = mux.resolve_pane_id(pane_id) {
+            if let Some(gui_win) = front_end().gui_window_for_mux_window(window_id) {
+                gui_win.window.focus();
+            }
+        }
+    }
+    if let Some(id) = report_id {
+        report_notification(
+            pane_id,
+            KittyDesktopNotification {
+                id: Some(id),
+                ..Default::default()
+            },
+        );
+    }
+}
+
 pub fn try_front_end() -> Option<Rc<GuiFrontEnd>> {
     FRONT_END.with(|f| f.borrow().as_ref().map(Rc::clone))
 }
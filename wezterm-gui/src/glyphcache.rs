@@ -23,7 +23,7 @@ use std::sync::mpsc::{sync_channel, Receiver, RecvTimeoutError, SyncSender, TryR
 use std::sync::{Arc, LazyLock, MutexGuard};
 use std::time::{Duration, Instant};
 use termwiz::color::RgbColor;
-use termwiz::image::{ImageData, ImageDataType};
+use termwiz::image::{AnimationState, ImageData, ImageDataType};
 use termwiz::surface::CursorShape;
 use wezterm_blob_leases::{BlobLease, BlobManager, BoxedReader};
 use wezterm_font::units::*;
@@ -490,6 +490,10 @@ pub struct DecodedImage {
     current_frame: RefCell<usize>,
     image: Arc<ImageData>,
     frames: RefCell<Option<FrameState>>,
+    /// The AnimationControl generation that we last applied
+    animation_generation: RefCell<usize>,
+    /// How many times we've looped through the frames
+    loops_played: RefCell<u32>,
 }
 
 impl DecodedImage {
@@ -500,6 +504,8 @@ impl DecodedImage {
             current_frame: RefCell::new(0),
             image: Arc::new(image),
             frames: RefCell::new(None),
+            animation_generation: RefCell::new(0),
+            loops_played: RefCell::new(0),
         }
     }
 
@@ -510,6 +516,8 @@ impl DecodedImage {
                 current_frame: RefCell::new(0),
                 image: Arc::clone(image_data),
                 frames: RefCell::new(Some(FrameState::new(rx))),
+                animation_generation: RefCell::new(0),
+                loops_played: RefCell::new(0),
             },
             Err(err) => {
                 log::error!("failed to start FrameDecoder: {err:#}");
@@ -530,18 +538,19 @@ impl DecodedImage {
                     Self::placeholder()
                 }
             },
-            ImageDataType::AnimRgba8 { durations, .. } => {
-                let current_frame = if durations.len() > 1 && durations[0].as_millis() == 0 {
-                    // Skip possible 0-duration root frame
-                    1
-                } else {
-                    0
-                };
+            ImageDataType::AnimRgba8 {
+                durations,
+                animation,
+                ..
+            } => {
+                let current_frame = animation.first_frame(durations);
                 Self {
                     frame_start: RefCell::new(Instant::now()),
                     current_frame: RefCell::new(current_frame),
                     image: Arc::clone(image_data),
                     frames: RefCell::new(None),
+                    animation_generation: RefCell::new(animation.generation),
+                    loops_played: RefCell::new(0),
                 }
             }
 
@@ -550,6 +559,8 @@ impl DecodedImage {
                 current_frame: RefCell::new(0),
                 image: Arc::clone(image_data),
                 frames: RefCell::new(None),
+                animation_generation: RefCell::new(0),
+                loops_played: RefCell::new(0),
             },
         }
     }
@@ -930,12 +941,30 @@ impl GlyphCache {
                 hashes,
                 frames,
                 durations,
+                animation,
                 ..
             } => {
                 let mut next = None;
                 let mut decoded_frame_start = decoded.frame_start.borrow_mut();
                 let mut decoded_current_frame = decoded.current_frame.borrow_mut();
-                if frames.len() > 1 {
+                let mut loops_played = decoded.loops_played.borrow_mut();
+
+                let mut generation = decoded.animation_generation.borrow_mut();
+                if *generation != animation.generation {
+                    // The application adjusted the playback controls;
+                    // restart from the requested frame
+                    *generation = animation.generation;
+                    *loops_played = 0;
+                    if let Some(frame) = animation.current_frame {
+                        if frame < frames.len() {
+                            *decoded_current_frame = frame;
+                            handle.current_frame = frame;
+                        }
+                    }
+                    *decoded_frame_start = Instant::now();
+                }
+
+                if frames.len() > 1 && animation.state != AnimationState::Stopped {
                     let now = Instant::now();
 
                     // We round up the frame duration to at least the minimum
@@ -948,25 +977,21 @@ impl GlyphCache {
                     // its neighbor while we are rendering the entire terminal
                     // frame, so we want to avoid that.
                     // <https://github.com/wezterm/wezterm/issues/3260>
-                    let mut next_due = *decoded_frame_start
+                    let next_due = *decoded_frame_start
                         + durations[*decoded_current_frame].max(min_frame_duration);
-                    if now >= next_due {
+                    if now < next_due {
+                        next.replace(next_due);
+                    } else if let Some(frame) =
+                        animation.next_frame(*decoded_current_frame, durations, &mut loops_played)
+                    {
                         // Advance to next frame
-                        *decoded_current_frame = *decoded_current_frame + 1;
-                        if *decoded_current_frame >= frames.len() {
-                            *decoded_current_frame = 0;
-                            // Skip potential 0-duration root frame
-                            if durations[0].as_millis() == 0 && frames.len() > 1 {
-                                *decoded_current_frame = *decoded_current_frame + 1;
-                            }
-                        }
+                        *decoded_current_frame = frame;
                         *decoded_frame_start = now;
-                        next_due = *decoded_frame_start
-                            + durations[*decoded_current_frame].max(min_frame_duration);
-                        handle.current_frame = *decoded_current_frame;
+                        handle.current_frame = frame;
+                        next.replace(now + durations[frame].max(min_frame_duration));
                     }
-
-                    next.replace(next_due);
+                    // Otherwise, remain on this frame until the application
+                    // adds more frames or changes the playback controls
                 }
 
                 let hash = hashes[*decoded_current_frame];
@@ -981,14 +1006,7 @@ impl GlyphCache {
 
                 frame_cache.insert(hash, sprite.clone());
 
-                return Ok((
-                    sprite,
-                    Some(
-                        *decoded_frame_start
-                            + durations[*decoded_current_frame].max(min_frame_duration),
-                    ),
-                    LoadState::Loaded,
-                ));
+                return Ok((sprite, next, LoadState::Loaded));
             }
             ImageDataType::EncodedLease(_) | ImageDataType::EncodedFile(_) => {
                 let mut frames = decoded.frames.borrow_mut();
@@ -447,6 +447,12 @@ async fn async_run_terminal_gui(
         let domain = mux
             .get_domain_by_name(name)
             .ok_or_else(|| anyhow!("invalid domain {name}"))?;
+        if opts.read_only {
+            domain
+                .downcast_ref::<ClientDomain>()
+                .ok_or_else(|| anyhow!("domain {name} cannot be attached read-only"))?
+                .set_read_only(true)?;
+        }
         Some(domain)
     } else {
         None
@@ -759,7 +765,7 @@ fn run_terminal_gui(opts: StartCommand, default_domain_name: Option<String>) ->
     let mut publish = Publish::resolve(
         &mux,
         &config,
-        opts.always_new_process || opts.position.is_some(),
+        opts.always_new_process || opts.position.is_some() || opts.read_only,
     );
     log::trace!("{:?}", publish);
     if publish.try_spawn(
@@ -1266,6 +1272,7 @@ fn run() -> anyhow::Result<()> {
                 new_tab: connect.new_tab,
                 always_new_process: true,
                 attach: true,
+                read_only: connect.read_only,
                 _cmd: false,
                 no_auto_connect: false,
                 cwd: None,
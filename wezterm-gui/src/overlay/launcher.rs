@@ -12,7 +12,7 @@ use crate::overlay::selector::{matcher_pattern, matcher_score};
 use crate::termwindow::TermWindowNotif;
 use config::configuration;
 use config::keyassignment::{KeyAssignment, SpawnCommand, SpawnTabDomain};
-use mux::domain::{DomainId, DomainState};
+use mux::domain::{DomainId, DomainState, PortForward};
 use mux::pane::PaneId;
 use mux::termwiztermtab::TermWizTerminal;
 use mux::window::WindowId;
@@ -58,6 +58,7 @@ pub struct LauncherArgs {
     title: String,
     active_workspace: String,
     workspaces: Vec<String>,
+    port_forwards: Vec<PortForward>,
     help_text: String,
     fuzzy_help_text: String,
     alphabet: String,
@@ -153,6 +154,12 @@ impl LauncherArgs {
             vec![]
         };
 
+        let port_forwards = if flags.contains(LauncherFlags::PORT_FORWARDS) {
+            mux.list_port_forwards().await
+        } else {
+            vec![]
+        };
+
         Self {
             flags,
             domains,
@@ -161,6 +168,7 @@ impl LauncherArgs {
             domain_id_of_current_tab,
             title: title.to_string(),
             workspaces,
+            port_forwards,
             active_workspace,
             help_text: help_text.to_string(),
             fuzzy_help_text: fuzzy_help_text.to_string(),
@@ -294,6 +302,31 @@ impl LauncherState {
             });
         }
 
+        for forward in &args.port_forwards {
+            let label = match (&forward.error, forward.enabled) {
+                (Some(err), _) => format!(
+                    "Retry port forward {} (domain `{}`, failed: {})",
+                    forward.description, forward.domain_name, err
+                ),
+                (None, true) => format!(
+                    "Disable port forward {} (domain `{}`)",
+                    forward.description, forward.domain_name
+                ),
+                (None, false) => format!(
+                    "Enable port forward {} (domain `{}`)",
+                    forward.description, forward.domain_name
+                ),
+            };
+            self.entries.push(Entry {
+                label,
+                action: KeyAssignment::SetPortForwardEnabled {
+                    domain: forward.domain_name.clone(),
+                    forward_id: forward.forward_id,
+                    enabled: Some(!forward.enabled),
+                },
+            });
+        }
+
         for tab in &args.tabs {
             self.entries.push(Entry {
                 label: match tab.pane_count {
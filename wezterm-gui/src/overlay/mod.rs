@@ -14,6 +14,7 @@ pub mod launcher;
 pub mod prompt;
 pub mod quickselect;
 pub mod selector;
+pub mod sftp;
 
 pub use confirm_close_pane::{
     confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
@@ -0,0 +1,672 @@
+use anyhow::Context;
+use mux::termwiztermtab::TermWizTerminal;
+use mux::{Mux, MuxNotification};
+use smol::io::{AsyncReadExt, AsyncWriteExt};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use termwiz::cell::{AttributeChange, CellAttributes, Intensity};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use termwiz_funcs::truncate_right;
+use wezterm_ssh::{FileType, Metadata, RenameOptions, Sftp, Utf8PathBuf};
+
+/// The title, status and help rows
+const ROW_OVERHEAD: usize = 3;
+
+/// How much data is transferred with each sftp request
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Limits how often the progress of a transfer is rendered
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+struct Entry {
+    name: String,
+    path: Utf8PathBuf,
+    meta: Metadata,
+}
+
+impl Entry {
+    fn label(&self) -> String {
+        match self.meta.ty {
+            FileType::Dir => format!("{}/", self.name),
+            FileType::Symlink => format!("{}@", self.name),
+            _ => match self.meta.size {
+                Some(size) => format!("{}  ({})", self.name, format_size(size)),
+                None => self.name.clone(),
+            },
+        }
+    }
+}
+
+struct SftpBrowser {
+    sftp: Sftp,
+    cwd: Utf8PathBuf,
+    entries: Vec<Entry>,
+    active_idx: usize,
+    top_row: usize,
+    max_items: usize,
+    status: String,
+    /// Files that were dropped onto the pane, which are
+    /// uploaded once the user has picked a directory
+    pending_uploads: Vec<PathBuf>,
+    /// The local path being typed in for an upload
+    upload_input: Option<String>,
+}
+
+fn format_size(size: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+    let mut size = size as f64 / 1024.;
+    for unit in &UNITS[..UNITS.len() - 1] {
+        if size < 1024. {
+            return format!("{:.1} {}", size, unit);
+        }
+        size /= 1024.;
+    }
+    format!("{:.1} {}", size, UNITS[UNITS.len() - 1])
+}
+
+fn format_progress(verb: &str, name: &str, done: u64, total: Option<u64>) -> String {
+    match total {
+        Some(total) if total > 0 => format!(
+            "{} {}: {} of {} ({}%)",
+            verb,
+            name,
+            format_size(done),
+            format_size(total),
+            done * 100 / total
+        ),
+        _ => format!("{} {}: {}", verb, name, format_size(done)),
+    }
+}
+
+/// Expands a leading `~/` in a local path that was typed in
+fn expand_local_path(path: &str) -> PathBuf {
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Some(home) = dirs_next::home_dir() {
+            return home.join(rest);
+        }
+    }
+    PathBuf::from(path)
+}
+
+impl SftpBrowser {
+    fn change_dir(&mut self, dir: Utf8PathBuf) -> anyhow::Result<()> {
+        let mut entries: Vec<Entry> = smol::block_on(self.sftp.read_dir(dir.clone()))
+            .with_context(|| format!("reading {}", dir))?
+            .into_iter()
+            .map(|(path, meta)| Entry {
+                name: path.file_name().unwrap_or(path.as_str()).to_string(),
+                path,
+                meta,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.meta
+                .ty
+                .is_dir()
+                .cmp(&a.meta.ty.is_dir())
+                .then_with(|| a.name.cmp(&b.name))
+        });
+
+        if let Some(parent) = dir.parent() {
+            if let Ok(meta) = smol::block_on(self.sftp.metadata(parent.to_path_buf())) {
+                entries.insert(
+                    0,
+                    Entry {
+                        name: "..".to_string(),
+                        path: parent.to_path_buf(),
+                        meta,
+                    },
+                );
+            }
+        }
+
+        self.cwd = dir;
+        self.entries = entries;
+        self.active_idx = 0;
+        self.top_row = 0;
+        Ok(())
+    }
+
+    fn refresh(&mut self) {
+        let active_idx = self.active_idx;
+        let top_row = self.top_row;
+        if let Err(err) = self.change_dir(self.cwd.clone()) {
+            self.status = format!("{:#}", err);
+            return;
+        }
+        self.active_idx = active_idx.min(self.entries.len().saturating_sub(1));
+        self.top_row = top_row.min(self.active_idx);
+    }
+
+    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(2);
+        self.max_items = size.rows.saturating_sub(ROW_OVERHEAD).max(1);
+        if self.active_idx >= self.top_row + self.max_items {
+            self.top_row = self.active_idx + 1 - self.max_items;
+        }
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            AttributeChange::Intensity(Intensity::Bold).into(),
+            Change::Text(truncate_right(&format!("SFTP: {}", self.cwd), max_width)),
+            Change::AllAttributes(CellAttributes::default()),
+            Change::Text("\r\n".to_string()),
+        ];
+
+        for (idx, entry) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.top_row)
+            .take(self.max_items)
+        {
+            if idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+            if entry.meta.ty.is_dir() {
+                changes.push(AttributeChange::Intensity(Intensity::Bold).into());
+            }
+            changes.push(Change::Text(truncate_right(
+                &format!(" {} ", entry.label()),
+                max_width,
+            )));
+            changes.push(Change::AllAttributes(CellAttributes::default()));
+            changes.push(Change::Text("\r\n".to_string()));
+        }
+
+        let status = match &self.upload_input {
+            Some(input) => format!("Upload local file: {}", input),
+            None if !self.pending_uploads.is_empty() && self.status.is_empty() => format!(
+                "{} dropped file(s): press 'p' to upload to this directory",
+                self.pending_uploads.len()
+            ),
+            None => self.status.clone(),
+        };
+        let help = if self.upload_input.is_some() {
+            "Enter: upload  Esc: cancel"
+        } else {
+            "Enter: open/download  Backspace: parent  d: download  u: upload  \
+             p: upload dropped  r: refresh  Esc: close"
+        };
+
+        changes.append(&mut vec![
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(size.rows.saturating_sub(2)),
+            },
+            Change::Text(truncate_right(&status, max_width)),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(size.rows.saturating_sub(1)),
+            },
+            AttributeChange::Intensity(Intensity::Half).into(),
+            Change::Text(truncate_right(help, max_width)),
+            Change::AllAttributes(CellAttributes::default()),
+        ]);
+
+        term.render(&changes)
+    }
+
+    /// Shows the progress of a transfer in the status line, and returns
+    /// an error if the user has pressed Escape to cancel it
+    fn progress(
+        &mut self,
+        term: &mut TermWizTerminal,
+        last_update: &mut Instant,
+        status: String,
+    ) -> anyhow::Result<()> {
+        while let Ok(Some(event)) = term.poll_input(Some(Duration::ZERO)) {
+            if let InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            }) = event
+            {
+                anyhow::bail!("Cancelled");
+            }
+        }
+        if last_update.elapsed() >= PROGRESS_INTERVAL {
+            *last_update = Instant::now();
+            self.status = status;
+            self.render(term)?;
+        }
+        Ok(())
+    }
+
+    /// Asks a yes/no question in the status line.
+    /// Returns true if the user answered yes.
+    fn confirm(&mut self, term: &mut TermWizTerminal, prompt: String) -> anyhow::Result<bool> {
+        self.status = format!("{} [y/n]", prompt);
+        self.render(term)?;
+        while let Ok(Some(event)) = term.poll_input(None) {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('y') | KeyCode::Char('Y'),
+                    ..
+                }) => return Ok(true),
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Escape,
+                    ..
+                }) => return Ok(false),
+                _ => {}
+            }
+        }
+        Ok(false)
+    }
+
+    /// Downloads the remote file `path` into a local temporary file,
+    /// which the download handler then moves to the download directory
+    fn download(&mut self, term: &mut TermWizTerminal, entry_idx: usize) -> anyhow::Result<()> {
+        let (name, path, size) = match self.entries.get(entry_idx) {
+            Some(entry) => (entry.name.clone(), entry.path.clone(), entry.meta.size),
+            None => return Ok(()),
+        };
+
+        let mut file = smol::block_on(self.sftp.open(path.clone()))
+            .with_context(|| format!("opening {}", path))?;
+        // The temporary file is removed when it is dropped,
+        // which takes care of errors and cancellation
+        let mut temp = tempfile::NamedTempFile::new().context("creating temporary file")?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut done = 0u64;
+        let mut last_update = Instant::now();
+        loop {
+            let len =
+                smol::block_on(file.read(&mut buf)).with_context(|| format!("reading {}", path))?;
+            if len == 0 {
+                break;
+            }
+            temp.write_all(&buf[..len])
+                .with_context(|| format!("writing {}", temp.path().display()))?;
+            done += len as u64;
+            self.progress(
+                term,
+                &mut last_update,
+                format_progress("Downloading", &name, done, size),
+            )?;
+        }
+        temp.flush()
+            .with_context(|| format!("writing {}", temp.path().display()))?;
+
+        let temp_path = temp
+            .into_temp_path()
+            .keep()
+            .context("keeping temporary file")?;
+        Mux::notify_from_any_thread(MuxNotification::SaveFileToDownloads {
+            name: Some(name.clone()),
+            path: temp_path,
+        });
+        self.status = format!("Downloaded {} ({})", name, format_size(done));
+        Ok(())
+    }
+
+    /// Uploads the local file `local` into the current directory.
+    /// The data is written to a temporary name that replaces the
+    /// target once it is complete, so that a failed upload doesn't
+    /// clobber an existing file.
+    fn upload(&mut self, term: &mut TermWizTerminal, local: &Path) -> anyhow::Result<()> {
+        let name = local
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow::anyhow!("{} has no usable file name", local.display()))?
+            .to_string();
+        let mut source =
+            std::fs::File::open(local).with_context(|| format!("opening {}", local.display()))?;
+        let meta = source.metadata()?;
+        anyhow::ensure!(
+            meta.is_file(),
+            "{} is not a regular file; only files can be uploaded",
+            local.display()
+        );
+        let size = meta.len();
+
+        let remote = self.cwd.join(&name);
+        if smol::block_on(self.sftp.symlink_metadata(remote.clone())).is_ok()
+            && !self.confirm(term, format!("{} already exists; overwrite it?", remote))?
+        {
+            self.status = "Upload cancelled".to_string();
+            return Ok(());
+        }
+
+        let temp = self.cwd.join(format!(".{}.wezterm-upload", name));
+        let mut dest = smol::block_on(self.sftp.create(temp.clone()))
+            .with_context(|| format!("creating {}", temp))?;
+
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut done = 0u64;
+        let mut last_update = Instant::now();
+        let result = (|| -> anyhow::Result<()> {
+            loop {
+                let len = source
+                    .read(&mut buf)
+                    .with_context(|| format!("reading {}", local.display()))?;
+                if len == 0 {
+                    break;
+                }
+                smol::block_on(dest.write_all(&buf[..len]))
+                    .with_context(|| format!("writing {}", temp))?;
+                done += len as u64;
+                self.progress(
+                    term,
+                    &mut last_update,
+                    format_progress("Uploading", &name, done, Some(size)),
+                )?;
+            }
+            smol::block_on(dest.close()).with_context(|| format!("closing {}", temp))?;
+            self.replace_remote(&temp, &remote)
+        })();
+
+        if let Err(err) = result {
+            // Don't leave a truncated file behind; the target is
+            // untouched as the temporary file never replaced it
+            drop(dest);
+            smol::block_on(self.sftp.remove_file(temp.clone())).ok();
+            return Err(err);
+        }
+
+        self.status = format!("Uploaded {} to {}", name, remote);
+        self.refresh();
+        Ok(())
+    }
+
+    /// Renames the uploaded file `temp` over `remote`
+    fn replace_remote(&self, temp: &Utf8PathBuf, remote: &Utf8PathBuf) -> anyhow::Result<()> {
+        let rename = |src: &Utf8PathBuf, dst: &Utf8PathBuf, overwrite: bool| {
+            let opts = RenameOptions {
+                overwrite,
+                atomic: overwrite,
+                native: false,
+            };
+            smol::block_on(self.sftp.rename(src.clone(), dst.clone(), opts))
+                .with_context(|| format!("renaming {} to {}", src, dst))
+        };
+
+        if rename(temp, remote, true).is_ok() {
+            return Ok(());
+        }
+
+        // Servers without posix-rename refuse to rename over an existing
+        // file, so move it aside first and put it back if that fails
+        let backup = remote.with_file_name(format!(
+            ".{}.wezterm-backup",
+            remote.file_name().unwrap_or_default()
+        ));
+        rename(remote, &backup, false)?;
+        if let Err(err) = rename(temp, remote, false) {
+            rename(&backup, remote, false).ok();
+            return Err(err);
+        }
+        smol::block_on(self.sftp.remove_file(backup)).ok();
+        Ok(())
+    }
+
+    fn upload_pending(&mut self, term: &mut TermWizTerminal) {
+        let uploads = std::mem::take(&mut self.pending_uploads);
+        for (idx, local) in uploads.iter().enumerate() {
+            if let Err(err) = self.upload(term, local) {
+                self.status = format!("{:#}", err);
+                // Keep the ones that we haven't done yet
+                self.pending_uploads = uploads[idx + 1..].to_vec();
+                return;
+            }
+        }
+    }
+
+    /// Enters the selected directory, or downloads the selected file
+    fn activate(&mut self, term: &mut TermWizTerminal) {
+        let (path, is_dir) = match self.entries.get(self.active_idx) {
+            Some(entry) if entry.meta.ty.is_symlink() => {
+                let is_dir = smol::block_on(self.sftp.metadata(entry.path.clone()))
+                    .map(|meta| meta.is_dir())
+                    .unwrap_or(false);
+                (entry.path.clone(), is_dir)
+            }
+            Some(entry) => (entry.path.clone(), entry.meta.ty.is_dir()),
+            None => return,
+        };
+
+        let result = if is_dir {
+            self.status.clear();
+            self.change_dir(path)
+        } else {
+            self.download(term, self.active_idx)
+        };
+        if let Err(err) = result {
+            self.status = format!("{:#}", err);
+        }
+    }
+
+    fn parent(&mut self) {
+        if let Some(parent) = self.cwd.parent().map(|p| p.to_path_buf()) {
+            self.status.clear();
+            if let Err(err) = self.change_dir(parent) {
+                self.status = format!("{:#}", err);
+            }
+        }
+    }
+
+    fn move_up(&mut self, amount: usize) {
+        self.active_idx = self.active_idx.saturating_sub(amount);
+        if self.active_idx < self.top_row {
+            self.top_row = self.active_idx;
+        }
+    }
+
+    fn move_down(&mut self, amount: usize) {
+        self.active_idx = (self.active_idx + amount).min(self.entries.len().saturating_sub(1));
+    }
+
+    /// Handles input while a local path is being typed in.
+    /// Returns true if the input was consumed.
+    fn handle_upload_input(&mut self, term: &mut TermWizTerminal, event: &InputEvent) -> bool {
+        let input = match self.upload_input.as_mut() {
+            Some(input) => input,
+            None => return false,
+        };
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            }) => {
+                self.upload_input.take();
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Backspace,
+                ..
+            }) => {
+                input.pop();
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Enter,
+                ..
+            }) => {
+                let path = expand_local_path(input.trim());
+                self.upload_input.take();
+                if let Err(err) = self.upload(term, &path) {
+                    self.status = format!("{:#}", err);
+                }
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(c),
+                modifiers: Modifiers::NONE | Modifiers::SHIFT,
+            }) => {
+                input.push(*c);
+            }
+            InputEvent::Paste(text) => {
+                input.push_str(text.trim_end_matches(|c| c == '\r' || c == '\n'));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        while let Ok(Some(event)) = term.poll_input(None) {
+            if self.handle_upload_input(term, &event) {
+                self.render(term)?;
+                continue;
+            }
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('q') | KeyCode::Escape,
+                    ..
+                }) => {
+                    break;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('k') | KeyCode::UpArrow,
+                    ..
+                }) => {
+                    self.move_up(1);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('j') | KeyCode::DownArrow,
+                    ..
+                }) => {
+                    self.move_down(1);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::PageUp,
+                    ..
+                }) => {
+                    self.move_up(self.max_items);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::PageDown,
+                    ..
+                }) => {
+                    self.move_down(self.max_items);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter | KeyCode::RightArrow | KeyCode::Char('l'),
+                    ..
+                }) => {
+                    self.activate(term);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace | KeyCode::LeftArrow | KeyCode::Char('h'),
+                    ..
+                }) => {
+                    self.parent();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('d'),
+                    ..
+                }) => {
+                    let is_file = self
+                        .entries
+                        .get(self.active_idx)
+                        .map(|entry| entry.meta.ty.is_file())
+                        .unwrap_or(false);
+                    if is_file {
+                        if let Err(err) = self.download(term, self.active_idx) {
+                            self.status = format!("{:#}", err);
+                        }
+                    } else {
+                        self.status = "Only files can be downloaded".to_string();
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('u'),
+                    ..
+                }) => {
+                    self.status.clear();
+                    self.upload_input.replace(String::new());
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('p'),
+                    ..
+                }) => {
+                    self.status.clear();
+                    self.upload_pending(term);
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('r'),
+                    ..
+                }) => {
+                    self.status.clear();
+                    self.refresh();
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Browses the remote filesystem via `sftp`, starting in `start_dir`
+/// or the home directory if that isn't known.  `uploads` are local
+/// files that were dropped onto the pane, which are uploaded once
+/// the user has navigated to the directory that they should go in.
+pub fn sftp_browser(
+    mut term: TermWizTerminal,
+    sftp: Sftp,
+    start_dir: Option<String>,
+    uploads: Vec<PathBuf>,
+) -> anyhow::Result<()> {
+    let mut browser = SftpBrowser {
+        sftp,
+        cwd: Utf8PathBuf::from("/"),
+        entries: vec![],
+        active_idx: 0,
+        top_row: 0,
+        max_items: 0,
+        status: String::new(),
+        pending_uploads: uploads,
+        upload_input: None,
+    };
+
+    term.set_raw_mode()?;
+    term.render(&[Change::Title("SFTP".to_string())])?;
+
+    let start_dir = match start_dir {
+        Some(dir) => Utf8PathBuf::from(dir),
+        None => smol::block_on(browser.sftp.canonicalize("."))
+            .context("resolving the remote home directory")?,
+    };
+    if let Err(err) = browser.change_dir(start_dir) {
+        // The cwd reported by the shell may not be accessible,
+        // so fall back to the home directory
+        browser.status = format!("{:#}", err);
+        let home = smol::block_on(browser.sftp.canonicalize("."))
+            .context("resolving the remote home directory")?;
+        browser.change_dir(home)?;
+    }
+
+    browser.render(&mut term)?;
+    browser.run_loop(&mut term)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+        assert_eq!(
+            format_progress("Uploading", "foo", 512, Some(2048)),
+            "Uploading foo: 512 B of 2.0 KiB (25%)"
+        );
+        assert_eq!(
+            format_progress("Downloading", "foo", 512, None),
+            "Downloading foo: 512 B"
+        );
+    }
+}
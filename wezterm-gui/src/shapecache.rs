@@ -4,11 +4,15 @@ use config::TextStyle;
 use std::rc::Rc;
 use wezterm_font::shaper::GlyphInfo;
 use wezterm_font::units::*;
+use wezterm_font::LoadedFontId;
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct ShapeCacheKey {
     pub style: TextStyle,
     pub text: String,
+    /// The font used for shaping, when it is not the one
+    /// resolved from `style`
+    pub font_id: Option<LoadedFontId>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -62,6 +66,7 @@ impl ShapedInfo {
 pub struct BorrowedShapeCacheKey<'a> {
     pub style: &'a TextStyle,
     pub text: &'a str,
+    pub font_id: Option<LoadedFontId>,
 }
 
 impl<'a> BorrowedShapeCacheKey<'a> {
@@ -69,6 +74,7 @@ impl<'a> BorrowedShapeCacheKey<'a> {
         ShapeCacheKey {
             style: self.style.clone(),
             text: self.text.to_owned(),
+            font_id: self.font_id,
         }
     }
 }
@@ -82,6 +88,7 @@ impl ShapeCacheKeyTrait for ShapeCacheKey {
         BorrowedShapeCacheKey {
             style: &self.style,
             text: &self.text,
+            font_id: self.font_id,
         }
     }
 }
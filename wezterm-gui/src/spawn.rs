@@ -15,6 +15,8 @@ pub enum SpawnWhere {
     NewWindow,
     NewTab,
     SplitPane(SplitRequest),
+    /// Float the new pane above the split tree of the active tab
+    Floating,
 }
 
 pub fn spawn_command_impl(
@@ -122,6 +124,25 @@ pub async fn spawn_command_internal(
                 bail!("there is no active tab while splitting pane!?");
             }
         }
+        SpawnWhere::Floating => {
+            let src_window_id = match src_window_id {
+                Some(id) => id,
+                None => anyhow::bail!("no src window when spawning a floating pane?"),
+            };
+            if let Some(tab) = mux.get_active_tab_for_window(src_window_id) {
+                let pane = tab
+                    .get_active_pane()
+                    .ok_or_else(|| anyhow!("tab to have a pane"))?;
+
+                let pane = mux
+                    .spawn_floating_pane(pane.pane_id(), cmd_builder, cwd, spawn.domain)
+                    .await
+                    .context("spawn_floating_pane")?;
+                pane.set_config(term_config);
+            } else {
+                bail!("there is no active tab while spawning a floating pane!?");
+            }
+        }
         _ => {
             let (_tab, pane, window_id) = mux
                 .spawn_tab_or_window(
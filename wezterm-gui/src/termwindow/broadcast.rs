@@ -0,0 +1,153 @@
+use config::keyassignment::InputBroadcastScope;
+use mux::pane::{Pane, PaneId};
+use mux::Mux;
+use std::io::Write;
+use std::sync::Arc;
+use wezterm_term::{KeyCode, KeyModifiers};
+
+impl crate::TermWindow {
+    /// Enables broadcasting of input to the panes selected by `scope`,
+    /// or disables it if that scope is already active
+    pub fn toggle_input_broadcast(&mut self, scope: InputBroadcastScope) {
+        if self.input_broadcast == Some(scope) {
+            self.input_broadcast = None;
+        } else {
+            self.input_broadcast.replace(scope);
+        }
+        log::info!("input broadcast is now {:?}", self.input_broadcast);
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// Adds or removes `pane_id` from the set of panes that receive
+    /// input when broadcasting to `MarkedPanes`
+    pub fn toggle_pane_broadcast_mark(&mut self, pane_id: PaneId) {
+        if !self.broadcast_marked_panes.remove(&pane_id) {
+            self.broadcast_marked_panes.insert(pane_id);
+        }
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// Returns true if `pane_id` is currently part of the broadcast
+    /// group, which includes the active pane that is the source of
+    /// the input.  Used to decide which panes get an outline.
+    pub fn is_input_broadcast_pane(&self, pane_id: PaneId) -> bool {
+        match self.input_broadcast {
+            None => false,
+            Some(InputBroadcastScope::MarkedPanes) => {
+                self.broadcast_marked_panes.contains(&pane_id)
+            }
+            // Every pane that we render belongs to the current tab,
+            // and therefore also to the current workspace
+            Some(InputBroadcastScope::CurrentTab | InputBroadcastScope::CurrentWorkspace) => true,
+        }
+    }
+
+    /// Returns the panes, other than `pane`, that should receive a copy
+    /// of the input that is sent to `pane`.  Input to overlays is
+    /// never broadcast.
+    pub fn input_broadcast_targets(&mut self, pane: &Arc<dyn Pane>) -> Vec<Arc<dyn Pane>> {
+        let scope = match self.input_broadcast {
+            Some(scope) => scope,
+            None => return vec![],
+        };
+
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return vec![],
+        };
+        if tab.get_active_pane().map(|active| active.pane_id()) != Some(pane.pane_id()) {
+            return vec![];
+        }
+
+        let source = pane.pane_id();
+        let panes: Vec<Arc<dyn Pane>> = match scope {
+            InputBroadcastScope::CurrentTab => tab
+                .iter_panes_ignoring_zoom()
+                .into_iter()
+                .map(|pos| pos.pane)
+                .collect(),
+            InputBroadcastScope::CurrentWorkspace => {
+                let workspace = match mux.get_window(self.mux_window_id) {
+                    Some(window) => window.get_workspace().to_string(),
+                    None => return vec![],
+                };
+                let mut panes = vec![];
+                for window_id in mux.iter_windows_in_workspace(&workspace) {
+                    if let Some(window) = mux.get_window(window_id) {
+                        for tab in window.iter() {
+                            panes.extend(
+                                tab.iter_panes_ignoring_zoom()
+                                    .into_iter()
+                                    .map(|pos| pos.pane),
+                            );
+                        }
+                    }
+                }
+                panes
+            }
+            InputBroadcastScope::MarkedPanes => {
+                if !self.broadcast_marked_panes.contains(&source) {
+                    return vec![];
+                }
+                // Forget about panes that have since been closed
+                self.broadcast_marked_panes
+                    .retain(|pane_id| mux.get_pane(*pane_id).is_some());
+                self.broadcast_marked_panes
+                    .iter()
+                    .filter_map(|pane_id| mux.get_pane(*pane_id))
+                    .collect()
+            }
+        };
+
+        panes
+            .into_iter()
+            .filter(|p| p.pane_id() != source && !p.is_dead())
+            .collect()
+    }
+
+    /// Mirrors a key press or release that was sent to `pane`
+    /// to the other panes in the broadcast group
+    pub fn broadcast_key(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        key: KeyCode,
+        mods: KeyModifiers,
+        is_down: bool,
+    ) {
+        for target in self.input_broadcast_targets(pane) {
+            let res = if is_down {
+                target.key_down(key, mods)
+            } else {
+                target.key_up(key, mods)
+            };
+            if let Err(err) = res {
+                log::error!("broadcast key to pane {}: {:#}", target.pane_id(), err);
+            }
+        }
+    }
+
+    /// Mirrors composed text that was sent to `pane` to the other
+    /// panes in the broadcast group
+    pub fn broadcast_text(&mut self, pane: &Arc<dyn Pane>, text: &str) {
+        for target in self.input_broadcast_targets(pane) {
+            if let Err(err) = target.writer().write_all(text.as_bytes()) {
+                log::error!("broadcast text to pane {}: {:#}", target.pane_id(), err);
+            }
+        }
+    }
+
+    /// Mirrors a paste into `pane` to the other panes in the
+    /// broadcast group
+    pub fn broadcast_paste(&mut self, pane: &Arc<dyn Pane>, text: &str) {
+        for target in self.input_broadcast_targets(pane) {
+            if let Err(err) = target.send_paste(text) {
+                log::error!("broadcast paste to pane {}: {:#}", target.pane_id(), err);
+            }
+        }
+    }
+}
@@ -1,11 +1,109 @@
+use crate::overlay::{confirm, start_overlay_pane};
 use crate::termwindow::TermWindowNotif;
 use crate::TermWindow;
 use config::keyassignment::{ClipboardCopyDestination, ClipboardPasteSource};
-use mux::pane::Pane;
+use config::ClipboardReadAccess;
+use mux::pane::{Pane, PaneId};
 use mux::Mux;
+use std::io::Write;
 use std::sync::Arc;
+use termwiz::escape::osc::{KittyClipboard, KittyClipboardType};
+use termwiz::escape::OperatingSystemCommand;
+use wezterm_term::ClipboardSelection;
 use window::{Clipboard, WindowOps};
 
+/// The largest amount of data to send in a single OSC 5522 response
+const CLIPBOARD_CHUNK_SIZE: usize = 3072;
+
+/// A request from an application in a pane to read the clipboard
+struct ClipboardReadRequest {
+    pane_id: PaneId,
+    selection: ClipboardSelection,
+    id: Option<String>,
+    mime_types: Vec<String>,
+}
+
+impl ClipboardReadRequest {
+    fn clipboard(&self) -> Clipboard {
+        match self.selection {
+            ClipboardSelection::Clipboard => Clipboard::Clipboard,
+            ClipboardSelection::PrimarySelection => Clipboard::PrimarySelection,
+        }
+    }
+
+    fn response(&self, status: &str) -> KittyClipboard {
+        KittyClipboard {
+            kind: KittyClipboardType::Read,
+            id: self.id.clone(),
+            primary: self.selection == ClipboardSelection::PrimarySelection,
+            status: Some(status.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn send(&self, responses: Vec<KittyClipboard>) {
+        let mux = Mux::get();
+        if let Some(pane) = mux.get_pane(self.pane_id) {
+            let mut writer = pane.writer();
+            for response in responses {
+                let response = OperatingSystemCommand::KittyClipboard(response);
+                if let Err(err) = write!(writer, "{}", response) {
+                    log::error!(
+                        "Failed to send clipboard to pane {}: {:#}",
+                        self.pane_id,
+                        err
+                    );
+                    return;
+                }
+            }
+        }
+    }
+
+    fn respond(&self, status: &str) {
+        self.send(vec![self.response(status)]);
+    }
+
+    /// Only plain text can be read from the windowing system, so
+    /// returns the first requested type that we can't provide
+    fn unsupported_mime_type(&self) -> Option<&str> {
+        self.mime_types
+            .iter()
+            .map(String::as_str)
+            .find(|&mime| mime != "." && !is_plain_text(mime))
+    }
+
+    /// Send the requested representations of the clipboard contents
+    fn send_text(&self, text: String) {
+        let mut responses = vec![self.response("OK")];
+        for mime in &self.mime_types {
+            let data = if mime == "." {
+                // List the available types
+                if text.is_empty() {
+                    continue;
+                }
+                b"text/plain".to_vec()
+            } else if is_plain_text(mime) {
+                text.as_bytes().to_vec()
+            } else {
+                continue;
+            };
+            for chunk in data.chunks(CLIPBOARD_CHUNK_SIZE) {
+                responses.push(KittyClipboard {
+                    mime: Some(mime.to_string()),
+                    payload: Some(chunk.to_vec()),
+                    ..self.response("DATA")
+                });
+            }
+        }
+        responses.push(self.response("DONE"));
+        self.send(responses);
+    }
+}
+
+fn is_plain_text(mime: &str) -> bool {
+    mime == "text/plain" || mime.starts_with("text/plain;")
+}
+
 impl TermWindow {
     pub fn copy_to_clipboard(&self, clipboard: ClipboardCopyDestination, text: String) {
         let clipboard = match clipboard {
@@ -50,6 +148,7 @@ impl TermWindow {
                         })
                     {
                         pane.send_paste(&clip).ok();
+                        myself.broadcast_paste(&pane, &clip);
                     }
                 })));
             }
@@ -57,4 +156,84 @@ impl TermWindow {
         .detach();
         self.maybe_scroll_to_bottom_for_input(&pane);
     }
+
+    /// Handle a request from an application to read the clipboard via
+    /// OSC 5522, prompting the user for permission if required
+    pub fn clipboard_read_request(
+        &mut self,
+        pane_id: PaneId,
+        selection: ClipboardSelection,
+        id: Option<String>,
+        mime_types: Vec<String>,
+    ) {
+        let mux = Mux::get();
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+        let domain_id = pane.domain_id();
+        let request = ClipboardReadRequest {
+            pane_id,
+            selection,
+            id,
+            mime_types,
+        };
+
+        if let Some(mime) = request.unsupported_mime_type() {
+            log::warn!("OSC 5522: cannot read {mime} from the clipboard");
+            request.respond("ENOSYS");
+            return;
+        }
+
+        match self.config.clipboard_read_access {
+            ClipboardReadAccess::Deny => request.respond("EPERM"),
+            ClipboardReadAccess::Allow => self.clipboard_read(request),
+            ClipboardReadAccess::Ask if self.clipboard_read_allowed.contains(&domain_id) => {
+                self.clipboard_read(request)
+            }
+            ClipboardReadAccess::Ask => {
+                let domain_name = mux
+                    .get_domain(domain_id)
+                    .map(|domain| domain.domain_name().to_string())
+                    .unwrap_or_else(|| format!("domain {domain_id}"));
+                let message =
+                    format!("📋 Allow programs running in {domain_name} to read the clipboard?");
+                let window = self.window.clone().unwrap();
+                let overlay_window = window.clone();
+                let (overlay, future) =
+                    start_overlay_pane(self, &pane, move |pane_id, mut term| {
+                        let allowed = confirm::run_confirmation(&message, &mut term)?;
+                        TermWindow::schedule_cancel_overlay_for_pane(overlay_window, pane_id);
+                        Ok(allowed)
+                    });
+                self.assign_overlay_for_pane(pane_id, overlay);
+                promise::spawn::spawn(async move {
+                    if let Ok(true) = future.await {
+                        window.notify(TermWindowNotif::Apply(Box::new(move |myself| {
+                            myself.clipboard_read_allowed.insert(domain_id);
+                            myself.clipboard_read(request);
+                        })));
+                    } else {
+                        request.respond("EPERM");
+                    }
+                })
+                .detach();
+            }
+        }
+    }
+
+    fn clipboard_read(&self, request: ClipboardReadRequest) {
+        let window = self.window.as_ref().unwrap().clone();
+        let future = window.get_clipboard(request.clipboard());
+        promise::spawn::spawn(async move {
+            match future.await {
+                Ok(text) => request.send_text(text),
+                Err(err) => {
+                    log::error!("Failed to read clipboard for OSC 5522: {:#}", err);
+                    request.respond("EIO");
+                }
+            }
+        })
+        .detach();
+    }
 }
@@ -402,6 +402,7 @@ impl super::TermWindow {
                     };
 
                     if did_encode {
+                        self.broadcast_key(&pane, term_key, tw_raw_modifiers, is_down);
                         if is_down
                             && !keycode.is_modifier()
                             && self.pane_state(pane.pane_id()).overlay.is_none()
@@ -703,6 +704,7 @@ impl super::TermWindow {
                 };
 
                 if res.is_ok() {
+                    self.broadcast_key(&pane, key, modifiers, window_key.key_is_down);
                     if window_key.key_is_down
                         && !key.is_modifier()
                         && self.pane_state(pane.pane_id()).overlay.is_none()
@@ -736,6 +738,7 @@ impl super::TermWindow {
                     log::info!("send to pane string={:?}", s);
                 }
                 pane.writer().write_all(s.as_bytes()).ok();
+                self.broadcast_text(&pane, &s);
                 self.maybe_scroll_to_bottom_for_input(&pane);
                 context.invalidate();
             }
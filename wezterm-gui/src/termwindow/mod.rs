@@ -30,23 +30,24 @@ use ::wezterm_term::input::{ClickPosition, MouseButton as TMB};
 use ::window::*;
 use anyhow::{anyhow, ensure, Context};
 use config::keyassignment::{
-    Confirmation, KeyAssignment, LauncherActionArgs, PaneDirection, Pattern, PromptInputLine,
-    QuickSelectArguments, RotationDirection, SpawnCommand, SplitSize,
+    Confirmation, InputBroadcastScope, KeyAssignment, LauncherActionArgs, PaneDirection, Pattern,
+    PromptInputLine, QuickSelectArguments, RotationDirection, SpawnCommand, SplitSize,
 };
 use config::window::WindowLevel;
 use config::{
     configuration, AudibleBell, ConfigHandle, Dimension, DimensionContext, FrontEndSelection,
-    GeometryOrigin, GuiPosition, TermConfig, WindowCloseConfirmation,
+    GeometryOrigin, GuiPosition, TabBarColors, TermConfig, WindowCloseConfirmation,
 };
 use lfucache::*;
 use mlua::{FromLua, LuaSerdeExt, UserData, UserDataFields};
+use mux::domain::DomainId;
 use mux::pane::{
     CachePolicy, CloseReason, Pane, PaneId, Pattern as MuxPattern, PerformAssignmentResult,
 };
 use mux::renderable::RenderableDimensions;
 use mux::tab::{
-    PositionedPane, PositionedSplit, SplitDirection, SplitRequest, SplitSize as MuxSplitSize, Tab,
-    TabId,
+    FloatingPaneOp, PositionedPane, PositionedSplit, SplitDirection, SplitRequest,
+    SplitSize as MuxSplitSize, Tab, TabId,
 };
 use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification};
@@ -54,7 +55,7 @@ use mux_lua::MuxPane;
 use smol::channel::Sender;
 use smol::Timer;
 use std::cell::{RefCell, RefMut};
-use std::collections::{HashMap, LinkedList};
+use std::collections::{HashMap, HashSet, LinkedList};
 use std::ops::Add;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -70,6 +71,7 @@ use wezterm_term::{Alert, Progress, StableRowIndex, TerminalConfiguration, Termi
 
 pub mod background;
 pub mod box_model;
+mod broadcast;
 pub mod charselect;
 pub mod clipboard;
 pub mod keyevent;
@@ -387,6 +389,11 @@ pub struct TermWindow {
     leader_is_down: Option<std::time::Instant>,
     dead_key_status: DeadKeyStatus,
     key_table_state: KeyTableState,
+    /// When set, input to the active pane is also sent to the
+    /// panes selected by this scope
+    input_broadcast: Option<InputBroadcastScope>,
+    /// The panes selected for `InputBroadcastScope::MarkedPanes`
+    broadcast_marked_panes: HashSet<PaneId>,
     show_tab_bar: bool,
     show_scroll_bar: bool,
     tab_bar: TabBarState,
@@ -444,6 +451,8 @@ pub struct TermWindow {
     modal: RefCell<Option<Rc<dyn Modal>>>,
 
     event_states: HashMap<String, EventState>,
+    /// Domains whose panes the user has allowed to read the clipboard
+    clipboard_read_allowed: HashSet<DomainId>,
     pub current_event: Option<Value>,
     has_animation: RefCell<Option<Instant>>,
     /// We use this to attempt to do something reasonable
@@ -776,6 +785,7 @@ impl TermWindow {
                 None,
             )),
             event_states: HashMap::new(),
+            clipboard_read_allowed: HashSet::new(),
             current_event: None,
             has_animation: RefCell::new(None),
             scheduled_animation: RefCell::new(None),
@@ -786,6 +796,8 @@ impl TermWindow {
             last_ui_item: None,
             is_click_to_focus_window: false,
             key_table_state: KeyTableState::default(),
+            input_broadcast: None,
+            broadcast_marked_panes: HashSet::new(),
             modal: RefCell::new(None),
             opengl_info: None,
         };
@@ -929,6 +941,8 @@ impl TermWindow {
                 // <https://github.com/wezterm/wezterm/issues/2295>
                 config::reload();
                 self.config_was_reloaded();
+                Mux::get()
+                    .set_color_scheme(crate::frontend::color_scheme_for_appearance(appearance));
                 Ok(true)
             }
             WindowEvent::PerformKeyAssignment(action) => {
@@ -1021,6 +1035,7 @@ impl TermWindow {
                     None => return Ok(true),
                 };
                 pane.send_paste(text.as_str())?;
+                self.broadcast_paste(&pane, text.as_str());
                 Ok(true)
             }
             WindowEvent::DroppedUrl(urls) => {
@@ -1035,6 +1050,7 @@ impl TermWindow {
                     .join(" ")
                     + " ";
                 pane.send_paste(urls.as_str())?;
+                self.broadcast_paste(&pane, urls.as_str());
                 Ok(true)
             }
             WindowEvent::DroppedFile(paths) => {
@@ -1042,6 +1058,12 @@ impl TermWindow {
                     Some(pane) => pane,
                     None => return Ok(true),
                 };
+                // Local paths are meaningless to a remote shell,
+                // so offer to upload the files instead
+                if mux::ssh::sftp_for_pane(&pane).is_some() {
+                    self.show_sftp_browser(paths);
+                    return Ok(true);
+                }
                 let paths = paths
                     .iter()
                     .map(|path| {
@@ -1053,6 +1075,7 @@ impl TermWindow {
                     .join(" ")
                     + " ";
                 pane.send_paste(&paths)?;
+                self.broadcast_paste(&pane, &paths);
                 Ok(true)
             }
             WindowEvent::DraggedFile(_) => Ok(true),
@@ -1223,6 +1246,8 @@ impl TermWindow {
                     // this overall invalidation for the palette
                     self.dispatch_notif(TermWindowNotif::InvalidateShapeCache, window)?;
                     self.mux_pane_output_event(pane_id);
+                    // The palette may also hold tab bar colors
+                    self.update_title();
                 }
                 MuxNotification::Alert {
                     alert: Alert::Bell,
@@ -1247,7 +1272,21 @@ impl TermWindow {
                     window.invalidate();
                 }
                 MuxNotification::Alert {
-                    alert: Alert::ToastNotification { .. },
+                    alert:
+                        Alert::ClipboardRead {
+                            selection,
+                            id,
+                            mime_types,
+                        },
+                    pane_id,
+                } => {
+                    if !self.window_contains_pane(pane_id) {
+                        return Ok(());
+                    }
+                    self.clipboard_read_request(pane_id, selection, id, mime_types);
+                }
+                MuxNotification::Alert {
+                    alert: Alert::ToastNotification { .. } | Alert::CloseToastNotification(_),
                     ..
                 } => {}
                 MuxNotification::TabAddedToWindow {
@@ -1291,12 +1330,17 @@ impl TermWindow {
                 MuxNotification::WindowRemoved(_window_id) => {
                     // Handled by frontend
                 }
-                MuxNotification::AssignClipboard { .. } => {
+                MuxNotification::AssignClipboard { .. }
+                | MuxNotification::AssignClipboardData { .. } => {
                     // Handled by frontend
                 }
-                MuxNotification::SaveToDownloads { .. } => {
+                MuxNotification::SaveToDownloads { .. }
+                | MuxNotification::SaveFileToDownloads { .. } => {
                     // Handled by frontend
                 }
+                MuxNotification::PaneLoggingStopped { .. } => {
+                    // The user is told via an Alert
+                }
                 MuxNotification::PaneFocused(_) => {
                     // Also handled by clientpane
                     self.update_title_post_status();
@@ -1462,6 +1506,7 @@ impl TermWindow {
                     | Alert::IconTitleChanged(_)
                     | Alert::Progress(_)
                     | Alert::SetUserVar { .. }
+                    | Alert::ClipboardRead { .. }
                     | Alert::Bell,
             }
             | MuxNotification::PaneFocused(pane_id)
@@ -1509,11 +1554,14 @@ impl TermWindow {
                 }
             }
             MuxNotification::Alert {
-                alert: Alert::ToastNotification { .. },
+                alert: Alert::ToastNotification { .. } | Alert::CloseToastNotification(_),
                 ..
             }
             | MuxNotification::AssignClipboard { .. }
+            | MuxNotification::AssignClipboardData { .. }
             | MuxNotification::SaveToDownloads { .. }
+            | MuxNotification::SaveFileToDownloads { .. }
+            | MuxNotification::PaneLoggingStopped { .. }
             | MuxNotification::WindowCreated(_)
             | MuxNotification::ActiveWorkspaceChanged(_)
             | MuxNotification::WorkspaceRenamed { .. }
@@ -1987,7 +2035,7 @@ impl TermWindow {
             },
             &tabs,
             &panes,
-            self.config.resolved_palette.tab_bar.as_ref(),
+            self.tab_bar_colors().as_ref(),
             &self.config,
             &self.left_status,
             &self.right_status,
@@ -2357,6 +2405,38 @@ impl TermWindow {
         promise::spawn::spawn(future).detach();
     }
 
+    /// Shows the sftp browser for the active pane, if it is an ssh
+    /// domain pane.  `uploads` are local files to be uploaded to the
+    /// remote directory that the user chooses.
+    fn show_sftp_browser(&mut self, uploads: Vec<std::path::PathBuf>) {
+        let pane = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        let sftp = match mux::ssh::sftp_for_pane(&pane) {
+            Some(sftp) => sftp,
+            None => {
+                log::error!(
+                    "ShowSftpBrowser: pane {} is not connected via ssh",
+                    pane.pane_id()
+                );
+                return;
+            }
+        };
+        let start_dir = mux::ssh::remote_working_dir(&pane);
+
+        let (overlay, future) = start_overlay_pane(self, &pane, move |_pane_id, term| {
+            crate::overlay::sftp::sftp_browser(term, sftp, start_dir, uploads)
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(async move {
+            if let Err(err) = future.await {
+                log::error!("sftp browser: {:#}", err);
+            }
+        })
+        .detach();
+    }
+
     fn show_tab_navigator(&mut self) {
         let mux = Mux::get();
         let active_tab_idx = match mux.get_window(self.mux_window_id) {
@@ -2382,7 +2462,8 @@ impl TermWindow {
                 | LauncherFlags::WORKSPACES
                 | LauncherFlags::DOMAINS
                 | LauncherFlags::KEY_ASSIGNMENTS
-                | LauncherFlags::COMMANDS,
+                | LauncherFlags::COMMANDS
+                | LauncherFlags::PORT_FORWARDS,
             help_text: None,
             fuzzy_help_text: None,
             alphabet: None,
@@ -3018,8 +3099,21 @@ impl TermWindow {
                 let switcher = crate::frontend::WorkspaceSwitcher::new(&name);
                 mux.set_active_workspace(&name);
 
+                let spawn = spawn.as_ref().map(|s| s.clone()).unwrap_or_default();
+                // A domain such as tmux may be able to populate the workspace
+                // itself, rather than us spawning a new window into it
+                let mut domain_workspace = false;
                 if mux.iter_windows_in_workspace(&name).is_empty() {
-                    let spawn = spawn.as_ref().map(|s| s.clone()).unwrap_or_default();
+                    let domain = mux
+                        .resolve_spawn_tab_domain(Some(pane.pane_id()), &spawn.domain)
+                        .ok();
+                    match mux.activate_domain_workspace(&name, domain.as_ref()) {
+                        Ok(activated) => domain_workspace = activated,
+                        Err(err) => log::error!("Failed to activate workspace {name}: {err:#}"),
+                    }
+                }
+
+                if mux.iter_windows_in_workspace(&name).is_empty() && !domain_workspace {
                     let size = self.terminal_size;
                     let term_config = Arc::new(TermConfig::with_config(self.config.clone()));
                     let src_window_id = self.mux_window_id;
@@ -3152,10 +3246,110 @@ impl TermWindow {
             PromptInputLine(args) => self.show_prompt_input_line(args),
             InputSelector(args) => self.show_input_selector(args),
             Confirmation(args) => self.show_confirmation(args),
+            ApplyLayout(path) => {
+                let activity = crate::Activity::new();
+                let path = std::path::PathBuf::from(path);
+                promise::spawn::spawn(async move {
+                    // Reading the file, and evaluating it if it is lua,
+                    // happens away from the main thread so that it
+                    // cannot stall the window
+                    let load_path = path.clone();
+                    let loaded = promise::spawn::spawn_into_new_thread(move || {
+                        mux::layout::Layout::load(&load_path)
+                    })
+                    .await;
+                    let result = match loaded {
+                        Ok(layout) => layout.apply().await.map(|_| ()),
+                        Err(err) => Err(err),
+                    };
+                    if let Err(err) = result {
+                        log::error!("Failed to apply layout {}: {:#}", path.display(), err);
+                    }
+                    drop(activity);
+                })
+                .detach();
+            }
+            StartPaneLogging(target) => {
+                if let Err(err) = pane.start_logging(target.clone()) {
+                    log::error!("Failed to log output of pane {}: {:#}", pane.pane_id(), err);
+                }
+            }
+            StopPaneLogging => {
+                if let Err(err) = pane.stop_logging() {
+                    log::error!(
+                        "Failed to stop logging output of pane {}: {:#}",
+                        pane.pane_id(),
+                        err
+                    );
+                }
+            }
+            ToggleInputBroadcast(scope) => {
+                self.toggle_input_broadcast(*scope);
+            }
+            TogglePaneBroadcastMark => {
+                self.toggle_pane_broadcast_mark(pane.pane_id());
+            }
+            SpawnFloatingPane(spawn) => {
+                self.spawn_command(spawn, SpawnWhere::Floating);
+            }
+            ToggleFloatingPanes => self.apply_floating_pane_op(FloatingPaneOp::Toggle),
+            MoveFloatingPane(direction, amount) => {
+                self.apply_floating_pane_op(FloatingPaneOp::Move(*direction, *amount))
+            }
+            ResizeFloatingPane(direction, amount) => {
+                self.apply_floating_pane_op(FloatingPaneOp::Resize(*direction, *amount))
+            }
+            PromoteFloatingPane(direction) => {
+                self.apply_floating_pane_op(FloatingPaneOp::Promote(*direction))
+            }
+            SetPortForwardEnabled {
+                domain,
+                forward_id,
+                enabled,
+            } => {
+                let domain = domain.clone();
+                let forward_id = *forward_id;
+                let enabled = *enabled;
+                promise::spawn::spawn(async move {
+                    if let Err(err) = Mux::get()
+                        .set_port_forward_enabled(&domain, forward_id, enabled)
+                        .await
+                    {
+                        log::error!(
+                            "Failed to change port forward {} of {}: {:#}",
+                            forward_id,
+                            domain,
+                            err
+                        );
+                    }
+                })
+                .detach();
+            }
+            ShowSftpBrowser => self.show_sftp_browser(vec![]),
         };
         Ok(PerformAssignmentResult::Handled)
     }
 
+    /// Applies `op` to the top-most floating pane of the active tab
+    fn apply_floating_pane_op(&self, op: FloatingPaneOp) {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+        if self.tab_state(tab.tab_id()).overlay.is_some() || tab.iter_floating_panes().is_empty() {
+            return;
+        }
+
+        let tab_id = tab.tab_id();
+        promise::spawn::spawn(async move {
+            if let Err(err) = Mux::get().apply_floating_pane_op(tab_id, op).await {
+                log::error!("Failed to apply {:?} to tab {}: {:#}", op, tab_id, err);
+            }
+        })
+        .detach();
+    }
+
     fn do_open_link_at_mouse_cursor(&self, pane: &Arc<dyn Pane>) {
         // They clicked on a link, so let's open it!
         // We need to ensure that we spawn the `open` call outside of the context
@@ -3376,6 +3570,21 @@ impl TermWindow {
             .and_then(|tab| tab.get_active_pane())
     }
 
+    /// The configured tab bar colors, adjusted by any colors that
+    /// the active pane has set via OSC 21
+    pub fn tab_bar_colors(&self) -> Option<TabBarColors> {
+        let colors = self.config.resolved_palette.tab_bar.as_ref();
+        match self.get_active_pane_or_overlay() {
+            Some(pane) => Some(
+                colors
+                    .cloned()
+                    .unwrap_or_default()
+                    .with_palette_overrides(&pane.palette()),
+            ),
+            None => colors.cloned(),
+        }
+    }
+
     /// Returns a Pane that we can interact with; this will typically be
     /// the active tab for the window, but if the window has a tab-wide
     /// overlay (such as the launcher / tab navigator),
@@ -3457,6 +3666,7 @@ impl TermWindow {
             .enumerate()
             .map(|(idx, tab)| {
                 let panes = self.get_pos_panes_for_tab(tab);
+                let floating_panes = tab.iter_floating_panes();
 
                 TabInformation {
                     tab_index: idx,
@@ -3470,6 +3680,7 @@ impl TermWindow {
                     tab_title: tab.get_title(),
                     active_pane: panes
                         .iter()
+                        .chain(floating_panes.iter())
                         .find(|p| p.is_active)
                         .map(Self::pos_pane_to_pane_info),
                 }
@@ -3527,6 +3738,27 @@ impl TermWindow {
         self.get_pos_panes_for_tab(&tab)
     }
 
+    /// Returns the floating panes of the active tab, bottom-most first,
+    /// or nothing if they are hidden or covered by a tab overlay
+    fn get_floating_panes_to_render(&self) -> Vec<PositionedPane> {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return vec![],
+        };
+        if !tab.floating_panes_visible() || self.tab_state(tab.tab_id()).overlay.is_some() {
+            return vec![];
+        }
+
+        let mut panes = tab.iter_floating_panes();
+        for p in &mut panes {
+            if let Some(overlay) = self.pane_state(p.pane.pane_id()).overlay.as_ref() {
+                p.pane = Arc::clone(&overlay.pane);
+            }
+        }
+        panes
+    }
+
     /// if pane_id.is_none(), removes any overlay for the specified tab.
     /// Otherwise: if the overlay is the specified pane for that tab, remove it.
     fn cancel_overlay_for_tab(&mut self, tab_id: TabId, pane_id: Option<PaneId>) {
@@ -9,7 +9,7 @@ use ::window::{
 use config::keyassignment::{KeyAssignment, MouseEventTrigger, SpawnTabDomain};
 use config::MouseEventAltScreen;
 use mux::pane::{Pane, WithPaneLines};
-use mux::tab::SplitDirection;
+use mux::tab::{PositionedPane, SplitDirection};
 use mux::Mux;
 use mux_lua::MuxPane;
 use std::convert::TryInto;
@@ -17,6 +17,7 @@ use std::ops::Sub;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::time::Duration;
+use termwiz::escape::osc::PointerShape;
 use termwiz::hyperlink::Hyperlink;
 use termwiz::surface::Line;
 use wezterm_dynamic::ToDynamic;
@@ -667,7 +668,31 @@ impl super::TermWindow {
             Some(MouseCapture::TerminalPane(_))
         );
 
-        for pos in self.get_panes_to_render() {
+        // While floating panes are shown, they obscure the split tree,
+        // so only they are eligible to receive the mouse, and the
+        // top-most of them takes precedence
+        let mut floating_panes = self.get_floating_panes_to_render();
+        floating_panes.reverse();
+        let is_floating = !floating_panes.is_empty();
+        let panes = if is_floating {
+            floating_panes
+        } else {
+            self.get_panes_to_render()
+        };
+        let activate = |pos: &PositionedPane| {
+            let mux = Mux::get();
+            if let Some(tab) = mux.get_active_tab_for_window(self.mux_window_id) {
+                if is_floating {
+                    if let Some(floating) = tab.iter_floating_panes().get(pos.index) {
+                        tab.set_active_pane(&floating.pane);
+                    }
+                } else {
+                    tab.set_active_idx(pos.index);
+                }
+            }
+        };
+
+        for pos in panes {
             if !is_already_captured
                 && row >= pos.top as i64
                 && row <= (pos.top + pos.height) as i64
@@ -678,18 +703,14 @@ impl super::TermWindow {
                     // We're over a pane that isn't active
                     match &event.kind {
                         WMEK::Press(_) => {
-                            let mux = Mux::get();
-                            mux.get_active_tab_for_window(self.mux_window_id)
-                                .map(|tab| tab.set_active_idx(pos.index));
+                            activate(&pos);
 
                             pane = Arc::clone(&pos.pane);
                             is_click_to_focus_pane = true;
                         }
                         WMEK::Move => {
                             if self.config.pane_focus_follows_mouse {
-                                let mux = Mux::get();
-                                mux.get_active_tab_for_window(self.mux_window_id)
-                                    .map(|tab| tab.set_active_idx(pos.index));
+                                activate(&pos);
 
                                 pane = Arc::clone(&pos.pane);
                                 context.invalidate();
@@ -837,7 +858,12 @@ impl super::TermWindow {
             // When hovering over a hyperlink, show an appropriate
             // mouse cursor to give the cue that it is clickable
             MouseCursor::Hand
-        } else if pane.is_mouse_grabbed() || outside_window {
+        } else if outside_window {
+            MouseCursor::Arrow
+        } else if let Some(shape) = pane.get_pointer_shape() {
+            // The application asked for a specific shape via OSC 22
+            pointer_shape_to_cursor(shape)
+        } else if pane.is_mouse_grabbed() {
             MouseCursor::Arrow
         } else {
             MouseCursor::Text
@@ -1044,6 +1070,41 @@ impl super::TermWindow {
     }
 }
 
+fn pointer_shape_to_cursor(shape: PointerShape) -> MouseCursor {
+    match shape {
+        PointerShape::Default => MouseCursor::Arrow,
+        PointerShape::Text => MouseCursor::Text,
+        PointerShape::Pointer => MouseCursor::Hand,
+        PointerShape::Help => MouseCursor::Help,
+        PointerShape::Wait => MouseCursor::Wait,
+        PointerShape::Progress => MouseCursor::Progress,
+        PointerShape::Crosshair => MouseCursor::Crosshair,
+        PointerShape::Cell => MouseCursor::Cell,
+        PointerShape::VerticalText => MouseCursor::VerticalText,
+        PointerShape::Move => MouseCursor::Move,
+        PointerShape::NResize => MouseCursor::SizeUp,
+        PointerShape::SResize => MouseCursor::SizeDown,
+        PointerShape::WResize => MouseCursor::SizeLeft,
+        PointerShape::EResize => MouseCursor::SizeRight,
+        PointerShape::NwResize => MouseCursor::SizeUpLeft,
+        PointerShape::NeResize => MouseCursor::SizeUpRight,
+        PointerShape::SwResize => MouseCursor::SizeDownLeft,
+        PointerShape::SeResize => MouseCursor::SizeDownRight,
+        PointerShape::EwResize => MouseCursor::SizeLeftRight,
+        PointerShape::NsResize => MouseCursor::SizeUpDown,
+        PointerShape::NeswResize => MouseCursor::SizeUpRightDownLeft,
+        PointerShape::NwseResize => MouseCursor::SizeUpLeftDownRight,
+        PointerShape::ZoomIn => MouseCursor::ZoomIn,
+        PointerShape::ZoomOut => MouseCursor::ZoomOut,
+        PointerShape::Alias => MouseCursor::Alias,
+        PointerShape::Copy => MouseCursor::Copy,
+        PointerShape::NotAllowed => MouseCursor::NotAllowed,
+        PointerShape::NoDrop => MouseCursor::NoDrop,
+        PointerShape::Grab => MouseCursor::Grab,
+        PointerShape::Grabbing => MouseCursor::Grabbing,
+    }
+}
+
 fn mouse_press_to_tmb(press: &MousePress) -> TMB {
     match press {
         MousePress::Left => TMB::Left,
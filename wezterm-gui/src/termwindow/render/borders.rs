@@ -1,7 +1,10 @@
 use crate::quad::TripleLayerQuadAllocator;
 use crate::utilsprites::RenderMetrics;
+use ::window::color::LinearRgba;
+use ::window::RectF;
 use ::window::ULength;
 use config::{ConfigHandle, DimensionContext};
+use mux::tab::PositionedPane;
 
 impl crate::TermWindow {
     pub fn paint_window_borders(
@@ -78,6 +81,87 @@ impl crate::TermWindow {
         Ok(())
     }
 
+    /// Outlines a pane that is taking part in input broadcasting
+    pub fn paint_input_broadcast_border(
+        &mut self,
+        layers: &mut TripleLayerQuadAllocator,
+        pos: &PositionedPane,
+    ) -> anyhow::Result<()> {
+        let color = self
+            .config
+            .resolved_palette
+            .input_broadcast_border
+            .map(|c| c.to_linear())
+            .unwrap_or_else(|| LinearRgba::with_srgba(0xff, 0x8c, 0x00, 0xff));
+        self.paint_pane_outline(layers, pos, color)
+    }
+
+    /// Fills in an opaque backdrop for a floating pane, so that the
+    /// panes beneath it don't show through, and outlines it using
+    /// the split color
+    pub fn paint_floating_pane_frame(
+        &mut self,
+        layers: &mut TripleLayerQuadAllocator,
+        pos: &PositionedPane,
+    ) -> anyhow::Result<()> {
+        let rect = self.pane_pixel_rect(pos)?;
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+        let palette = pos.pane.palette();
+        self.filled_rectangle(
+            layers,
+            0,
+            rect.inflate(cell_width / 2., cell_height / 2.),
+            palette.background.to_linear(),
+        )?;
+
+        self.paint_pane_outline(layers, pos, palette.split.to_linear())
+    }
+
+    /// Computes the pixel bounds of the cells of a pane
+    fn pane_pixel_rect(&mut self, pos: &PositionedPane) -> anyhow::Result<RectF> {
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+
+        let border = self.get_os_border();
+        let first_row_offset = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
+            self.tab_bar_pixel_height()?
+        } else {
+            0.
+        } + border.top.get() as f32;
+        let (padding_left, padding_top) = self.padding_left_top();
+
+        Ok(euclid::rect(
+            pos.left as f32 * cell_width + padding_left + border.left.get() as f32,
+            pos.top as f32 * cell_height + first_row_offset + padding_top,
+            pos.width as f32 * cell_width,
+            pos.height as f32 * cell_height,
+        ))
+    }
+
+    fn paint_pane_outline(
+        &mut self,
+        layers: &mut TripleLayerQuadAllocator,
+        pos: &PositionedPane,
+        color: LinearRgba,
+    ) -> anyhow::Result<()> {
+        let thickness = (self.render_metrics.underline_height as f32 * 2.).max(1.);
+        let rect = self.pane_pixel_rect(pos)?;
+        let (left, top) = (rect.min_x(), rect.min_y());
+        let (width, height) = (rect.width(), rect.height());
+
+        for rect in [
+            euclid::rect(left, top, width, thickness),
+            euclid::rect(left, top + height - thickness, width, thickness),
+            euclid::rect(left, top, thickness, height),
+            euclid::rect(left + width - thickness, top, thickness, height),
+        ] {
+            self.filled_rectangle(layers, 2, rect, color)?;
+        }
+
+        Ok(())
+    }
+
     pub fn get_os_border_impl(
         os_parameters: &Option<window::parameters::Parameters>,
         config: &ConfigHandle,
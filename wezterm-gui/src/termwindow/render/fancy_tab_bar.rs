@@ -60,13 +60,16 @@ impl crate::TermWindow {
         let font = self.fonts.title_font()?;
         let metrics = RenderMetrics::with_font_metrics(&font.metrics());
         let items = self.tab_bar.items();
-        let colors = self
+        let mut colors = self
             .config
             .colors
             .as_ref()
             .and_then(|c| c.tab_bar.as_ref())
             .cloned()
             .unwrap_or_else(TabBarColors::default);
+        if let Some(pane) = self.get_active_pane_or_overlay() {
+            colors = colors.with_palette_overrides(&pane.palette());
+        }
 
         let mut left_status = vec![];
         let mut left_eles = vec![];
@@ -790,6 +790,7 @@ impl crate::TermWindow {
         let key = BorrowedShapeCacheKey {
             style,
             text: &cluster.text,
+            font_id: font.map(|f| f.id()),
         };
         let glyph_info = match self.lookup_cached_shape(&key) {
             Some(Ok(info)) => info,
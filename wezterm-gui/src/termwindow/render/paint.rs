@@ -255,6 +255,10 @@ impl crate::TermWindow {
                 }
             }
             self.paint_pane(&pos, &mut layers).context("paint_pane")?;
+            if self.is_input_broadcast_pane(pos.pane.pane_id()) {
+                self.paint_input_broadcast_border(&mut layers, &pos)
+                    .context("paint_input_broadcast_border")?;
+            }
         }
 
         if let Some(pane) = self.get_active_pane_or_overlay() {
@@ -269,6 +273,33 @@ impl crate::TermWindow {
             self.paint_tab_bar(&mut layers).context("paint_tab_bar")?;
         }
 
+        let floating_panes = self.get_floating_panes_to_render();
+        if !floating_panes.is_empty() {
+            // Floating panes are layered above the split tree
+            let gl_state = self.render_state.as_ref().unwrap();
+            let layer = gl_state
+                .layer_for_zindex(5)
+                .context("layer_for_zindex(5)")?;
+            let mut floating_layers = layer.quad_allocator();
+            for pos in floating_panes {
+                if pos.is_active {
+                    self.update_text_cursor(&pos);
+                    if focused {
+                        pos.pane.advise_focus();
+                        mux::Mux::get().record_focus_for_current_identity(pos.pane.pane_id());
+                    }
+                }
+                self.paint_floating_pane_frame(&mut floating_layers, &pos)
+                    .context("paint_floating_pane_frame")?;
+                self.paint_pane(&pos, &mut floating_layers)
+                    .context("paint_pane")?;
+                if self.is_input_broadcast_pane(pos.pane.pane_id()) {
+                    self.paint_input_broadcast_border(&mut floating_layers, &pos)
+                        .context("paint_input_broadcast_border")?;
+                }
+            }
+        }
+
         self.paint_window_borders(&mut layers)
             .context("paint_window_borders")?;
         drop(layers);
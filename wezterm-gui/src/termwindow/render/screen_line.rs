@@ -11,8 +11,9 @@ use config::{HsbTransform, TextStyle};
 use std::ops::Range;
 use std::rc::Rc;
 use std::time::Instant;
-use termwiz::cell::{unicode_column_width, Blink};
+use termwiz::cell::{unicode_column_width, Blink, TextSizeHorizontalAlign, TextSizeVerticalAlign};
 use termwiz::color::LinearRgba;
+use termwiz::image::KITTY_PLACEHOLDER;
 use termwiz::surface::CursorShape;
 use wezterm_bidi::Direction;
 use wezterm_term::color::ColorAttribute;
@@ -444,6 +445,33 @@ impl crate::TermWindow {
                 }
             };
 
+            // Text sized via OSC 66 was shaped and rasterized with a
+            // font scaled to its size.  Only the top row of the block
+            // holds the text; the glyphs extend down over the blank
+            // filler cells in the rows beneath it.
+            let text_size = cluster.attrs.text_size().filter(|size| size.row == 0);
+            let text_scale = text_size.map(|size| size.font_scale() as f32).unwrap_or(1.);
+            let (text_x_adjust, text_y_adjust) = match text_size {
+                Some(size) => {
+                    let block_width = cluster.width as f32 * cell_width;
+                    let block_height = size.scale as f32 * cell_height;
+                    let text_width = item.pixel_width * width_scale;
+                    let text_height = cell_height * text_scale;
+                    let x = match size.horizontal_align {
+                        TextSizeHorizontalAlign::Left => 0.,
+                        TextSizeHorizontalAlign::Right => block_width - text_width,
+                        TextSizeHorizontalAlign::Center => (block_width - text_width) / 2.,
+                    };
+                    let y = match size.vertical_align {
+                        TextSizeVerticalAlign::Top => 0.,
+                        TextSizeVerticalAlign::Bottom => block_height - text_height,
+                        TextSizeVerticalAlign::Center => (block_height - text_height) / 2.,
+                    };
+                    (x.max(0.), y.max(0.))
+                }
+                None => (0., 0.),
+            };
+
             // TODO: remember logical/visual mapping for selection
             #[allow(unused_variables)]
             let mut phys_cell_idx = cluster.first_cell_idx;
@@ -458,6 +486,9 @@ impl crate::TermWindow {
                 };
             }
 
+            let cluster_start_x_pos = cluster_x_pos;
+            cluster_x_pos += text_x_adjust;
+
             for info in glyph_info.iter() {
                 let glyph = &info.glyph;
 
@@ -489,10 +520,12 @@ impl crate::TermWindow {
                     // First, resolve this glyph to a texture
                     let mut texture = glyph.texture.as_ref().cloned();
 
-                    let mut top = cell_height
-                        + (params.render_metrics.descender.get() as f32 + valign_adjust
+                    let mut top = cell_height * text_scale
+                        + ((params.render_metrics.descender.get() as f32 + valign_adjust)
+                            * text_scale
                             - (glyph.y_offset + glyph.bearing_y).get() as f32)
-                            * height_scale;
+                            * height_scale
+                        + text_y_adjust;
 
                     if self.config.custom_block_glyphs {
                         if let Some(block) = &info.block_key {
@@ -629,6 +662,11 @@ impl crate::TermWindow {
                                 // can cause a ghostly outline of the invisible glyph to appear.
                                 continue;
                             }
+                            if cluster.text.starts_with(KITTY_PLACEHOLDER) {
+                                // Kitty image placeholders are displayed by
+                                // the image attached to the cell
+                                continue;
+                            }
 
                             let pixel_rect = euclid::rect(
                                 texture.coords.origin.x + (range.start - (pos_x + adjust)) as isize,
@@ -676,13 +714,24 @@ impl crate::TermWindow {
                 }
                 phys_cell_idx += info.pos.num_cells as usize;
                 visual_cell_idx += info.pos.num_cells as usize;
-                cluster_x_pos += if params.use_pixel_positioning {
+                cluster_x_pos += if params.use_pixel_positioning || text_size.is_some() {
                     glyph.x_advance.get() as f32 * width_scale
                 } else {
                     info.pos.num_cells as f32 * cell_width
                 };
             }
 
+            if text_size.is_some() {
+                // Resume at the edge of the block, regardless of
+                // how far the scaled glyphs advanced
+                cluster_x_pos = match direction {
+                    Direction::LeftToRight => {
+                        cluster_start_x_pos + cluster.width as f32 * cell_width
+                    }
+                    Direction::RightToLeft => cluster_start_x_pos,
+                };
+            }
+
             match direction {
                 Direction::RightToLeft => {
                     // And decrement it again
@@ -855,11 +904,21 @@ impl crate::TermWindow {
 
             let style_params = last_style.as_ref().expect("we just set it up").clone();
 
+            // Text sized via OSC 66 is shaped and rasterized at its
+            // scaled size, rather than scaling up the regular glyphs
+            let scaled_font = match cluster.attrs.text_size() {
+                Some(size) if size.row == 0 && size.font_scale() != 1. => Some(
+                    self.fonts
+                        .resolve_scaled_font(style_params.style, size.font_scale())?,
+                ),
+                _ => None,
+            };
+
             let glyph_info = self.cached_cluster_shape(
                 style_params.style,
                 &cluster,
                 &gl_state,
-                None,
+                scaled_font.as_ref(),
                 &self.render_metrics,
             )?;
             let pixel_width = glyph_info
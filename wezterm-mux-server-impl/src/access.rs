@@ -0,0 +1,481 @@
+//! Decides which clients may use the mux server, and keeps an
+//! audit trail of what they did with it.
+use anyhow::Context;
+use config::ConfigHandle;
+use mux::pane::PaneId;
+use mux::{Mux, MuxNotification};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+lazy_static::lazy_static! {
+    static ref REVOKED: Mutex<RevokedClients> = Mutex::new(RevokedClients::default());
+    static ref AUDIT_WRITER: Mutex<Option<Sender<(PathBuf, String)>>> = Mutex::new(None);
+}
+
+/// How often a connection re-checks that its peer may still use the
+/// server.  Revoking or denying a client takes effect within this time.
+const ACCESS_RECHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A cached copy of the revocation list.  The list may be changed
+/// by any wezterm process that shares our data dir, so it is
+/// reloaded whenever the file is modified.
+#[derive(Default)]
+struct RevokedClients {
+    names: BTreeSet<String>,
+    modified: Option<SystemTime>,
+}
+
+/// Describes the peer on the other end of a mux connection
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "transport", rename_all = "lowercase")]
+pub enum PeerIdentity {
+    /// Connected via a unix domain socket, and thus running
+    /// with the same privileges as the server
+    Unix,
+    /// Authenticated by a TLS client certificate
+    Tls {
+        /// The client name embedded in the certificate, if any
+        client_name: Option<String>,
+        address: Option<SocketAddr>,
+    },
+}
+
+impl PeerIdentity {
+    pub fn client_name(&self) -> Option<&str> {
+        match self {
+            Self::Unix => None,
+            Self::Tls { client_name, .. } => client_name.as_deref(),
+        }
+    }
+
+    /// Returns true if the server configuration requires the peer
+    /// to be read-only, regardless of what it asked for
+    pub fn is_forced_read_only(&self) -> bool {
+        match self {
+            Self::Unix => false,
+            Self::Tls { client_name, .. } => is_read_only_client(
+                &config::configuration().mux_read_only_clients,
+                client_name.as_deref(),
+            ),
+        }
+    }
+
+    /// Checks that the peer is still permitted to use the server.
+    /// This is re-evaluated as the connection is used so that
+    /// revoking a client takes effect on its existing sessions.
+    pub fn check_access(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Unix => Ok(()),
+            Self::Tls { client_name, .. } => {
+                check_client_access(&config::configuration(), client_name.as_deref())
+            }
+        }
+    }
+}
+
+/// Re-checks the access of the peer of a connection as the connection
+/// is used.  That involves checking the revocation file, so rather than
+/// doing it for every PDU it is done at most every `ACCESS_RECHECK_INTERVAL`.
+pub struct AccessCheck {
+    last_checked: Instant,
+}
+
+/// The access of the peer was checked when it connected
+impl Default for AccessCheck {
+    fn default() -> Self {
+        Self {
+            last_checked: Instant::now(),
+        }
+    }
+}
+
+impl AccessCheck {
+    pub fn check(&mut self, peer: &PeerIdentity) -> anyhow::Result<()> {
+        if self.last_checked.elapsed() < ACCESS_RECHECK_INTERVAL {
+            return Ok(());
+        }
+        peer.check_access()?;
+        self.last_checked = Instant::now();
+        Ok(())
+    }
+}
+
+/// An entry in the audit log
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum AuditEvent {
+    Connect {
+        username: String,
+        hostname: String,
+        pid: u32,
+    },
+    Disconnect {
+        username: String,
+        hostname: String,
+        pid: u32,
+    },
+    Refused {
+        reason: String,
+    },
+    Spawn {
+        domain: String,
+        command: Option<Vec<String>>,
+        cwd: Option<String>,
+    },
+    ApplyLayout,
+    IssueCredentials {
+        client_name: Option<String>,
+    },
+    Revoke {
+        client_name: String,
+    },
+    Restore {
+        client_name: String,
+    },
+    PortForward {
+        domain: String,
+        forward_id: usize,
+        enabled: Option<bool>,
+    },
+    /// Both `path` and `command` are None when logging is stopped
+    PaneLogging {
+        pane_id: PaneId,
+        path: Option<PathBuf>,
+        command: Option<Vec<String>>,
+    },
+    /// Writing the log of a pane failed, so it is no longer logged
+    PaneLoggingStopped {
+        pane_id: PaneId,
+        reason: String,
+    },
+}
+
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    time: String,
+    /// None for events that were not requested by a peer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    peer: Option<&'a PeerIdentity>,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+}
+
+/// Appends an event to the audit log, if `mux_audit_log` is configured
+pub fn audit(peer: &PeerIdentity, event: AuditEvent) {
+    audit_impl(Some(peer), event)
+}
+
+/// Ensures that panes that stop being logged because writing
+/// their log failed are recorded in the audit log
+pub fn audit_pane_logging_failures() {
+    static SUBSCRIBE: std::sync::Once = std::sync::Once::new();
+    SUBSCRIBE.call_once(|| {
+        Mux::get().subscribe(|n| {
+            if let MuxNotification::PaneLoggingStopped { pane_id, reason } = n {
+                audit_impl(None, AuditEvent::PaneLoggingStopped { pane_id, reason });
+            }
+            true
+        });
+    });
+}
+
+fn audit_impl(peer: Option<&PeerIdentity>, event: AuditEvent) {
+    let config = config::configuration();
+    let path = match &config.mux_audit_log {
+        Some(path) => path,
+        None => return,
+    };
+    let record = AuditRecord {
+        time: chrono::Utc::now().to_rfc3339(),
+        peer,
+        event: &event,
+    };
+    let mut line = match serde_json::to_string(&record) {
+        Ok(line) => line,
+        Err(err) => {
+            log::error!("failed to serialize {event:?}: {err:#}");
+            return;
+        }
+    };
+    line.push('\n');
+    if let Err(err) = send_audit_line(path.clone(), line) {
+        log::error!("failed to write {event:?} to {}: {err:#}", path.display());
+    }
+}
+
+/// Queues a line for the audit writer thread, so that the main
+/// thread doesn't wait on the filesystem
+fn send_audit_line(path: PathBuf, line: String) -> anyhow::Result<()> {
+    let mut writer = AUDIT_WRITER.lock().unwrap();
+    if writer.is_none() {
+        let (sender, receiver) = channel::<(PathBuf, String)>();
+        std::thread::Builder::new()
+            .name("mux-audit-log".to_string())
+            .spawn(move || {
+                for (path, line) in receiver {
+                    if let Err(err) = append_audit_line(&path, &line) {
+                        log::error!(
+                            "failed to write audit record to {}: {err:#}",
+                            path.display()
+                        );
+                    }
+                }
+            })
+            .context("spawning audit log thread")?;
+        writer.replace(sender);
+    }
+    writer
+        .as_ref()
+        .unwrap()
+        .send((path, line))
+        .map_err(|_| anyhow::anyhow!("audit log thread has stopped"))
+}
+
+fn append_audit_line(path: &std::path::Path, line: &str) -> anyhow::Result<()> {
+    // The log records commands and client identities, so only
+    // the owner may read it
+    let mut options = std::fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+pub fn validate_client_name(name: &str) -> anyhow::Result<()> {
+    config::validate_tls_client_name(name).map_err(|err| anyhow::anyhow!(err))
+}
+
+pub fn revoked_clients_file() -> PathBuf {
+    config::DATA_DIR.join("mux-revoked-clients.json")
+}
+
+fn load_revoked_clients() -> BTreeSet<String> {
+    let path = revoked_clients_file();
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return BTreeSet::new(),
+        Err(err) => {
+            log::error!("reading {}: {err:#}", path.display());
+            return BTreeSet::new();
+        }
+    };
+    match serde_json::from_slice(&data) {
+        Ok(revoked) => revoked,
+        Err(err) => {
+            log::error!("parsing {}: {err:#}", path.display());
+            BTreeSet::new()
+        }
+    }
+}
+
+fn with_revoked_clients<R>(func: impl FnOnce(&mut BTreeSet<String>) -> R) -> R {
+    let mut revoked = REVOKED.lock().unwrap();
+    let modified = std::fs::metadata(revoked_clients_file())
+        .and_then(|meta| meta.modified())
+        .ok();
+    if modified != revoked.modified {
+        revoked.names = load_revoked_clients();
+        revoked.modified = modified;
+    }
+    func(&mut revoked.names)
+}
+
+pub fn is_client_revoked(name: &str) -> bool {
+    with_revoked_clients(|revoked| revoked.contains(name))
+}
+
+/// Adds or removes `name` from the revocation list, and saves
+/// it so that the revocation outlives the server process
+pub fn set_client_revoked(name: &str, revoke: bool) -> anyhow::Result<()> {
+    validate_client_name(name)?;
+    with_revoked_clients(|revoked| {
+        let changed = if revoke {
+            revoked.insert(name.to_string())
+        } else {
+            revoked.remove(name)
+        };
+        if !changed {
+            return Ok(());
+        }
+        config::create_user_owned_dirs(&config::DATA_DIR)?;
+        let path = revoked_clients_file();
+        let data = serde_json::to_vec_pretty(&*revoked)?;
+        std::fs::write(&path, data).with_context(|| format!("writing {}", path.display()))
+    })
+}
+
+/// Checks whether a TLS client presenting a certificate issued
+/// for `client_name` may use the server
+pub fn check_client_access(config: &ConfigHandle, client_name: Option<&str>) -> anyhow::Result<()> {
+    check_access_lists(
+        client_name,
+        &config.mux_allowed_clients,
+        &config.mux_denied_clients,
+        is_client_revoked,
+    )
+}
+
+/// Checks whether a TLS client presenting a certificate without a
+/// client name may obtain fresh credentials.  Such a client may ask
+/// for a certificate with any name, so once names are being denied
+/// or revoked this would be a way around that, and is refused.
+pub fn check_unnamed_client_may_issue(config: &ConfigHandle) -> anyhow::Result<()> {
+    check_unnamed_issue_lists(
+        &config.mux_denied_clients,
+        with_revoked_clients(|revoked| !revoked.is_empty()),
+    )
+}
+
+fn check_unnamed_issue_lists(denied: &[String], any_revoked: bool) -> anyhow::Result<()> {
+    if !denied.is_empty() || any_revoked {
+        anyhow::bail!(
+            "certificates without a client name cannot obtain credentials \
+             while mux_denied_clients or revoked clients are in use"
+        );
+    }
+    Ok(())
+}
+
+fn check_access_lists(
+    client_name: Option<&str>,
+    allowed: &[String],
+    denied: &[String],
+    is_revoked: impl Fn(&str) -> bool,
+) -> anyhow::Result<()> {
+    let name = match client_name {
+        Some(name) => name,
+        None if allowed.is_empty() => return Ok(()),
+        None => anyhow::bail!(
+            "certificates without a client name are refused because mux_allowed_clients is set"
+        ),
+    };
+    if denied.iter().any(|n| n == name) {
+        anyhow::bail!("client `{name}` is listed in mux_denied_clients");
+    }
+    if is_revoked(name) {
+        anyhow::bail!("client `{name}` has been revoked");
+    }
+    if !allowed.is_empty() && !allowed.iter().any(|n| n == name) {
+        anyhow::bail!("client `{name}` is not listed in mux_allowed_clients");
+    }
+    Ok(())
+}
+
+fn is_read_only_client(read_only: &[String], client_name: Option<&str>) -> bool {
+    read_only
+        .iter()
+        .any(|n| n == "*" || Some(n.as_str()) == client_name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn access_lists() {
+        let not_revoked = |_: &str| false;
+
+        // No lists: everyone is permitted
+        assert!(check_access_lists(None, &[], &[], not_revoked).is_ok());
+        assert!(check_access_lists(Some("alice"), &[], &[], not_revoked).is_ok());
+
+        let allowed = names(&["alice", "bob"]);
+        assert!(check_access_lists(Some("alice"), &allowed, &[], not_revoked).is_ok());
+        assert!(check_access_lists(Some("carol"), &allowed, &[], not_revoked).is_err());
+        assert!(check_access_lists(None, &allowed, &[], not_revoked).is_err());
+
+        // Denial takes precedence over being allowed
+        let denied = names(&["bob"]);
+        assert!(check_access_lists(Some("bob"), &allowed, &denied, not_revoked).is_err());
+        assert!(check_access_lists(Some("bob"), &[], &denied, not_revoked).is_err());
+        assert!(check_access_lists(None, &[], &denied, not_revoked).is_ok());
+
+        let revoked = |name: &str| name == "alice";
+        assert!(check_access_lists(Some("alice"), &allowed, &[], revoked).is_err());
+        assert!(check_access_lists(Some("bob"), &allowed, &[], revoked).is_ok());
+    }
+
+    #[test]
+    fn unnamed_clients_issuing() {
+        assert!(check_unnamed_issue_lists(&[], false).is_ok());
+        assert!(check_unnamed_issue_lists(&names(&["bob"]), false).is_err());
+        assert!(check_unnamed_issue_lists(&[], true).is_err());
+    }
+
+    #[test]
+    fn read_only_clients() {
+        assert!(!is_read_only_client(&[], Some("alice")));
+        assert!(!is_read_only_client(&[], None));
+
+        let read_only = names(&["alice"]);
+        assert!(is_read_only_client(&read_only, Some("alice")));
+        assert!(!is_read_only_client(&read_only, Some("bob")));
+        assert!(!is_read_only_client(&read_only, None));
+
+        // A wildcard also covers certificates without a client name
+        let read_only = names(&["*"]);
+        assert!(is_read_only_client(&read_only, Some("bob")));
+        assert!(is_read_only_client(&read_only, None));
+    }
+
+    #[test]
+    fn client_names() {
+        assert!(validate_client_name("laptop-1").is_ok());
+        assert!(validate_client_name("alice@example.com").is_ok());
+        assert!(validate_client_name("").is_err());
+        assert!(validate_client_name("alice/bob").is_err());
+        assert!(validate_client_name("a b").is_err());
+        assert!(validate_client_name("$(id)").is_err());
+    }
+
+    #[test]
+    fn audit_record_format() {
+        let peer = PeerIdentity::Tls {
+            client_name: Some("alice".to_string()),
+            address: None,
+        };
+        let event = AuditEvent::Revoke {
+            client_name: "bob".to_string(),
+        };
+        let record = AuditRecord {
+            time: "2023-01-01T00:00:00+00:00".to_string(),
+            peer: Some(&peer),
+            event: &event,
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"time":"2023-01-01T00:00:00+00:00","peer":{"transport":"tls","client_name":"alice","address":null},"event":"revoke","client_name":"bob"}"#
+        );
+
+        let event = AuditEvent::PaneLoggingStopped {
+            pane_id: 3,
+            reason: "No space left on device".to_string(),
+        };
+        let record = AuditRecord {
+            time: "2023-01-01T00:00:00+00:00".to_string(),
+            peer: None,
+            event: &event,
+        };
+        assert_eq!(
+            serde_json::to_string(&record).unwrap(),
+            r#"{"time":"2023-01-01T00:00:00+00:00","event":"pane-logging-stopped","pane_id":3,"reason":"No space left on device"}"#
+        );
+    }
+}
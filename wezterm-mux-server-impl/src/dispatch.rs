@@ -1,3 +1,4 @@
+use crate::access::{audit, AccessCheck, AuditEvent, PeerIdentity};
 use crate::sessionhandler::{PduSender, SessionHandler};
 use anyhow::Context;
 use async_ossl::AsyncSslStream;
@@ -23,7 +24,7 @@ enum Item {
     Readable,
 }
 
-pub async fn process<T>(stream: T) -> anyhow::Result<()>
+pub async fn process<T>(stream: T, peer: PeerIdentity) -> anyhow::Result<()>
 where
     T: 'static,
     T: std::io::Read,
@@ -33,10 +34,10 @@ where
     T: async_io::IoSafe,
 {
     let stream = smol::Async::new(stream)?;
-    process_async(stream).await
+    process_async(stream, peer).await
 }
 
-pub async fn process_async<T>(mut stream: Async<T>) -> anyhow::Result<()>
+pub async fn process_async<T>(mut stream: Async<T>, peer: PeerIdentity) -> anyhow::Result<()>
 where
     T: 'static,
     T: std::io::Read,
@@ -56,7 +57,7 @@ where
                 .map_err(|e| anyhow::anyhow!("{:?}", e))
         }
     });
-    let mut handler = SessionHandler::new(pdu_sender);
+    let mut handler = SessionHandler::new(pdu_sender, peer.clone());
 
     {
         let mux = Mux::get();
@@ -64,12 +65,25 @@ where
         mux.subscribe(move |n| tx.try_send(Item::Notif(n)).is_ok());
     }
 
+    let mut access = AccessCheck::default();
+
     loop {
         let rx_msg = item_rx.recv();
         let wait_for_read = stream.readable().map(|_| Ok(Item::Readable));
 
         match smol::future::or(rx_msg, wait_for_read).await {
             Ok(Item::Readable) => {
+                if let Err(err) = access.check(&peer) {
+                    // The client was revoked or denied since it connected
+                    log::warn!("disconnecting {peer:?}: {err:#}");
+                    audit(
+                        &peer,
+                        AuditEvent::Refused {
+                            reason: format!("{err:#}"),
+                        },
+                    );
+                    return Ok(());
+                }
                 let decoded = match Pdu::decode_async(&mut stream, None).await {
                     Ok(data) => data,
                     Err(err) => {
@@ -127,6 +141,8 @@ where
                 handler.schedule_pane_push(pane_id);
             }
             Ok(Item::Notif(MuxNotification::SaveToDownloads { .. })) => {}
+            Ok(Item::Notif(MuxNotification::SaveFileToDownloads { .. })) => {}
+            Ok(Item::Notif(MuxNotification::PaneLoggingStopped { .. })) => {}
             Ok(Item::Notif(MuxNotification::AssignClipboard {
                 pane_id,
                 selection,
@@ -141,6 +157,20 @@ where
                 .await?;
                 stream.flush().await.context("flushing PDU to client")?;
             }
+            Ok(Item::Notif(MuxNotification::AssignClipboardData {
+                pane_id,
+                selection,
+                items,
+            })) => {
+                Pdu::SetClipboardData(codec::SetClipboardData {
+                    pane_id,
+                    items,
+                    selection,
+                })
+                .encode_async(&mut stream, 0)
+                .await?;
+                stream.flush().await.context("flushing PDU to client")?;
+            }
             Ok(Item::Notif(MuxNotification::TabAddedToWindow { tab_id, window_id })) => {
                 Pdu::TabAddedToWindow(codec::TabAddedToWindow { tab_id, window_id })
                     .encode_async(&mut stream, 0)
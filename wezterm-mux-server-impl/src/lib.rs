@@ -5,8 +5,10 @@ use mux::Mux;
 use std::sync::Arc;
 use wezterm_client::domain::{ClientDomain, ClientDomainConfig};
 
+pub mod access;
 pub mod dispatch;
 pub mod local;
+pub mod persist;
 pub mod pki;
 pub mod sessionhandler;
 
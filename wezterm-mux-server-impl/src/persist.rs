@@ -0,0 +1,530 @@
+//! Saves the window/tab/pane layout of the mux server to disk so
+//! that it can be rebuilt when the server is next started.
+use anyhow::Context;
+use config::keyassignment::SpawnTabDomain;
+use config::ConfigHandle;
+use mux::domain::{DomainId, SplitSource};
+use mux::layout::{split_percent, url_to_path};
+use mux::pane::{CachePolicy, Pane};
+use mux::tab::{PaneNode, SplitDirection, SplitRequest, SplitSize, Tab};
+use mux::{Mux, MuxNotification};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use termwiz::escape::parser::Parser;
+use wezterm_term::TerminalSize;
+
+/// Bump this when making incompatible changes to the snapshot format
+const SESSION_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SessionSnapshot {
+    pub version: u32,
+    pub windows: Vec<WindowSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WindowSnapshot {
+    pub workspace: String,
+    pub title: String,
+    pub active_tab: usize,
+    pub tabs: Vec<TabSnapshot>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TabSnapshot {
+    pub title: String,
+    pub size: TerminalSize,
+    pub layout: PaneLayout,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum PaneLayout {
+    Split {
+        direction: SplitDirection,
+        /// The size of each side, in cells, along the split axis
+        first_cells: usize,
+        second_cells: usize,
+        first: Box<PaneLayout>,
+        second: Box<PaneLayout>,
+    },
+    Pane(PaneSnapshot),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PaneSnapshot {
+    pub cwd: Option<String>,
+    /// The argv of the foreground process at the time of the snapshot
+    pub foreground_process: Option<Vec<String>>,
+    pub is_active: bool,
+    pub is_zoomed: bool,
+    /// The tail of the scrollback, encoded as escape sequences
+    pub scrollback: Option<String>,
+}
+
+impl PaneLayout {
+    /// Returns the top/left-most pane in this portion of the layout
+    fn first_pane(&self) -> &PaneSnapshot {
+        match self {
+            Self::Split { first, .. } => first.first_pane(),
+            Self::Pane(pane) => pane,
+        }
+    }
+}
+
+pub fn session_file() -> PathBuf {
+    config::DATA_DIR.join("mux-session.json")
+}
+
+fn pane_cwd(pane: &Arc<dyn Pane>) -> Option<String> {
+    let url = pane.get_current_working_dir(CachePolicy::AllowStale)?;
+    url_to_path(&url)
+}
+
+fn pane_scrollback(pane: &Arc<dyn Pane>, max_lines: usize) -> Option<String> {
+    if max_lines == 0 || pane.is_alt_screen_active() {
+        return None;
+    }
+    let dims = pane.get_dimensions();
+    let end = pane.get_cursor_position().y + 1;
+    let start = (end - max_lines as isize).max(dims.scrollback_top);
+    let (_first_row, lines) = pane.get_lines(start..end);
+    match termwiz_funcs::lines_to_escapes(lines) {
+        Ok(text) => Some(text),
+        Err(err) => {
+            log::warn!(
+                "failed to save scrollback of pane {}: {err:#}",
+                pane.pane_id()
+            );
+            None
+        }
+    }
+}
+
+fn snapshot_node(
+    mux: &Mux,
+    domain_id: DomainId,
+    node: &PaneNode,
+    scrollback_lines: usize,
+) -> Option<PaneLayout> {
+    match node {
+        PaneNode::Empty => None,
+        // Floating panes are transient, so only the split tree is persisted
+        PaneNode::Floating { tree, .. } => snapshot_node(mux, domain_id, tree, scrollback_lines),
+        PaneNode::Split { left, right, node } => {
+            let (first_cells, second_cells) = match node.direction {
+                SplitDirection::Horizontal => (node.first.cols, node.second.cols),
+                SplitDirection::Vertical => (node.first.rows, node.second.rows),
+            };
+            Some(PaneLayout::Split {
+                direction: node.direction,
+                first_cells,
+                second_cells,
+                first: Box::new(snapshot_node(mux, domain_id, left, scrollback_lines)?),
+                second: Box::new(snapshot_node(mux, domain_id, right, scrollback_lines)?),
+            })
+        }
+        PaneNode::Leaf(entry) => {
+            let pane = mux.get_pane(entry.pane_id)?;
+            // Panes from other domains (eg: ssh) can't be respawned
+            // locally, so we don't attempt to persist them
+            if pane.domain_id() != domain_id {
+                return None;
+            }
+            Some(PaneLayout::Pane(PaneSnapshot {
+                cwd: pane_cwd(&pane),
+                foreground_process: pane
+                    .get_foreground_process_info(CachePolicy::AllowStale)
+                    .map(|info| info.argv)
+                    .filter(|argv| !argv.is_empty()),
+                is_active: entry.is_active_pane,
+                is_zoomed: entry.is_zoomed_pane,
+                scrollback: pane_scrollback(&pane, scrollback_lines),
+            }))
+        }
+    }
+}
+
+/// Captures the layout of the panes in the default domain.
+/// Tabs that contain panes from other domains are skipped.
+pub fn snapshot(config: &ConfigHandle) -> SessionSnapshot {
+    let mux = Mux::get();
+    let domain_id = mux.default_domain().domain_id();
+    let mut windows = vec![];
+
+    for window_id in mux.iter_windows() {
+        let (workspace, title, active_idx, tabs) = match mux.get_window(window_id) {
+            Some(window) => (
+                window.get_workspace().to_string(),
+                window.get_title().to_string(),
+                window.get_active_idx(),
+                window.iter().cloned().collect::<Vec<Arc<Tab>>>(),
+            ),
+            None => continue,
+        };
+
+        let mut snapshot = WindowSnapshot {
+            workspace,
+            title,
+            active_tab: 0,
+            tabs: vec![],
+        };
+        for (idx, tab) in tabs.iter().enumerate() {
+            let layout = match snapshot_node(
+                &mux,
+                domain_id,
+                &tab.codec_pane_tree(),
+                config.mux_session_scrollback_lines,
+            ) {
+                Some(layout) => layout,
+                None => continue,
+            };
+            if idx == active_idx {
+                snapshot.active_tab = snapshot.tabs.len();
+            }
+            snapshot.tabs.push(TabSnapshot {
+                title: tab.get_title(),
+                size: tab.get_size(),
+                layout,
+            });
+        }
+
+        if !snapshot.tabs.is_empty() {
+            windows.push(snapshot);
+        }
+    }
+
+    SessionSnapshot {
+        version: SESSION_FORMAT_VERSION,
+        windows,
+    }
+}
+
+fn remove_file_if_exists(path: &std::path::Path) -> anyhow::Result<()> {
+    match std::fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("removing {}", path.display())),
+    }
+}
+
+/// Writes a serialized snapshot to the session file
+fn write_snapshot(data: &[u8]) -> anyhow::Result<()> {
+    config::create_user_owned_dirs(&config::DATA_DIR)?;
+    let path = session_file();
+    let temp = path.with_extension("json.tmp");
+
+    // The snapshot includes scrollback, so only the owner may read it.
+    // The mode only applies when the file is created, so start afresh
+    // rather than reusing a temporary file left behind by a crash.
+    remove_file_if_exists(&temp)?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options
+        .open(&temp)
+        .and_then(|mut file| file.write_all(data))
+        .with_context(|| format!("writing {}", temp.display()))?;
+
+    // Rename into place so that a crash while writing doesn't
+    // leave us with a truncated snapshot
+    std::fs::rename(&temp, &path)
+        .with_context(|| format!("renaming {} -> {}", temp.display(), path.display()))?;
+    Ok(())
+}
+
+/// A request for the thread that writes the session file
+enum SnapshotFileRequest {
+    /// Replace the session file with this serialized snapshot
+    Save(Vec<u8>),
+    /// There is nothing to restore; remove the session file
+    Remove,
+}
+
+/// Spawns the thread that writes the session file, so that the disk
+/// I/O doesn't stall the main thread. Requests are processed in order.
+fn spawn_snapshot_writer() -> Sender<SnapshotFileRequest> {
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        for request in rx {
+            let result = match request {
+                SnapshotFileRequest::Save(data) => write_snapshot(&data),
+                SnapshotFileRequest::Remove => remove_file_if_exists(&session_file()),
+            };
+            if let Err(err) = result {
+                log::error!("failed to save mux session: {err:#}");
+            }
+        }
+    });
+    tx
+}
+
+pub fn load_snapshot() -> anyhow::Result<Option<SessionSnapshot>> {
+    let path = session_file();
+    let data = match std::fs::read(&path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+    let snapshot: SessionSnapshot =
+        serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))?;
+    if snapshot.version != SESSION_FORMAT_VERSION {
+        log::warn!(
+            "ignoring {} as it has version {} but we expected {}",
+            path.display(),
+            snapshot.version,
+            SESSION_FORMAT_VERSION
+        );
+        return Ok(None);
+    }
+    Ok(Some(snapshot))
+}
+
+fn restore_pane_state(config: &ConfigHandle, pane: &Arc<dyn Pane>, snapshot: &PaneSnapshot) {
+    if let Some(scrollback) = &snapshot.scrollback {
+        let mut parser = Parser::new();
+        pane.perform_actions(parser.parse_as_vec(scrollback.as_bytes()));
+    }
+
+    // Only re-run commands that the user has explicitly allowed;
+    // blindly re-running whatever was in the foreground could have
+    // unwanted side effects
+    if let Some(argv) = &snapshot.foreground_process {
+        let name = argv.first().and_then(|prog| {
+            std::path::Path::new(prog)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        });
+        if let Some(name) = name {
+            if config.mux_session_restore_processes.contains(&name) {
+                let command = format!("{}\r", shell_words::join(argv));
+                if let Err(err) = pane.writer().write_all(command.as_bytes()) {
+                    log::error!("failed to restore `{command}` in pane: {err:#}");
+                }
+            }
+        }
+    }
+}
+
+async fn restore_tab(
+    config: &ConfigHandle,
+    window_id: mux::window::WindowId,
+    snapshot: &TabSnapshot,
+) -> anyhow::Result<Arc<Tab>> {
+    let mux = Mux::get();
+    let domain = mux.default_domain();
+
+    let tab = domain
+        .spawn(
+            snapshot.size,
+            None,
+            snapshot.layout.first_pane().cwd.clone(),
+            window_id,
+        )
+        .await?;
+    if !snapshot.title.is_empty() {
+        tab.set_title(&snapshot.title);
+    }
+    let root = tab
+        .get_active_pane()
+        .ok_or_else(|| anyhow::anyhow!("newly spawned tab has no pane"))?;
+
+    let mut active = None;
+    let mut zoomed = false;
+    let mut stack = vec![(&snapshot.layout, root)];
+
+    while let Some((layout, pane)) = stack.pop() {
+        match layout {
+            PaneLayout::Split {
+                direction,
+                first_cells,
+                second_cells,
+                first,
+                second,
+            } => {
+                // Give the second half the same proportion of the space
+                // that it had when the snapshot was taken, even if the tab
+                // size has changed. The split divider occupies a cell.
+                let percent = split_percent(*second_cells, first_cells + second_cells + 1);
+                let (new_pane, _size) = mux
+                    .split_pane(
+                        pane.pane_id(),
+                        SplitRequest {
+                            direction: *direction,
+                            target_is_second: true,
+                            top_level: false,
+                            size: SplitSize::Percent(percent),
+                        },
+                        SplitSource::Spawn {
+                            command: None,
+                            command_dir: second.first_pane().cwd.clone(),
+                        },
+                        SpawnTabDomain::CurrentPaneDomain,
+                    )
+                    .await?;
+                stack.push((second, new_pane));
+                stack.push((first, pane));
+            }
+            PaneLayout::Pane(pane_snapshot) => {
+                restore_pane_state(config, &pane, pane_snapshot);
+                if pane_snapshot.is_active {
+                    zoomed = pane_snapshot.is_zoomed;
+                    active = Some(pane);
+                }
+            }
+        }
+    }
+
+    if let Some(pane) = active {
+        tab.set_active_pane(&pane);
+        if zoomed {
+            tab.set_zoomed(true);
+        }
+    }
+
+    Ok(tab)
+}
+
+/// Rebuilds the windows, tabs and panes described by the saved
+/// session, if any.  Returns true if anything was restored.
+pub async fn restore_session(config: &ConfigHandle) -> anyhow::Result<bool> {
+    let snapshot = match load_snapshot()? {
+        Some(snapshot) => snapshot,
+        None => return Ok(false),
+    };
+    let mux = Mux::get();
+    let mut restored = false;
+
+    for window_snapshot in &snapshot.windows {
+        let window_id = *mux.new_empty_window(Some(window_snapshot.workspace.clone()), None);
+
+        for tab_snapshot in &window_snapshot.tabs {
+            match restore_tab(config, window_id, tab_snapshot).await {
+                Ok(_) => restored = true,
+                Err(err) => log::error!("failed to restore tab: {err:#}"),
+            }
+        }
+
+        if let Some(mut window) = mux.get_window_mut(window_id) {
+            if !window_snapshot.title.is_empty() {
+                window.set_title(&window_snapshot.title);
+            }
+            if window_snapshot.active_tab < window.len() {
+                window.set_active_without_saving(window_snapshot.active_tab);
+            }
+        }
+    }
+
+    Ok(restored)
+}
+
+/// Periodically saves the session while `mux_session_persistence`
+/// is enabled.  The configuration is re-evaluated on each iteration
+/// so that changes take effect without restarting the server.
+pub async fn run_session_saver() {
+    let writer = spawn_snapshot_writer();
+
+    // Once the last window has been closed there is nothing to restore,
+    // and the previous snapshot would bring back what the user closed
+    {
+        let writer = writer.clone();
+        Mux::get().subscribe(move |n| {
+            if matches!(n, MuxNotification::Empty)
+                && config::configuration().mux_session_persistence
+            {
+                return writer.send(SnapshotFileRequest::Remove).is_ok();
+            }
+            true
+        });
+    }
+
+    loop {
+        let config = config::configuration();
+        let interval = Duration::from_millis(config.mux_session_save_interval_ms.max(1000));
+        smol::Timer::after(interval).await;
+
+        let config = config::configuration();
+        if !config.mux_session_persistence {
+            continue;
+        }
+        // The snapshot is taken and serialized here, on the main thread,
+        // while the writer thread takes care of the disk I/O
+        let snapshot = snapshot(&config);
+        let request = if snapshot.windows.is_empty() {
+            SnapshotFileRequest::Remove
+        } else {
+            match serde_json::to_vec(&snapshot) {
+                Ok(data) => SnapshotFileRequest::Save(data),
+                Err(err) => {
+                    log::error!("failed to serialize mux session: {err:#}");
+                    continue;
+                }
+            }
+        };
+        if writer.send(request).is_err() {
+            log::error!("mux session writer has stopped; no longer saving the session");
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn snapshot_round_trip() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 0,
+            pixel_height: 0,
+            dpi: 0,
+        };
+        let pane = |cwd: &str, is_active| PaneSnapshot {
+            cwd: Some(cwd.to_string()),
+            foreground_process: Some(vec!["vim".to_string(), "notes.md".to_string()]),
+            is_active,
+            is_zoomed: false,
+            scrollback: None,
+        };
+        let snapshot = SessionSnapshot {
+            version: SESSION_FORMAT_VERSION,
+            windows: vec![WindowSnapshot {
+                workspace: "default".to_string(),
+                title: String::new(),
+                active_tab: 0,
+                tabs: vec![TabSnapshot {
+                    title: "editor".to_string(),
+                    size,
+                    layout: PaneLayout::Split {
+                        direction: SplitDirection::Horizontal,
+                        first_cells: 40,
+                        second_cells: 39,
+                        first: Box::new(PaneLayout::Pane(pane("/tmp/a", false))),
+                        second: Box::new(PaneLayout::Pane(pane("/tmp/b", true))),
+                    },
+                }],
+            }],
+        };
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let decoded: SessionSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, snapshot);
+        assert_eq!(
+            decoded.windows[0].tabs[0]
+                .layout
+                .first_pane()
+                .cwd
+                .as_deref(),
+            Some("/tmp/a")
+        );
+    }
+}
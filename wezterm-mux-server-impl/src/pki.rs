@@ -84,12 +84,21 @@ impl Pki {
         Ok(Self { pki_dir, ca_cert })
     }
 
-    pub fn generate_client_cert(&self) -> anyhow::Result<String> {
+    /// Generates a client cert signed by our CA.
+    /// If `client_name` is provided, it is encoded into the CN in
+    /// the form `user:unixname/client_name` so that the server can
+    /// tell which client is connecting.
+    pub fn generate_client_cert(&self, client_name: Option<&str>) -> anyhow::Result<String> {
         let unix_name = config::username_from_env()?;
 
-        let mut params = CertificateParams::new(vec![unix_name.clone()]);
+        let common_name = match client_name {
+            Some(name) => format!("user:{unix_name}/{name}"),
+            None => unix_name.clone(),
+        };
+
+        let mut params = CertificateParams::new(vec![unix_name]);
         let mut dn = DistinguishedName::new();
-        dn.push(DnType::CommonName, unix_name);
+        dn.push(DnType::CommonName, common_name);
         params.distinguished_name = dn;
 
         let client_cert = Certificate::from_params(params)?;
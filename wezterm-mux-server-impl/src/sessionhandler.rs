@@ -1,9 +1,11 @@
+use crate::access::{self, audit, audit_pane_logging_failures, AuditEvent, PeerIdentity};
 use crate::PKI;
 use anyhow::{anyhow, Context};
 use codec::*;
 use config::TermConfig;
 use mux::client::ClientId;
 use mux::domain::SplitSource;
+use mux::layout::Layout;
 use mux::pane::{CachePolicy, Pane, PaneId};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
 use mux::tab::TabId;
@@ -12,10 +14,11 @@ use promise::spawn::spawn_into_main_thread;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
+use termwiz::escape::osc::PointerShape;
 use termwiz::surface::SequenceNo;
 use url::Url;
 use wezterm_term::terminal::Alert;
-use wezterm_term::StableRowIndex;
+use wezterm_term::{StableRowIndex, TerminalSize};
 
 #[derive(Clone)]
 pub struct PduSender {
@@ -42,6 +45,7 @@ pub(crate) struct PerPane {
     working_dir: Option<Url>,
     dimensions: RenderableDimensions,
     mouse_grabbed: bool,
+    pointer_shape: Option<PointerShape>,
     sent_initial_palette: bool,
     seqno: SequenceNo,
     config_generation: usize,
@@ -60,6 +64,11 @@ impl PerPane {
             changed = true;
         }
 
+        let pointer_shape = pane.get_pointer_shape();
+        if pointer_shape != self.pointer_shape {
+            changed = true;
+        }
+
         let dims = pane.get_dimensions();
         if dims != self.dimensions {
             changed = true;
@@ -126,11 +135,13 @@ impl PerPane {
         self.working_dir = working_dir.clone();
         self.dimensions = dims;
         self.mouse_grabbed = mouse_grabbed;
+        self.pointer_shape = pointer_shape;
 
         let bonus_lines = bonus_lines.into();
         Some(GetPaneRenderChangesResponse {
             pane_id: pane.pane_id(),
             mouse_grabbed,
+            pointer_shape,
             dirty_lines: all_dirty_lines.iter().cloned().collect(),
             dimensions: dims,
             cursor_position,
@@ -201,11 +212,21 @@ pub struct SessionHandler {
     per_pane: HashMap<TabId, Arc<Mutex<PerPane>>>,
     client_id: Option<Arc<ClientId>>,
     proxy_client_id: Option<ClientId>,
+    read_only: bool,
+    peer: PeerIdentity,
 }
 
 impl Drop for SessionHandler {
     fn drop(&mut self) {
         if let Some(client_id) = self.client_id.take() {
+            audit(
+                &self.peer,
+                AuditEvent::Disconnect {
+                    username: client_id.username.clone(),
+                    hostname: client_id.hostname.clone(),
+                    pid: client_id.pid,
+                },
+            );
             let mux = Mux::get();
             mux.unregister_client(&client_id);
         }
@@ -213,12 +234,14 @@ impl Drop for SessionHandler {
 }
 
 impl SessionHandler {
-    pub fn new(to_write_tx: PduSender) -> Self {
+    pub fn new(to_write_tx: PduSender, peer: PeerIdentity) -> Self {
         Self {
             to_write_tx,
             per_pane: HashMap::new(),
             client_id: None,
             proxy_client_id: None,
+            read_only: false,
+            peer,
         }
     }
 
@@ -274,6 +297,27 @@ impl SessionHandler {
             send_response(f());
         }
 
+        // The server configuration may force this peer to be read-only.
+        // It is checked for each request so that a config reload also
+        // applies to established sessions.
+        if !self.read_only && self.peer.is_forced_read_only() {
+            self.read_only = true;
+            if let Some(client_id) = self.client_id.clone() {
+                spawn_into_main_thread(async move {
+                    Mux::get().set_client_read_only(&client_id);
+                })
+                .detach();
+            }
+        }
+
+        if self.read_only && !decoded.pdu.is_safe_for_read_only() {
+            send_response(Err(anyhow!(
+                "{} refused because this client is attached read-only",
+                decoded.pdu.pdu_name()
+            )));
+            return;
+        }
+
         match decoded.pdu {
             Pdu::Ping(Ping {}) => send_response(Ok(Pdu::Pong(Pong {}))),
             Pdu::SetWindowWorkspace(SetWindowWorkspace {
@@ -298,7 +342,11 @@ impl SessionHandler {
             Pdu::SetClientId(SetClientId {
                 mut client_id,
                 is_proxy,
+                read_only,
             }) => {
+                // Once a session is read-only, it stays that way
+                self.read_only |= read_only;
+                let read_only = self.read_only;
                 if is_proxy {
                     if self.proxy_client_id.is_none() {
                         // Copy proxy identity, but don't assign it to the mux;
@@ -318,11 +366,27 @@ impl SessionHandler {
                             format!("{} (via proxy pid {})", client_id.hostname, proxy_id.pid);
                     }
 
+                    audit(
+                        &self.peer,
+                        AuditEvent::Connect {
+                            username: client_id.username.clone(),
+                            hostname: client_id.hostname.clone(),
+                            pid: client_id.pid,
+                        },
+                    );
+
                     let client_id = Arc::new(client_id);
                     self.client_id.replace(client_id.clone());
+                    let identity = self.peer.client_name().map(|name| name.to_string());
                     spawn_into_main_thread(async move {
                         let mux = Mux::get();
-                        mux.register_client(client_id);
+                        mux.register_client(client_id.clone());
+                        if read_only {
+                            mux.set_client_read_only(&client_id);
+                        }
+                        if let Some(identity) = identity {
+                            mux.set_client_identity(&client_id, identity);
+                        }
                     })
                     .detach();
                 }
@@ -469,6 +533,22 @@ impl SessionHandler {
                 })
                 .detach();
             }
+            Pdu::SetColorScheme(SetColorScheme { pane_id, scheme }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            pane.set_color_scheme(scheme);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    );
+                })
+                .detach();
+            }
             Pdu::KillPane(KillPane { pane_id }) => {
                 let sender = self.to_write_tx.clone();
                 let per_pane = self.per_pane(pane_id);
@@ -635,18 +715,14 @@ impl SessionHandler {
                 pane_id,
                 size,
             }) => {
+                let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
                     catch(
                         move || {
                             let mux = Mux::get();
-                            let pane = mux
-                                .get_pane(pane_id)
-                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
-                            pane.resize(size)?;
-                            let tab = mux
-                                .get_tab(containing_tab_id)
+                            mux.get_tab(containing_tab_id)
                                 .ok_or_else(|| anyhow!("no such tab {}", containing_tab_id))?;
-                            tab.rebuild_splits_sizes_from_contained_panes();
+                            mux.resize_pane_for_client(client_id.as_deref(), pane_id, size)?;
                             Ok(Pdu::UnitResponse(UnitResponse {}))
                         },
                         send_response,
@@ -710,6 +786,14 @@ impl SessionHandler {
             }
 
             Pdu::SpawnV2(spawn) => {
+                audit(
+                    &self.peer,
+                    AuditEvent::Spawn {
+                        domain: format!("{:?}", spawn.domain),
+                        command: spawn.command.as_ref().map(command_argv),
+                        cwd: spawn.command_dir.clone(),
+                    },
+                );
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
                     schedule_domain_spawn_v2(spawn, send_response, client_id);
@@ -718,6 +802,16 @@ impl SessionHandler {
             }
 
             Pdu::SplitPane(split) => {
+                if split.move_pane_id.is_none() {
+                    audit(
+                        &self.peer,
+                        AuditEvent::Spawn {
+                            domain: format!("{:?}", split.domain),
+                            command: split.command.as_ref().map(command_argv),
+                            cwd: split.command_dir.clone(),
+                        },
+                    );
+                }
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
                     schedule_split_pane(split, send_response, client_id);
@@ -725,6 +819,48 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::SpawnFloatingPane(spawn) => {
+                audit(
+                    &self.peer,
+                    AuditEvent::Spawn {
+                        domain: format!("{:?}", spawn.domain),
+                        command: spawn.command.as_ref().map(command_argv),
+                        cwd: spawn.command_dir.clone(),
+                    },
+                );
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_spawn_floating_pane(spawn, send_response, client_id);
+                })
+                .detach();
+            }
+
+            Pdu::ApplyFloatingPaneOp(ApplyFloatingPaneOp { tab_id, op }) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get();
+                        let _identity = mux.with_identity(client_id);
+                        send_response(
+                            mux.apply_floating_pane_op(tab_id, op)
+                                .await
+                                .map(|_| Pdu::UnitResponse(UnitResponse {})),
+                        )
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
+            Pdu::ApplyLayout(ApplyLayout { layout }) => {
+                audit(&self.peer, AuditEvent::ApplyLayout);
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    schedule_apply_layout(layout, send_response, client_id);
+                })
+                .detach();
+            }
+
             Pdu::MovePaneToNewTab(request) => {
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
@@ -866,11 +1002,35 @@ impl SessionHandler {
                 }
             }
 
-            Pdu::GetTlsCreds(_) => {
+            Pdu::GetTlsCreds(GetTlsCreds { client_name }) => {
+                let peer = self.peer.clone();
                 catch(
                     move || {
-                        let client_cert_pem = PKI.generate_client_cert()?;
+                        if let Some(name) = &client_name {
+                            access::validate_client_name(name)?;
+                        }
+                        // A named client may renew its own credentials,
+                        // but must not be able to assume another identity
+                        if let Some(own_name) = peer.client_name() {
+                            if client_name.as_deref() != Some(own_name) {
+                                anyhow::bail!(
+                                    "client `{own_name}` may only obtain credentials for itself"
+                                );
+                            }
+                        }
+                        if let PeerIdentity::Tls {
+                            client_name: None, ..
+                        } = &peer
+                        {
+                            access::check_unnamed_client_may_issue(&config::configuration())?;
+                        }
+                        access::check_client_access(
+                            &config::configuration(),
+                            client_name.as_deref(),
+                        )?;
+                        let client_cert_pem = PKI.generate_client_cert(client_name.as_deref())?;
                         let ca_cert_pem = PKI.ca_pem_string()?;
+                        audit(&peer, AuditEvent::IssueCredentials { client_name });
                         Ok(Pdu::GetTlsCredsResponse(GetTlsCredsResponse {
                             client_cert_pem,
                             ca_cert_pem,
@@ -879,6 +1039,120 @@ impl SessionHandler {
                     send_response,
                 );
             }
+            Pdu::RevokeClient(RevokeClient {
+                client_name,
+                restore,
+            }) => {
+                let peer = self.peer.clone();
+                catch(
+                    move || {
+                        if peer != PeerIdentity::Unix {
+                            anyhow::bail!("clients can only be revoked via the local mux socket");
+                        }
+                        access::set_client_revoked(&client_name, !restore)?;
+                        audit(
+                            &peer,
+                            if restore {
+                                AuditEvent::Restore { client_name }
+                            } else {
+                                AuditEvent::Revoke { client_name }
+                            },
+                        );
+                        Ok(Pdu::UnitResponse(UnitResponse {}))
+                    },
+                    send_response,
+                );
+            }
+            Pdu::ListPortForwards(ListPortForwards {}) => {
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get();
+                        let forwards = mux.list_port_forwards().await;
+                        send_response(Ok(Pdu::ListPortForwardsResponse(
+                            ListPortForwardsResponse { forwards },
+                        )))
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+            Pdu::SetPortForwardEnabled(SetPortForwardEnabled {
+                domain_name,
+                forward_id,
+                enabled,
+            }) => {
+                // Forwards listen on the network on behalf of the server,
+                // so only allow them to be changed by local users
+                if self.peer != PeerIdentity::Unix {
+                    send_response(Err(anyhow!(
+                        "port forwards can only be changed via the local mux socket"
+                    )));
+                    return;
+                }
+                audit(
+                    &self.peer,
+                    AuditEvent::PortForward {
+                        domain: domain_name.clone(),
+                        forward_id,
+                        enabled,
+                    },
+                );
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get();
+                        send_response(
+                            mux.set_port_forward_enabled(&domain_name, forward_id, enabled)
+                                .await
+                                .map(|enabled| {
+                                    Pdu::SetPortForwardEnabledResponse(
+                                        SetPortForwardEnabledResponse { enabled },
+                                    )
+                                }),
+                        )
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+            Pdu::SetPaneLogging(SetPaneLogging { pane_id, target }) => {
+                // The command runs with the privileges of the server,
+                // so only allow it to be set by local users
+                let has_command = target.as_ref().map_or(false, |t| t.command.is_some());
+                if has_command && self.peer != PeerIdentity::Unix {
+                    send_response(Err(anyhow!(
+                        "panes can only be logged to a command via the local mux socket"
+                    )));
+                    return;
+                }
+                audit(
+                    &self.peer,
+                    AuditEvent::PaneLogging {
+                        pane_id,
+                        path: target.as_ref().and_then(|t| t.path.clone()),
+                        command: target.as_ref().and_then(|t| t.command.clone()),
+                    },
+                );
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            match target {
+                                Some(target) => {
+                                    audit_pane_logging_failures();
+                                    pane.start_logging(target)?
+                                }
+                                None => pane.stop_logging()?,
+                            }
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
             Pdu::WindowTitleChanged(WindowTitleChanged { window_id, title }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -991,6 +1265,7 @@ impl SessionHandler {
             Pdu::Pong { .. }
             | Pdu::ListPanesResponse { .. }
             | Pdu::SetClipboard { .. }
+            | Pdu::SetClipboardData { .. }
             | Pdu::NotifyAlert { .. }
             | Pdu::SpawnResponse { .. }
             | Pdu::GetPaneRenderChangesResponse { .. }
@@ -1003,11 +1278,14 @@ impl SessionHandler {
             | Pdu::WindowWorkspaceChanged { .. }
             | Pdu::GetTlsCredsResponse { .. }
             | Pdu::GetClientListResponse { .. }
+            | Pdu::ListPortForwardsResponse { .. }
+            | Pdu::SetPortForwardEnabledResponse { .. }
             | Pdu::PaneRemoved { .. }
             | Pdu::PaneFocused { .. }
             | Pdu::TabResized { .. }
             | Pdu::GetImageCellResponse { .. }
             | Pdu::MovePaneToNewTabResponse { .. }
+            | Pdu::ApplyLayoutResponse { .. }
             | Pdu::TabAddedToWindow { .. }
             | Pdu::GetPaneRenderableDimensionsResponse { .. }
             | Pdu::ErrorResponse { .. } => {
@@ -1017,6 +1295,13 @@ impl SessionHandler {
     }
 }
 
+fn command_argv(cmd: &portable_pty::CommandBuilder) -> Vec<String> {
+    cmd.get_argv()
+        .iter()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect()
+}
+
 // Dancing around a little bit here; we can't directly spawn_into_main_thread the domain_spawn
 // function below because the compiler thinks that all of its locals then need to be Send.
 // We need to shimmy through this helper to break that aspect of the compiler flow
@@ -1040,6 +1325,39 @@ where
         .detach();
 }
 
+fn schedule_spawn_floating_pane<SND>(
+    spawn: SpawnFloatingPane,
+    send_response: SND,
+    client_id: Option<Arc<ClientId>>,
+) where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(
+        async move { send_response(spawn_floating_pane(spawn, client_id).await) },
+    )
+    .detach();
+}
+
+fn schedule_apply_layout<SND>(layout: Layout, send_response: SND, client_id: Option<Arc<ClientId>>)
+where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(apply_layout(layout, client_id).await) })
+        .detach();
+}
+
+async fn apply_layout(layout: Layout, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    let applied = layout.apply().await?;
+
+    Ok::<Pdu, anyhow::Error>(Pdu::ApplyLayoutResponse(ApplyLayoutResponse {
+        window_ids: applied.window_ids,
+        pane_ids: applied.pane_ids,
+    }))
+}
+
 async fn split_pane(split: SplitPane, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
     let mux = Mux::get();
     let _identity = mux.with_identity(client_id);
@@ -1069,6 +1387,41 @@ async fn split_pane(split: SplitPane, client_id: Option<Arc<ClientId>>) -> anyho
     }))
 }
 
+async fn spawn_floating_pane(
+    spawn: SpawnFloatingPane,
+    client_id: Option<Arc<ClientId>>,
+) -> anyhow::Result<Pdu> {
+    let mux = Mux::get();
+    let _identity = mux.with_identity(client_id);
+
+    let (_pane_domain_id, window_id, tab_id) = mux
+        .resolve_pane_id(spawn.pane_id)
+        .ok_or_else(|| anyhow!("pane_id {} invalid", spawn.pane_id))?;
+
+    let pane = mux
+        .spawn_floating_pane(
+            spawn.pane_id,
+            spawn.command,
+            spawn.command_dir,
+            spawn.domain,
+        )
+        .await?;
+
+    let dims = pane.get_dimensions();
+    Ok::<Pdu, anyhow::Error>(Pdu::SpawnResponse(SpawnResponse {
+        pane_id: pane.pane_id(),
+        tab_id,
+        window_id,
+        size: TerminalSize {
+            cols: dims.cols,
+            rows: dims.viewport_rows,
+            pixel_width: dims.pixel_width,
+            pixel_height: dims.pixel_height,
+            dpi: dims.dpi,
+        },
+    }))
+}
+
 async fn domain_spawn_v2(spawn: SpawnV2, client_id: Option<Arc<ClientId>>) -> anyhow::Result<Pdu> {
     let mux = Mux::get();
     let _identity = mux.with_identity(client_id);
@@ -268,6 +268,8 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
             if let Err(err) = update_mux_domains_for_server(&config::configuration()) {
                 log::error!("Error updating mux domains: {:#}", err);
             }
+            // The window size policy may have changed
+            Mux::get().apply_window_size_policy();
         })
         .detach();
         true
@@ -286,7 +288,23 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
         .iter()
         .any(|p| p.domain_id() == domain.domain_id());
 
-    if !have_panes_in_domain {
+    // Only restore the saved session if nothing else has already
+    // populated the mux, and the user didn't ask for a specific program
+    let restored = if !have_panes_in_domain && cmd.is_none() && config.mux_session_persistence {
+        match wezterm_mux_server_impl::persist::restore_session(&config).await {
+            Ok(restored) => restored,
+            Err(err) => {
+                log::error!("failed to restore mux session: {:#}", err);
+                false
+            }
+        }
+    } else {
+        false
+    };
+
+    promise::spawn::spawn(wezterm_mux_server_impl::persist::run_session_saver()).detach();
+
+    if !have_panes_in_domain && !restored {
         let workspace = None;
         let position = None;
         let window_id = mux.new_empty_window(workspace, position);
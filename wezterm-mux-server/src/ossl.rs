@@ -7,6 +7,7 @@ use promise::spawn::spawn_into_main_thread;
 use std::net::TcpListener;
 use std::path::Path;
 use std::sync::Arc;
+use wezterm_mux_server_impl::access::{audit, AuditEvent, PeerIdentity};
 use wezterm_mux_server_impl::PKI;
 
 struct OpenSSLNetListener {
@@ -31,7 +32,11 @@ impl OpenSSLNetListener {
     ///   user running this mux server instance, or must match
     ///   a special encoded prefix set up by a proprietary PKI
     ///   infrastructure in an environment used by the author.
-    fn verify_peer_cert<T>(stream: &SslStream<T>) -> anyhow::Result<()> {
+    ///   Certificates issued by `wezterm cli tlscreds --client-name`
+    ///   use that same `user:unixname/NAME` form.
+    ///
+    /// Returns the client name portion of the CN, if any.
+    fn verify_peer_cert<T>(stream: &SslStream<T>) -> anyhow::Result<Option<String>> {
         let cert = stream
             .ssl()
             .peer_certificate()
@@ -51,18 +56,18 @@ impl OpenSSLNetListener {
                 cn_str,
                 wanted_unix_name
             );
-            Ok(())
+            Ok(None)
         } else {
             // Some environments that are used by the author of this
             // program encode the CN in the form `user:unixname/DATA`
             let maybe_encoded = format!("user:{}/", wanted_unix_name);
-            if cn_str.starts_with(&maybe_encoded) {
+            if let Some(client_name) = cn_str.strip_prefix(&maybe_encoded) {
                 log::trace!(
                     "Peer certificate CN `{}` matches $USER `{}`",
                     cn_str,
                     wanted_unix_name
                 );
-                Ok(())
+                Ok(Some(client_name.to_string()))
             } else {
                 anyhow::bail!("CN `{}` did not match $USER `{}`", cn_str, wanted_unix_name);
             }
@@ -74,19 +79,38 @@ impl OpenSSLNetListener {
             match stream {
                 Ok(stream) => {
                     stream.set_nodelay(true).ok();
+                    let address = stream.peer_addr().ok();
                     let acceptor = self.acceptor.clone();
 
                     match acceptor.accept(stream) {
                         Ok(stream) => {
-                            if let Err(err) = Self::verify_peer_cert(&stream) {
-                                log::error!("problem with peer cert: {}", err);
-                                break;
+                            let client_name = match Self::verify_peer_cert(&stream) {
+                                Ok(client_name) => client_name,
+                                Err(err) => {
+                                    log::error!("problem with peer cert: {}", err);
+                                    continue;
+                                }
+                            };
+                            let peer = PeerIdentity::Tls {
+                                client_name,
+                                address,
+                            };
+                            if let Err(err) = peer.check_access() {
+                                log::error!("refusing {:?}: {:#}", peer, err);
+                                audit(
+                                    &peer,
+                                    AuditEvent::Refused {
+                                        reason: format!("{:#}", err),
+                                    },
+                                );
+                                continue;
                             }
                             spawn_into_main_thread(async move {
                                 log::error!("Making new AsyncSslStream");
-                                wezterm_mux_server_impl::dispatch::process(AsyncSslStream::new(
-                                    stream,
-                                ))
+                                wezterm_mux_server_impl::dispatch::process(
+                                    AsyncSslStream::new(stream),
+                                    peer,
+                                )
                                 .await
                                 .map_err(|e| {
                                     log::error!("process: {:?}", e);
@@ -344,14 +344,23 @@ impl ParsedConfigFile {
                 }
 
                 fn add_option(options: &mut ConfigMap, k: String, v: &str) {
+                    if is_superseded(options, &k) {
+                        return;
+                    }
                     // first option wins in ssh_config, except for identityfile
-                    // which explicitly allows multiple entries to combine together
-                    let is_identity_file = k == "identityfile";
+                    // and the port forwarding options, which explicitly allow
+                    // multiple entries to combine together.  Forwards contain
+                    // whitespace, so they are combined as a comma separated list.
+                    let separator = match k.as_str() {
+                        "identityfile" => Some(' '),
+                        "localforward" | "remoteforward" | "dynamicforward" => Some(','),
+                        _ => None,
+                    };
                     options
                         .entry(k)
                         .and_modify(|e| {
-                            if is_identity_file {
-                                e.push(' ');
+                            if let Some(separator) = separator {
+                                e.push(separator);
                                 e.push_str(v);
                             }
                         })
@@ -381,7 +390,9 @@ impl ParsedConfigFile {
         let mut needs_reparse = false;
 
         for (k, v) in &self.options {
-            target.entry(k.to_string()).or_insert_with(|| v.to_string());
+            if !is_superseded(target, k) {
+                target.entry(k.to_string()).or_insert_with(|| v.to_string());
+            }
         }
         for group in &self.groups {
             if group.context != Context::FirstPass {
@@ -389,7 +400,9 @@ impl ParsedConfigFile {
             }
             if group.is_match(hostname, user, local_user, context) {
                 for (k, v) in &group.options {
-                    target.entry(k.to_string()).or_insert_with(|| v.to_string());
+                    if !is_superseded(target, k) {
+                        target.entry(k.to_string()).or_insert_with(|| v.to_string());
+                    }
                 }
             }
         }
@@ -398,6 +411,16 @@ impl ParsedConfigFile {
     }
 }
 
+/// ProxyJump and ProxyCommand are alternative ways to reach the host,
+/// so, as with other options, whichever of them is specified first wins
+fn is_superseded(options: &ConfigMap, key: &str) -> bool {
+    match key {
+        "proxyjump" => options.contains_key("proxycommand"),
+        "proxycommand" => options.contains_key("proxyjump"),
+        _ => false,
+    }
+}
+
 /// A context for resolving configuration values.
 /// Holds a combination of environment and token expansion state,
 /// as well as the set of configs that should be consulted.
@@ -551,6 +574,9 @@ impl Config {
         token_map.insert("%h".to_string(), result["hostname"].to_string());
         token_map.insert("%n".to_string(), host.to_string());
         token_map.insert("%r".to_string(), target_user.to_string());
+        if let Some(proxy_jump) = result.get("proxyjump") {
+            token_map.insert("%j".to_string(), proxy_jump.to_string());
+        }
         token_map.insert(
             "%p".to_string(),
             result
@@ -611,8 +637,14 @@ impl Config {
     /// expansion.
     fn should_expand_environment(&self, key: &str) -> bool {
         match key {
-            "certificatefile" | "controlpath" | "identityagent" | "identityfile"
-            | "userknownhostsfile" | "localforward" | "remoteforward" => true,
+            "certificatefile"
+            | "controlpath"
+            | "identityagent"
+            | "identityfile"
+            | "userknownhostsfile"
+            | "localforward"
+            | "remoteforward"
+            | "wezterm_ssh_control_path" => true,
             _ => false,
         }
     }
@@ -620,8 +652,15 @@ impl Config {
     /// Returns a set of tokens that should be expanded for a given option name
     fn should_expand_tokens(&self, key: &str) -> Option<&[&str]> {
         match key {
-            "certificatefile" | "controlpath" | "identityagent" | "identityfile"
-            | "localforward" | "remotecommand" | "remoteforward" | "userknownkostsfile" => {
+            "certificatefile"
+            | "controlpath"
+            | "identityagent"
+            | "identityfile"
+            | "localforward"
+            | "remotecommand"
+            | "remoteforward"
+            | "userknownkostsfile"
+            | "wezterm_ssh_control_path" => {
                 Some(&["%C", "%d", "%h", "%i", "%L", "%l", "%n", "%p", "%r", "%u"])
             }
             "hostname" => Some(&["%h"]),
@@ -703,13 +742,8 @@ impl Config {
                     *value = items.join(" ");
                 }
             } else if t == "%j" {
-                // %j: The contents of the ProxyJump option, or the empty string if this option is unset
-                // We don't directly support ProxyJump, and this %j token referencing
-                // may technically put this into two-phase evaluation territory which
-                // we don't support.
-                // Let's silently gloss over this and treat this token as the empty
-                // string.
-                // Someone in the future will probably curse this.
+                // %j: The contents of the ProxyJump option, or the empty string if this option is unset.
+                // When it is set, it is present in the token_map.
                 *value = value.replace(t, "");
             } else if t == "%T" {
                 // %T: The local tun(4) or tap(4) network interface assigned if tunnel
@@ -798,6 +832,84 @@ impl Config {
     }
 }
 
+/// One of the hosts listed in the `ProxyJump` option, through
+/// which the connection to the target host is tunnelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl JumpHost {
+    /// Parses the value of the `ProxyJump` option, which is a comma
+    /// separated list of `[user@]host[:port]` or `ssh://[user@]host[:port]`
+    /// entries.  Returns an empty list for the special value `none`.
+    pub fn parse_list(proxy_jump: &str) -> anyhow::Result<Vec<Self>> {
+        if proxy_jump.eq_ignore_ascii_case("none") {
+            return Ok(vec![]);
+        }
+        proxy_jump
+            .split(',')
+            .map(|spec| Self::parse(spec.trim()))
+            .collect()
+    }
+
+    fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.strip_prefix("ssh://").unwrap_or(spec);
+        let (user, host_port) = match spec.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user.to_string()), host_port),
+            None => (None, spec),
+        };
+
+        let (host, port) = if let Some(bracketed) = host_port.strip_prefix('[') {
+            // An IPv6 address, such as `[::1]:2222`
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| anyhow::anyhow!("missing ] in ProxyJump host {spec}"))?;
+            (host, rest.strip_prefix(':'))
+        } else {
+            match host_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            }
+        };
+
+        if host.is_empty() {
+            anyhow::bail!("missing host name in ProxyJump host {spec}");
+        }
+        let port = port
+            .map(|port| {
+                port.parse::<u16>()
+                    .map_err(|_| anyhow::anyhow!("invalid port in ProxyJump host {spec}"))
+            })
+            .transpose()?;
+
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl std::fmt::Display for JumpHost {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            write!(f, "{user}@")?;
+        }
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            write!(f, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(f, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1633,4 +1745,79 @@ Config {
 "#
         );
     }
+
+    #[test]
+    fn parse_proxy_jump() {
+        let hosts = JumpHost::parse_list("bastion, me@gw:2222,ssh://you@[::1]:22").unwrap();
+        assert_eq!(
+            hosts,
+            vec![
+                JumpHost {
+                    user: None,
+                    host: "bastion".to_string(),
+                    port: None,
+                },
+                JumpHost {
+                    user: Some("me".to_string()),
+                    host: "gw".to_string(),
+                    port: Some(2222),
+                },
+                JumpHost {
+                    user: Some("you".to_string()),
+                    host: "::1".to_string(),
+                    port: Some(22),
+                },
+            ]
+        );
+        assert_eq!(hosts[2].to_string(), "you@[::1]:22");
+
+        assert_eq!(JumpHost::parse_list("none").unwrap(), vec![]);
+        assert!(JumpHost::parse_list("gw:nope").is_err());
+        assert!(JumpHost::parse_list("me@").is_err());
+
+        let mut config = Config::new();
+        config.add_config_string(
+            r#"
+        Host target
+            ProxyJump me@gw:2222
+            "#,
+        );
+        let mut fake_env = ConfigMap::new();
+        fake_env.insert("HOME".to_string(), "/home/me".to_string());
+        fake_env.insert("USER".to_string(), "me".to_string());
+        config.assign_environment(fake_env.clone());
+
+        let opts = config.for_host("target");
+        assert_eq!(opts["proxyjump"], "me@gw:2222");
+
+        // Whichever of ProxyJump and ProxyCommand comes first wins
+        let mut config = Config::new();
+        config.add_config_string(
+            r#"
+        Host a
+            ProxyCommand nc gw 22
+            ProxyJump gw
+
+        Host b
+            ProxyJump gw
+
+        Host *
+            ProxyCommand nc gw 22
+            "#,
+        );
+        config.assign_environment(fake_env);
+
+        let opts = config.for_host("a");
+        assert_eq!(opts["proxycommand"], "nc gw 22");
+        assert!(!opts.contains_key("proxyjump"));
+
+        let opts = config.for_host("b");
+        assert_eq!(opts["proxyjump"], "gw");
+        assert!(!opts.contains_key("proxycommand"));
+
+        config.set_option("proxyjump", "other");
+        let opts = config.for_host("a");
+        assert_eq!(opts["proxyjump"], "other");
+        assert!(!opts.contains_key("proxycommand"));
+    }
 }
@@ -0,0 +1,986 @@
+//! Sharing of authenticated sessions between wezterm processes via a
+//! unix domain socket, in the spirit of the `ControlMaster` and
+//! `ControlPath` options of OpenSSH, although the two are not
+//! compatible with each other.
+//!
+//! The process that owns the connection listens on the socket.  Each
+//! connection to the socket opens a single channel on the session,
+//! and the file descriptors for that channel are passed back over
+//! the socket, so that the data flows directly between the session
+//! thread of the owning process and the other process.  After that,
+//! the socket carries resize and kill requests in one direction and
+//! the exit status of the channel in the other.
+use crate::config::ConfigMap;
+use crate::pty::{NewPty, ResizePty, SshChildProcess, SshPty};
+use crate::session::{DirectTcpIp, Exec, ExecResult, SessionRequest, SignalChannel, WeakSession};
+use crate::sessioninner::{dispatch, ChannelId, IdleState, IDLE_POLL_INTERVAL};
+use anyhow::Context;
+use filedescriptor::{poll, pollfd, FileDescriptor, POLLIN};
+use passfd::FdPassingExt;
+use portable_pty::{ChildKiller, ExitStatus, MasterPty, PtySize};
+use smol::channel::{bounded, Receiver, Sender};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{Read, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, MetadataExt, PermissionsExt};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Bumped whenever the messages below change incompatibly
+const CONTROL_PROTOCOL_VERSION: u32 = 1;
+
+/// No legitimate message comes anywhere close to this size
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// Returns the path of the socket that is used to share
+/// connections to the host described by `config`, if any
+pub(crate) fn control_path(config: &ConfigMap) -> Option<PathBuf> {
+    match config.get("wezterm_ssh_control_path").map(|s| s.as_str()) {
+        None | Some("") | Some("none") => None,
+        Some(path) => Some(PathBuf::from(path)),
+    }
+}
+
+#[derive(Debug)]
+enum ControlRequest {
+    /// Used to check that the owning process is alive,
+    /// and that it speaks the same protocol version
+    Ping,
+    Pty {
+        term: String,
+        size: PtySize,
+        command_line: Option<String>,
+        env: Option<HashMap<String, String>>,
+    },
+    Exec {
+        command_line: String,
+        env: Option<HashMap<String, String>>,
+    },
+    DirectTcpIp {
+        host: String,
+        port: u16,
+    },
+    Resize(PtySize),
+    Kill,
+}
+
+#[derive(Debug)]
+enum ControlResponse {
+    /// The request succeeded; any file descriptors for
+    /// the channel follow this message
+    Ok,
+    Error(String),
+    Exit(ExitStatus),
+}
+
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn opt_str(&mut self, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                self.str(value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn size(&mut self, size: &PtySize) {
+        self.u16(size.rows);
+        self.u16(size.cols);
+        self.u16(size.pixel_width);
+        self.u16(size.pixel_height);
+    }
+
+    fn env(&mut self, env: &Option<HashMap<String, String>>) {
+        match env {
+            Some(env) => {
+                self.u8(1);
+                self.u32(env.len() as u32);
+                for (k, v) in env {
+                    self.str(k);
+                    self.str(v);
+                }
+            }
+            None => self.u8(0),
+        }
+    }
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        anyhow::ensure!(self.0.len() >= len, "truncated control message");
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let len = self.u32()? as usize;
+        Ok(String::from_utf8(self.take(len)?.to_vec())?)
+    }
+
+    fn opt_string(&mut self) -> anyhow::Result<Option<String>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.string()?)),
+        }
+    }
+
+    fn size(&mut self) -> anyhow::Result<PtySize> {
+        Ok(PtySize {
+            rows: self.u16()?,
+            cols: self.u16()?,
+            pixel_width: self.u16()?,
+            pixel_height: self.u16()?,
+        })
+    }
+
+    fn env(&mut self) -> anyhow::Result<Option<HashMap<String, String>>> {
+        match self.u8()? {
+            0 => Ok(None),
+            _ => {
+                let mut env = HashMap::new();
+                for _ in 0..self.u32()? {
+                    let k = self.string()?;
+                    let v = self.string()?;
+                    env.insert(k, v);
+                }
+                Ok(Some(env))
+            }
+        }
+    }
+}
+
+impl ControlRequest {
+    fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::default();
+        enc.u32(CONTROL_PROTOCOL_VERSION);
+        match self {
+            Self::Ping => enc.u8(0),
+            Self::Pty {
+                term,
+                size,
+                command_line,
+                env,
+            } => {
+                enc.u8(1);
+                enc.str(term);
+                enc.size(size);
+                enc.opt_str(command_line.as_deref());
+                enc.env(env);
+            }
+            Self::Exec { command_line, env } => {
+                enc.u8(2);
+                enc.str(command_line);
+                enc.env(env);
+            }
+            Self::DirectTcpIp { host, port } => {
+                enc.u8(3);
+                enc.str(host);
+                enc.u16(*port);
+            }
+            Self::Resize(size) => {
+                enc.u8(4);
+                enc.size(size);
+            }
+            Self::Kill => enc.u8(5),
+        }
+        enc.0
+    }
+
+    fn decode(buf: &[u8]) -> anyhow::Result<Self> {
+        let mut dec = Decoder(buf);
+        let version = dec.u32()?;
+        anyhow::ensure!(
+            version == CONTROL_PROTOCOL_VERSION,
+            "control protocol version {} is not supported; expected {}",
+            version,
+            CONTROL_PROTOCOL_VERSION
+        );
+        Ok(match dec.u8()? {
+            0 => Self::Ping,
+            1 => Self::Pty {
+                term: dec.string()?,
+                size: dec.size()?,
+                command_line: dec.opt_string()?,
+                env: dec.env()?,
+            },
+            2 => Self::Exec {
+                command_line: dec.string()?,
+                env: dec.env()?,
+            },
+            3 => Self::DirectTcpIp {
+                host: dec.string()?,
+                port: dec.u16()?,
+            },
+            4 => Self::Resize(dec.size()?),
+            5 => Self::Kill,
+            tag => anyhow::bail!("unknown control request {}", tag),
+        })
+    }
+}
+
+impl ControlResponse {
+    fn encode(&self) -> Vec<u8> {
+        let mut enc = Encoder::default();
+        match self {
+            Self::Ok => enc.u8(0),
+            Self::Error(err) => {
+                enc.u8(1);
+                enc.str(err);
+            }
+            Self::Exit(status) => {
+                enc.u8(2);
+                enc.u32(status.exit_code());
+                enc.opt_str(status.signal());
+            }
+        }
+        enc.0
+    }
+
+    fn decode(buf: &[u8]) -> anyhow::Result<Self> {
+        let mut dec = Decoder(buf);
+        Ok(match dec.u8()? {
+            0 => Self::Ok,
+            1 => Self::Error(dec.string()?),
+            2 => {
+                let code = dec.u32()?;
+                match dec.opt_string()? {
+                    Some(signal) => Self::Exit(ExitStatus::with_signal(&signal)),
+                    None => Self::Exit(ExitStatus::with_exit_code(code)),
+                }
+            }
+            tag => anyhow::bail!("unknown control response {}", tag),
+        })
+    }
+}
+
+fn write_frame<W: Write>(w: &mut W, payload: &[u8]) -> std::io::Result<()> {
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(payload)
+}
+
+/// Reads exactly one frame; in particular, this doesn't buffer, so
+/// that any file descriptors that follow the frame are not consumed
+fn read_frame<R: Read>(r: &mut R) -> anyhow::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    anyhow::ensure!(len <= MAX_FRAME_SIZE, "control message is too large");
+    let mut buf = vec![0u8; len];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn send_fd(stream: &UnixStream, fd: &FileDescriptor) -> anyhow::Result<()> {
+    stream
+        .as_raw_fd()
+        .send_fd(fd.as_raw_fd())
+        .context("passing file descriptor")
+}
+
+fn recv_fd(stream: &UnixStream) -> anyhow::Result<FileDescriptor> {
+    let fd = stream
+        .as_raw_fd()
+        .recv_fd()
+        .context("receiving file descriptor")?;
+    Ok(unsafe { FileDescriptor::from_raw_fd(fd) })
+}
+
+/// Starts listening on `path` so that other wezterm processes can
+/// open channels on `session`.  If another process is already
+/// listening there, then we leave it be.
+pub(crate) fn spawn_master(path: PathBuf, session: WeakSession) {
+    let listener = match bind_control_path(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            log::warn!(
+                "Unable to share ssh connection via {}: {:#}",
+                path.display(),
+                err
+            );
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        let ino = std::fs::metadata(&path).map(|m| m.ino()).ok();
+        if let Err(err) = run_master(&listener, &session) {
+            log::error!("shared ssh connection {}: {:#}", path.display(), err);
+        }
+        // Only remove the socket if it hasn't been replaced
+        // by another process in the meantime
+        if std::fs::metadata(&path).map(|m| m.ino()).ok() == ino {
+            std::fs::remove_file(&path).ok();
+        }
+    });
+}
+
+fn bind_control_path(path: &Path) -> anyhow::Result<UnixListener> {
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) => {
+            // Don't clobber something that the user pointed
+            // ControlPath at by mistake
+            anyhow::ensure!(
+                meta.file_type().is_socket(),
+                "{} exists and is not a socket",
+                path.display()
+            );
+            if UnixStream::connect(path).is_ok() {
+                anyhow::bail!("another process is already listening");
+            }
+            // Nobody is listening; it was left behind by
+            // a process that didn't exit cleanly
+            std::fs::remove_file(path).context("removing stale socket")?;
+        }
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+        Err(err) => {
+            return Err(err).with_context(|| format!("checking {}", path.display()));
+        }
+    }
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(parent)
+        .with_context(|| format!("creating {}", parent.display()))?;
+
+    // Bind in a directory that only we can access, and then move the
+    // socket into place, so that other users can never connect to it,
+    // not even before its permissions have been restricted
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{} has no file name", path.display()))?;
+    let private = parent.join(format!(
+        ".{}.{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    std::fs::remove_dir_all(&private).ok();
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private)
+        .with_context(|| format!("creating {}", private.display()))?;
+    let temp = private.join("socket");
+    let result = UnixListener::bind(&temp)
+        .context("bind")
+        .and_then(|listener| {
+            std::fs::set_permissions(&temp, std::fs::Permissions::from_mode(0o600))
+                .context("restricting permissions")?;
+            std::fs::rename(&temp, path)
+                .with_context(|| format!("moving socket to {}", path.display()))?;
+            Ok(listener)
+        });
+    std::fs::remove_dir_all(&private).ok();
+
+    let listener = result?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+/// Returns the uid of the process on the other end of `stream`
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut cred: libc::ucred = unsafe { std::mem::zeroed() };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if res != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(cred.uid)
+}
+
+/// Returns the uid of the process on the other end of `stream`
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut uid = 0;
+    let mut gid = 0;
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(uid)
+}
+
+/// Channels are only shared with processes running as the same user
+fn check_peer_uid(stream: &UnixStream) -> anyhow::Result<()> {
+    let uid = peer_uid(stream).context("checking the owner of the peer")?;
+    let our_uid = unsafe { libc::getuid() };
+    if uid != our_uid {
+        anyhow::bail!("refusing connection from uid {uid}, as we are uid {our_uid}");
+    }
+    Ok(())
+}
+
+fn run_master(listener: &UnixListener, session: &WeakSession) -> anyhow::Result<()> {
+    while !session.is_closed() && session.upgrade().is_some() {
+        let mut pfd = [pollfd {
+            fd: listener.as_raw_fd(),
+            events: POLLIN,
+            revents: 0,
+        }];
+        poll(&mut pfd, Some(Duration::from_secs(1)))?;
+
+        match listener.accept() {
+            Ok((stream, _addr)) => {
+                stream.set_nonblocking(false)?;
+                let session = session.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = serve_control_connection(stream, session) {
+                        log::error!("shared ssh connection client: {:#}", err);
+                    }
+                });
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(err) => return Err(err).context("accept"),
+        }
+    }
+    Ok(())
+}
+
+fn serve_control_connection(mut stream: UnixStream, session: WeakSession) -> anyhow::Result<()> {
+    check_peer_uid(&stream)?;
+    let request = ControlRequest::decode(&read_frame(&mut stream)?);
+    let result = match request {
+        Ok(request) => open_channel(&mut stream, request, &session),
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        write_frame(
+            &mut stream,
+            &ControlResponse::Error(format!("{:#}", err)).encode(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Carries out `request` on behalf of the other process.
+/// Errors are reported back to the other process by our caller.
+fn open_channel(
+    stream: &mut UnixStream,
+    request: ControlRequest,
+    session: &WeakSession,
+) -> anyhow::Result<()> {
+    let session = session.upgrade().ok_or(crate::session::DeadSession)?;
+    match request {
+        ControlRequest::Ping => {
+            write_frame(stream, &ControlResponse::Ok.encode())?;
+        }
+        ControlRequest::Pty {
+            term,
+            size,
+            command_line,
+            env,
+        } => {
+            let (pty, child) =
+                smol::block_on(session.request_pty(&term, size, command_line.as_deref(), env))?;
+            drop(session);
+            write_frame(stream, &ControlResponse::Ok.encode())?;
+            send_fd(stream, &pty.reader)?;
+            send_fd(stream, &pty.writer)?;
+            serve_channel(stream, Some(pty), child);
+        }
+        ControlRequest::Exec { command_line, env } => {
+            let exec = smol::block_on(session.exec(&command_line, env))?;
+            drop(session);
+            write_frame(stream, &ControlResponse::Ok.encode())?;
+            send_fd(stream, &exec.stdin)?;
+            send_fd(stream, &exec.stdout)?;
+            send_fd(stream, &exec.stderr)?;
+            // Don't hold our copies open, so that the other
+            // process can close stdin to signal EOF
+            let ExecResult {
+                stdin,
+                stdout,
+                stderr,
+                child,
+            } = exec;
+            drop((stdin, stdout, stderr));
+            serve_channel(stream, None, child);
+        }
+        ControlRequest::DirectTcpIp { host, port } => {
+            let fd = smol::block_on(session.direct_tcpip(&host, port))?;
+            write_frame(stream, &ControlResponse::Ok.encode())?;
+            send_fd(stream, &fd)?;
+        }
+        ControlRequest::Resize(_) | ControlRequest::Kill => {
+            anyhow::bail!("no channel has been opened");
+        }
+    }
+    Ok(())
+}
+
+/// Relays resize and kill requests for a channel from the other
+/// process until it closes the connection, and sends back the
+/// exit status of the channel
+fn serve_channel(stream: &mut UnixStream, pty: Option<SshPty>, mut child: SshChildProcess) {
+    let mut killer = child.clone_killer();
+    let exit_waiter = match stream.try_clone() {
+        Ok(mut exit_stream) => std::thread::spawn(move || {
+            match smol::block_on(child.wait_for_exit_status()) {
+                Some(status) => {
+                    write_frame(&mut exit_stream, &ControlResponse::Exit(status).encode()).ok();
+                }
+                None => {
+                    // Our connection was lost; closing the stream
+                    // without an exit status tells the other process
+                    exit_stream.shutdown(Shutdown::Both).ok();
+                }
+            }
+        }),
+        Err(err) => {
+            log::error!("cloning shared connection stream: {:#}", err);
+            killer.kill().ok();
+            return;
+        }
+    };
+
+    loop {
+        let request = match read_frame(stream).and_then(|buf| ControlRequest::decode(&buf)) {
+            Ok(request) => request,
+            Err(_) => {
+                // The other process has gone away; don't leave
+                // the remote command running without anyone
+                // to talk to it
+                if !exit_waiter.is_finished() {
+                    killer.kill().ok();
+                }
+                return;
+            }
+        };
+        match (request, &pty) {
+            (ControlRequest::Resize(size), Some(pty)) => {
+                if let Err(err) = pty.resize(size) {
+                    log::error!("resizing shared pty: {:#}", err);
+                }
+            }
+            (ControlRequest::Kill, _) => {
+                killer.kill().ok();
+            }
+            (request, _) => {
+                log::error!("unexpected request {:?} for shared channel", request);
+            }
+        }
+    }
+}
+
+/// A channel opened by a `ControlClient`
+struct ControlChannel {
+    stream: Mutex<UnixStream>,
+    exit_reader: JoinHandle<()>,
+}
+
+/// Stands in for `SessionInner` in a process that is using the
+/// connection of another wezterm process.
+pub(crate) struct ControlClient {
+    path: PathBuf,
+    rx_req: Receiver<SessionRequest>,
+    sender_read: FileDescriptor,
+    channels: HashMap<ChannelId, ControlChannel>,
+    next_channel_id: ChannelId,
+    session_was_dropped: bool,
+    /// Set when the owning process is no longer accepting
+    /// connections, which means that its connection has closed
+    owner_gone: bool,
+    idle: IdleState,
+}
+
+impl ControlClient {
+    /// Checks that there is a process listening on `path` that
+    /// is able to share its connection with us
+    pub fn connect(
+        path: &Path,
+        rx_req: Receiver<SessionRequest>,
+        sender_read: FileDescriptor,
+        idle_timeout: Option<Duration>,
+    ) -> anyhow::Result<Self> {
+        let mut client = Self {
+            path: path.to_path_buf(),
+            rx_req,
+            sender_read,
+            channels: HashMap::new(),
+            next_channel_id: 1,
+            session_was_dropped: false,
+            owner_gone: false,
+            idle: IdleState::new(idle_timeout),
+        };
+        client.request(&ControlRequest::Ping)?;
+        Ok(client)
+    }
+
+    pub fn run(mut self) {
+        loop {
+            let request = smol::block_on(smol::future::or(
+                async { Some(self.rx_req.recv().await) },
+                async {
+                    smol::Timer::after(IDLE_POLL_INTERVAL).await;
+                    None
+                },
+            ));
+            let mut buf = [0u8; 16];
+            let _ = self.sender_read.read(&mut buf);
+
+            match request {
+                Some(Ok(request)) => self.dispatch_request(request),
+                Some(Err(_)) => {
+                    log::trace!("all clients are closed");
+                    return;
+                }
+                None => {}
+            }
+
+            self.channels
+                .retain(|_, channel| !channel.exit_reader.is_finished());
+            if self.channels.is_empty() && self.session_was_dropped {
+                return;
+            }
+            if self.owner_gone {
+                // Returning closes our Session, so that the next
+                // Session::connect_shared makes a new connection
+                log::debug!("Shared connection {} has closed", self.path.display());
+                return;
+            }
+            if self.idle.is_idle(!self.channels.is_empty()) {
+                log::debug!(
+                    "Closing shared connection {} as it has been idle for {:?}",
+                    self.path.display(),
+                    self.idle.since.elapsed()
+                );
+                return;
+            }
+        }
+    }
+
+    fn dispatch_request(&mut self, request: SessionRequest) {
+        let _ = match request {
+            SessionRequest::SessionDropped => {
+                self.session_was_dropped = true;
+                Ok(true)
+            }
+            SessionRequest::NewPty(newpty, reply) => {
+                dispatch(reply, || self.new_pty(newpty), "NewPty")
+            }
+            SessionRequest::ResizePty(resize, reply) => {
+                let result = self.resize_pty(resize);
+                match reply {
+                    Some(reply) => dispatch(reply, || result, "resize_pty"),
+                    None => {
+                        if let Err(err) = result {
+                            log::error!("error in resize_pty: {:#}", err);
+                        }
+                        Ok(true)
+                    }
+                }
+            }
+            SessionRequest::Exec(exec, reply) => dispatch(reply, || self.exec(exec), "exec"),
+            SessionRequest::DirectTcpIp(direct, reply) => {
+                dispatch(reply, || self.direct_tcpip(direct), "DirectTcpIp")
+            }
+            SessionRequest::SignalChannel(info) => {
+                if let Err(err) = self.signal_channel(&info) {
+                    log::error!("{:?} -> error: {:#}", info, err);
+                }
+                Ok(true)
+            }
+            SessionRequest::ListForwards(reply) => dispatch(reply, || Ok(vec![]), "ListForwards"),
+            SessionRequest::AddForward(_, reply) => dispatch(
+                reply,
+                || Err(self.not_supported("port forwarding")),
+                "AddForward",
+            ),
+            SessionRequest::SetForwardEnabled(_, reply) => dispatch(
+                reply,
+                || Err(self.not_supported("port forwarding")),
+                "SetForwardEnabled",
+            ),
+            SessionRequest::Sftp(request) => {
+                // Dropping the request causes the caller to
+                // receive an error
+                log::error!("{:#}: {:?}", self.not_supported("sftp"), request);
+                Ok(true)
+            }
+        };
+    }
+
+    fn not_supported(&self, what: &str) -> anyhow::Error {
+        anyhow::anyhow!(
+            "{} is not available via the shared connection at {}",
+            what,
+            self.path.display()
+        )
+    }
+
+    /// Opens a new connection to the owning process and asks it
+    /// to carry out `request`
+    fn request(&mut self, request: &ControlRequest) -> anyhow::Result<UnixStream> {
+        let mut stream = match UnixStream::connect(&self.path) {
+            Ok(stream) => stream,
+            Err(err) => {
+                self.owner_gone = true;
+                return Err(err).with_context(|| format!("connecting to {}", self.path.display()));
+            }
+        };
+        check_peer_uid(&stream)?;
+        write_frame(&mut stream, &request.encode())?;
+        match ControlResponse::decode(&read_frame(&mut stream)?)? {
+            ControlResponse::Ok => Ok(stream),
+            ControlResponse::Error(err) => Err(anyhow::anyhow!("{}", err)),
+            ControlResponse::Exit(_) => anyhow::bail!("unexpected exit status"),
+        }
+    }
+
+    /// Registers a newly opened channel, and starts a thread to
+    /// wait for its exit status
+    fn add_channel(&mut self, stream: UnixStream) -> anyhow::Result<(ChannelId, SshChildProcess)> {
+        let channel_id = self.next_channel_id;
+        self.next_channel_id += 1;
+
+        let (exit_tx, exit_rx) = bounded(1);
+        let mut exit_stream = stream.try_clone()?;
+        let exit_reader = std::thread::spawn(move || wait_for_exit(&mut exit_stream, exit_tx));
+
+        self.channels.insert(
+            channel_id,
+            ControlChannel {
+                stream: Mutex::new(stream),
+                exit_reader,
+            },
+        );
+
+        let child = SshChildProcess {
+            channel: channel_id,
+            tx: None,
+            exit: exit_rx,
+            exited: None,
+        };
+        Ok((channel_id, child))
+    }
+
+    fn new_pty(&mut self, newpty: NewPty) -> anyhow::Result<(SshPty, SshChildProcess)> {
+        let stream = self.request(&ControlRequest::Pty {
+            term: newpty.term,
+            size: newpty.size,
+            command_line: newpty.command_line,
+            env: newpty.env,
+        })?;
+        let reader = recv_fd(&stream)?;
+        let writer = recv_fd(&stream)?;
+        let (channel_id, child) = self.add_channel(stream)?;
+
+        let ssh_pty = SshPty {
+            channel: channel_id,
+            tx: None,
+            reader,
+            writer,
+            size: Mutex::new(newpty.size),
+        };
+        Ok((ssh_pty, child))
+    }
+
+    fn exec(&mut self, exec: Exec) -> anyhow::Result<ExecResult> {
+        let stream = self.request(&ControlRequest::Exec {
+            command_line: exec.command_line,
+            env: exec.env,
+        })?;
+        let stdin = recv_fd(&stream)?;
+        let stdout = recv_fd(&stream)?;
+        let stderr = recv_fd(&stream)?;
+        let (_channel_id, child) = self.add_channel(stream)?;
+        Ok(ExecResult {
+            stdin,
+            stdout,
+            stderr,
+            child,
+        })
+    }
+
+    fn direct_tcpip(&mut self, direct: DirectTcpIp) -> anyhow::Result<FileDescriptor> {
+        let stream = self.request(&ControlRequest::DirectTcpIp {
+            host: direct.host,
+            port: direct.port,
+        })?;
+        recv_fd(&stream)
+    }
+
+    fn send_to_channel(&self, channel: ChannelId, request: ControlRequest) -> anyhow::Result<()> {
+        let channel = self
+            .channels
+            .get(&channel)
+            .ok_or_else(|| anyhow::anyhow!("invalid channel id {}", channel))?;
+        let mut stream = channel.stream.lock().unwrap();
+        write_frame(&mut *stream, &request.encode())?;
+        Ok(())
+    }
+
+    fn resize_pty(&mut self, resize: ResizePty) -> anyhow::Result<()> {
+        self.send_to_channel(resize.channel, ControlRequest::Resize(resize.size))
+    }
+
+    fn signal_channel(&mut self, info: &SignalChannel) -> anyhow::Result<()> {
+        self.send_to_channel(info.channel, ControlRequest::Kill)
+    }
+}
+
+fn wait_for_exit(stream: &mut UnixStream, exit_tx: Sender<ExitStatus>) {
+    // If the connection is lost without an exit status, then dropping
+    // exit_tx causes the child to be reported as having failed
+    loop {
+        match read_frame(stream).and_then(|buf| ControlResponse::decode(&buf)) {
+            Ok(ControlResponse::Exit(status)) => {
+                exit_tx.try_send(status).ok();
+                return;
+            }
+            Ok(response) => {
+                log::error!("unexpected response {:?} for shared channel", response);
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn request_round_trip() {
+        let mut env = HashMap::new();
+        env.insert("WEZTERM_REMOTE_PANE".to_string(), "1".to_string());
+        let request = ControlRequest::Pty {
+            term: "xterm-256color".to_string(),
+            size: PtySize {
+                rows: 24,
+                cols: 80,
+                pixel_width: 800,
+                pixel_height: 600,
+            },
+            command_line: None,
+            env: Some(env),
+        };
+
+        let mut buf = vec![];
+        write_frame(&mut buf, &request.encode()).unwrap();
+        let decoded = ControlRequest::decode(&read_frame(&mut buf.as_slice()).unwrap()).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", request));
+
+        let request = ControlRequest::DirectTcpIp {
+            host: "db".to_string(),
+            port: 5432,
+        };
+        let decoded = ControlRequest::decode(&request.encode()).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", request));
+    }
+
+    #[test]
+    fn response_round_trip() {
+        match ControlResponse::decode(
+            &ControlResponse::Exit(ExitStatus::with_exit_code(3)).encode(),
+        )
+        .unwrap()
+        {
+            ControlResponse::Exit(status) => {
+                assert_eq!(status.exit_code(), 3);
+                assert_eq!(status.signal(), None);
+            }
+            response => panic!("unexpected {:?}", response),
+        }
+
+        match ControlResponse::decode(
+            &ControlResponse::Exit(ExitStatus::with_signal("HUP")).encode(),
+        )
+        .unwrap()
+        {
+            ControlResponse::Exit(status) => assert_eq!(status.signal(), Some("HUP")),
+            response => panic!("unexpected {:?}", response),
+        }
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut buf = ControlRequest::Ping.encode();
+        buf[0] = buf[0].wrapping_add(1);
+        assert!(ControlRequest::decode(&buf).is_err());
+        assert!(ControlRequest::decode(&buf[0..2]).is_err());
+    }
+
+    #[test]
+    fn private_socket() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("sockets").join("control");
+        let _listener = bind_control_path(&path).unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        assert_eq!(mode(&path), 0o600);
+        // Only the socket itself is left behind
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+
+        let stream = UnixStream::connect(&path).unwrap();
+        assert_eq!(peer_uid(&stream).unwrap(), unsafe { libc::getuid() });
+        assert!(check_peer_uid(&stream).is_ok());
+
+        assert!(bind_control_path(&path).is_err());
+    }
+
+    #[test]
+    fn keeps_other_files() {
+        let dir = assert_fs::TempDir::new().unwrap();
+        let path = dir.path().join("config");
+        std::fs::write(&path, "Host *\n").unwrap();
+
+        assert!(bind_control_path(&path).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Host *\n");
+    }
+}
@@ -0,0 +1,915 @@
+use crate::channelwrap::ChannelWrap;
+use crate::config::ConfigMap;
+use crate::sessioninner::SessionInner;
+use crate::sessionwrap::{DirectTcpipOpen, RemoteListener, SessionWrap};
+use anyhow::Context;
+use filedescriptor::FileDescriptor;
+use smol::channel::Sender;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub type ForwardId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ForwardKind {
+    /// Connections to a local port are tunnelled to a host:port
+    /// that is reachable from the remote host; `ssh -L`
+    Local,
+    /// Connections to a port on the remote host are tunnelled to
+    /// a host:port that is reachable from the local host; `ssh -R`
+    Remote,
+    /// A local SOCKS5 proxy that makes connections from the
+    /// remote host; `ssh -D`
+    Dynamic,
+}
+
+impl ForwardKind {
+    /// The ssh_config option that specifies forwards of this kind
+    pub fn option_name(self) -> &'static str {
+        match self {
+            Self::Local => "localforward",
+            Self::Remote => "remoteforward",
+            Self::Dynamic => "dynamicforward",
+        }
+    }
+}
+
+/// Describes a port forward, as specified by the `LocalForward`,
+/// `RemoteForward` or `DynamicForward` options
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardSpec {
+    pub kind: ForwardKind,
+    /// The address on which to listen.  For `Remote` forwards
+    /// this is an address on the remote host.
+    /// `None` means the loopback interface, while `*` means
+    /// all interfaces.
+    pub bind_address: Option<String>,
+    pub bind_port: u16,
+    /// The host and port to which connections are made.
+    /// Not used by `Dynamic` forwards.
+    pub target: Option<(String, u16)>,
+}
+
+impl ForwardSpec {
+    /// Parses a forward in either the ssh_config form, eg:
+    /// `[bind_address:]port host:hostport`, or the form used by
+    /// the ssh command line, eg: `[bind_address:]port:host:hostport`.
+    /// `Dynamic` forwards are specified as `[bind_address:]port`.
+    pub fn parse(kind: ForwardKind, spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        if spec.starts_with('/') {
+            anyhow::bail!("forwarding unix domain sockets is not supported: {spec}");
+        }
+
+        let mut fields = vec![];
+        for arg in spec.split_ascii_whitespace() {
+            fields.append(&mut split_fields(arg)?);
+        }
+
+        fn parse_port(port: &str, spec: &str) -> anyhow::Result<u16> {
+            port.parse::<u16>()
+                .map_err(|_| anyhow::anyhow!("invalid port `{port}` in forward {spec}"))
+        }
+
+        let (bind_address, bind_port, target) = match (kind, fields.as_slice()) {
+            (ForwardKind::Dynamic, [port]) => (None, parse_port(port, spec)?, None),
+            (ForwardKind::Dynamic, [bind, port]) => {
+                (Some(bind.to_string()), parse_port(port, spec)?, None)
+            }
+            (ForwardKind::Local | ForwardKind::Remote, [port, host, host_port]) => (
+                None,
+                parse_port(port, spec)?,
+                Some((host.to_string(), parse_port(host_port, spec)?)),
+            ),
+            (ForwardKind::Local | ForwardKind::Remote, [bind, port, host, host_port]) => (
+                Some(bind.to_string()),
+                parse_port(port, spec)?,
+                Some((host.to_string(), parse_port(host_port, spec)?)),
+            ),
+            (ForwardKind::Remote, [_] | [_, _]) => {
+                anyhow::bail!("remote dynamic forwarding is not supported: {spec}");
+            }
+            _ => anyhow::bail!("invalid {} {spec}", kind.option_name()),
+        };
+
+        if let Some((host, _)) = &target {
+            if host.is_empty() {
+                anyhow::bail!("missing host name in forward {spec}");
+            }
+        }
+
+        Ok(Self {
+            kind,
+            bind_address,
+            bind_port,
+            target,
+        })
+    }
+
+    /// Returns the forwards specified by the `LocalForward`,
+    /// `RemoteForward` and `DynamicForward` options in `config`.
+    /// Invalid entries are logged and skipped.
+    pub fn from_config(config: &ConfigMap) -> Vec<Self> {
+        let mut forwards = vec![];
+        for kind in [
+            ForwardKind::Local,
+            ForwardKind::Remote,
+            ForwardKind::Dynamic,
+        ] {
+            if let Some(value) = config.get(kind.option_name()) {
+                for spec in value.split(',') {
+                    match Self::parse(kind, spec) {
+                        Ok(forward) => forwards.push(forward),
+                        Err(err) => log::error!("ignoring {}: {:#}", kind.option_name(), err),
+                    }
+                }
+            }
+        }
+        forwards
+    }
+
+    /// The address to listen on for `Local` and `Dynamic` forwards
+    fn local_bind_address(&self) -> &str {
+        match self.bind_address.as_deref() {
+            None | Some("localhost") => "127.0.0.1",
+            Some("*") | Some("") => "0.0.0.0",
+            Some(address) => address,
+        }
+    }
+
+    /// The address that the remote host should listen on for
+    /// `Remote` forwards
+    fn remote_bind_address(&self) -> Option<&str> {
+        match self.bind_address.as_deref() {
+            None => Some("localhost"),
+            Some("*") => Some(""),
+            Some(address) => Some(address),
+        }
+    }
+}
+
+fn format_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{host}]:{port}")
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+impl std::fmt::Display for ForwardSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let kind = match self.kind {
+            ForwardKind::Local => "local",
+            ForwardKind::Remote => "remote",
+            ForwardKind::Dynamic => "dynamic",
+        };
+        write!(f, "{kind} ")?;
+        match &self.bind_address {
+            Some(bind) => write!(f, "{}", format_host_port(bind, self.bind_port))?,
+            None => write!(f, "{}", self.bind_port)?,
+        }
+        match &self.target {
+            Some((host, port)) => write!(f, " -> {}", format_host_port(host, *port)),
+            None => write!(f, " (SOCKS5)"),
+        }
+    }
+}
+
+/// Splits a forwarding argument into its `:` or `/` separated
+/// fields, treating `[...]` as a single field so that IPv6
+/// addresses can be used
+fn split_fields(arg: &str) -> anyhow::Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut chars = arg.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => loop {
+                match chars.next() {
+                    Some(']') => break,
+                    Some(c) => field.push(c),
+                    None => anyhow::bail!("missing ] in forward {arg}"),
+                }
+            },
+            ':' | '/' => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardState {
+    /// Listening for connections
+    Active,
+    /// Known to the session, but not listening
+    Disabled,
+    /// The most recent attempt to listen failed
+    Failed(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ForwardInfo {
+    pub id: ForwardId,
+    pub spec: ForwardSpec,
+    pub state: ForwardState,
+    /// The port that is being listened on, which differs from
+    /// `spec.bind_port` when that is 0
+    pub bound_port: Option<u16>,
+    /// Who registered the forward via `Session::add_forward`,
+    /// or None for the forwards from the ssh config
+    pub owner: Option<String>,
+}
+
+pub(crate) enum ForwardListener {
+    Local(TcpListener),
+    Remote { listener: RemoteListener, port: u16 },
+}
+
+pub(crate) struct ForwardEntry {
+    pub id: ForwardId,
+    pub spec: ForwardSpec,
+    pub owner: Option<String>,
+    pub state: ForwardState,
+    pub listener: Option<ForwardListener>,
+}
+
+impl ForwardEntry {
+    fn info(&self) -> ForwardInfo {
+        ForwardInfo {
+            id: self.id,
+            spec: self.spec.clone(),
+            state: self.state.clone(),
+            bound_port: match &self.listener {
+                Some(ForwardListener::Local(listener)) => {
+                    listener.local_addr().ok().map(|addr| addr.port())
+                }
+                Some(ForwardListener::Remote { port, .. }) => Some(*port),
+                None => None,
+            },
+            owner: self.owner.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct AddForward {
+    pub spec: ForwardSpec,
+    pub enabled: bool,
+    pub owner: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct SetForwardEnabled {
+    pub id: ForwardId,
+    pub enabled: bool,
+}
+
+/// Work that is completed by helper threads and then handed back
+/// to the session thread, which owns the ssh channels
+#[derive(Debug)]
+pub(crate) enum ForwardEvent {
+    /// A SOCKS client connected to a `Dynamic` forward has asked
+    /// to be connected to host:port
+    SocksConnect {
+        stream: TcpStream,
+        host: String,
+        port: u16,
+    },
+    /// The local connection for a channel that arrived on a `Remote`
+    /// forward has been established, or has failed
+    RemoteConnected {
+        token: usize,
+        stream: std::io::Result<TcpStream>,
+    },
+}
+
+/// A connection to one of our forwards for which the channel to
+/// the remote host is still being opened
+pub(crate) struct PendingDirectTcpip {
+    host: String,
+    port: u16,
+    /// The local connection, or None once we've given up on the open
+    /// and only need to close the channel when libssh2 finishes it
+    stream: Option<TcpStream>,
+    /// Whether the connection is from a SOCKS client, which is
+    /// waiting for us to reply to its connect request
+    socks: bool,
+    open: DirectTcpipOpen,
+    started: Instant,
+}
+
+/// How long we wait for the remote host to open the channel for a
+/// connection to one of our forwards before failing that connection
+pub(crate) const DIRECT_TCPIP_OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Allows helper threads to post a `ForwardEvent` and wake up
+/// the session thread
+#[derive(Clone)]
+pub(crate) struct ForwardNotifier {
+    pub tx: Sender<ForwardEvent>,
+    pub pipe: Arc<Mutex<FileDescriptor>>,
+}
+
+impl ForwardNotifier {
+    fn notify(&self, event: ForwardEvent) {
+        if self.tx.try_send(event).is_ok() {
+            let _ = self.pipe.lock().unwrap().write(b"x");
+        }
+    }
+}
+
+fn stream_to_fd(stream: TcpStream) -> FileDescriptor {
+    #[cfg(unix)]
+    {
+        FileDescriptor::new(stream)
+    }
+    #[cfg(windows)]
+    unsafe {
+        use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+        FileDescriptor::from_raw_socket(stream.into_raw_socket())
+    }
+}
+
+/// Tells a SOCKS client that we couldn't connect it to its target;
+/// other clients only see their connection close
+fn fail_direct_tcpip(mut stream: TcpStream, socks: bool) {
+    if socks {
+        let _ = stream.write_all(&socks5_reply(SOCKS5_GENERAL_FAILURE));
+    }
+}
+
+const SOCKS5_SUCCEEDED: u8 = 0;
+const SOCKS5_GENERAL_FAILURE: u8 = 1;
+const SOCKS5_COMMAND_NOT_SUPPORTED: u8 = 7;
+const SOCKS5_ADDRESS_TYPE_NOT_SUPPORTED: u8 = 8;
+
+fn socks5_reply(status: u8) -> [u8; 10] {
+    // We don't know the address that the remote host used to make
+    // the connection, so we report 0.0.0.0:0, as OpenSSH does
+    [5, status, 0, 1, 0, 0, 0, 0, 0, 0]
+}
+
+/// Carries out the server side of a SOCKS5 handshake, accepting only
+/// unauthenticated CONNECT requests.  Returns the host and port to
+/// which the client wants to connect.  The caller is responsible for
+/// sending the final reply once the connection has been attempted.
+fn socks5_handshake<S: Read + Write>(stream: &mut S) -> anyhow::Result<(String, u16)> {
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header)?;
+    if header[0] != 5 {
+        anyhow::bail!("unsupported SOCKS version {}", header[0]);
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods)?;
+    if !methods.contains(&0) {
+        stream.write_all(&[5, 0xff])?;
+        anyhow::bail!("SOCKS client requires authentication");
+    }
+    stream.write_all(&[5, 0])?;
+
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request)?;
+    if request[0] != 5 {
+        anyhow::bail!("unsupported SOCKS version {}", request[0]);
+    }
+    if request[1] != 1 {
+        stream.write_all(&socks5_reply(SOCKS5_COMMAND_NOT_SUPPORTED))?;
+        anyhow::bail!("unsupported SOCKS command {}", request[1]);
+    }
+
+    let host = match request[3] {
+        1 => {
+            let mut addr = [0u8; 4];
+            stream.read_exact(&mut addr)?;
+            Ipv4Addr::from(addr).to_string()
+        }
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len)?;
+            let mut name = vec![0u8; len[0] as usize];
+            stream.read_exact(&mut name)?;
+            String::from_utf8(name).context("SOCKS host name is not UTF-8")?
+        }
+        4 => {
+            let mut addr = [0u8; 16];
+            stream.read_exact(&mut addr)?;
+            Ipv6Addr::from(addr).to_string()
+        }
+        address_type => {
+            stream.write_all(&socks5_reply(SOCKS5_ADDRESS_TYPE_NOT_SUPPORTED))?;
+            anyhow::bail!("unsupported SOCKS address type {address_type}");
+        }
+    };
+
+    let mut port = [0u8; 2];
+    stream.read_exact(&mut port)?;
+    Ok((host, u16::from_be_bytes(port)))
+}
+
+impl SessionInner {
+    /// Registers the forwards specified by the ssh config, and
+    /// starts listening for connections.  As with ssh, none are
+    /// registered when `ClearAllForwardings` is set.
+    pub fn establish_configured_forwards(&mut self, sess: &mut SessionWrap) {
+        if let Some("yes") = self.config.get("clearallforwardings").map(|s| s.as_str()) {
+            return;
+        }
+        for spec in ForwardSpec::from_config(&self.config) {
+            self.add_forward(
+                sess,
+                AddForward {
+                    spec,
+                    enabled: true,
+                    owner: None,
+                },
+            );
+        }
+    }
+
+    pub fn add_forward(&mut self, sess: &mut SessionWrap, add: AddForward) -> ForwardId {
+        // Several users of a shared session may each try to register
+        // their forwards; only the first registration counts
+        if let Some(entry) = self
+            .forwards
+            .values()
+            .find(|entry| entry.owner == add.owner && entry.spec == add.spec)
+        {
+            return entry.id;
+        }
+
+        let id = self.next_forward_id;
+        self.next_forward_id += 1;
+        self.forwards.insert(
+            id,
+            ForwardEntry {
+                id,
+                spec: add.spec,
+                owner: add.owner,
+                state: ForwardState::Disabled,
+                listener: None,
+            },
+        );
+        if add.enabled {
+            // Failure is recorded in the state of the entry
+            let _ = self.set_forward_enabled(sess, SetForwardEnabled { id, enabled: true });
+        }
+        id
+    }
+
+    pub fn list_forwards(&self) -> Vec<ForwardInfo> {
+        self.forwards.values().map(ForwardEntry::info).collect()
+    }
+
+    pub fn set_forward_enabled(
+        &mut self,
+        sess: &mut SessionWrap,
+        request: SetForwardEnabled,
+    ) -> anyhow::Result<()> {
+        let entry = self
+            .forwards
+            .get_mut(&request.id)
+            .ok_or_else(|| anyhow::anyhow!("invalid forward id {}", request.id))?;
+
+        if !request.enabled {
+            match entry.listener.take() {
+                Some(ForwardListener::Remote { listener, port }) if !listener.cancels_on_drop() => {
+                    // The remote host will continue to listen until the
+                    // session ends; we refuse connections while disabled
+                    // and pick this back up if we are re-enabled.
+                    entry.listener = Some(ForwardListener::Remote { listener, port });
+                }
+                _ => {}
+            }
+            entry.state = ForwardState::Disabled;
+            log::info!("disabled forward {}: {}", entry.id, entry.spec);
+            return Ok(());
+        }
+
+        if entry.listener.is_none() {
+            let result = match entry.spec.kind {
+                ForwardKind::Local | ForwardKind::Dynamic => {
+                    let address = entry.spec.local_bind_address();
+                    TcpListener::bind((address, entry.spec.bind_port))
+                        .and_then(|listener| {
+                            listener.set_nonblocking(true)?;
+                            Ok(listener)
+                        })
+                        .with_context(|| {
+                            format!(
+                                "listening on {}",
+                                format_host_port(address, entry.spec.bind_port)
+                            )
+                        })
+                        .map(ForwardListener::Local)
+                }
+                ForwardKind::Remote => sess
+                    .listen_remote_forward(entry.spec.remote_bind_address(), entry.spec.bind_port)
+                    .with_context(|| {
+                        format!(
+                            "asking the remote host to listen on port {}",
+                            entry.spec.bind_port
+                        )
+                    })
+                    .map(|(listener, port)| ForwardListener::Remote { listener, port }),
+            };
+
+            match result {
+                Ok(listener) => {
+                    entry.listener.replace(listener);
+                }
+                Err(err) => {
+                    log::error!("forward {}: {}: {:#}", entry.id, entry.spec, err);
+                    entry.state = ForwardState::Failed(format!("{:#}", err));
+                    return Err(err);
+                }
+            }
+        }
+
+        entry.state = ForwardState::Active;
+        log::info!("enabled forward {}: {}", entry.id, entry.spec);
+        Ok(())
+    }
+
+    /// Returns the listening sockets for the active local forwards,
+    /// so that they can be included in the poll set
+    pub fn forward_listeners(&self) -> impl Iterator<Item = &TcpListener> {
+        self.forwards
+            .values()
+            .filter(|entry| entry.state == ForwardState::Active)
+            .filter_map(|entry| match &entry.listener {
+                Some(ForwardListener::Local(listener)) => Some(listener),
+                _ => None,
+            })
+    }
+
+    /// Accepts any pending connections on our forwards, and picks up
+    /// the results of connections that were set up by helper threads
+    pub fn accept_forward_connections(&mut self, sess: &mut SessionWrap) {
+        let mut accepted = vec![];
+        for entry in self.forwards.values() {
+            if entry.state != ForwardState::Active {
+                continue;
+            }
+            if let Some(ForwardListener::Local(listener)) = &entry.listener {
+                loop {
+                    match listener.accept() {
+                        Ok((stream, peer)) => {
+                            log::trace!("forward {}: connection from {peer}", entry.id);
+                            accepted.push((entry.spec.clone(), stream));
+                        }
+                        Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                        Err(err) => {
+                            log::error!("forward {}: accept failed: {:#}", entry.id, err);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        for (spec, stream) in accepted {
+            if let Err(err) = self.local_forward_connection(&spec, stream) {
+                log::error!("{spec}: {:#}", err);
+            }
+        }
+
+        loop {
+            let mut listeners: Vec<(u16, &mut RemoteListener)> = self
+                .forwards
+                .values_mut()
+                .filter_map(|entry| match &mut entry.listener {
+                    Some(ForwardListener::Remote { listener, port }) => Some((*port, listener)),
+                    _ => None,
+                })
+                .collect();
+            if listeners.is_empty() {
+                break;
+            }
+            let accepted = sess.accept_remote_forward(&mut listeners);
+            drop(listeners);
+            match accepted {
+                Some((port, channel)) => self.remote_forward_connection(port, channel),
+                None => break,
+            }
+        }
+
+        while let Ok(event) = self.forward_rx.try_recv() {
+            if let Err(err) = self.process_forward_event(event) {
+                log::error!("forward: {:#}", err);
+            }
+        }
+
+        self.open_pending_direct_tcpip(sess);
+    }
+
+    /// Makes progress on opening the channels for connections to our
+    /// forwards.  This doesn't block, so that the session continues to
+    /// service its other channels while the remote host connects to
+    /// the target.  The channels are opened one at a time, in the
+    /// order that the connections arrived.
+    /// An open that takes longer than `DIRECT_TCPIP_OPEN_TIMEOUT` fails
+    /// its connection.  libssh tracks the open in the channel, so we
+    /// can simply drop it, but libssh2 tracks it in the session and
+    /// would hand the channel to whatever we open next, so with ssh2
+    /// we keep driving the open and close the channel once it arrives.
+    fn open_pending_direct_tcpip(&mut self, sess: &mut SessionWrap) {
+        while let Some(pending) = self.pending_direct_tcpip.front_mut() {
+            let result =
+                match sess.poll_direct_tcpip(&pending.host, pending.port, &mut pending.open) {
+                    Ok(None) if pending.started.elapsed() < DIRECT_TCPIP_OPEN_TIMEOUT => return,
+                    Ok(None) => {
+                        if let Some(stream) = pending.stream.take() {
+                            let target = format_host_port(&pending.host, pending.port);
+                            log::error!(
+                                "opening channel to {target}: timed out after {:?}",
+                                DIRECT_TCPIP_OPEN_TIMEOUT
+                            );
+                            fail_direct_tcpip(stream, pending.socks);
+                        }
+                        if sess.opens_one_channel_at_a_time() {
+                            return;
+                        }
+                        self.pending_direct_tcpip.pop_front();
+                        continue;
+                    }
+                    Ok(Some(channel)) => Ok(channel),
+                    Err(err) => Err(err),
+                };
+            let pending = self.pending_direct_tcpip.pop_front().unwrap();
+            let target = format_host_port(&pending.host, pending.port);
+
+            let mut stream = match pending.stream {
+                Some(stream) => stream,
+                None => {
+                    // We already failed this connection
+                    match result {
+                        Ok(mut channel) => channel.close(),
+                        Err(err) => log::debug!("opening channel to {target}: {:#}", err),
+                    }
+                    continue;
+                }
+            };
+
+            match result {
+                Ok(mut channel) => {
+                    if pending.socks {
+                        if let Err(err) = stream.write_all(&socks5_reply(SOCKS5_SUCCEEDED)) {
+                            log::debug!("SOCKS client for {target} went away: {:#}", err);
+                            channel.close();
+                            continue;
+                        }
+                    }
+                    if let Err(err) = self.add_stream_channel(channel, stream_to_fd(stream)) {
+                        log::error!("forwarding to {target}: {:#}", err);
+                    }
+                }
+                Err(err) => {
+                    fail_direct_tcpip(stream, pending.socks);
+                    log::error!("opening channel to {target}: {:#}", err);
+                }
+            }
+        }
+    }
+
+    fn local_forward_connection(
+        &mut self,
+        spec: &ForwardSpec,
+        stream: TcpStream,
+    ) -> anyhow::Result<()> {
+        match &spec.target {
+            Some((host, port)) => {
+                self.pending_direct_tcpip.push_back(PendingDirectTcpip {
+                    host: host.clone(),
+                    port: *port,
+                    stream: Some(stream),
+                    socks: false,
+                    open: DirectTcpipOpen::default(),
+                    started: Instant::now(),
+                });
+            }
+            None => {
+                // The SOCKS handshake requires a couple of round trips,
+                // so do that in a helper thread rather than blocking
+                // the session
+                stream.set_nonblocking(false)?;
+                let notifier = self.forward_notifier.clone();
+                std::thread::spawn(move || {
+                    let mut stream = stream;
+                    let _ = stream.set_read_timeout(Some(Duration::from_secs(30)));
+                    match socks5_handshake(&mut stream) {
+                        Ok((host, port)) => {
+                            let _ = stream.set_read_timeout(None);
+                            notifier.notify(ForwardEvent::SocksConnect { stream, host, port });
+                        }
+                        Err(err) => log::debug!("SOCKS handshake failed: {:#}", err),
+                    }
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn remote_forward_connection(&mut self, port: u16, mut channel: ChannelWrap) {
+        let target = self
+            .forwards
+            .values()
+            .find_map(|entry| match &entry.listener {
+                Some(ForwardListener::Remote { port: p, .. }) if *p == port => {
+                    if entry.state == ForwardState::Active {
+                        entry.spec.target.clone()
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            });
+
+        let (host, target_port) = match target {
+            Some(target) => target,
+            None => {
+                log::debug!("refusing connection on remote port {port}");
+                channel.close();
+                return;
+            }
+        };
+
+        let token = self.next_channel_id;
+        self.next_channel_id += 1;
+        self.pending_forward_channels.insert(token, channel);
+
+        let notifier = self.forward_notifier.clone();
+        std::thread::spawn(move || {
+            let stream = TcpStream::connect((host.as_str(), target_port));
+            notifier.notify(ForwardEvent::RemoteConnected { token, stream });
+        });
+    }
+
+    fn process_forward_event(&mut self, event: ForwardEvent) -> anyhow::Result<()> {
+        match event {
+            ForwardEvent::SocksConnect { stream, host, port } => {
+                self.pending_direct_tcpip.push_back(PendingDirectTcpip {
+                    host,
+                    port,
+                    stream: Some(stream),
+                    socks: true,
+                    open: DirectTcpipOpen::default(),
+                    started: Instant::now(),
+                });
+            }
+            ForwardEvent::RemoteConnected { token, stream } => {
+                let mut channel = self
+                    .pending_forward_channels
+                    .remove(&token)
+                    .ok_or_else(|| anyhow::anyhow!("no pending forward channel {token}"))?;
+                match stream {
+                    Ok(stream) => {
+                        self.add_stream_channel(channel, stream_to_fd(stream))?;
+                    }
+                    Err(err) => {
+                        channel.close();
+                        return Err(err).context("connecting remote forward to its target");
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn parse_forwards() {
+        assert_eq!(
+            ForwardSpec::parse(ForwardKind::Local, "8080 localhost:80").unwrap(),
+            ForwardSpec {
+                kind: ForwardKind::Local,
+                bind_address: None,
+                bind_port: 8080,
+                target: Some(("localhost".to_string(), 80)),
+            }
+        );
+        assert_eq!(
+            ForwardSpec::parse(ForwardKind::Remote, "*:9000:[::1]:3000").unwrap(),
+            ForwardSpec {
+                kind: ForwardKind::Remote,
+                bind_address: Some("*".to_string()),
+                bind_port: 9000,
+                target: Some(("::1".to_string(), 3000)),
+            }
+        );
+        assert_eq!(
+            ForwardSpec::parse(ForwardKind::Dynamic, "[::1]:1080").unwrap(),
+            ForwardSpec {
+                kind: ForwardKind::Dynamic,
+                bind_address: Some("::1".to_string()),
+                bind_port: 1080,
+                target: None,
+            }
+        );
+        assert_eq!(
+            ForwardSpec::parse(ForwardKind::Local, "127.0.0.1/5432 db/5432")
+                .unwrap()
+                .to_string(),
+            "local 127.0.0.1:5432 -> db:5432"
+        );
+
+        assert!(ForwardSpec::parse(ForwardKind::Local, "8080").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Local, "8080 db:nope").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Remote, "9000").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Local, "/tmp/sock db:80").is_err());
+
+        let mut config = ConfigMap::new();
+        config.insert(
+            "localforward".to_string(),
+            "8080 localhost:80,bogus,9090 db:5432".to_string(),
+        );
+        config.insert("dynamicforward".to_string(), "1080".to_string());
+        let forwards: Vec<String> = ForwardSpec::from_config(&config)
+            .iter()
+            .map(|f| f.to_string())
+            .collect();
+        assert_eq!(
+            forwards,
+            vec![
+                "local 8080 -> localhost:80",
+                "local 9090 -> db:5432",
+                "dynamic 1080 (SOCKS5)",
+            ]
+        );
+    }
+
+    struct FakeStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for FakeStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for FakeStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn socks5() {
+        let mut stream = FakeStream {
+            input: Cursor::new(vec![
+                5, 1, 0, // version, one method: no auth
+                5, 1, 0, 3, 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0, 80,
+            ]),
+            output: vec![],
+        };
+        assert_eq!(
+            socks5_handshake(&mut stream).unwrap(),
+            ("example".to_string(), 80)
+        );
+        assert_eq!(stream.output, vec![5, 0]);
+
+        let mut stream = FakeStream {
+            input: Cursor::new(
+                [
+                    &[5, 1, 0, 5, 1, 0, 4][..],
+                    &Ipv6Addr::LOCALHOST.octets(),
+                    &8080u16.to_be_bytes(),
+                ]
+                .concat(),
+            ),
+            output: vec![],
+        };
+        assert_eq!(
+            socks5_handshake(&mut stream).unwrap(),
+            ("::1".to_string(), 8080)
+        );
+
+        // Authentication is not supported
+        let mut stream = FakeStream {
+            input: Cursor::new(vec![5, 1, 2]),
+            output: vec![],
+        };
+        assert!(socks5_handshake(&mut stream).is_err());
+        assert_eq!(stream.output, vec![5, 0xff]);
+
+        // Nor is BIND
+        let mut stream = FakeStream {
+            input: Cursor::new(vec![5, 1, 0, 5, 2, 0, 1, 127, 0, 0, 1, 0, 80]),
+            output: vec![],
+        };
+        assert!(socks5_handshake(&mut stream).is_err());
+        assert_eq!(stream.output[2..4], [5, SOCKS5_COMMAND_NOT_SUPPORTED]);
+    }
+}
@@ -4,8 +4,11 @@ compile_error!("Either libssh-rs or ssh2 must be enabled!");
 mod auth;
 mod channelwrap;
 mod config;
+#[cfg(unix)]
+mod control;
 mod dirwrap;
 mod filewrap;
+mod forward;
 mod host;
 mod pty;
 mod session;
@@ -13,9 +16,11 @@ mod sessioninner;
 mod sessionwrap;
 mod sftp;
 mod sftpwrap;
+mod shared;
 
 pub use auth::*;
 pub use config::*;
+pub use forward::*;
 pub use host::*;
 pub use pty::*;
 pub use session::*;
@@ -114,6 +114,18 @@ impl SshChildProcess {
             }
         }
     }
+
+    /// Like `async_wait`, but returns None rather than a failed status
+    /// if the channel went away without reporting how the command
+    /// exited, which happens when the connection is lost.
+    pub async fn wait_for_exit_status(&mut self) -> Option<ExitStatus> {
+        if let Some(status) = self.exited.as_ref() {
+            return Some(status.clone());
+        }
+        let status = self.exit.recv().await.ok()?;
+        self.exited.replace(status.clone());
+        Some(status)
+    }
 }
 
 impl portable_pty::Child for SshChildProcess {
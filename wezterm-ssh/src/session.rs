@@ -1,15 +1,16 @@
 use crate::auth::*;
 use crate::config::ConfigMap;
+use crate::forward::*;
 use crate::host::*;
 use crate::pty::*;
 use crate::sessioninner::*;
 use crate::sftp::{Sftp, SftpRequest};
 use filedescriptor::{socketpair, FileDescriptor};
 use portable_pty::PtySize;
-use smol::channel::{bounded, Receiver, Sender};
-use std::collections::HashMap;
+use smol::channel::{bounded, unbounded, Receiver, Sender};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::io::Write;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 use std::time::{Duration, Instant};
 
 #[derive(Debug)]
@@ -58,9 +59,19 @@ pub(crate) enum SessionRequest {
     Exec(Exec, Sender<anyhow::Result<ExecResult>>),
     Sftp(SftpRequest),
     SignalChannel(SignalChannel),
+    DirectTcpIp(DirectTcpIp, Sender<anyhow::Result<FileDescriptor>>),
+    AddForward(AddForward, Sender<anyhow::Result<ForwardId>>),
+    ListForwards(Sender<anyhow::Result<Vec<ForwardInfo>>>),
+    SetForwardEnabled(SetForwardEnabled, Sender<anyhow::Result<()>>),
     SessionDropped,
 }
 
+#[derive(Debug)]
+pub(crate) struct DirectTcpIp {
+    pub host: String,
+    pub port: u16,
+}
+
 #[derive(Debug)]
 pub(crate) struct SignalChannel {
     pub channel: ChannelId,
@@ -76,16 +87,66 @@ pub(crate) struct Exec {
 #[derive(Clone)]
 pub struct Session {
     tx: SessionSender,
+    handle: Arc<SessionHandle>,
 }
 
-impl Drop for Session {
+/// Shared by all of the clones of a `Session`; lets the session
+/// know when the last of them has been dropped
+#[derive(Debug)]
+struct SessionHandle {
+    tx: SessionSender,
+}
+
+impl Drop for SessionHandle {
     fn drop(&mut self) {
         self.tx.try_send(SessionRequest::SessionDropped).ok();
         log::trace!("Drop Session");
     }
 }
 
+/// A reference to a `Session` that doesn't keep it alive
+#[derive(Clone)]
+pub(crate) struct WeakSession {
+    tx: SessionSender,
+    handle: Weak<SessionHandle>,
+}
+
+impl WeakSession {
+    pub fn upgrade(&self) -> Option<Session> {
+        Some(Session {
+            tx: self.tx.clone(),
+            handle: self.handle.upgrade()?,
+        })
+    }
+
+    /// Returns true if the session has terminated
+    pub fn is_closed(&self) -> bool {
+        self.tx.tx.is_closed()
+    }
+}
+
 impl Session {
+    fn with_sender(tx: SessionSender) -> Self {
+        Self {
+            handle: Arc::new(SessionHandle { tx: tx.clone() }),
+            tx,
+        }
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakSession {
+        WeakSession {
+            tx: self.tx.clone(),
+            handle: Arc::downgrade(&self.handle),
+        }
+    }
+
+    /// Returns true if the session has terminated, either because
+    /// it failed to connect, the connection was lost, or because it
+    /// was idle for longer than its idle timeout
+    pub fn is_closed(&self) -> bool {
+        self.tx.tx.is_closed()
+    }
+
     pub fn connect(config: ConfigMap) -> anyhow::Result<(Self, Receiver<SessionEvent>)> {
         let (tx_event, rx_event) = bounded(8);
         let (tx_req, rx_req) = bounded(8);
@@ -98,6 +159,41 @@ impl Session {
             pipe: Arc::new(Mutex::new(sender_write)),
         };
 
+        let idle_timeout = config
+            .get("wezterm_ssh_idle_timeout")
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs);
+
+        // If another wezterm process is sharing its connection to
+        // this host, then we can use that rather than connecting
+        // and authenticating all over again
+        #[cfg(unix)]
+        let control_path = crate::control::control_path(&config);
+        #[cfg(unix)]
+        if let Some(path) = &control_path {
+            match crate::control::ControlClient::connect(
+                path,
+                rx_req.clone(),
+                sender_read.try_clone()?,
+                idle_timeout,
+            ) {
+                Ok(client) => {
+                    tx_event
+                        .try_send(SessionEvent::Banner(Some(format!(
+                            "Using the shared connection at {}",
+                            path.display()
+                        ))))
+                        .ok();
+                    tx_event.try_send(SessionEvent::Authenticated).ok();
+                    std::thread::spawn(move || client.run());
+                    return Ok((Self::with_sender(session_sender), rx_event));
+                }
+                Err(err) => {
+                    log::debug!("not using shared connection {}: {:#}", path.display(), err);
+                }
+            }
+        }
+
         let keep_alive = config.get("serveraliveinterval").and_then(|value| {
             let seconds: u64 = value.parse().ok()?;
             if seconds == 0 {
@@ -108,6 +204,7 @@ impl Session {
         });
 
         let now = Instant::now();
+        let (forward_tx, forward_rx) = unbounded();
 
         let mut inner = SessionInner {
             config,
@@ -123,9 +220,26 @@ impl Session {
             shown_accept_env_error: false,
             last_keep_alive: now,
             keep_alive,
+            idle: IdleState::new(idle_timeout),
+            forwards: BTreeMap::new(),
+            next_forward_id: 1,
+            forward_notifier: ForwardNotifier {
+                tx: forward_tx,
+                pipe: Arc::clone(&session_sender.pipe),
+            },
+            forward_rx,
+            pending_forward_channels: HashMap::new(),
+            pending_direct_tcpip: VecDeque::new(),
         };
         std::thread::spawn(move || inner.run());
-        Ok((Self { tx: session_sender }, rx_event))
+        let session = Self::with_sender(session_sender);
+
+        #[cfg(unix)]
+        if let Some(path) = control_path {
+            crate::control::spawn_master(path, session.downgrade());
+        }
+
+        Ok((session, rx_event))
     }
 
     pub async fn request_pty(
@@ -175,6 +289,81 @@ impl Session {
         Ok(exec)
     }
 
+    /// Asks the remote host to open a TCP connection to `host:port`,
+    /// and returns a local socket that is connected to it via the
+    /// ssh session.  This is the mechanism that underpins `ProxyJump`.
+    pub async fn direct_tcpip(&self, host: &str, port: u16) -> anyhow::Result<FileDescriptor> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::DirectTcpIp(
+                DirectTcpIp {
+                    host: host.to_string(),
+                    port,
+                },
+                reply,
+            ))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Registers a port forward with the session.  If `enabled` is
+    /// true, then the session starts listening for connections
+    /// right away.  If that fails, the forward remains registered
+    /// in the `Failed` state, and can be retried via
+    /// `set_forward_enabled`.
+    /// Forwards from the `LocalForward`, `RemoteForward` and
+    /// `DynamicForward` options are registered automatically once
+    /// the session has been authenticated.
+    /// `owner` identifies the user of a shared session that the forward
+    /// belongs to; adding the same forward for the same owner again
+    /// returns the id of the existing forward.
+    pub async fn add_forward(
+        &self,
+        spec: ForwardSpec,
+        enabled: bool,
+        owner: Option<String>,
+    ) -> anyhow::Result<ForwardId> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::AddForward(
+                AddForward {
+                    spec,
+                    enabled,
+                    owner,
+                },
+                reply,
+            ))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Returns the port forwards that are registered with the session
+    pub async fn list_forwards(&self) -> anyhow::Result<Vec<ForwardInfo>> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::ListForwards(reply))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Starts or stops listening for connections on a port forward.
+    /// Connections that were made while the forward was enabled
+    /// are not affected by disabling it.
+    pub async fn set_forward_enabled(&self, id: ForwardId, enabled: bool) -> anyhow::Result<()> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::SetForwardEnabled(
+                SetForwardEnabled { id, enabled },
+                reply,
+            ))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
     /// Creates a new reference to the sftp channel for filesystem operations
     ///
     /// ### Note
@@ -1,9 +1,13 @@
 use crate::channelwrap::ChannelWrap;
 use crate::config::ConfigMap;
+use crate::config::{Config, JumpHost};
 use crate::dirwrap::DirWrap;
 use crate::filewrap::FileWrap;
+use crate::forward::{ForwardEntry, ForwardEvent, ForwardId, ForwardNotifier, PendingDirectTcpip};
 use crate::pty::*;
-use crate::session::{Exec, ExecResult, SessionEvent, SessionRequest, SignalChannel};
+use crate::session::{
+    DirectTcpIp, Exec, ExecResult, Session, SessionEvent, SessionRequest, SignalChannel,
+};
 use crate::sessionwrap::SessionWrap;
 use crate::sftp::dir::{Dir, DirId, DirRequest};
 use crate::sftp::file::{File, FileId, FileRequest};
@@ -17,7 +21,7 @@ use filedescriptor::{
 use portable_pty::ExitStatus;
 use smol::channel::{bounded, Receiver, Sender, TryRecvError};
 use socket2::{Domain, Socket, Type};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::io::{Read, Write};
 use std::net::ToSocketAddrs;
 use std::time::{Duration, Instant};
@@ -52,6 +56,16 @@ pub(crate) struct SessionInner {
     pub shown_accept_env_error: bool,
     pub last_keep_alive: Instant,
     pub keep_alive: Option<Duration>,
+    pub idle: IdleState,
+    pub forwards: BTreeMap<ForwardId, ForwardEntry>,
+    pub next_forward_id: ForwardId,
+    pub forward_notifier: ForwardNotifier,
+    pub forward_rx: Receiver<ForwardEvent>,
+    /// Channels that arrived on a remote forward, and that are
+    /// waiting for the connection to their local target
+    pub pending_forward_channels: HashMap<usize, ChannelWrap>,
+    /// Connections to our forwards whose channels are being opened
+    pub pending_direct_tcpip: VecDeque<PendingDirectTcpip>,
 }
 
 impl Drop for SessionInner {
@@ -331,7 +345,14 @@ impl SessionInner {
         hostname: &str,
         port: u16,
         verbose: bool,
-    ) -> anyhow::Result<(Socket, Option<KillOnDropChild>)> {
+    ) -> anyhow::Result<(Socket, Option<ProxyGuard>)> {
+        if let Some(proxy_jump) = self.config.get("proxyjump") {
+            let jump_hosts = JumpHost::parse_list(proxy_jump)?;
+            if !jump_hosts.is_empty() {
+                return self.connect_via_jump_hosts(&jump_hosts, hostname, port);
+            }
+        }
+
         match self.config.get("proxycommand").map(|s| s.as_str()) {
             Some("none") | None => {}
             Some(proxy_command) => {
@@ -365,14 +386,17 @@ impl SessionInner {
                         _ => raw,
                     };
 
-                    return Ok((Socket::from_raw_fd(dest), Some(KillOnDropChild(child))));
+                    return Ok((
+                        Socket::from_raw_fd(dest),
+                        Some(ProxyGuard::Command(KillOnDropChild(child))),
+                    ));
                 }
                 #[cfg(windows)]
                 unsafe {
                     use std::os::windows::io::{FromRawSocket, IntoRawSocket};
                     return Ok((
                         Socket::from_raw_socket(a.into_raw_socket()),
-                        Some(KillOnDropChild(child)),
+                        Some(ProxyGuard::Command(KillOnDropChild(child))),
                     ));
                 }
             }
@@ -403,6 +427,119 @@ impl SessionInner {
         Ok((sock, None))
     }
 
+    /// Connects to the last of `jump_hosts`, which will in turn
+    /// connect via the preceding jump hosts, and then asks it to open
+    /// a tunnel to `hostname:port`.  The host verification and
+    /// authentication prompts for the jump hosts are routed through
+    /// our own event channel.
+    fn connect_via_jump_hosts(
+        &self,
+        jump_hosts: &[JumpHost],
+        hostname: &str,
+        port: u16,
+    ) -> anyhow::Result<(Socket, Option<ProxyGuard>)> {
+        let depth = self
+            .config
+            .get("wezterm_ssh_jump_depth")
+            .and_then(|depth| depth.parse::<usize>().ok())
+            .unwrap_or(0);
+        if depth >= MAX_JUMP_DEPTH {
+            anyhow::bail!(
+                "too many nested ProxyJump hosts connecting to {hostname}; \
+                 does the ssh config for a jump host refer to itself?"
+            );
+        }
+
+        let (jump_host, preceding) = jump_hosts.split_last().unwrap();
+
+        let mut config = Config::new();
+        config.add_default_config_files();
+        // Options overridden for the target, such as via `ssh_option`,
+        // apply to the hops too
+        if let Some(overrides) = self.config.get("wezterm_ssh_option_overrides") {
+            for key in overrides.split_whitespace() {
+                if HOST_SPECIFIC_OPTIONS.contains(&key) {
+                    continue;
+                }
+                if let Some(value) = self.config.get(key) {
+                    config.set_option(key, value);
+                }
+            }
+            config.set_option("wezterm_ssh_option_overrides", overrides);
+        }
+        if let Some(user) = &jump_host.user {
+            config.set_option("user", user);
+        }
+        if let Some(port) = jump_host.port {
+            config.set_option("port", port.to_string());
+        }
+        if !preceding.is_empty() {
+            // The jump hosts that precede this one take precedence
+            // over any ProxyJump from its own config
+            let preceding: Vec<String> = preceding.iter().map(|h| h.to_string()).collect();
+            config.set_option("proxyjump", preceding.join(","));
+        }
+        // Use the same settings for the hop as for the target
+        for key in ["wezterm_ssh_backend", "wezterm_ssh_verbose"] {
+            if let Some(value) = self.config.get(key) {
+                config.set_option(key, value);
+            }
+        }
+        config.set_option("wezterm_ssh_jump_depth", (depth + 1).to_string());
+        // As with ssh, the forwards are only established by the target
+        config.set_option("clearallforwardings", "yes");
+
+        let jump_config = config.for_host(&jump_host.host);
+
+        self.tx_event
+            .try_send(SessionEvent::Banner(Some(format!(
+                "Connecting to {hostname}:{port} via jump host {jump_host}"
+            ))))
+            .context("notifying user of banner")?;
+
+        let (session, events) = Session::connect(jump_config)?;
+        loop {
+            match smol::block_on(events.recv()) {
+                Ok(SessionEvent::Authenticated) => break,
+                Ok(SessionEvent::Error(err)) => {
+                    anyhow::bail!("jump host {jump_host}: {err}");
+                }
+                Ok(event) => {
+                    smol::block_on(self.tx_event.send(event))
+                        .context("forwarding jump host event")?;
+                }
+                Err(_) => anyhow::bail!("connection to jump host {jump_host} was closed"),
+            }
+        }
+
+        let fd = smol::block_on(session.direct_tcpip(hostname, port))
+            .with_context(|| format!("tunnelling to {hostname}:{port} via {jump_host}"))?;
+
+        // Nothing else is expected from the hop, but keep reading its
+        // events so that it is never blocked on sending one to us
+        let hop = jump_host.to_string();
+        std::thread::spawn(move || {
+            while let Ok(event) = smol::block_on(events.recv()) {
+                if let SessionEvent::Error(err) = event {
+                    log::error!("jump host {hop}: {err}");
+                }
+            }
+        });
+
+        #[cfg(unix)]
+        let sock = unsafe {
+            use std::os::unix::io::{FromRawFd, IntoRawFd};
+            Socket::from_raw_fd(fd.into_raw_fd())
+        };
+        #[cfg(windows)]
+        let sock = unsafe {
+            use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+            Socket::from_raw_socket(fd.into_raw_socket())
+        };
+
+        Ok((sock, Some(ProxyGuard::JumpHost(session))))
+    }
+
     /// Used to restrict to_socket_addrs results to the address
     /// family specified by the config
     fn filter_sock_addr(&self, addr: &std::net::SocketAddr) -> bool {
@@ -447,12 +584,22 @@ impl SessionInner {
     fn request_loop(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
         let mut sleep_delay = Duration::from_millis(100);
 
+        sess.set_blocking(true);
+        self.establish_configured_forwards(sess);
+        sess.set_blocking(false);
+        self.idle.since = Instant::now();
+
         loop {
             self.do_keepalive(sess)?;
             self.tick_io()?;
             self.drain_request_pipe();
-            self.dispatch_pending_requests(sess)?;
+            // libssh2 can only open one channel at a time, so with ssh2,
+            // requests that may open channels wait until our forwards are done
+            if self.pending_direct_tcpip.is_empty() || !sess.opens_one_channel_at_a_time() {
+                self.dispatch_pending_requests(sess)?;
+            }
             self.connect_pending_agent_forward_channels(sess);
+            self.accept_forward_connections(sess);
 
             if self.channels.is_empty() && self.session_was_dropped {
                 log::trace!(
@@ -460,6 +607,20 @@ impl SessionInner {
                 );
                 return Ok(());
             }
+            // Port forwards are in use even when nothing is connected
+            // to them, so they keep the session alive
+            let in_use = !self.channels.is_empty() || !self.forwards.is_empty();
+            if self.idle.is_idle(in_use) {
+                log::debug!(
+                    "Closing ssh session to {} as it has been idle for {:?}",
+                    self.config
+                        .get("hostname")
+                        .map(|s| s.as_str())
+                        .unwrap_or("?"),
+                    self.idle.since.elapsed()
+                );
+                return Ok(());
+            }
 
             let mut poll_array = vec![
                 pollfd {
@@ -494,14 +655,33 @@ impl SessionInner {
                 }
             }
 
+            // Listening sockets for our forwards; we only need to wake up
+            // when they are readable, as they are serviced at the top
+            // of the loop by accept_forward_connections
+            for listener in self.forward_listeners() {
+                poll_array.push(pollfd {
+                    fd: listener.as_socket_descriptor(),
+                    events: POLLIN,
+                    revents: 0,
+                });
+            }
+
             poll(&mut poll_array, Some(sleep_delay)).context("poll")?;
             sleep_delay += sleep_delay;
+            if self.idle.timeout.is_some() {
+                // Wake up often enough to notice that we've become idle
+                sleep_delay = sleep_delay.min(IDLE_POLL_INTERVAL);
+            }
+            if !self.pending_direct_tcpip.is_empty() {
+                // Wake up often enough to time out channel opens
+                sleep_delay = sleep_delay.min(Duration::from_secs(1));
+            }
 
             for (idx, poll) in poll_array.iter().enumerate() {
                 if poll.revents != 0 {
                     sleep_delay = Duration::from_millis(100);
                 }
-                if idx == 0 || idx == 1 {
+                if idx == 0 || idx == 1 || idx - 2 >= mapping.len() {
                     // Dealt with at the top of the loop
                 } else if poll.revents != 0 {
                     let (channel_id, fd_num) = mapping[idx - 2];
@@ -667,6 +847,20 @@ impl SessionInner {
                     SessionRequest::Exec(exec, reply) => {
                         dispatch(reply, || self.exec(sess, exec), "exec")
                     }
+                    SessionRequest::DirectTcpIp(direct, reply) => {
+                        dispatch(reply, || self.direct_tcpip(sess, direct), "DirectTcpIp")
+                    }
+                    SessionRequest::AddForward(add, reply) => {
+                        dispatch(reply, || Ok(self.add_forward(sess, add)), "AddForward")
+                    }
+                    SessionRequest::ListForwards(reply) => {
+                        dispatch(reply, || Ok(self.list_forwards()), "ListForwards")
+                    }
+                    SessionRequest::SetForwardEnabled(request, reply) => dispatch(
+                        reply,
+                        || self.set_forward_enabled(sess, request),
+                        "SetForwardEnabled",
+                    ),
                     SessionRequest::SignalChannel(info) => {
                         if let Err(err) = self.signal_channel(&info) {
                             log::error!("{:?} -> error: {:#}", info, err);
@@ -916,6 +1110,55 @@ impl SessionInner {
         }
     }
 
+    pub fn direct_tcpip(
+        &mut self,
+        sess: &mut SessionWrap,
+        direct: DirectTcpIp,
+    ) -> anyhow::Result<FileDescriptor> {
+        let channel = sess.open_direct_tcpip(&direct.host, direct.port)?;
+
+        let (ours, theirs) = socketpair()?;
+        self.add_stream_channel(channel, ours)?;
+        Ok(theirs)
+    }
+
+    /// Registers a channel whose data is pumped to and from `fd`,
+    /// which is a socket connected to some local peer
+    pub fn add_stream_channel(
+        &mut self,
+        channel: ChannelWrap,
+        mut fd: FileDescriptor,
+    ) -> anyhow::Result<()> {
+        fd.set_non_blocking(true)?;
+
+        let read_from_peer = fd;
+        let write_to_peer = read_from_peer.try_clone()?;
+        let channel_id = self.next_channel_id;
+        self.next_channel_id += 1;
+        let info = ChannelInfo {
+            channel_id,
+            channel,
+            exit: None,
+            exited: false,
+            descriptors: [
+                DescriptorState {
+                    fd: Some(read_from_peer),
+                    buf: VecDeque::with_capacity(8192),
+                },
+                DescriptorState {
+                    fd: Some(write_to_peer),
+                    buf: VecDeque::with_capacity(8192),
+                },
+                DescriptorState {
+                    fd: None,
+                    buf: VecDeque::with_capacity(8192),
+                },
+            ],
+        };
+        self.channels.insert(channel_id, info);
+        Ok(())
+    }
+
     pub fn signal_channel(&mut self, info: &SignalChannel) -> anyhow::Result<()> {
         let chan_info = self
             .channels
@@ -1116,7 +1359,7 @@ fn read_into_buf<R: Read>(r: &mut R, buf: &mut VecDeque<u8>) -> std::io::Result<
 
 /// A little helper to ensure that the Result returned by `f()`
 /// is routed via a Sender
-fn dispatch<T, F>(reply: Sender<T>, f: F, what: &str) -> anyhow::Result<bool>
+pub(crate) fn dispatch<T, F>(reply: Sender<T>, f: F, what: &str) -> anyhow::Result<bool>
 where
     F: FnOnce() -> T,
     T: Send + Sync + 'static,
@@ -1127,6 +1370,83 @@ where
     Ok(true)
 }
 
+/// Guards against runaway recursion when the config for a jump
+/// host specifies a ProxyJump that leads back to itself
+const MAX_JUMP_DEPTH: usize = 8;
+
+/// Options that describe how to reach the target host itself, and
+/// so are not carried over from the target to its jump hosts
+const HOST_SPECIFIC_OPTIONS: &[&str] = &[
+    "hostname",
+    "user",
+    "port",
+    "proxyjump",
+    "proxycommand",
+    "localforward",
+    "remoteforward",
+    "dynamicforward",
+    "wezterm_ssh_jump_depth",
+];
+
+/// How long a session with an idle timeout waits for its first
+/// channel before it is considered to be idle
+const STARTUP_IDLE_GRACE: Duration = Duration::from_secs(30);
+
+/// The longest that a session with an idle timeout will sleep
+/// between checks for idleness
+pub(crate) const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Tracks how long a session has been without any channels
+#[derive(Debug)]
+pub(crate) struct IdleState {
+    /// If set, the session is closed after it has had no channels
+    /// for this long, even if there are still `Session` handles
+    pub timeout: Option<Duration>,
+    pub since: Instant,
+    had_channels: bool,
+}
+
+impl IdleState {
+    pub fn new(timeout: Option<Duration>) -> Self {
+        Self {
+            timeout,
+            since: Instant::now(),
+            had_channels: false,
+        }
+    }
+
+    /// Returns true if the session has had no channels for
+    /// longer than the idle timeout
+    pub fn is_idle(&mut self, have_channels: bool) -> bool {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => return false,
+        };
+        if have_channels {
+            self.had_channels = true;
+            self.since = Instant::now();
+            return false;
+        }
+        // Give whoever caused us to connect a chance to open
+        // their first channel
+        let timeout = if self.had_channels {
+            timeout
+        } else {
+            timeout.max(STARTUP_IDLE_GRACE)
+        };
+        self.since.elapsed() >= timeout
+    }
+}
+
+/// Keeps alive whatever is relaying the connection to the remote host.
+/// The contents are never read; they are only held so that they are
+/// dropped along with the connection.
+#[allow(dead_code)]
+enum ProxyGuard {
+    Command(KillOnDropChild),
+    JumpHost(Session),
+}
+
 /// A little helper to ensure the Child process is killed on Drop.
 struct KillOnDropChild(std::process::Child);
 
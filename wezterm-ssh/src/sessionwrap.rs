@@ -14,6 +14,44 @@ pub(crate) struct LibSshSession {
     pub sftp: Option<SftpWrap>,
 }
 
+/// A port on the remote host that is listening on our behalf
+pub(crate) enum RemoteListener {
+    #[cfg(feature = "ssh2")]
+    Ssh2(ssh2::Listener),
+
+    /// libssh accepts connections for all of the remote forwards
+    /// via the session, so there is no per-forward state
+    #[cfg(feature = "libssh-rs")]
+    LibSsh,
+}
+
+impl RemoteListener {
+    /// Returns true if dropping this listener asks the remote host
+    /// to stop listening
+    pub fn cancels_on_drop(&self) -> bool {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(_) => true,
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh => false,
+        }
+    }
+}
+
+/// The progress of a direct-tcpip channel that is being opened
+/// without blocking
+#[derive(Default)]
+pub(crate) struct DirectTcpipOpen {
+    #[cfg(feature = "libssh-rs")]
+    channel: Option<libssh_rs::Channel>,
+}
+
+/// The libssh2 error code that indicates that the operation
+/// would block
+#[cfg(feature = "ssh2")]
+const LIBSSH2_ERROR_EAGAIN: std::os::raw::c_int = -37;
+
 pub(crate) enum SessionWrap {
     #[cfg(feature = "ssh2")]
     Ssh2(Ssh2Session),
@@ -43,6 +81,18 @@ impl SessionWrap {
         }
     }
 
+    /// libssh2 tracks the progress of opening a channel in the session,
+    /// so it can't open another channel until that one is done
+    pub fn opens_one_channel_at_a_time(&self) -> bool {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(_) => true,
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(_) => false,
+        }
+    }
+
     pub fn get_poll_flags(&self) -> i16 {
         match self {
             #[cfg(feature = "ssh2")]
@@ -93,6 +143,121 @@ impl SessionWrap {
         }
     }
 
+    pub fn open_direct_tcpip(&self, host: &str, port: u16) -> anyhow::Result<ChannelWrap> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let channel = sess.sess.channel_direct_tcpip(host, port, None)?;
+                Ok(ChannelWrap::Ssh2(channel))
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let channel = sess.sess.new_channel()?;
+                channel.open_forward(host, port, "127.0.0.1", 0)?;
+                Ok(ChannelWrap::LibSsh(channel))
+            }
+        }
+    }
+
+    /// Opens a direct-tcpip channel without blocking.  Returns None if
+    /// we are still waiting on the remote host, in which case this
+    /// should be called again, with the same arguments, once the session
+    /// is ready.  libssh2 tracks the progress of the open in the session,
+    /// so no other channel may be opened in the meantime.
+    pub fn poll_direct_tcpip(
+        &self,
+        host: &str,
+        port: u16,
+        open: &mut DirectTcpipOpen,
+    ) -> anyhow::Result<Option<ChannelWrap>> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => match sess.sess.channel_direct_tcpip(host, port, None) {
+                Ok(channel) => Ok(Some(ChannelWrap::Ssh2(channel))),
+                Err(err)
+                    if matches!(err.code(), ssh2::ErrorCode::Session(LIBSSH2_ERROR_EAGAIN)) =>
+                {
+                    Ok(None)
+                }
+                Err(err) => Err(err.into()),
+            },
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let channel = match open.channel.take() {
+                    Some(channel) => channel,
+                    None => sess.sess.new_channel()?,
+                };
+                match channel.open_forward(host, port, "127.0.0.1", 0) {
+                    Ok(()) => Ok(Some(ChannelWrap::LibSsh(channel))),
+                    Err(libssh_rs::Error::TryAgain) => {
+                        open.channel.replace(channel);
+                        Ok(None)
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    }
+
+    /// Asks the remote host to listen on `bind_address:port`.
+    /// Returns the listener and the port that is being listened on,
+    /// which is allocated by the remote host when `port` is 0.
+    pub fn listen_remote_forward(
+        &mut self,
+        bind_address: Option<&str>,
+        port: u16,
+    ) -> anyhow::Result<(RemoteListener, u16)> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let (listener, port) =
+                    sess.sess.channel_forward_listen(port, bind_address, None)?;
+                Ok((RemoteListener::Ssh2(listener), port))
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let bound_port = sess.sess.listen_forward(bind_address, port)?;
+                Ok((
+                    RemoteListener::LibSsh,
+                    if bound_port == 0 { port } else { bound_port },
+                ))
+            }
+        }
+    }
+
+    /// Returns the next pending connection that the remote host
+    /// received on one of `listeners`, along with the port on which
+    /// it was received.  `listeners` are (port, listener) pairs.
+    pub fn accept_remote_forward(
+        &mut self,
+        listeners: &mut [(u16, &mut RemoteListener)],
+    ) -> Option<(u16, ChannelWrap)> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(_sess) => {
+                for (port, listener) in listeners.iter_mut() {
+                    #[allow(irrefutable_let_patterns)]
+                    if let RemoteListener::Ssh2(listener) = listener {
+                        if let Ok(channel) = listener.accept() {
+                            return Some((*port, ChannelWrap::Ssh2(channel)));
+                        }
+                    }
+                }
+                None
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => sess
+                .sess
+                .accept_forward(std::time::Duration::ZERO)
+                .ok()
+                .map(|(port, channel)| (port, ChannelWrap::LibSsh(channel))),
+        }
+    }
+
     pub fn accept_agent_forward(&mut self) -> Option<ChannelWrap> {
         match self {
             // Unimplemented for now, an error message was printed earlier when the user tries to
@@ -0,0 +1,187 @@
+//! Sharing of authenticated sessions between the users of this
+//! crate within the same process, so that opening another tab to
+//! a host doesn't require authenticating all over again.
+use crate::config::ConfigMap;
+use crate::session::{Session, SessionEvent};
+use smol::channel::{bounded, Receiver, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+struct SharedSession {
+    id: usize,
+    key: String,
+    session: Session,
+    state: SharedState,
+}
+
+enum SharedState {
+    /// The session is still connecting; the senders are
+    /// notified of the outcome
+    Authenticating(Vec<Sender<SessionEvent>>),
+    Authenticated,
+}
+
+static SHARED_SESSIONS: Mutex<Vec<SharedSession>> = Mutex::new(Vec::new());
+static NEXT_SHARED_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Computes the identity of the connection that `config` would make.
+/// Sessions with the same identity are interchangeable once they
+/// have been authenticated.  Every option is included, as options
+/// such as `IdentityFile` and `ForwardAgent` affect what the
+/// remote host allows the session to do.
+fn shared_session_key(config: &ConfigMap) -> String {
+    config
+        .iter()
+        .map(|(name, value)| format!("{name}={value}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Session {
+    /// Like `Session::connect`, but if there is already a session in
+    /// this process that is connected to the same host, as the same
+    /// user, then that session is returned instead of making a new
+    /// connection.
+    ///
+    /// Only the caller that causes the connection to be made receives
+    /// the host verification and authentication events; the others
+    /// receive `SessionEvent::Authenticated` once it has completed, or
+    /// `SessionEvent::Error` if it failed.
+    ///
+    /// A shared session remains open until it has been idle for the
+    /// `wezterm_ssh_idle_timeout` from its config, after which
+    /// `is_closed` returns true and the next call to this function
+    /// makes a new connection.
+    pub fn connect_shared(config: ConfigMap) -> anyhow::Result<(Self, Receiver<SessionEvent>)> {
+        let key = shared_session_key(&config);
+        let mut shared = SHARED_SESSIONS.lock().unwrap();
+        shared.retain(|entry| !entry.session.is_closed());
+
+        if let Some(entry) = shared.iter_mut().find(|entry| entry.key == key) {
+            let (tx, rx) = bounded(2);
+            match &mut entry.state {
+                SharedState::Authenticated => {
+                    tx.try_send(SessionEvent::Authenticated).ok();
+                }
+                SharedState::Authenticating(waiters) => {
+                    tx.try_send(SessionEvent::Banner(Some(
+                        "Waiting for the connection to be authenticated in another pane"
+                            .to_string(),
+                    )))
+                    .ok();
+                    waiters.push(tx);
+                }
+            }
+            return Ok((entry.session.clone(), rx));
+        }
+
+        let (session, events) = Session::connect(config)?;
+        let id = NEXT_SHARED_ID.fetch_add(1, Ordering::Relaxed);
+        shared.push(SharedSession {
+            id,
+            key,
+            session: session.clone(),
+            state: SharedState::Authenticating(vec![]),
+        });
+        drop(shared);
+
+        let (tx, rx) = bounded(8);
+        std::thread::spawn(move || relay_shared_events(id, events, tx));
+        Ok((session, rx))
+    }
+}
+
+/// Passes the events from a newly connecting shared session on to the
+/// caller that initiated it, and then notifies any other callers that
+/// are waiting to use it of the outcome.
+fn relay_shared_events(id: usize, events: Receiver<SessionEvent>, tx: Sender<SessionEvent>) {
+    let outcome = loop {
+        match smol::block_on(events.recv()) {
+            Ok(SessionEvent::Authenticated) => {
+                smol::block_on(tx.send(SessionEvent::Authenticated)).ok();
+                break None;
+            }
+            Ok(SessionEvent::Error(err)) => {
+                smol::block_on(tx.send(SessionEvent::Error(err.clone()))).ok();
+                break Some(err);
+            }
+            Ok(event) => {
+                if smol::block_on(tx.send(event)).is_err() {
+                    // Nobody is there to answer any prompts,
+                    // so authentication will fail and we'll
+                    // pick that up on the next iteration
+                    log::trace!("initiator of shared session {} went away", id);
+                }
+            }
+            Err(_) => break Some("the connection was closed".to_string()),
+        }
+    };
+
+    let mut shared = SHARED_SESSIONS.lock().unwrap();
+    let idx = match shared.iter().position(|entry| entry.id == id) {
+        Some(idx) => idx,
+        None => return,
+    };
+    let waiters = match std::mem::replace(&mut shared[idx].state, SharedState::Authenticated) {
+        SharedState::Authenticating(waiters) => waiters,
+        SharedState::Authenticated => vec![],
+    };
+    let event = match outcome {
+        None => SessionEvent::Authenticated,
+        Some(err) => {
+            shared.remove(idx);
+            SessionEvent::Error(format!("shared connection failed: {}", err))
+        }
+    };
+    for waiter in waiters {
+        let event = match &event {
+            SessionEvent::Error(err) => SessionEvent::Error(err.clone()),
+            _ => SessionEvent::Authenticated,
+        };
+        waiter.try_send(event).ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config_for(pairs: &[(&str, &str)]) -> ConfigMap {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn shared_key() {
+        let a = config_for(&[("user", "me"), ("hostname", "foo"), ("port", "22")]);
+        let b = config_for(&[
+            ("user", "me"),
+            ("hostname", "foo"),
+            ("port", "22"),
+            ("identityfile", "/some/key"),
+        ]);
+        let c = config_for(&[("user", "you"), ("hostname", "foo"), ("port", "22")]);
+        let d = config_for(&[
+            ("user", "me"),
+            ("hostname", "foo"),
+            ("port", "22"),
+            ("proxyjump", "bastion"),
+        ]);
+
+        let e = config_for(&[
+            ("user", "me"),
+            ("hostname", "foo"),
+            ("port", "22"),
+            ("forwardagent", "yes"),
+        ]);
+
+        assert_eq!(shared_session_key(&a), shared_session_key(&a.clone()));
+        // Authenticating differently may give the session different rights
+        assert_ne!(shared_session_key(&a), shared_session_key(&b));
+        assert_ne!(shared_session_key(&a), shared_session_key(&c));
+        assert_ne!(shared_session_key(&a), shared_session_key(&d));
+        assert_ne!(shared_session_key(&a), shared_session_key(&e));
+    }
+}
@@ -769,7 +769,7 @@ impl Line {
             self.bits |= LineBits::HAS_HYPERLINK;
         }
 
-        if let CellStorage::C(cl) = &mut self.cells {
+        if let (CellStorage::C(cl), None) = (&mut self.cells, attr.text_size()) {
             if idx > cl.len() && text == " " && attr == CellAttributes::blank() {
                 // Appending blank beyond end of line; is already
                 // implicitly blank
@@ -822,7 +822,7 @@ impl Line {
             self.bits |= LineBits::HAS_HYPERLINK;
         }
 
-        if let CellStorage::C(cl) = &mut self.cells {
+        if let (CellStorage::C(cl), None) = (&mut self.cells, cell.attrs().text_size()) {
             if idx > cl.len() && cell == Cell::blank() {
                 // Appending blank beyond end of line; is already
                 // implicitly blank
@@ -858,8 +858,12 @@ impl Line {
 
         // For double-wide or wider chars, ensure that the cells that
         // are overlapped by this one are blanked out.
-        for i in 1..=width.saturating_sub(1) {
-            self.raw_set_cell(idx + i, Cell::blank_with_attrs(cell.attrs().clone()), clear);
+        if width > 1 {
+            let mut attrs = cell.attrs().clone();
+            attrs.set_text_size(None);
+            for i in 1..width {
+                self.raw_set_cell(idx + i, Cell::blank_with_attrs(attrs.clone()), clear);
+            }
         }
 
         self.raw_set_cell(idx, cell, clear);
@@ -1068,6 +1072,15 @@ impl Line {
     /// for mutation.
     pub fn compress_for_scrollback(&mut self) {
         let cv = match &self.cells {
+            // The clustered representation cannot represent the width
+            // of text-sized cells, so leave those lines uncompressed
+            CellStorage::V(_)
+                if self
+                    .visible_cells()
+                    .any(|cell| cell.attrs().text_size().is_some()) =>
+            {
+                return
+            }
             CellStorage::V(v) => ClusteredLine::from_cell_vec(v.len(), self.visible_cells()),
             CellStorage::C(_) => return,
         };
@@ -1,10 +1,11 @@
 #![cfg(all(not(target_os = "macos"), not(windows)))]
 //! See <https://developer.gnome.org/notification-spec/>
 
-use crate::ToastNotification;
+use crate::{ToastNotification, ToastUrgency};
 use futures_util::stream::{abortable, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
 use zbus::proxy;
 use zvariant::{Type, Value};
 
@@ -86,6 +87,9 @@ impl Reason {
     }
 }
 
+/// Maps the tag of a notification to the id assigned by the server
+static TAGGED: LazyLock<Mutex<HashMap<String, u32>>> = LazyLock::new(Default::default);
+
 async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::error::Error>> {
     let connection = zbus::ConnectionBuilder::session()?.build().await?;
 
@@ -100,24 +104,48 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
     }
 
     let mut hints = HashMap::new();
-    hints.insert("urgency", Value::U8(2 /* Critical */));
+    hints.insert(
+        "urgency",
+        Value::U8(match notif.urgency {
+            ToastUrgency::Low => 0,
+            ToastUrgency::Normal => 1,
+            ToastUrgency::Critical => 2,
+        }),
+    );
+
+    let mut actions = vec![];
+    if notif.url.is_some() {
+        actions.extend_from_slice(&["show", "Show"]);
+    }
+    if notif.on_activate.is_some() {
+        // The "default" action is invoked when the notification
+        // itself is clicked
+        actions.extend_from_slice(&["default", "Activate"]);
+    }
+
+    let replaces_id = notif
+        .tag
+        .as_ref()
+        .and_then(|tag| TAGGED.lock().unwrap().get(tag).copied())
+        .unwrap_or(0);
+
     let notification = proxy
         .notify(
             "wezterm",
-            0,
+            replaces_id,
             "org.wezfurlong.wezterm",
             &notif.title,
             &notif.message,
-            if notif.url.is_some() {
-                &["show", "Show"]
-            } else {
-                &[]
-            },
+            &actions,
             &hints,
             notif.timeout.map(|d| d.as_millis() as _).unwrap_or(0),
         )
         .await?;
 
+    if let Some(tag) = &notif.tag {
+        TAGGED.lock().unwrap().insert(tag.clone(), notification);
+    }
+
     let (mut invoked_stream, abort_invoked) = abortable(proxy.receive_action_invoked().await?);
     let (mut closed_stream, abort_closed) = abortable(proxy.receive_notification_closed().await?);
 
@@ -126,7 +154,11 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
             while let Some(signal) = invoked_stream.next().await {
                 let args = signal.args()?;
                 if args.nid == notification {
-                    if let Some(url) = notif.url.as_ref() {
+                    if args.action_key == "default" {
+                        if let Some(on_activate) = &notif.on_activate {
+                            on_activate.call();
+                        }
+                    } else if let Some(url) = notif.url.as_ref() {
                         wezterm_open_url::open_url(url);
                         abort_closed.abort();
                         break;
@@ -140,6 +172,15 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
                 let args = signal.args()?;
                 let _reason = Reason::new(args.reason);
                 if args.nid == notification {
+                    if let Some(tag) = &notif.tag {
+                        let mut tagged = TAGGED.lock().unwrap();
+                        if tagged.get(tag) == Some(&notification) {
+                            tagged.remove(tag);
+                        }
+                    }
+                    if let Some(on_close) = &notif.on_close {
+                        on_close.call();
+                    }
                     abort_invoked.abort();
                     break;
                 }
@@ -162,3 +203,21 @@ pub fn show_notif(notif: ToastNotification) -> Result<(), Box<dyn std::error::Er
     });
     Ok(())
 }
+
+pub fn close_notif(tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let nid = match TAGGED.lock().unwrap().get(tag).copied() {
+        Some(nid) => nid,
+        None => return Ok(()),
+    };
+    std::thread::spawn(move || {
+        let res = async_io::block_on(async move {
+            let connection = zbus::ConnectionBuilder::session()?.build().await?;
+            let proxy = NotificationsProxy::new(&connection).await?;
+            proxy.close_notification(nid).await
+        });
+        if let Err(err) = res {
+            log::error!("while closing notification: {:#}", err);
+        }
+    });
+    Ok(())
+}
@@ -2,12 +2,50 @@ mod dbus;
 mod macos;
 mod windows;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ToastNotification {
     pub title: String,
     pub message: String,
     pub url: Option<String>,
     pub timeout: Option<std::time::Duration>,
+    /// Identifies the notification so that it can be replaced by a
+    /// later notification with the same tag, or closed via
+    /// `close_notification`
+    pub tag: Option<String>,
+    pub urgency: ToastUrgency,
+    /// Called when the user activates (clicks on) the notification
+    pub on_activate: Option<ToastCallback>,
+    /// Called when the notification is dismissed or closed
+    pub on_close: Option<ToastCallback>,
+}
+
+/// Notifications have always been shown as critical, so that remains
+/// the default; only applications that ask for it get something else
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ToastUrgency {
+    Low,
+    Normal,
+    #[default]
+    Critical,
+}
+
+#[derive(Clone)]
+pub struct ToastCallback(std::sync::Arc<dyn Fn() + Send + Sync>);
+
+impl ToastCallback {
+    pub fn new<F: Fn() + Send + Sync + 'static>(func: F) -> Self {
+        Self(std::sync::Arc::new(func))
+    }
+
+    pub fn call(&self) {
+        (self.0)()
+    }
+}
+
+impl std::fmt::Debug for ToastCallback {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str("ToastCallback")
+    }
 }
 
 impl ToastNotification {
@@ -30,6 +68,11 @@ mod nop {
     pub fn show_notif(_: ToastNotification) -> Result<(), Box<dyn std::error::Error>> {
         Ok(())
     }
+
+    #[allow(dead_code)]
+    pub fn close_notif(_: &str) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
 }
 
 pub fn show(notif: ToastNotification) {
@@ -38,12 +81,19 @@ pub fn show(notif: ToastNotification) {
     }
 }
 
+/// Close a notification that was previously shown with the specified tag
+pub fn close_notification(tag: &str) {
+    if let Err(err) = backend::close_notif(tag) {
+        log::error!("Failed to close notification: {}", err);
+    }
+}
+
 pub fn persistent_toast_notification_with_click_to_open_url(title: &str, message: &str, url: &str) {
     show(ToastNotification {
         title: title.to_string(),
         message: message.to_string(),
         url: Some(url.to_string()),
-        timeout: None,
+        ..Default::default()
     });
 }
 
@@ -51,8 +101,7 @@ pub fn persistent_toast_notification(title: &str, message: &str) {
     show(ToastNotification {
         title: title.to_string(),
         message: message.to_string(),
-        url: None,
-        timeout: None,
+        ..Default::default()
     });
 }
 
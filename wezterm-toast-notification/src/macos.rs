@@ -1,5 +1,5 @@
 #![cfg(target_os = "macos")]
-use crate::ToastNotification;
+use crate::{ToastCallback, ToastNotification};
 use block2::{Block, RcBlock};
 use objc2::rc::Retained;
 use objc2::runtime::{Bool, NSObject, NSObjectProtocol, ProtocolObject};
@@ -11,7 +11,20 @@ use objc2_user_notifications::{
     UNNotificationPresentationOptions, UNNotificationRequest, UNNotificationResponse,
     UNUserNotificationCenter, UNUserNotificationCenterDelegate,
 };
-use std::sync::{LazyLock, Once};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex, Once};
+
+const DEFAULT_ACTION: &str = "com.apple.UNNotificationDefaultActionIdentifier";
+const DISMISS_ACTION: &str = "com.apple.UNNotificationDismissActionIdentifier";
+
+#[derive(Default)]
+struct Callbacks {
+    on_activate: Option<ToastCallback>,
+    on_close: Option<ToastCallback>,
+}
+
+/// Maps notification identifiers to the callbacks registered for them
+static CALLBACKS: LazyLock<Mutex<HashMap<String, Callbacks>>> = LazyLock::new(Default::default);
 
 const NEEDS_SIGN: &str = "Note that the application must be code-signed \
                           for UNUserNotificationCenter to work";
@@ -60,15 +73,30 @@ define_class!(
             completion_handler: &Block<dyn Fn()>,
         ) {
             let action = response.actionIdentifier();
-            let user_info = response.notification().request().content().userInfo();
+            let request = response.notification().request();
+            let identifier = request.identifier().to_string();
+            let user_info = request.content().userInfo();
             let url = user_info.valueForKey(ns_string!("url"));
 
             log::debug!("did_receive_notification -> action={action:?} url={url:?}");
 
-            if let Some(url) = url {
+            let action = action.to_string();
+            if action == DISMISS_ACTION {
+                if let Some(callbacks) = CALLBACKS.lock().unwrap().remove(&identifier) {
+                    if let Some(on_close) = callbacks.on_close {
+                        on_close.call();
+                    }
+                }
+            } else if let Some(url) = url {
                 if let Ok(url_str) = url.downcast::<NSString>() {
                     wezterm_open_url::open_url(&url_str.to_string());
                 }
+            } else if action == DEFAULT_ACTION {
+                if let Some(callbacks) = CALLBACKS.lock().unwrap().get(&identifier) {
+                    if let Some(on_activate) = &callbacks.on_activate {
+                        on_activate.call();
+                    }
+                }
             }
 
             completion_handler.call(());
@@ -123,7 +151,17 @@ pub fn initialize() {
                 &NSArray::from_slice(&[]),
                 UNNotificationCategoryOptions::CustomDismissAction,
             );
-        CENTER.setNotificationCategories(&NSSet::from_retained_slice(&[show_url_cat]));
+        // A category without any buttons, so that we get told about
+        // the notification being dismissed
+        let activate_cat =
+            UNNotificationCategory::categoryWithIdentifier_actions_intentIdentifiers_options(
+                ns_string!("ACTIVATE_ACTION"),
+                &NSArray::from_slice(&[]),
+                &NSArray::from_slice(&[]),
+                UNNotificationCategoryOptions::CustomDismissAction,
+            );
+        CENTER
+            .setNotificationCategories(&NSSet::from_retained_slice(&[show_url_cat, activate_cat]));
 
         let delegate = NotifDelegate::new();
         let delegate_proto = ProtocolObject::from_retained(delegate.clone());
@@ -154,6 +192,11 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
         notif.setTitle(&NSString::from_str(&toast.title));
         notif.setBody(&NSString::from_str(&toast.message));
 
+        let identifier = toast
+            .tag
+            .clone()
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
         if let Some(url) = &toast.url {
             let info =
                 NSDictionary::from_slices(&[ns_string!("url")], &[&*NSString::from_str(&url)]);
@@ -162,9 +205,19 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
                     .expect("is NSDictionary"),
             );
             notif.setCategoryIdentifier(ns_string!("SHOW_URL_ACTION"));
+        } else if toast.on_activate.is_some() || toast.on_close.is_some() {
+            notif.setCategoryIdentifier(ns_string!("ACTIVATE_ACTION"));
         }
 
-        let identifier = uuid::Uuid::new_v4().to_string();
+        if toast.on_activate.is_some() || toast.on_close.is_some() {
+            CALLBACKS.lock().unwrap().insert(
+                identifier.clone(),
+                Callbacks {
+                    on_activate: toast.on_activate.clone(),
+                    on_close: toast.on_close.clone(),
+                },
+            );
+        }
         let request = UNNotificationRequest::requestWithIdentifier_content_trigger(
             &NSString::from_str(&identifier),
             &*notif,
@@ -199,3 +252,13 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
 
     Ok(())
 }
+
+pub fn close_notif(tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    CALLBACKS.lock().unwrap().remove(tag);
+    unsafe {
+        let ident_array = NSArray::from_retained_slice(&[NSString::from_str(tag)]);
+        CENTER.removePendingNotificationRequestsWithIdentifiers(&ident_array);
+        CENTER.removeDeliveredNotificationsWithIdentifiers(&ident_array);
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 #![cfg(windows)]
 
-use crate::ToastNotification as TN;
+use crate::{ToastNotification as TN, ToastUrgency};
 use xml::escape::escape_str_pcdata;
 
 use windows::core::{Error as WinError, IInspectable, Interface, HSTRING};
@@ -8,9 +8,12 @@ use windows::Data::Xml::Dom::XmlDocument;
 use windows::Foundation::TypedEventHandler;
 use windows::Win32::Foundation::E_POINTER;
 use windows::UI::Notifications::{
-    ToastActivatedEventArgs, ToastNotification, ToastNotificationManager,
+    ToastActivatedEventArgs, ToastDismissedEventArgs, ToastNotification, ToastNotificationManager,
 };
 
+const APP_ID: &str = "org.wezfurlong.wezterm";
+const GROUP: &str = "wezterm";
+
 fn unwrap_arg<T>(a: &Option<T>) -> Result<&T, WinError> {
     match a {
         Some(t) => Ok(t),
@@ -32,7 +35,7 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
     };
 
     xml.LoadXml(HSTRING::from(format!(
-        r#"<toast duration="long">
+        r#"<toast duration="{}">
         <visual>
             <binding template="ToastGeneric">
                 <text>{}</text>
@@ -41,12 +44,29 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
         </visual>
         {}
     </toast>"#,
+        match toast.urgency {
+            ToastUrgency::Low => "short",
+            ToastUrgency::Normal | ToastUrgency::Critical => "long",
+        },
         escape_str_pcdata(&toast.title),
         escape_str_pcdata(&toast.message),
         url_actions
     )))?;
 
     let notif = ToastNotification::CreateToastNotification(xml)?;
+    if let Some(tag) = &toast.tag {
+        notif.SetTag(HSTRING::from(tag.as_str()))?;
+        notif.SetGroup(HSTRING::from(GROUP))?;
+    }
+
+    if let Some(on_close) = toast.on_close.clone() {
+        notif.Dismissed(TypedEventHandler::new(
+            move |_: &Option<ToastNotification>, _: &Option<ToastDismissedEventArgs>| {
+                on_close.call();
+                Ok(())
+            },
+        ))?;
+    }
 
     notif.Activated(TypedEventHandler::new(
         move |_: &Option<ToastNotification>, result: &Option<IInspectable>| {
@@ -59,6 +79,8 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
                 if let Some(url) = toast.url.as_ref() {
                     wezterm_open_url::open_url(url);
                 }
+            } else if let Some(on_activate) = toast.on_activate.as_ref() {
+                on_activate.call();
             }
 
             Ok(())
@@ -77,9 +99,7 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
     }))?;
     */
 
-    let notifier = ToastNotificationManager::CreateToastNotifierWithId(HSTRING::from(
-        "org.wezfurlong.wezterm",
-    ))?;
+    let notifier = ToastNotificationManager::CreateToastNotifierWithId(HSTRING::from(APP_ID))?;
 
     notifier.Show(&notif)?;
 
@@ -98,3 +118,12 @@ pub fn show_notif(notif: TN) -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+pub fn close_notif(tag: &str) -> Result<(), Box<dyn std::error::Error>> {
+    ToastNotificationManager::History()?.RemoveGroupedTagWithId(
+        HSTRING::from(tag),
+        HSTRING::from(GROUP),
+        HSTRING::from(APP_ID),
+    )?;
+    Ok(())
+}
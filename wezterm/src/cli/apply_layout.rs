@@ -0,0 +1,36 @@
+use clap::{Parser, ValueHint};
+use mux::layout::Layout;
+use std::path::PathBuf;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct ApplyLayout {
+    /// Override the workspace of every window in the layout
+    #[arg(long)]
+    workspace: Option<String>,
+
+    /// The layout file to apply.
+    /// The format is determined from the extension, which must
+    /// be one of `toml`, `yaml`, `yml`, `json` or `lua`.
+    #[arg(value_parser, value_hint=ValueHint::FilePath)]
+    file: PathBuf,
+}
+
+impl ApplyLayout {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let mut layout = Layout::load(&self.file)?;
+        if let Some(workspace) = &self.workspace {
+            for window in &mut layout.windows {
+                window.workspace.replace(workspace.clone());
+            }
+        }
+
+        let applied = client.apply_layout(codec::ApplyLayout { layout }).await?;
+
+        log::debug!("{:?}", applied);
+        for pane_id in applied.pane_ids {
+            println!("{pane_id}");
+        }
+        Ok(())
+    }
+}
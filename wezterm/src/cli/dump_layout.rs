@@ -0,0 +1,31 @@
+use clap::Parser;
+use mux::layout::Layout;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct DumpLayout {
+    /// Only include the windows in the named workspace
+    #[arg(long)]
+    workspace: Option<String>,
+
+    /// The output format.
+    /// "toml", "yaml" and "json" are possible formats.
+    #[arg(long, default_value = "toml")]
+    format: String,
+}
+
+impl DumpLayout {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let panes = client.list_panes().await?;
+        let mut layout =
+            Layout::from_pane_trees(&panes.tabs, &panes.tab_titles, &panes.window_titles);
+        if let Some(workspace) = &self.workspace {
+            layout
+                .windows
+                .retain(|window| window.workspace.as_ref() == Some(workspace));
+        }
+
+        print!("{}", layout.to_format_string(&self.format)?);
+        Ok(())
+    }
+}
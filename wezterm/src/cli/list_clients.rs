@@ -57,6 +57,14 @@ impl ListClientsCommand {
                         name: "FOCUS".to_string(),
                         alignment: Alignment::Right,
                     },
+                    Column {
+                        name: "ACCESS".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "IDENTITY".to_string(),
+                        alignment: Alignment::Left,
+                    },
                     Column {
                         name: "SSH_AUTH_SOCK".to_string(),
                         alignment: Alignment::Left,
@@ -99,6 +107,13 @@ impl ListClientsCommand {
                         info.focused_pane_id
                             .map(|id| id.to_string())
                             .unwrap_or_else(String::new),
+                        if info.read_only {
+                            "read-only"
+                        } else {
+                            "read-write"
+                        }
+                        .to_string(),
+                        info.identity.as_deref().unwrap_or("").to_string(),
                         info.client_id
                             .ssh_auth_sock
                             .as_deref()
@@ -128,6 +143,8 @@ struct CliListClientsResultItem {
     workspace: String,
     focused_pane_id: Option<mux::pane::PaneId>,
     ssh_auth_sock: Option<String>,
+    read_only: bool,
+    identity: Option<String>,
 }
 
 impl From<mux::client::ClientInfo> for CliListClientsResultItem {
@@ -140,6 +157,8 @@ impl From<mux::client::ClientInfo> for CliListClientsResultItem {
             active_workspace,
             focused_pane_id,
             client_id,
+            read_only,
+            identity,
             ..
         } = client_info;
 
@@ -165,6 +184,8 @@ impl From<mux::client::ClientInfo> for CliListClientsResultItem {
             workspace: active_workspace.as_deref().unwrap_or("").to_string(),
             focused_pane_id,
             ssh_auth_sock: ssh_auth_sock.as_ref().map(|s| s.to_string()),
+            read_only,
+            identity,
         }
     }
 }
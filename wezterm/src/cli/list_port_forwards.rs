@@ -0,0 +1,89 @@
+use crate::cli::CliOutputFormatKind;
+use clap::Parser;
+use serde::Serializer as _;
+use tabout::{tabulate_output, Alignment, Column};
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone, Copy)]
+pub struct ListPortForwards {
+    /// Controls the output format.
+    /// "table" and "json" are possible formats.
+    #[arg(long = "format", default_value = "table")]
+    format: CliOutputFormatKind,
+}
+
+impl ListPortForwards {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let out = std::io::stdout();
+        let forwards = client.list_port_forwards().await?.forwards;
+        match self.format {
+            CliOutputFormatKind::Json => {
+                let forwards = forwards.into_iter().map(CliPortForwardItem::from);
+                let mut writer = serde_json::Serializer::pretty(out.lock());
+                writer.collect_seq(forwards)?;
+            }
+            CliOutputFormatKind::Table => {
+                let cols = vec![
+                    Column {
+                        name: "DOMAIN".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "ID".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "STATE".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "FORWARD".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                ];
+                let data: Vec<Vec<String>> = forwards
+                    .into_iter()
+                    .map(|forward| {
+                        vec![
+                            forward.domain_name,
+                            forward.forward_id.to_string(),
+                            match (&forward.error, forward.enabled) {
+                                (Some(err), _) => format!("failed: {err}"),
+                                (None, true) => "enabled".to_string(),
+                                (None, false) => "disabled".to_string(),
+                            },
+                            forward.description,
+                        ]
+                    })
+                    .collect();
+                tabulate_output(&cols, &data, &mut out.lock())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// This will be serialized to JSON via the 'ListPortForwards' command.
+// As such it is intended to be a stable output format,
+// Thus we need to be careful about the stability of the fields and types
+// herein as they are directly reflected in the output.
+#[derive(serde::Serialize)]
+struct CliPortForwardItem {
+    domain: String,
+    forward_id: usize,
+    description: String,
+    enabled: bool,
+    error: Option<String>,
+}
+
+impl From<mux::domain::PortForward> for CliPortForwardItem {
+    fn from(forward: mux::domain::PortForward) -> Self {
+        Self {
+            domain: forward.domain_name,
+            forward_id: forward.forward_id,
+            description: forward.description,
+            enabled: forward.enabled,
+            error: forward.error,
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use anyhow::Context;
+use clap::{Parser, ValueHint};
+use config::keyassignment::{PaneLogMode, PaneLogTarget};
+use mux::pane::PaneId;
+use std::path::PathBuf;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct LogPane {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Stop logging the output of the pane
+    #[arg(long, conflicts_with_all=&["file", "text", "prog"])]
+    stop: bool,
+
+    /// Remove escape sequences from the output, leaving only
+    /// the text
+    #[arg(long)]
+    text: bool,
+
+    /// Append the output of the pane to FILE
+    #[arg(long, value_hint=ValueHint::FilePath, conflicts_with="prog")]
+    file: Option<PathBuf>,
+
+    /// Instead of a file, write the output of the pane to the
+    /// stdin of PROG, which is run by the mux server.
+    /// For example: `wezterm cli log-pane -- sh -c 'gzip > out.gz'`
+    #[arg(value_hint=ValueHint::CommandWithArguments, num_args=1..)]
+    prog: Vec<String>,
+}
+
+impl LogPane {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+
+        let target = if self.stop {
+            None
+        } else {
+            let mode = if self.text {
+                PaneLogMode::Text
+            } else {
+                PaneLogMode::Raw
+            };
+            match (self.file, self.prog.is_empty()) {
+                (Some(file), true) => {
+                    // The mux server may have a different cwd, so
+                    // resolve the path relative to ours
+                    let path = std::env::current_dir()
+                        .context("resolving the current directory")?
+                        .join(file);
+                    Some(PaneLogTarget {
+                        path: Some(path),
+                        command: None,
+                        mode,
+                    })
+                }
+                (None, false) => Some(PaneLogTarget {
+                    path: None,
+                    command: Some(self.prog),
+                    mode,
+                }),
+                _ => anyhow::bail!("one of --file, --stop or PROG must be specified"),
+            }
+        };
+
+        client
+            .set_pane_logging(codec::SetPaneLogging { pane_id, target })
+            .await?;
+        Ok(())
+    }
+}
@@ -7,20 +7,26 @@ mod activate_pane;
 mod activate_pane_direction;
 mod activate_tab;
 mod adjust_pane_size;
+mod apply_layout;
+mod dump_layout;
 mod get_pane_direction;
 mod get_text;
 mod kill_pane;
 mod list;
 mod list_clients;
+mod list_port_forwards;
+mod log_pane;
 mod move_pane_to_new_tab;
 mod proxy;
 mod rename_workspace;
+mod revoke_client;
 mod send_text;
 mod set_tab_title;
 mod set_window_title;
 mod spawn_command;
 mod split_pane;
 mod tls_creds;
+mod toggle_port_forward;
 mod zoom_pane;
 
 #[derive(Debug, Parser, Clone, Copy)]
@@ -163,6 +169,40 @@ Outputs the pane-id for the newly created pane on success"
     /// Zoom, unzoom, or toggle zoom state
     #[command(name = "zoom-pane", rename_all = "kebab")]
     ZoomPane(zoom_pane::ZoomPane),
+
+    /// Spawn the windows, tabs and panes described by a layout file.
+    ///
+    /// Outputs the pane-id of each newly created pane on success.
+    #[command(name = "apply-layout", rename_all = "kebab")]
+    ApplyLayout(apply_layout::ApplyLayout),
+
+    /// Output the layout of the windows, tabs and panes in a format
+    /// that can be used with `apply-layout`
+    #[command(name = "dump-layout", rename_all = "kebab")]
+    DumpLayout(dump_layout::DumpLayout),
+
+    /// Refuse connections from a named TLS client.
+    ///
+    /// Existing connections from that client are dropped, and the
+    /// revocation persists across restarts of the mux server.
+    #[command(name = "revoke-client", rename_all = "kebab")]
+    RevokeClient(revoke_client::RevokeClient),
+
+    /// Copy the output of a pane to a file or to the stdin of a command.
+    ///
+    /// Only output produced after logging starts is recorded.
+    #[command(name = "log-pane", rename_all = "kebab")]
+    LogPane(log_pane::LogPane),
+
+    /// List the port forwards of ssh domains
+    #[command(name = "list-port-forwards", rename_all = "kebab")]
+    ListPortForwards(list_port_forwards::ListPortForwards),
+
+    /// Enable, disable or toggle a port forward of an ssh domain.
+    ///
+    /// Outputs the new state of the forward.
+    #[command(name = "toggle-port-forward", rename_all = "kebab")]
+    TogglePortForward(toggle_port_forward::TogglePortForward),
 }
 
 async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()> {
@@ -199,6 +239,12 @@ async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()>
         CliSubCommand::SetWindowTitle(cmd) => cmd.run(client).await,
         CliSubCommand::RenameWorkspace(cmd) => cmd.run(client).await,
         CliSubCommand::ZoomPane(cmd) => cmd.run(client).await,
+        CliSubCommand::ApplyLayout(cmd) => cmd.run(client).await,
+        CliSubCommand::DumpLayout(cmd) => cmd.run(client).await,
+        CliSubCommand::RevokeClient(cmd) => cmd.run(client).await,
+        CliSubCommand::LogPane(cmd) => cmd.run(client).await,
+        CliSubCommand::ListPortForwards(cmd) => cmd.run(client).await,
+        CliSubCommand::TogglePortForward(cmd) => cmd.run(client).await,
     }
 }
 
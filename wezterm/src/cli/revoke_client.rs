@@ -0,0 +1,25 @@
+use clap::Parser;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct RevokeClient {
+    /// The client name that was passed to `wezterm cli tlscreds
+    /// --client-name` when the client obtained its credentials
+    client_name: String,
+
+    /// Lift a prior revocation, allowing the client to connect again
+    #[arg(long)]
+    restore: bool,
+}
+
+impl RevokeClient {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        client
+            .revoke_client(codec::RevokeClient {
+                client_name: self.client_name,
+                restore: self.restore,
+            })
+            .await?;
+        Ok(())
+    }
+}
@@ -13,11 +13,24 @@ pub struct TlsCredsCommand {
     /// and start a shell with no additional authentication.
     #[clap(long)]
     pem: bool,
+
+    /// Embed this name in the client certificate.
+    ///
+    /// The mux server uses the name to identify the client in
+    /// its audit log, and it can be used with `mux_allowed_clients`,
+    /// `mux_denied_clients` and `wezterm cli revoke-client` to
+    /// control which clients may connect.
+    #[clap(long)]
+    client_name: Option<String>,
 }
 
 impl TlsCredsCommand {
     pub async fn run(self, client: Client) -> anyhow::Result<()> {
-        let creds = client.get_tls_creds().await?;
+        let creds = client
+            .get_tls_creds(codec::GetTlsCreds {
+                client_name: self.client_name,
+            })
+            .await?;
         if self.pem {
             println!("{}", creds.client_cert_pem);
             // RFC 4346 says that each successive cert certifies the
@@ -0,0 +1,47 @@
+use clap::Parser;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct TogglePortForward {
+    /// The name of the domain that owns the forward, as shown
+    /// by `wezterm cli list-port-forwards`
+    domain: String,
+
+    /// The id of the forward, as shown by
+    /// `wezterm cli list-port-forwards`
+    forward_id: usize,
+
+    /// Start listening, rather than toggling
+    #[arg(long, conflicts_with = "disable")]
+    enable: bool,
+
+    /// Stop listening, rather than toggling
+    #[arg(long)]
+    disable: bool,
+}
+
+impl TogglePortForward {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let enabled = match (self.enable, self.disable) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+        let response = client
+            .set_port_forward_enabled(codec::SetPortForwardEnabled {
+                domain_name: self.domain,
+                forward_id: self.forward_id,
+                enabled,
+            })
+            .await?;
+        println!(
+            "{}",
+            if response.enabled {
+                "enabled"
+            } else {
+                "disabled"
+            }
+        );
+        Ok(())
+    }
+}
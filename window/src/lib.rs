@@ -50,6 +50,20 @@ impl Default for Clipboard {
     }
 }
 
+/// One representation of the clipboard contents, identified by
+/// its MIME type
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardItem {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ClipboardItem {
+    pub fn is_text(&self) -> bool {
+        self.mime_type == "text/plain" || self.mime_type.starts_with("text/plain;")
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Dimensions {
     pub pixel_width: usize,
@@ -71,6 +85,31 @@ pub enum MouseCursor {
     Text,
     SizeUpDown,
     SizeLeftRight,
+    Help,
+    Wait,
+    Progress,
+    Crosshair,
+    Cell,
+    VerticalText,
+    Move,
+    SizeUp,
+    SizeDown,
+    SizeLeft,
+    SizeRight,
+    SizeUpLeft,
+    SizeUpRight,
+    SizeDownLeft,
+    SizeDownRight,
+    SizeUpRightDownLeft,
+    SizeUpLeftDownRight,
+    ZoomIn,
+    ZoomOut,
+    Alias,
+    Copy,
+    NotAllowed,
+    NoDrop,
+    Grab,
+    Grabbing,
 }
 
 /// Represents the preferred appearance of the windowing
@@ -319,6 +358,16 @@ pub trait WindowOps {
     /// Set some text in the clipboard
     fn set_clipboard(&self, clipboard: Clipboard, text: String);
 
+    /// Set the clipboard to hold the same content in several MIME types.
+    /// The default implementation places only the plain text
+    /// representation into the clipboard, and leaves the clipboard
+    /// untouched if there is no such representation.
+    fn set_clipboard_data(&self, clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        if let Some(item) = items.into_iter().find(ClipboardItem::is_text) {
+            self.set_clipboard(clipboard, String::from_utf8_lossy(&item.data).into_owned());
+        }
+    }
+
     /// Set window level. Depending on the environment and user preferences
     fn set_window_level(&self, _level: WindowLevel) {}
 
@@ -1,7 +1,27 @@
 use crate::macos::{nsstring, nsstring_to_str};
+use crate::ClipboardItem;
 use cocoa::appkit::{NSFilenamesPboardType, NSPasteboard, NSStringPboardType};
 use cocoa::base::*;
-use cocoa::foundation::NSArray;
+use cocoa::foundation::{NSArray, NSData, NSUInteger};
+use std::ffi::c_void;
+
+/// Maps a MIME type to the pasteboard type that macOS applications
+/// expect for it. Types without a well known equivalent are used as-is.
+fn pasteboard_type(item: &ClipboardItem) -> &str {
+    if item.is_text() {
+        return "public.utf8-plain-text";
+    }
+    match item.mime_type.as_str() {
+        "text/html" => "public.html",
+        "text/rtf" => "public.rtf",
+        "text/uri-list" => "public.url",
+        "image/png" => "public.png",
+        "image/jpeg" => "public.jpeg",
+        "image/tiff" => "public.tiff",
+        "application/pdf" => "com.adobe.pdf",
+        mime => mime,
+    }
+}
 
 pub struct Clipboard {
     pasteboard: id,
@@ -48,4 +68,32 @@ impl Clipboard {
             Ok(())
         }
     }
+
+    /// Replace the pasteboard contents with the items, each under
+    /// the pasteboard type that corresponds to its MIME type
+    pub fn write_items(&mut self, items: &[ClipboardItem]) -> anyhow::Result<()> {
+        unsafe {
+            let types: Vec<_> = items
+                .iter()
+                .map(|item| nsstring(pasteboard_type(item)))
+                .collect();
+            let type_ids: Vec<id> = types.iter().map(|t| **t).collect();
+            self.pasteboard
+                .declareTypes_owner(NSArray::arrayWithObjects(nil, &type_ids), nil);
+            for (item, pb_type) in items.iter().zip(type_ids) {
+                let data = NSData::dataWithBytes_length_(
+                    nil,
+                    item.data.as_ptr() as *const c_void,
+                    item.data.len() as NSUInteger,
+                );
+                let success: BOOL = self.pasteboard.setData_forType(data, pb_type);
+                anyhow::ensure!(
+                    success == YES,
+                    "pasteboard write for {} returned false",
+                    item.mime_type
+                );
+            }
+            Ok(())
+        }
+    }
 }
@@ -8,10 +8,10 @@ use crate::connection::ConnectionOps;
 use crate::os::macos::menu::{MenuItem, RepresentedItem};
 use crate::parameters::{Border, Parameters, TitleBar};
 use crate::{
-    Clipboard, Connection, DeadKeyStatus, Dimensions, Handled, KeyCode, KeyEvent, Modifiers,
-    MouseButtons, MouseCursor, MouseEvent, MouseEventKind, MousePress, Point, RawKeyEvent, Rect,
-    RequestedWindowGeometry, ResizeIncrement, ResolvedGeometry, ScreenPoint, Size, ULength,
-    WindowDecorations, WindowEvent, WindowEventSender, WindowOps, WindowState,
+    Clipboard, ClipboardItem, Connection, DeadKeyStatus, Dimensions, Handled, KeyCode, KeyEvent,
+    Modifiers, MouseButtons, MouseCursor, MouseEvent, MouseEventKind, MousePress, Point,
+    RawKeyEvent, Rect, RequestedWindowGeometry, ResizeIncrement, ResolvedGeometry, ScreenPoint,
+    Size, ULength, WindowDecorations, WindowEvent, WindowEventSender, WindowOps, WindowState,
 };
 use anyhow::{anyhow, bail, ensure};
 use async_trait::async_trait;
@@ -832,6 +832,12 @@ impl WindowOps for Window {
         ClipboardContext::new().write(text).ok();
     }
 
+    fn set_clipboard_data(&self, _clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        if let Err(err) = ClipboardContext::new().write_items(&items) {
+            log::error!("Failed to set clipboard: {:#}", err);
+        }
+    }
+
     fn toggle_fullscreen(&self) {
         Connection::with_window_inner(self.id, move |inner| {
             inner.toggle_fullscreen();
@@ -1221,6 +1227,37 @@ impl WindowInner {
                     MouseCursor::Hand => msg_send![ns_cursor_cls, pointingHandCursor],
                     MouseCursor::SizeUpDown => msg_send![ns_cursor_cls, resizeUpDownCursor],
                     MouseCursor::SizeLeftRight => msg_send![ns_cursor_cls, resizeLeftRightCursor],
+                    MouseCursor::SizeUp => msg_send![ns_cursor_cls, resizeUpCursor],
+                    MouseCursor::SizeDown => msg_send![ns_cursor_cls, resizeDownCursor],
+                    MouseCursor::SizeLeft => msg_send![ns_cursor_cls, resizeLeftCursor],
+                    MouseCursor::SizeRight => msg_send![ns_cursor_cls, resizeRightCursor],
+                    MouseCursor::Crosshair | MouseCursor::Cell => {
+                        msg_send![ns_cursor_cls, crosshairCursor]
+                    }
+                    MouseCursor::VerticalText => {
+                        msg_send![ns_cursor_cls, IBeamCursorForVerticalLayout]
+                    }
+                    MouseCursor::Grab | MouseCursor::Move => {
+                        msg_send![ns_cursor_cls, openHandCursor]
+                    }
+                    MouseCursor::Grabbing => msg_send![ns_cursor_cls, closedHandCursor],
+                    MouseCursor::Copy => msg_send![ns_cursor_cls, dragCopyCursor],
+                    MouseCursor::Alias => msg_send![ns_cursor_cls, dragLinkCursor],
+                    MouseCursor::NotAllowed | MouseCursor::NoDrop => {
+                        msg_send![ns_cursor_cls, operationNotAllowedCursor]
+                    }
+                    // macOS has no public cursors for these
+                    MouseCursor::Help
+                    | MouseCursor::Wait
+                    | MouseCursor::Progress
+                    | MouseCursor::SizeUpLeft
+                    | MouseCursor::SizeUpRight
+                    | MouseCursor::SizeDownLeft
+                    | MouseCursor::SizeDownRight
+                    | MouseCursor::SizeUpRightDownLeft
+                    | MouseCursor::SizeUpLeftDownRight
+                    | MouseCursor::ZoomIn
+                    | MouseCursor::ZoomOut => msg_send![ns_cursor_cls, arrowCursor],
                 };
                 let () = msg_send![ns_cursor_cls, setHiddenUntilMouseMoves: NO];
                 let () = msg_send![instance, set];
@@ -10,7 +10,7 @@ use toolkit::primary_selection::selection::PrimarySelectionSourceHandler;
 use wayland_protocols::wp::primary_selection::zv1::client::zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1;
 use wayland_protocols::wp::primary_selection::zv1::client::zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1;
 
-use crate::{Clipboard, ConnectionOps};
+use crate::{Clipboard, ClipboardItem, ConnectionOps};
 
 use super::data_device::TEXT_MIME_TYPE;
 use super::state::WaylandState;
@@ -63,6 +63,30 @@ impl CopyAndPaste {
     }
 
     pub(super) fn set_clipboard_data(&mut self, clipboard: Clipboard, data: String) {
+        self.set_clipboard_items(
+            clipboard,
+            vec![ClipboardItem {
+                mime_type: TEXT_MIME_TYPE.to_string(),
+                data: data.into_bytes(),
+            }],
+        );
+    }
+
+    /// Offer the items in the clipboard, one MIME type per item.
+    /// The plain text item is offered as `TEXT_MIME_TYPE`.
+    pub(super) fn set_clipboard_items(&mut self, clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        let mime_types: Vec<String> = items
+            .iter()
+            .map(|item| {
+                if item.is_text() {
+                    TEXT_MIME_TYPE.to_string()
+                } else {
+                    item.mime_type.clone()
+                }
+            })
+            .collect();
+        let mime_types = mime_types.iter().map(String::as_str);
+
         let conn = crate::Connection::get().unwrap().wayland();
         let qh = conn.event_queue.borrow().handle();
         let mut wayland_state = conn.wayland_state.borrow_mut();
@@ -77,19 +101,19 @@ impl CopyAndPaste {
         match primary_selection {
             Some(primary_selection) => {
                 let manager = wayland_state.primary_selection_manager.as_ref().unwrap();
-                let source = manager.create_selection_source(&qh, [TEXT_MIME_TYPE]);
+                let source = manager.create_selection_source(&qh, mime_types);
                 source.set_selection(&primary_selection, last_serial);
                 wayland_state
                     .primary_selection_source
-                    .replace((source, data));
+                    .replace((source, items));
             }
             None => {
                 let data_device = &wayland_state.data_device;
                 let source = wayland_state
                     .data_device_manager_state
-                    .create_copy_paste_source(&qh, vec![TEXT_MIME_TYPE]);
+                    .create_copy_paste_source(&qh, mime_types);
                 source.set_selection(data_device.as_ref().unwrap(), last_serial);
-                wayland_state.copy_paste_source.replace((source, data));
+                wayland_state.copy_paste_source.replace((source, items));
             }
         }
     }
@@ -108,9 +132,15 @@ impl WaylandState {
     }
 }
 
-pub(super) fn write_selection_to_pipe(fd: WritePipe, text: &str) {
-    if let Err(e) = write_pipe_with_timeout(fd, text.as_bytes()) {
-        log::error!("while sending primary selection to pipe: {}", e);
+/// Sends the item that matches the requested MIME type, if any
+pub(super) fn write_selection_to_pipe(fd: WritePipe, items: &[ClipboardItem], mime: &str) {
+    let item = items
+        .iter()
+        .find(|item| item.mime_type == mime || (mime == TEXT_MIME_TYPE && item.is_text()));
+    if let Some(item) = item {
+        if let Err(e) = write_pipe_with_timeout(fd, &item.data) {
+            log::error!("while sending primary selection to pipe: {}", e);
+        }
     }
 }
 
@@ -171,15 +201,11 @@ impl PrimarySelectionSourceHandler for WaylandState {
         mime: String,
         write_pipe: toolkit::data_device_manager::WritePipe,
     ) {
-        if mime != TEXT_MIME_TYPE {
-            return;
-        };
-
-        if let Some((ps_source, data)) = &self.primary_selection_source {
+        if let Some((ps_source, items)) = &self.primary_selection_source {
             if ps_source.inner() != source {
                 return;
             }
-            write_selection_to_pipe(write_pipe, data);
+            write_selection_to_pipe(write_pipe, items, &mime);
         }
     }
 
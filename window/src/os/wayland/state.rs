@@ -35,6 +35,7 @@ use wayland_protocols::wp::text_input::zv3::client::zwp_text_input_v3::ZwpTextIn
 use wayland_protocols_plasma::blur::client::org_kde_kwin_blur_manager::OrgKdeKwinBlurManager;
 
 use crate::x11::KeyboardWithFallback;
+use crate::ClipboardItem;
 
 use super::inputhandler::{TextInputData, TextInputState};
 use super::pointer::{PendingMouse, PointerUserData};
@@ -66,10 +67,10 @@ pub(super) struct WaylandState {
 
     pub(super) data_device_manager_state: DataDeviceManagerState,
     pub(super) data_device: Option<DataDevice>,
-    pub(super) copy_paste_source: Option<(CopyPasteSource, String)>,
+    pub(super) copy_paste_source: Option<(CopyPasteSource, Vec<ClipboardItem>)>,
     pub(super) primary_selection_manager: Option<PrimarySelectionManagerState>,
     pub(super) primary_selection_device: Option<PrimarySelectionDevice>,
-    pub(super) primary_selection_source: Option<(PrimarySelectionSource, String)>,
+    pub(super) primary_selection_source: Option<(PrimarySelectionSource, Vec<ClipboardItem>)>,
     pub(super) shm: Shm,
     pub(super) mem_pool: RefCell<SlotPool>,
     pub(super) kde_blur_manager: Option<OrgKdeKwinBlurManager>,
@@ -53,8 +53,8 @@ use wezterm_input_types::{
 use crate::wayland::WaylandConnection;
 use crate::x11::KeyboardWithFallback;
 use crate::{
-    Appearance, Clipboard, Connection, ConnectionOps, Dimensions, MouseCursor, Point, Rect,
-    RequestedWindowGeometry, ResizeIncrement, ResolvedGeometry, Window, WindowEvent,
+    Appearance, Clipboard, ClipboardItem, Connection, ConnectionOps, Dimensions, MouseCursor,
+    Point, Rect, RequestedWindowGeometry, ResizeIncrement, ResolvedGeometry, Window, WindowEvent,
     WindowEventSender, WindowKeyEvent, WindowOps, WindowState,
 };
 
@@ -462,6 +462,17 @@ impl WindowOps for WaylandWindow {
         });
     }
 
+    fn set_clipboard_data(&self, clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner
+                .copy_and_paste
+                .lock()
+                .unwrap()
+                .set_clipboard_items(clipboard, items);
+            Ok(())
+        });
+    }
+
     fn toggle_fullscreen(&self) {
         WaylandConnection::with_window_inner(self.0, move |inner| {
             if inner.window_state.contains(WindowState::FULL_SCREEN) {
@@ -964,6 +975,31 @@ impl WaylandWindowInner {
                         MouseCursor::SizeUpDown => CursorIcon::NsResize,
                         MouseCursor::SizeLeftRight => CursorIcon::EwResize,
                         MouseCursor::Text => CursorIcon::Text,
+                        MouseCursor::Help => CursorIcon::Help,
+                        MouseCursor::Wait => CursorIcon::Wait,
+                        MouseCursor::Progress => CursorIcon::Progress,
+                        MouseCursor::Crosshair => CursorIcon::Crosshair,
+                        MouseCursor::Cell => CursorIcon::Cell,
+                        MouseCursor::VerticalText => CursorIcon::VerticalText,
+                        MouseCursor::Move => CursorIcon::Move,
+                        MouseCursor::SizeUp => CursorIcon::NResize,
+                        MouseCursor::SizeDown => CursorIcon::SResize,
+                        MouseCursor::SizeLeft => CursorIcon::WResize,
+                        MouseCursor::SizeRight => CursorIcon::EResize,
+                        MouseCursor::SizeUpLeft => CursorIcon::NwResize,
+                        MouseCursor::SizeUpRight => CursorIcon::NeResize,
+                        MouseCursor::SizeDownLeft => CursorIcon::SwResize,
+                        MouseCursor::SizeDownRight => CursorIcon::SeResize,
+                        MouseCursor::SizeUpRightDownLeft => CursorIcon::NeswResize,
+                        MouseCursor::SizeUpLeftDownRight => CursorIcon::NwseResize,
+                        MouseCursor::ZoomIn => CursorIcon::ZoomIn,
+                        MouseCursor::ZoomOut => CursorIcon::ZoomOut,
+                        MouseCursor::Alias => CursorIcon::Alias,
+                        MouseCursor::Copy => CursorIcon::Copy,
+                        MouseCursor::NotAllowed => CursorIcon::NotAllowed,
+                        MouseCursor::NoDrop => CursorIcon::NoDrop,
+                        MouseCursor::Grab => CursorIcon::Grab,
+                        MouseCursor::Grabbing => CursorIcon::Grabbing,
                     },
                 ) {
                     log::error!("set_cursor: {}", err);
@@ -2,10 +2,10 @@ use super::*;
 use crate::connection::ConnectionOps;
 use crate::parameters::{self, Parameters};
 use crate::{
-    Appearance, Clipboard, DeadKeyStatus, Dimensions, Handled, KeyCode, KeyEvent, Modifiers,
-    MouseButtons, MouseCursor, MouseEvent, MouseEventKind, MousePress, Point, RawKeyEvent, Rect,
-    RequestedWindowGeometry, ResolvedGeometry, ScreenPoint, ScreenRect, ULength, WindowDecorations,
-    WindowEvent, WindowEventSender, WindowOps, WindowState,
+    Appearance, Clipboard, ClipboardItem, DeadKeyStatus, Dimensions, Handled, KeyCode, KeyEvent,
+    Modifiers, MouseButtons, MouseCursor, MouseEvent, MouseEventKind, MousePress, Point,
+    RawKeyEvent, Rect, RequestedWindowGeometry, ResolvedGeometry, ScreenPoint, ScreenRect, ULength,
+    WindowDecorations, WindowEvent, WindowEventSender, WindowOps, WindowState,
 };
 use anyhow::{bail, Context};
 use async_trait::async_trait;
@@ -44,6 +44,7 @@ use winapi::um::sysinfoapi::{GetTickCount, GetVersionExW};
 use winapi::um::uxtheme::{
     CloseThemeData, GetThemeFont, GetThemeSysFont, OpenThemeData, SetWindowTheme,
 };
+use winapi::um::winbase::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
 use winapi::um::wingdi::{LOGFONTW, MAKEPOINTS};
 use winapi::um::winnt::OSVERSIONINFOW;
 use winapi::um::winuser::*;
@@ -132,6 +133,56 @@ pub(crate) struct WindowInner {
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
 pub struct Window(HWindow);
 
+/// Places all of the items into the clipboard at once.  The plain text
+/// goes in as CF_UNICODETEXT, while the other items use a clipboard
+/// format that is registered under the name of their MIME type.
+fn set_clipboard_items(items: &[ClipboardItem]) -> anyhow::Result<()> {
+    let formats = items
+        .iter()
+        .map(|item| {
+            if item.is_text() {
+                let data = String::from_utf8_lossy(&item.data)
+                    .encode_utf16()
+                    .chain(std::iter::once(0))
+                    .flat_map(u16::to_le_bytes)
+                    .collect();
+                Ok((CF_UNICODETEXT, data))
+            } else {
+                let format = clipboard_win::raw::register_format(item.mime_type.as_str())
+                    .with_context(|| format!("registering clipboard format {}", item.mime_type))?;
+                Ok((format, item.data.clone()))
+            }
+        })
+        .collect::<anyhow::Result<Vec<(UINT, Vec<u8>)>>>()?;
+
+    // The clipboard is closed again when this is dropped
+    let _clipboard = clipboard_win::Clipboard::new().context("opening clipboard")?;
+    clipboard_win::raw::empty().context("emptying clipboard")?;
+    for (format, data) in formats {
+        unsafe {
+            let handle = GlobalAlloc(GMEM_MOVEABLE, data.len());
+            if handle.is_null() {
+                bail!("GlobalAlloc failed: {}", IoError::last_os_error());
+            }
+            let ptr = GlobalLock(handle) as *mut u8;
+            if ptr.is_null() {
+                let err = IoError::last_os_error();
+                GlobalFree(handle);
+                bail!("GlobalLock failed: {}", err);
+            }
+            std::ptr::copy_nonoverlapping(data.as_ptr(), ptr, data.len());
+            GlobalUnlock(handle);
+            // On success, the clipboard owns the memory
+            if SetClipboardData(format, handle).is_null() {
+                let err = IoError::last_os_error();
+                GlobalFree(handle);
+                bail!("SetClipboardData failed for format {}: {}", format, err);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn wuicolor_to_linearrgba(color: WUIColor) -> LinearRgba {
     LinearRgba::with_srgba(color.R, color.G, color.B, 255)
 }
@@ -969,6 +1020,12 @@ impl WindowOps for Window {
         clipboard_win::set_clipboard_string(&text).ok();
     }
 
+    fn set_clipboard_data(&self, _clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        if let Err(err) = set_clipboard_items(&items) {
+            log::error!("Failed to set clipboard: {:#}", err);
+        }
+    }
+
     fn set_window_drag_position(&self, coords: ScreenPoint) {
         Connection::with_window_inner(self.0, move |inner| {
             inner.window_drag_position = Some(coords);
@@ -1724,6 +1781,25 @@ fn apply_mouse_cursor(cursor: Option<MouseCursor>) {
                     MouseCursor::Text => IDC_IBEAM,
                     MouseCursor::SizeUpDown => IDC_SIZENS,
                     MouseCursor::SizeLeftRight => IDC_SIZEWE,
+                    MouseCursor::Help => IDC_HELP,
+                    MouseCursor::Wait => IDC_WAIT,
+                    MouseCursor::Progress => IDC_APPSTARTING,
+                    MouseCursor::Crosshair | MouseCursor::Cell => IDC_CROSS,
+                    MouseCursor::VerticalText => IDC_IBEAM,
+                    MouseCursor::Move | MouseCursor::Grab | MouseCursor::Grabbing => IDC_SIZEALL,
+                    MouseCursor::SizeUp | MouseCursor::SizeDown => IDC_SIZENS,
+                    MouseCursor::SizeLeft | MouseCursor::SizeRight => IDC_SIZEWE,
+                    MouseCursor::SizeUpLeft
+                    | MouseCursor::SizeDownRight
+                    | MouseCursor::SizeUpLeftDownRight => IDC_SIZENWSE,
+                    MouseCursor::SizeUpRight
+                    | MouseCursor::SizeDownLeft
+                    | MouseCursor::SizeUpRightDownLeft => IDC_SIZENESW,
+                    MouseCursor::NotAllowed | MouseCursor::NoDrop => IDC_NO,
+                    MouseCursor::ZoomIn
+                    | MouseCursor::ZoomOut
+                    | MouseCursor::Alias
+                    | MouseCursor::Copy => IDC_ARROW,
                 },
             ));
         },
@@ -652,7 +652,7 @@ impl XConnection {
         Ok(())
     }
 
-    fn intern_atom(conn: &xcb::Connection, name: &str) -> anyhow::Result<Atom> {
+    pub(crate) fn intern_atom(conn: &xcb::Connection, name: &str) -> anyhow::Result<Atom> {
         let cookie = conn.send_request(&xcb::x::InternAtom {
             only_if_exists: false,
             name: name.as_bytes(),
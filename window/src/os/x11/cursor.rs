@@ -14,11 +14,25 @@ use xcb::x::Cursor;
 use xcb::Xid;
 
 // X11 classic Cursor glyphs
+pub const X_CURSOR: u16 = 0;
+pub const BOTTOM_LEFT_CORNER: u16 = 12;
+pub const BOTTOM_RIGHT_CORNER: u16 = 14;
+pub const BOTTOM_SIDE: u16 = 16;
+pub const CROSSHAIR: u16 = 34;
+pub const FLEUR: u16 = 52;
 pub const HAND1: u16 = 58;
+pub const HAND2: u16 = 60;
+pub const LEFT_SIDE: u16 = 70;
+pub const PLUS: u16 = 90;
+pub const QUESTION_ARROW: u16 = 92;
+pub const RIGHT_SIDE: u16 = 96;
 pub const SB_H_DOUBLE_ARROW: u16 = 108;
 pub const SB_V_DOUBLE_ARROW: u16 = 116;
 pub const TOP_LEFT_ARROW: u16 = 132;
 pub const TOP_LEFT_CORNER: u16 = 134;
+pub const TOP_RIGHT_CORNER: u16 = 136;
+pub const TOP_SIDE: u16 = 138;
+pub const WATCH: u16 = 150;
 pub const XTERM: u16 = 152;
 
 pub struct XcbCursor {
@@ -317,6 +331,31 @@ impl CursorInfo {
             MouseCursor::Text => &["xterm"],
             MouseCursor::SizeUpDown => &["sb_v_double_arrow"],
             MouseCursor::SizeLeftRight => &["sb_h_double_arrow"],
+            MouseCursor::Help => &["help", "question_arrow"],
+            MouseCursor::Wait => &["wait", "watch"],
+            MouseCursor::Progress => &["progress", "left_ptr_watch"],
+            MouseCursor::Crosshair => &["crosshair", "cross"],
+            MouseCursor::Cell => &["cell", "plus"],
+            MouseCursor::VerticalText => &["vertical-text"],
+            MouseCursor::Move => &["move", "fleur"],
+            MouseCursor::SizeUp => &["n-resize", "top_side"],
+            MouseCursor::SizeDown => &["s-resize", "bottom_side"],
+            MouseCursor::SizeLeft => &["w-resize", "left_side"],
+            MouseCursor::SizeRight => &["e-resize", "right_side"],
+            MouseCursor::SizeUpLeft => &["nw-resize", "top_left_corner"],
+            MouseCursor::SizeUpRight => &["ne-resize", "top_right_corner"],
+            MouseCursor::SizeDownLeft => &["sw-resize", "bottom_left_corner"],
+            MouseCursor::SizeDownRight => &["se-resize", "bottom_right_corner"],
+            MouseCursor::SizeUpRightDownLeft => &["nesw-resize", "fd_double_arrow"],
+            MouseCursor::SizeUpLeftDownRight => &["nwse-resize", "bd_double_arrow"],
+            MouseCursor::ZoomIn => &["zoom-in"],
+            MouseCursor::ZoomOut => &["zoom-out"],
+            MouseCursor::Alias => &["alias", "dnd-link"],
+            MouseCursor::Copy => &["copy", "dnd-copy"],
+            MouseCursor::NotAllowed => &["not-allowed", "crossed_circle"],
+            MouseCursor::NoDrop => &["no-drop", "dnd-no-drop"],
+            MouseCursor::Grab => &["grab", "openhand"],
+            MouseCursor::Grabbing => &["grabbing", "closedhand"],
         };
 
         let mut theme_list = vec![theme.to_string()];
@@ -376,6 +415,23 @@ impl CursorInfo {
             MouseCursor::Text => XTERM,
             MouseCursor::SizeUpDown => SB_V_DOUBLE_ARROW,
             MouseCursor::SizeLeftRight => SB_H_DOUBLE_ARROW,
+            MouseCursor::Help => QUESTION_ARROW,
+            MouseCursor::Wait | MouseCursor::Progress => WATCH,
+            MouseCursor::Crosshair => CROSSHAIR,
+            MouseCursor::Cell | MouseCursor::ZoomIn | MouseCursor::Copy => PLUS,
+            MouseCursor::VerticalText => XTERM,
+            MouseCursor::Move | MouseCursor::Grabbing => FLEUR,
+            MouseCursor::SizeUp => TOP_SIDE,
+            MouseCursor::SizeDown => BOTTOM_SIDE,
+            MouseCursor::SizeLeft => LEFT_SIDE,
+            MouseCursor::SizeRight => RIGHT_SIDE,
+            MouseCursor::SizeUpLeft | MouseCursor::SizeUpLeftDownRight => TOP_LEFT_CORNER,
+            MouseCursor::SizeUpRight | MouseCursor::SizeUpRightDownLeft => TOP_RIGHT_CORNER,
+            MouseCursor::SizeDownLeft => BOTTOM_LEFT_CORNER,
+            MouseCursor::SizeDownRight => BOTTOM_RIGHT_CORNER,
+            MouseCursor::ZoomOut | MouseCursor::Alias => TOP_LEFT_ARROW,
+            MouseCursor::NotAllowed | MouseCursor::NoDrop => X_CURSOR,
+            MouseCursor::Grab => HAND2,
         };
         log::trace!("loading X11 basic cursor {} for {:?}", id_no, cursor);
 
@@ -3,8 +3,8 @@ use crate::bitmaps::*;
 use crate::connection::ConnectionOps;
 use crate::os::{xkeysyms, Connection, Window};
 use crate::{
-    Appearance, Clipboard, DeadKeyStatus, Dimensions, MouseButtons, MouseCursor, MouseEvent,
-    MouseEventKind, MousePress, Point, Rect, RequestedWindowGeometry, ResizeIncrement,
+    Appearance, Clipboard, ClipboardItem, DeadKeyStatus, Dimensions, MouseButtons, MouseCursor,
+    MouseEvent, MouseEventKind, MousePress, Point, Rect, RequestedWindowGeometry, ResizeIncrement,
     ResolvedGeometry, ScreenPoint, ScreenRect, WindowDecorations, WindowEvent, WindowEventSender,
     WindowOps, WindowState,
 };
@@ -33,6 +33,9 @@ use xcb::{Event, Xid};
 struct CopyAndPaste {
     clipboard_owned: Option<String>,
     primary_selection_owned: Option<String>,
+    /// Additional, non-text, representations of the owned clipboard
+    clipboard_data: Vec<(Atom, Vec<u8>)>,
+    primary_selection_data: Vec<(Atom, Vec<u8>)>,
     clipboard_request: Option<Promise<String>>,
     selection_request: Option<Promise<String>>,
     time: u32,
@@ -53,6 +56,20 @@ impl CopyAndPaste {
         }
     }
 
+    fn data(&self, clipboard: Clipboard) -> &[(Atom, Vec<u8>)] {
+        match clipboard {
+            Clipboard::PrimarySelection => &self.primary_selection_data,
+            Clipboard::Clipboard => &self.clipboard_data,
+        }
+    }
+
+    fn data_mut(&mut self, clipboard: Clipboard) -> &mut Vec<(Atom, Vec<u8>)> {
+        match clipboard {
+            Clipboard::PrimarySelection => &mut self.primary_selection_data,
+            Clipboard::Clipboard => &mut self.clipboard_data,
+        }
+    }
+
     fn request_mut(&mut self, clipboard: Clipboard) -> &mut Option<Promise<String>> {
         match clipboard {
             Clipboard::PrimarySelection => &mut self.selection_request,
@@ -949,6 +966,15 @@ impl XWindowInner {
         Ok(())
     }
 
+    /// The largest selection that we can hand over in a single
+    /// ChangeProperty request.  Larger ones would require the INCR
+    /// protocol, which we don't implement, so they are refused.
+    fn max_selection_size(&self) -> usize {
+        // The maximum request length is measured in 4 byte units, and
+        // the request header takes 28 bytes when BIG-REQUESTS is in use
+        (self.conn().get_maximum_request_length() as usize * 4).saturating_sub(28)
+    }
+
     fn selection_atom_to_clipboard(&self, atom: Atom) -> Option<Clipboard> {
         if atom == xcb::x::ATOM_PRIMARY {
             Some(Clipboard::PrimarySelection)
@@ -964,6 +990,7 @@ impl XWindowInner {
         log::debug!("SEL: window_id={window_id:?} {:?}", request);
         if let Some(clipboard) = self.selection_atom_to_clipboard(request.selection()) {
             self.copy_and_paste.clipboard_mut(clipboard).take();
+            self.copy_and_paste.data_mut(clipboard).clear();
             self.copy_and_paste.request_mut(clipboard).take();
             self.update_selection_owner(clipboard)?;
         }
@@ -987,7 +1014,17 @@ impl XWindowInner {
 
         let selprop = if request.target() == conn.atom_targets {
             // They want to know which targets we support
-            let atoms: [Atom; 1] = [conn.atom_utf8_string];
+            let mut atoms: Vec<Atom> = vec![conn.atom_targets, conn.atom_utf8_string];
+            if let Some(clipboard) = self.selection_atom_to_clipboard(request.selection()) {
+                let max_size = self.max_selection_size();
+                atoms.extend(
+                    self.copy_and_paste
+                        .data(clipboard)
+                        .iter()
+                        .filter(|(_, data)| data.len() <= max_size)
+                        .map(|(atom, _)| *atom),
+                );
+            }
             log::trace!("SEL: window_id={window_id:?} requestor wants supported targets");
             conn.send_request_no_reply(&xcb::x::ChangeProperty {
                 mode: PropMode::Replace,
@@ -1025,6 +1062,34 @@ impl XWindowInner {
             } else {
                 xcb::x::ATOM_NONE
             }
+        } else if let Some(data) = self
+            .selection_atom_to_clipboard(request.selection())
+            .and_then(|clipboard| {
+                self.copy_and_paste
+                    .data(clipboard)
+                    .iter()
+                    .find(|(atom, _)| *atom == request.target())
+            })
+            .map(|(_, data)| data)
+        {
+            log::trace!("SEL: window_id={window_id:?} requestor wants additional data");
+            if data.len() > self.max_selection_size() {
+                log::warn!(
+                    "SEL: window_id={window_id:?} refusing to transfer {} bytes of \
+                     selection data, which is too large for a single request",
+                    data.len()
+                );
+                xcb::x::ATOM_NONE
+            } else {
+                conn.send_request_no_reply(&xcb::x::ChangeProperty {
+                    mode: PropMode::Replace,
+                    window: request.requestor(),
+                    property: request.property(),
+                    r#type: request.target(),
+                    data: data.as_slice(),
+                })?;
+                request.property()
+            }
         } else {
             // We didn't support their request, so there is nothing
             // we can report back to them.
@@ -2164,6 +2229,34 @@ impl WindowOps for XWindow {
                 .copy_and_paste
                 .clipboard_mut(clipboard)
                 .replace(text.clone());
+            inner.copy_and_paste.data_mut(clipboard).clear();
+            inner.update_selection_owner(clipboard)?;
+            Ok(())
+        });
+    }
+
+    /// Set the clipboard to hold the same content in several MIME types
+    fn set_clipboard_data(&self, clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        let window_id = self.0;
+        XConnection::with_window_inner(window_id, move |inner| {
+            let conn = inner.conn();
+            let mut text = String::new();
+            let mut data = vec![];
+            for item in &items {
+                if item.is_text() {
+                    text = String::from_utf8_lossy(&item.data).into_owned();
+                } else {
+                    let atom = XConnection::intern_atom(&conn, &item.mime_type)?;
+                    data.push((atom, item.data.clone()));
+                }
+            }
+            log::trace!(
+                "SEL: window_id={window_id:?} now owns selection \
+                for {clipboard:?} with {} items",
+                items.len()
+            );
+            inner.copy_and_paste.clipboard_mut(clipboard).replace(text);
+            *inner.copy_and_paste.data_mut(clipboard) = data;
             inner.update_selection_owner(clipboard)?;
             Ok(())
         });
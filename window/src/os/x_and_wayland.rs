@@ -9,8 +9,8 @@ use crate::os::x11::connection::XConnection;
 use crate::os::x11::window::XWindow;
 use crate::screen::Screens;
 use crate::{
-    Appearance, Clipboard, MouseCursor, Rect, RequestedWindowGeometry, ResizeIncrement,
-    ScreenPoint, WindowEvent, WindowOps,
+    Appearance, Clipboard, ClipboardItem, MouseCursor, Rect, RequestedWindowGeometry,
+    ResizeIncrement, ScreenPoint, WindowEvent, WindowOps,
 };
 use async_trait::async_trait;
 use config::ConfigHandle;
@@ -408,4 +408,11 @@ impl WindowOps for Window {
             Self::Wayland(w) => w.set_clipboard(clipboard, text),
         }
     }
+    fn set_clipboard_data(&self, clipboard: Clipboard, items: Vec<ClipboardItem>) {
+        match self {
+            Self::X11(x) => x.set_clipboard_data(clipboard, items),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_clipboard_data(clipboard, items),
+        }
+    }
 }